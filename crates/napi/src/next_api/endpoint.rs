@@ -1,6 +1,6 @@
-use std::{ops::Deref, sync::Arc};
+use std::{ops::Deref, path::Path, sync::Arc};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use futures_util::TryFutureExt;
 use napi::{JsFunction, bindgen_prelude::External};
 use next_api::{
@@ -12,12 +12,16 @@ use next_api::{
     },
 };
 use tracing::Instrument;
-use turbo_tasks::{Completion, Effects, OperationVc, ReadRef, Vc};
+use turbo_tasks::{
+    Completion, Effects, OperationVc, ReadRef, TaskPriority, TurboTasksBackendApi, Vc,
+    task_graph::write_task_graph,
+};
 use turbopack_core::{diagnostics::PlainDiagnostic, issue::PlainIssue};
 
 use super::utils::{
     DetachedVc, NapiDiagnostic, NapiIssue, RootTask, TurbopackResult,
-    strongly_consistent_catch_collectables, subscribe,
+    strongly_consistent_catch_collectables, subscribe, subscribe_with_priority,
+    with_slow_task_issues,
 };
 
 #[napi(object)]
@@ -144,7 +148,11 @@ pub async fn endpoint_write_to_disk(
                 .await?;
             effects.apply().await?;
 
-            Ok((written.clone(), issues.clone(), diagnostics.clone()))
+            Ok((
+                written.clone(),
+                with_slow_task_issues(issues.clone()).await?,
+                diagnostics.clone(),
+            ))
         })
         .or_else(|e| ctx.throw_turbopack_internal_result(&e.into()))
         .await?;
@@ -155,6 +163,39 @@ pub async fn endpoint_write_to_disk(
     })
 }
 
+/// Writes the tasks that are reachable from the task that writes the endpoint to disk, including
+/// their dependencies, dirty state and collectibles. Writes the Graphviz DOT format when the path
+/// ends with `.dot` or `.gv` and JSON otherwise. Children are followed up to `max_depth` levels
+/// deep, 5 by default.
+#[napi]
+pub async fn endpoint_write_task_graph(
+    #[napi(ts_arg_type = "{ __napiType: \"Endpoint\" }")] endpoint: External<ExternalEndpoint>,
+    path: String,
+    max_depth: Option<u32>,
+) -> napi::Result<()> {
+    let ctx = endpoint.turbopack_ctx().clone();
+    let endpoint_op = ***endpoint;
+    let task_id = ctx
+        .turbo_tasks()
+        .run(async move {
+            let written_entrypoint_with_issues_op =
+                get_written_endpoint_with_issues_operation(endpoint_op);
+            Ok(OperationVc::into_raw(written_entrypoint_with_issues_op).try_get_task_id())
+        })
+        .or_else(|e| ctx.throw_turbopack_internal_result(&e.into()))
+        .await?
+        .context("the endpoint is not backed by a task")?;
+    tokio::task::spawn_blocking(move || {
+        let graph = ctx
+            .turbo_tasks()
+            .task_graph(task_id, max_depth.unwrap_or(5) as usize);
+        write_task_graph(&graph, Path::new(&path))
+    })
+    .await
+    .context("panicked while writing the task graph")??;
+    Ok(())
+}
+
 #[tracing::instrument(level = "info", name = "get server-side endpoint changes", skip_all)]
 #[napi(ts_return_type = "{ __napiType: \"RootTask\" }")]
 pub fn endpoint_server_changed_subscribe(
@@ -254,8 +295,9 @@ pub fn endpoint_client_changed_subscribe(
 ) -> napi::Result<External<RootTask>> {
     let turbopack_ctx = endpoint.turbopack_ctx().clone();
     let endpoint_op = ***endpoint;
-    subscribe(
+    subscribe_with_priority(
         turbopack_ctx,
+        TaskPriority::Interactive,
         func,
         move || {
             async move {
@@ -1,4 +1,10 @@
-use std::{borrow::Cow, io::Write, path::PathBuf, sync::Arc, thread, time::Duration};
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
 
 use anyhow::{Context, Result, anyhow, bail};
 use flate2::write::GzEncoder;
@@ -34,13 +40,16 @@ use tracing_subscriber::{Registry, layer::SubscriberExt, util::SubscriberInitExt
 use turbo_rcstr::{RcStr, rcstr};
 use turbo_tasks::{
     Effects, FxIndexSet, NonLocalValue, OperationValue, OperationVc, ReadRef, ResolvedVc,
-    TaskInput, TransientInstance, TryJoinIterExt, TurboTasksApi, UpdateInfo, Vc, get_effects,
+    TaskInput, TaskPriority, TransientInstance, TryJoinIterExt, TurboTasksApi, UpdateInfo, Vc,
+    get_effects,
     message_queue::{CompilationEvent, Severity},
+    task_statistics::write_task_statistics,
     trace::TraceRawVcs,
 };
 use turbo_tasks_backend::{BackingStorage, db_invalidation::invalidation_reasons};
 use turbo_tasks_fs::{
-    DiskFileSystem, FileContent, FileSystem, FileSystemPath, util::uri_from_file,
+    DiskFileSystem, File, FileContent, FileSystem, FileSystemPath, OverlayFileSystem,
+    util::uri_from_file,
 };
 use turbo_unix_path::{get_relative_path_to, sys_to_unix};
 use turbopack_core::{
@@ -66,11 +75,12 @@ use crate::{
         endpoint::ExternalEndpoint,
         turbopack_ctx::{
             NapiNextTurbopackCallbacks, NapiNextTurbopackCallbacksJsObject, NextTurboTasks,
-            NextTurbopackContext, create_turbo_tasks,
+            NextTurbopackContext, create_turbo_tasks, export_turbo_tasks_cache,
         },
         utils::{
             DetachedVc, NapiDiagnostic, NapiIssue, RootTask, TurbopackResult, get_diagnostics,
-            get_issues, strongly_consistent_catch_collectables, subscribe,
+            get_issues, strongly_consistent_catch_collectables, subscribe, subscribe_with_priority,
+            with_slow_task_issues,
         },
     },
     util::DhatProfilerGuard,
@@ -173,6 +183,11 @@ pub struct NapiProjectOptions {
     /// debugging/profiling purposes.
     pub no_mangling: bool,
 
+    /// Reads project files through an overlay so that `project_set_file_override` can replace
+    /// their contents. Opts the project files out of the persistent cache, so this is only meant
+    /// for dev and editor sessions.
+    pub file_overrides: Option<bool>,
+
     /// The version of Node.js that is available/currently running.
     pub current_node_js_version: RcStr,
 }
@@ -282,6 +297,7 @@ impl From<NapiProjectOptions> for ProjectOptions {
             preview_props: val.preview_props.into(),
             browserslist_query: val.browserslist_query,
             no_mangling: val.no_mangling,
+            file_overrides: val.file_overrides.unwrap_or_default(),
             current_node_js_version: val.current_node_js_version,
         }
     }
@@ -331,6 +347,7 @@ impl From<NapiDefineEnv> for DefineEnv {
 pub struct ProjectInstance {
     turbopack_ctx: NextTurbopackContext,
     container: ResolvedVc<ProjectContainer>,
+    root_path: RcStr,
     exit_receiver: tokio::sync::Mutex<Option<ExitReceiver>>,
 }
 
@@ -463,6 +480,7 @@ pub fn project_new(
             let is_short_session = turbo_engine_options.is_short_session.unwrap_or(false);
             let turbo_tasks = create_turbo_tasks(
                 PathBuf::from(&options.dist_dir),
+                &options.root_path,
                 persistent_caching,
                 memory_limit,
                 dependency_tracking,
@@ -475,11 +493,7 @@ pub fn project_new(
                 let task_stats = turbo_tasks.task_statistics().enable().clone();
                 exit.on_exit(async move {
                     tokio::task::spawn_blocking(move || {
-                        let mut file = std::fs::File::create(&stats_path)
-                            .with_context(|| format!("failed to create or open {stats_path:?}"))?;
-                        serde_json::to_writer(&file, &task_stats)
-                            .context("failed to serialize or write task statistics")?;
-                        file.flush().context("failed to flush file")
+                        write_task_statistics(&task_stats.report(), stats_path.as_ref())
                     })
                     .await
                     .unwrap()
@@ -487,6 +501,7 @@ pub fn project_new(
                 });
             }
 
+            let root_path = options.root_path.clone();
             let options: ProjectOptions = options.into();
             let is_dev = options.dev;
             let container = turbo_tasks
@@ -526,6 +541,7 @@ pub fn project_new(
             Ok(External::new(ProjectInstance {
                 turbopack_ctx,
                 container,
+                root_path,
                 exit_receiver: tokio::sync::Mutex::new(Some(exit_receiver)),
             }))
         }
@@ -571,6 +587,7 @@ impl CompilationEvent for SlowFilesystemEvent {
 /// - https://github.com/oven-sh/bun/blob/06a9aa80c38b08b3148bfeabe560/src/install/install.zig#L3038
 async fn benchmark_file_io(turbo_tasks: NextTurboTasks, directory: FileSystemPath) -> Result<()> {
     // try to get the real file path on disk so that we can use it with tokio
+    let directory = OverlayFileSystem::strip_overlay(directory).await?;
     let fs = ResolvedVc::try_downcast_type::<DiskFileSystem>(directory.fs)
         .context(anyhow!(
             "expected node_root to be a DiskFileSystem, cannot benchmark"
@@ -656,6 +673,147 @@ pub async fn project_invalidate_file_system_cache(
     Ok(())
 }
 
+#[napi(object)]
+pub struct NapiCacheInvalidation {
+    /// Seconds since the unix epoch.
+    pub timestamp: i64,
+    /// A human-readable explanation of why the cache couldn't be used.
+    pub message: String,
+    /// The structured invalidation reason as JSON.
+    pub reason: String,
+}
+
+/// Returns the log of past filesystem cache invalidations and version changes, oldest first. This
+/// explains why a build didn't use the filesystem cache.
+#[napi]
+pub async fn project_get_file_system_cache_invalidation_log(
+    #[napi(ts_arg_type = "{ __napiType: \"Project\" }")] project: External<ProjectInstance>,
+) -> napi::Result<Vec<NapiCacheInvalidation>> {
+    let log = tokio::task::spawn_blocking(move || {
+        project
+            .turbopack_ctx
+            .turbo_tasks()
+            .backend()
+            .backing_storage()
+            .invalidation_log()
+    })
+    .await
+    .context("panicked while reading the filesystem cache invalidation log")?;
+    Ok(log
+        .into_iter()
+        .map(|entry| NapiCacheInvalidation {
+            timestamp: entry.timestamp,
+            message: entry.reason.to_string(),
+            reason: serde_json::to_string(&entry.reason).unwrap_or_default(),
+        })
+        .collect())
+}
+
+#[napi(object)]
+pub struct NapiReexecutionSummary {
+    /// The invalidation that caused the re-executions, e.g. a file change.
+    pub root_cause: String,
+    /// The number of re-executed tasks.
+    pub task_count: u32,
+    /// The longest chain of tasks from the invalidation to one of the re-executed tasks.
+    pub chain: Vec<String>,
+    /// A human-readable summary.
+    pub message: String,
+}
+
+/// Returns the tasks that have been re-executed since the last call, grouped by the invalidation
+/// that caused them. This explains slow updates. Only returns results when the
+/// `TURBO_ENGINE_TRACK_DIRTY_CAUSES` environment variable is set.
+#[napi]
+pub async fn project_get_reexecution_summary(
+    #[napi(ts_arg_type = "{ __napiType: \"Project\" }")] project: External<ProjectInstance>,
+) -> napi::Result<Vec<NapiReexecutionSummary>> {
+    let summary = tokio::task::spawn_blocking(move || {
+        project
+            .turbopack_ctx
+            .turbo_tasks()
+            .backend()
+            .take_reexecution_summary()
+    })
+    .await
+    .context("panicked while summarizing re-executed tasks")?;
+    Ok(summary
+        .into_iter()
+        .map(|summary| NapiReexecutionSummary {
+            message: summary.to_string(),
+            task_count: summary.task_count.try_into().unwrap_or(u32::MAX),
+            chain: summary
+                .longest_chain
+                .links
+                .into_iter()
+                .rev()
+                .map(|link| link.task)
+                .collect(),
+            root_cause: summary.longest_chain.root_cause,
+        })
+        .collect())
+}
+
+#[turbo_tasks::function(operation)]
+fn project_overlay_fs_operation(container: ResolvedVc<ProjectContainer>) -> Vc<OverlayFileSystem> {
+    container.project().overlay_fs()
+}
+
+/// Replaces the content of a file in the compilation without changing it on disk, e.g. with the
+/// unsaved content of an editor buffer. `null` content hides the file. The path is absolute or
+/// relative to the root path of the project. Requires the project to be created with
+/// `fileOverrides` enabled.
+#[napi]
+pub async fn project_set_file_override(
+    #[napi(ts_arg_type = "{ __napiType: \"Project\" }")] project: External<ProjectInstance>,
+    file_path: RcStr,
+    content: Option<String>,
+) -> napi::Result<()> {
+    let content = match content {
+        Some(content) => FileContent::Content(File::from(content)),
+        None => FileContent::NotFound,
+    };
+    update_file_override(&project, file_path, Some(content)).await
+}
+
+/// Removes an override that has been set with `project_set_file_override`, so the compilation
+/// uses the content on disk again.
+#[napi]
+pub async fn project_clear_file_override(
+    #[napi(ts_arg_type = "{ __napiType: \"Project\" }")] project: External<ProjectInstance>,
+    file_path: RcStr,
+) -> napi::Result<()> {
+    update_file_override(&project, file_path, None).await
+}
+
+async fn update_file_override(
+    project: &ProjectInstance,
+    file_path: RcStr,
+    content: Option<FileContent>,
+) -> napi::Result<()> {
+    let ctx = &project.turbopack_ctx;
+    let container = project.container;
+    ctx.turbo_tasks()
+        .run(async move {
+            let overlay_fs = project_overlay_fs_operation(container)
+                .read_strongly_consistent()
+                .await?;
+            let disk_fs_vc = ResolvedVc::try_downcast_type::<DiskFileSystem>(overlay_fs.base())
+                .context("expected the project filesystem to be a DiskFileSystem")?;
+            let path = disk_fs_vc
+                .await?
+                .try_from_sys_path(disk_fs_vc, Path::new(&*file_path), None)
+                .with_context(|| format!("{file_path} is outside of the project"))?;
+            match content {
+                Some(content) => overlay_fs.set_override(&path.path, content)?,
+                None => overlay_fs.clear_override(&path.path)?,
+            }
+            Ok(())
+        })
+        .or_else(|e| ctx.throw_turbopack_internal_result(&e.into()))
+        .await
+}
+
 /// Runs exit handlers for the project registered using the [`ExitHandler`] API.
 ///
 /// This is called by `project_shutdown`, so if you're calling that API, you shouldn't call this
@@ -685,7 +843,19 @@ async fn project_on_exit_internal(project: &ProjectInstance) {
 pub async fn project_shutdown(
     #[napi(ts_arg_type = "{ __napiType: \"Project\" }")] project: External<ProjectInstance>,
 ) {
-    project.turbopack_ctx.turbo_tasks().stop_and_wait().await;
+    let turbo_tasks = project.turbopack_ctx.turbo_tasks().clone();
+    turbo_tasks.stop_and_wait().await;
+    let root_path = project.root_path.clone();
+    let result =
+        tokio::task::spawn_blocking(move || export_turbo_tasks_cache(&turbo_tasks, &root_path))
+            .await
+            .unwrap();
+    if let Err(err) = result {
+        println!(
+            "Failed to export the Turbopack cache: {}",
+            PrettyPrintError(&err)
+        );
+    }
     project_on_exit_internal(&project).await;
 }
 
@@ -950,7 +1120,11 @@ pub async fn project_write_all_entrypoints_to_disk(
             // Write the files to disk
             effects.apply().await?;
 
-            Ok((entrypoints.clone(), issues.clone(), diagnostics.clone()))
+            Ok((
+                entrypoints.clone(),
+                with_slow_task_issues(issues.clone()).await?,
+                diagnostics.clone(),
+            ))
         })
         .or_else(|e| ctx.throw_turbopack_internal_result(&e.into()))
         .await?;
@@ -1056,7 +1230,11 @@ pub fn project_entrypoints_subscribe(
                     .await?;
 
                 effects.apply().await?;
-                Ok((entrypoints.clone(), issues.clone(), diagnostics.clone()))
+                Ok((
+                    entrypoints.clone(),
+                    with_slow_task_issues(issues.clone()).await?,
+                    diagnostics.clone(),
+                ))
             }
             .instrument(tracing::info_span!("entrypoints subscription"))
         },
@@ -1128,8 +1306,9 @@ pub fn project_hmr_events(
 ) -> napi::Result<External<RootTask>> {
     let container = project.container;
     let session = TransientInstance::new(());
-    subscribe(
+    subscribe_with_priority(
         project.turbopack_ctx.clone(),
+        TaskPriority::Interactive,
         func,
         {
             let outer_identifier = identifier.clone();
@@ -1163,7 +1342,11 @@ pub fn project_hmr_events(
                             state.set(to.clone()).await?;
                         }
                     }
-                    Ok((Some(update.clone()), issues.clone(), diagnostics.clone()))
+                    Ok((
+                        Some(update.clone()),
+                        with_slow_task_issues(issues.clone()).await?,
+                        diagnostics.clone(),
+                    ))
                 }
             }
         },
@@ -6,10 +6,10 @@ use std::{
     io::{self, BufRead, Write},
     path::PathBuf,
     sync::{Arc, Mutex},
-    time::Instant,
+    time::{Duration, Instant},
 };
 
-use anyhow::Result;
+use anyhow::{Result, bail};
 use either::Either;
 use napi::{JsFunction, threadsafe_function::ThreadsafeFunction};
 use once_cell::sync::Lazy;
@@ -19,12 +19,13 @@ use terminal_hyperlink::Hyperlink;
 use turbo_tasks::{
     TurboTasks, TurboTasksApi,
     backend::TurboTasksExecutionError,
-    message_queue::{CompilationEvent, Severity},
+    message_queue::{CompilationEvent, DiagnosticEvent, Severity},
+    slow_tasks::SlowTaskWatchdogOptions,
 };
 use turbo_tasks_backend::{
-    BackendOptions, DefaultBackingStorage, GitVersionInfo, NoopBackingStorage, StartupCacheState,
-    TurboTasksBackend, db_invalidation::invalidation_reasons, default_backing_storage,
-    noop_backing_storage,
+    BackendOptions, DefaultBackingStorage, GitVersionInfo, InvalidationReason, NoopBackingStorage,
+    StartupCacheState, TurboTasksBackend, db_invalidation::invalidation_reasons,
+    default_backing_storage, noop_backing_storage,
 };
 use turbopack_core::error::PrettyPrintError;
 
@@ -181,24 +182,37 @@ impl NapiNextTurbopackCallbacks {
 
 pub fn create_turbo_tasks(
     output_path: PathBuf,
+    root_path: &str,
     persistent_caching: bool,
-    _memory_limit: usize,
+    memory_limit: usize,
     dependency_tracking: bool,
     is_ci: bool,
     is_short_session: bool,
 ) -> Result<NextTurboTasks> {
-    Ok(if persistent_caching {
-        let version_info = GitVersionInfo {
-            describe: env!("VERGEN_GIT_DESCRIBE"),
-            dirty: option_env!("CI").is_none_or(|value| value.is_empty())
-                && env!("VERGEN_GIT_DIRTY") == "true",
-        };
+    let track_dirty_causes = std::env::var("TURBO_ENGINE_TRACK_DIRTY_CAUSES").is_ok();
+    // Fails reads that would close a cycle of waiting tasks instead of hanging
+    let detect_task_cycles = std::env::var("TURBO_ENGINE_DETECT_TASK_CYCLES").is_ok();
+    // The fraction of tasks to recompute to find non-deterministic functions, e.g. `1` or `0.1`
+    let verify_determinism = std::env::var("TURBO_ENGINE_VERIFY_DETERMINISM")
+        .ok()
+        .and_then(|rate| rate.parse::<f64>().ok());
+    // Logs task executions that take longer than this, e.g. `10000`. They are also reported as
+    // issues when `TURBO_ENGINE_SLOW_TASK_ISSUES` is set.
+    let slow_task_threshold = std::env::var("TURBO_ENGINE_SLOW_TASK_THRESHOLD_MS")
+        .ok()
+        .and_then(|ms| ms.parse::<u64>().ok())
+        .map(Duration::from_millis);
+    let tt = if persistent_caching {
+        let version_info = git_version_info();
         let (backing_storage, cache_state) = default_backing_storage(
             &output_path.join("cache/turbopack"),
             &version_info,
             is_ci,
             is_short_session,
         )?;
+        // Merges a cache exported by another checkout, e.g. on CI, see `export_turbo_tasks_cache`
+        let import_result = env::var_os("TURBO_ENGINE_CACHE_IMPORT_DIR")
+            .map(|dir| backing_storage.import_cache(dir.as_ref(), &version_info, root_path));
         let tt = TurboTasks::new(TurboTasksBackend::new(
             BackendOptions {
                 storage_mode: Some(if std::env::var("TURBO_ENGINE_READ_ONLY").is_ok() {
@@ -208,12 +222,33 @@ pub fn create_turbo_tasks(
                 }),
                 dependency_tracking,
                 num_workers: Some(tokio::runtime::Handle::current().metrics().num_workers()),
+                memory_limit: (memory_limit != usize::MAX).then_some(memory_limit),
+                track_dirty_causes,
+                verify_determinism,
+                detect_task_cycles,
                 ..Default::default()
             },
             Either::Left(backing_storage),
         ));
-        if let StartupCacheState::Invalidated { reason_code } = cache_state {
-            tt.send_compilation_event(Arc::new(StartupCacheInvalidationEvent { reason_code }));
+        match cache_state {
+            StartupCacheState::Invalidated { reason_code } => {
+                tt.send_compilation_event(Arc::new(StartupCacheInvalidationEvent { reason_code }));
+            }
+            StartupCacheState::VersionChanged { previous, current } => {
+                tt.send_compilation_event(Arc::new(StartupCacheVersionChangedEvent {
+                    reason: InvalidationReason::VersionChanged { previous, current },
+                }));
+            }
+            StartupCacheState::NoCache | StartupCacheState::Cached => {}
+        }
+        if let Some(Err(err)) = import_result {
+            tt.send_compilation_event(Arc::new(DiagnosticEvent::new(
+                Severity::Warning,
+                format!(
+                    "Failed to import the Turbopack cache: {}",
+                    PrettyPrintError(&err)
+                ),
+            )));
         }
         tt
     } else {
@@ -221,11 +256,42 @@ pub fn create_turbo_tasks(
             BackendOptions {
                 storage_mode: None,
                 dependency_tracking,
+                track_dirty_causes,
+                verify_determinism,
+                detect_task_cycles,
                 ..Default::default()
             },
             Either::Right(noop_backing_storage()),
         ))
-    })
+    };
+    if let Some(threshold) = slow_task_threshold {
+        tt.start_slow_task_watchdog(SlowTaskWatchdogOptions {
+            threshold,
+            collect_reports: std::env::var("TURBO_ENGINE_SLOW_TASK_ISSUES").is_ok(),
+        });
+    }
+    Ok(tt)
+}
+
+fn git_version_info() -> GitVersionInfo {
+    GitVersionInfo {
+        describe: env!("VERGEN_GIT_DESCRIBE"),
+        dirty: option_env!("CI").is_none_or(|value| value.is_empty())
+            && env!("VERGEN_GIT_DIRTY") == "true",
+    }
+}
+
+/// Exports the persistent cache into `TURBO_ENGINE_CACHE_EXPORT_DIR` when it's set, so that other
+/// checkouts can import it via `TURBO_ENGINE_CACHE_IMPORT_DIR`. Must be called after
+/// [`TurboTasks::stop_and_wait`].
+pub fn export_turbo_tasks_cache(turbo_tasks: &NextTurboTasks, root_path: &str) -> Result<()> {
+    let Some(dir) = env::var_os("TURBO_ENGINE_CACHE_EXPORT_DIR") else {
+        return Ok(());
+    };
+    let Either::Left(backing_storage) = turbo_tasks.backend().backing_storage() else {
+        bail!("Exporting the cache requires persistent caching");
+    };
+    backing_storage.export_cache(dir.as_ref(), &git_version_info(), root_path)
 }
 
 #[derive(Serialize)]
@@ -261,6 +327,33 @@ impl CompilationEvent for StartupCacheInvalidationEvent {
     }
 }
 
+#[derive(Serialize)]
+struct StartupCacheVersionChangedEvent {
+    reason: InvalidationReason,
+}
+
+impl CompilationEvent for StartupCacheVersionChangedEvent {
+    fn type_name(&self) -> &'static str {
+        "StartupCacheVersionChangedEvent"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Info
+    }
+
+    fn message(&self) -> String {
+        format!(
+            "Turbopack's filesystem cache can't be reused: {}. Builds or page loads may be slower \
+             as a result.",
+            self.reason
+        )
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
 static LOG_THROTTLE: Mutex<Option<Instant>> = Mutex::new(None);
 static LOG_DIVIDER: &str = "---------------------------";
 static PANIC_LOG: Lazy<PathBuf> = Lazy::new(|| {
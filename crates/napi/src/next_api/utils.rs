@@ -10,14 +10,15 @@ use napi::{
 use rustc_hash::FxHashMap;
 use serde::Serialize;
 use turbo_tasks::{
-    Effects, OperationVc, ReadRef, TaskId, TryJoinIterExt, Vc, VcValueType, get_effects,
+    Effects, OperationVc, ReadRef, ResolvedVc, TaskId, TaskPriority, TryJoinIterExt, Vc,
+    VcValueType, get_effects, turbo_tasks,
 };
 use turbo_tasks_fs::FileContent;
 use turbopack_core::{
     diagnostics::{Diagnostic, DiagnosticContextExt, PlainDiagnostic},
     issue::{
-        CollectibleIssuesExt, IssueSeverity, PlainIssue, PlainIssueSource, PlainSource,
-        StyledString,
+        CollectibleIssuesExt, Issue, IssueSeverity, PlainIssue, PlainIssueSource, PlainSource,
+        StyledString, slow_task::SlowTaskIssue, task_cycle::TaskCycleIssue,
     },
     source_pos::SourcePos,
 };
@@ -328,9 +329,25 @@ pub fn subscribe<T: 'static + Send + Sync, F: Future<Output = Result<T>> + Send,
     func: JsFunction,
     handler: impl 'static + Sync + Send + Clone + Fn() -> F,
     mapper: impl 'static + Sync + Send + FnMut(ThreadSafeCallContext<T>) -> napi::Result<Vec<V>>,
+) -> napi::Result<External<RootTask>> {
+    subscribe_with_priority(ctx, TaskPriority::default(), func, handler, mapper)
+}
+
+/// Like [`subscribe`], but the root task and all tasks it schedules are executed with the given
+/// priority. Used for subscriptions the user is actively waiting for, like HMR updates.
+pub fn subscribe_with_priority<
+    T: 'static + Send + Sync,
+    F: Future<Output = Result<T>> + Send,
+    V: ToNapiValue,
+>(
+    ctx: NextTurbopackContext,
+    priority: TaskPriority,
+    func: JsFunction,
+    handler: impl 'static + Sync + Send + Clone + Fn() -> F,
+    mapper: impl 'static + Sync + Send + FnMut(ThreadSafeCallContext<T>) -> napi::Result<Vec<V>>,
 ) -> napi::Result<External<RootTask>> {
     let func: ThreadsafeFunction<T> = func.create_threadsafe_function(0, mapper)?;
-    let task_id = ctx.turbo_tasks().spawn_root_task({
+    let task_id = ctx.turbo_tasks().spawn_root_task_with_priority(priority, {
         let ctx = ctx.clone();
         move || {
             let ctx = ctx.clone();
@@ -368,7 +385,14 @@ pub async fn strongly_consistent_catch_collectables<R: VcValueType + Send>(
     Arc<Effects>,
 )> {
     let result = source_op.read_strongly_consistent().await;
-    let issues = get_issues(source_op).await?;
+    let mut issues = get_issues(source_op).await?;
+    // Tasks in a dependency cycle fail without emitting an issue themselves
+    if let Err(err) = &result
+        && let Some(issue) = TaskCycleIssue::from_error(err)
+    {
+        let issue = ResolvedVc::upcast::<Box<dyn Issue>>(issue.resolved_cell());
+        Arc::make_mut(&mut issues).push(PlainIssue::from_issue(issue, None).await?);
+    }
     let diagnostics = get_diagnostics(source_op).await?;
     let effects = Arc::new(get_effects(source_op).await?);
 
@@ -381,6 +405,20 @@ pub async fn strongly_consistent_catch_collectables<R: VcValueType + Send>(
     Ok((result, issues, diagnostics, effects))
 }
 
+/// Adds the slow tasks that the watchdog has noticed since the last call to the issues of a
+/// result. Taking them is a side effect, so this must be called by the root or once task that
+/// reads the result and never from a memoized function.
+pub async fn with_slow_task_issues(
+    mut issues: Arc<Vec<ReadRef<PlainIssue>>>,
+) -> Result<Arc<Vec<ReadRef<PlainIssue>>>> {
+    for slow_task in turbo_tasks().take_slow_tasks() {
+        let issue =
+            ResolvedVc::upcast::<Box<dyn Issue>>(SlowTaskIssue::new(&slow_task).resolved_cell());
+        Arc::make_mut(&mut issues).push(PlainIssue::from_issue(issue, None).await?);
+    }
+    Ok(issues)
+}
+
 #[napi]
 pub fn expand_next_js_template(
     content: Buffer,
@@ -150,7 +150,7 @@ impl Asset for NftJsonAsset {
             let mut result: BTreeSet<RcStr> = BTreeSet::new();
 
             let output_root_ref = this.project.output_fs().root().await?;
-            let project_root_ref = this.project.project_fs().root().await?;
+            let project_root_ref = this.project.project_root_path().await?;
             let next_config = this.project.next_config();
 
             let output_file_tracing_includes = &*next_config.output_file_tracing_includes().await?;
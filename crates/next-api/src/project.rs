@@ -38,7 +38,9 @@ use turbo_tasks::{
     trace::TraceRawVcs,
 };
 use turbo_tasks_env::{EnvMap, ProcessEnv};
-use turbo_tasks_fs::{DiskFileSystem, FileSystem, FileSystemPath, VirtualFileSystem, invalidation};
+use turbo_tasks_fs::{
+    DiskFileSystem, FileSystem, FileSystemPath, OverlayFileSystem, VirtualFileSystem, invalidation,
+};
 use turbo_unix_path::{join_path, unix_to_sys};
 use turbopack::{
     ModuleAssetContext, evaluate_context::node_build_environment,
@@ -190,6 +192,13 @@ pub struct ProjectOptions {
     /// debugging/profiling purposes.
     pub no_mangling: bool,
 
+    /// Reads project files through an [`OverlayFileSystem`] so that editors can override their
+    /// contents with unsaved changes. Overridden contents only live for the current session, so
+    /// this opts the project files out of the persistent cache. Only meant for dev and editor
+    /// sessions.
+    #[serde(default)]
+    pub file_overrides: bool,
+
     /// The version of Node.js that is available/currently running.
     pub current_node_js_version: RcStr,
 }
@@ -453,6 +462,7 @@ impl ProjectContainer {
         let preview_props;
         let browserslist_query;
         let no_mangling;
+        let file_overrides;
         let current_node_js_version;
         {
             let options = self.options_state.get();
@@ -476,6 +486,7 @@ impl ProjectContainer {
             preview_props = options.preview_props.clone();
             browserslist_query = options.browserslist_query.clone();
             no_mangling = options.no_mangling;
+            file_overrides = options.file_overrides;
             current_node_js_version = options.current_node_js_version.clone();
         }
 
@@ -501,6 +512,7 @@ impl ProjectContainer {
             encryption_key,
             preview_props,
             no_mangling,
+            file_overrides,
             current_node_js_version,
         }
         .cell())
@@ -587,6 +599,9 @@ pub struct Project {
     /// debugging/profiling purposes.
     no_mangling: bool,
 
+    /// Whether project files are read through [`Project::overlay_fs`].
+    file_overrides: bool,
+
     current_node_js_version: RcStr,
 }
 
@@ -689,6 +704,16 @@ impl Project {
         ))
     }
 
+    /// The project filesystem with the unsaved changes of editors layered over it. Project files
+    /// are only read through it when [`ProjectOptions::file_overrides`] is enabled.
+    #[turbo_tasks::function]
+    pub async fn overlay_fs(self: Vc<Self>) -> Result<Vc<OverlayFileSystem>> {
+        if !self.await?.file_overrides {
+            bail!("File overrides are not enabled for this project");
+        }
+        Ok(OverlayFileSystem::new(Vc::upcast(self.project_fs())))
+    }
+
     #[turbo_tasks::function]
     pub fn client_fs(self: Vc<Self>) -> Vc<Box<dyn FileSystem>> {
         let virtual_fs = VirtualFileSystem::new_with_name(rcstr!("client-fs"));
@@ -734,8 +759,12 @@ impl Project {
     }
 
     #[turbo_tasks::function]
-    pub fn project_root_path(self: Vc<Self>) -> Vc<FileSystemPath> {
-        self.project_fs().root()
+    pub async fn project_root_path(self: Vc<Self>) -> Result<Vc<FileSystemPath>> {
+        Ok(if self.await?.file_overrides {
+            self.overlay_fs().root()
+        } else {
+            self.project_fs().root()
+        })
     }
 
     #[turbo_tasks::function]
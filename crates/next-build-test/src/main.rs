@@ -1,6 +1,7 @@
 use std::{
     cell::RefCell,
     convert::Infallible,
+    path::PathBuf,
     str::FromStr,
     time::{Duration, Instant},
 };
@@ -13,7 +14,7 @@ use next_core::tracing_presets::{
 };
 use tracing_subscriber::{Registry, layer::SubscriberExt, util::SubscriberInitExt};
 use turbo_rcstr::rcstr;
-use turbo_tasks::TurboTasks;
+use turbo_tasks::{TurboTasks, TurboTasksApi, task_statistics::write_task_statistics};
 use turbo_tasks_backend::{BackendOptions, TurboTasksBackend, noop_backing_storage};
 use turbo_tasks_malloc::TurboMalloc;
 use turbopack_trace_utils::{
@@ -143,7 +144,12 @@ fn main() {
                         },
                         noop_backing_storage(),
                     ));
+                    let task_statistics = std::env::var_os("NEXT_TURBOPACK_TASK_STATISTICS")
+                        .map(|path| (PathBuf::from(path), tt.task_statistics().enable().clone()));
                     let result = main_inner(&tt, strategy, factor, limit, files).await;
+                    if let Some((path, task_statistics)) = task_statistics {
+                        write_task_statistics(&task_statistics.report(), &path)?;
+                    }
                     let memory = TurboMalloc::memory_usage();
                     tracing::info!("memory usage: {} MiB", memory / 1024 / 1024);
                     let start = Instant::now();
@@ -182,6 +188,7 @@ fn main() {
                                      Safari versions, last 1 Edge versions"
                     .into(),
                 no_mangling: false,
+                file_overrides: false,
                 current_node_js_version: rcstr!("18.0.0"),
             };
 
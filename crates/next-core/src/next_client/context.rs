@@ -156,8 +156,11 @@ pub async fn get_client_resolve_options_context(
         custom_conditions.push(rcstr!("next-js"));
     };
 
+    let root_dir = project_path.root().owned().await?;
     let resolve_options_context = ResolveOptionsContext {
-        enable_node_modules: Some(project_path.root().owned().await?),
+        // Only used when the manifest exists
+        enable_pnp: Some(root_dir.join(".pnp.cjs")?),
+        enable_node_modules: Some(root_dir),
         custom_conditions,
         import_map: Some(next_client_import_map),
         fallback_import_map: Some(next_client_fallback_import_map),
@@ -157,8 +157,11 @@ pub async fn get_edge_resolve_options_context(
         custom_conditions.push(rcstr!("next-js"));
     };
 
+    let root_dir = project_path.root().owned().await?;
     let resolve_options_context = ResolveOptionsContext {
-        enable_node_modules: Some(project_path.root().owned().await?),
+        // Only used when the manifest exists
+        enable_pnp: Some(root_dir.join(".pnp.cjs")?),
+        enable_node_modules: Some(root_dir),
         enable_edge_node_externals: true,
         custom_conditions,
         import_map: Some(next_edge_import_map),
@@ -314,6 +314,8 @@ pub async fn get_server_resolve_options_context(
 
     let resolve_options_context = ResolveOptionsContext {
         enable_node_modules: Some(root_dir.clone()),
+        // Only used when the manifest exists
+        enable_pnp: Some(root_dir.join(".pnp.cjs")?),
         enable_node_externals: true,
         enable_node_native_modules: true,
         module: true,
@@ -79,6 +79,17 @@ export interface NapiWrittenEndpoint {
 export declare function endpointWriteToDisk(endpoint: {
   __napiType: 'Endpoint'
 }): Promise<TurbopackResult>
+/**
+ * Writes the tasks that are reachable from the task that writes the endpoint to disk, including
+ * their dependencies, dirty state and collectibles. Writes the Graphviz DOT format when the path
+ * ends with `.dot` or `.gv` and JSON otherwise. Children are followed up to `max_depth` levels
+ * deep, 5 by default.
+ */
+export declare function endpointWriteTaskGraph(
+  endpoint: { __napiType: 'Endpoint' },
+  path: string,
+  maxDepth?: number | undefined | null
+): Promise<void>
 export declare function endpointServerChangedSubscribe(
   endpoint: { __napiType: 'Endpoint' },
   issues: boolean,
@@ -155,6 +166,12 @@ export interface NapiProjectOptions {
    * debugging/profiling purposes.
    */
   noMangling: boolean
+  /**
+   * Reads project files through an overlay so that `project_set_file_override` can replace
+   * their contents. Opts the project files out of the persistent cache, so this is only meant
+   * for dev and editor sessions.
+   */
+  fileOverrides?: boolean
   /** The version of Node.js that is available/currently running. */
   currentNodeJsVersion: RcStr
 }
@@ -239,6 +256,58 @@ export declare function projectUpdate(
 export declare function projectInvalidateFileSystemCache(project: {
   __napiType: 'Project'
 }): Promise<void>
+export interface NapiCacheInvalidation {
+  /** Seconds since the unix epoch. */
+  timestamp: number
+  /** A human-readable explanation of why the cache couldn't be used. */
+  message: string
+  /** The structured invalidation reason as JSON. */
+  reason: string
+}
+/**
+ * Returns the log of past filesystem cache invalidations and version changes, oldest first. This
+ * explains why a build didn't use the filesystem cache.
+ */
+export declare function projectGetFileSystemCacheInvalidationLog(project: {
+  __napiType: 'Project'
+}): Promise<Array<NapiCacheInvalidation>>
+export interface NapiReexecutionSummary {
+  /** The invalidation that caused the re-executions, e.g. a file change. */
+  rootCause: string
+  /** The number of re-executed tasks. */
+  taskCount: number
+  /** The longest chain of tasks from the invalidation to one of the re-executed tasks. */
+  chain: Array<string>
+  /** A human-readable summary. */
+  message: string
+}
+/**
+ * Returns the tasks that have been re-executed since the last call, grouped by the invalidation
+ * that caused them. This explains slow updates. Only returns results when the
+ * `TURBO_ENGINE_TRACK_DIRTY_CAUSES` environment variable is set.
+ */
+export declare function projectGetReexecutionSummary(project: {
+  __napiType: 'Project'
+}): Promise<Array<NapiReexecutionSummary>>
+/**
+ * Replaces the content of a file in the compilation without changing it on disk, e.g. with the
+ * unsaved content of an editor buffer. `null` content hides the file. The path is absolute or
+ * relative to the root path of the project. Requires the project to be created with
+ * `fileOverrides` enabled.
+ */
+export declare function projectSetFileOverride(
+  project: { __napiType: 'Project' },
+  filePath: RcStr,
+  content?: string | undefined | null
+): Promise<void>
+/**
+ * Removes an override that has been set with `project_set_file_override`, so the compilation
+ * uses the content on disk again.
+ */
+export declare function projectClearFileOverride(
+  project: { __napiType: 'Project' },
+  filePath: RcStr
+): Promise<void>
 /**
  * Runs exit handlers for the project registered using the [`ExitHandler`] API.
  *
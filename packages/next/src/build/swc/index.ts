@@ -18,8 +18,10 @@ import type {
 import { isDeepStrictEqual } from 'util'
 import { type DefineEnvOptions, getDefineEnv } from '../define-env'
 import type {
+  NapiCacheInvalidation,
   NapiPartialProjectOptions,
   NapiProjectOptions,
+  NapiReexecutionSummary,
   NapiSourceDiagnostic,
 } from './generated-native'
 import type {
@@ -761,6 +763,30 @@ function bindingToApi(
       return binding.projectInvalidateFileSystemCache(this._nativeProject)
     }
 
+    getFileSystemCacheInvalidationLog(): Promise<
+      Array<NapiCacheInvalidation>
+    > {
+      return binding.projectGetFileSystemCacheInvalidationLog(
+        this._nativeProject
+      )
+    }
+
+    getReexecutionSummary(): Promise<Array<NapiReexecutionSummary>> {
+      return binding.projectGetReexecutionSummary(this._nativeProject)
+    }
+
+    setFileOverride(filePath: string, content: string | null): Promise<void> {
+      return binding.projectSetFileOverride(
+        this._nativeProject,
+        filePath,
+        content
+      )
+    }
+
+    clearFileOverride(filePath: string): Promise<void> {
+      return binding.projectClearFileOverride(this._nativeProject, filePath)
+    }
+
     shutdown(): Promise<void> {
       return binding.projectShutdown(this._nativeProject)
     }
@@ -783,6 +809,14 @@ function bindingToApi(
       )) as TurbopackResult<WrittenEndpoint>
     }
 
+    writeTaskGraph(path: string, maxDepth?: number): Promise<void> {
+      return binding.endpointWriteTaskGraph(
+        this._nativeEndpoint,
+        path,
+        maxDepth
+      )
+    }
+
     async clientChanged(): Promise<AsyncIterableIterator<TurbopackResult>> {
       const clientSubscription = subscribe<TurbopackResult>(
         false,
@@ -3,6 +3,8 @@ import type { __ApiPreviewProps } from '../../server/api-utils'
 import type {
   ExternalObject,
   RefCell,
+  NapiCacheInvalidation,
+  NapiReexecutionSummary,
   NapiTurboEngineOptions,
   NapiSourceDiagnostic,
 } from './generated-native'
@@ -265,6 +267,19 @@ export interface Project {
 
   invalidateFileSystemCache(): Promise<void>
 
+  getFileSystemCacheInvalidationLog(): Promise<Array<NapiCacheInvalidation>>
+
+  getReexecutionSummary(): Promise<Array<NapiReexecutionSummary>>
+
+  /**
+   * Replaces the content of a file in the compilation without changing it on
+   * disk, e.g. with the unsaved content of an editor buffer. `null` content
+   * hides the file. Requires the project to be created with `fileOverrides`.
+   */
+  setFileOverride(filePath: string, content: string | null): Promise<void>
+
+  clearFileOverride(filePath: string): Promise<void>
+
   shutdown(): Promise<void>
 
   onExit(): Promise<void>
@@ -301,6 +316,12 @@ export interface Endpoint {
   /** Write files for the endpoint to disk. */
   writeToDisk(): Promise<TurbopackResult<WrittenEndpoint>>
 
+  /**
+   * Write the task graph of the endpoint to a file for debugging, as Graphviz
+   * DOT when the path ends with `.dot` or `.gv` and as JSON otherwise.
+   */
+  writeTaskGraph(path: string, maxDepth?: number): Promise<void>
+
   /**
    * Listen to client-side changes to the endpoint.
    * After clientChanged() has been awaited it will listen to changes.
@@ -438,6 +459,13 @@ export interface ProjectOptions {
    */
   noMangling: boolean
 
+  /**
+   * Reads project files through an overlay so that `setFileOverride` can
+   * replace their contents. Opts the project files out of the persistent
+   * cache, so this is only meant for dev and editor sessions.
+   */
+  fileOverrides?: boolean
+
   /**
    * The version of Node.js that is available/currently running.
    */
@@ -1,22 +1,134 @@
 #![feature(iter_intersperse)]
 
-use std::path::PathBuf;
+use std::{
+    cmp::Ordering,
+    io::{BufWriter, Write, stdout},
+    path::{Path, PathBuf},
+};
 
 use anyhow::{Context, Result, bail};
-use turbo_persistence::{MetaFileEntryInfo, SerialScheduler, TurboPersistence};
+use clap::{Args, Parser, Subcommand};
+use data_encoding::{HEXLOWER, HEXLOWER_PERMISSIVE};
+use turbo_persistence::{
+    ArcSlice, FamilyIter, MetaFileEntryInfo, SerialScheduler, TurboPersistence, hash_key,
+};
+
+type Database = TurboPersistence<SerialScheduler>;
+
+/// Inspection and maintenance tools for TurboPersistence databases (e.g. `.next/cache`).
+#[derive(Parser)]
+#[command()]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Prints the meta files and SST files of the database.
+    Info(DatabaseArgs),
+    /// Prints size and file statistics per key family.
+    Stats(DatabaseArgs),
+    /// Looks up a single key and prints the value as hex.
+    Get(GetArgs),
+    /// Writes all entries of a key family as JSON lines to stdout.
+    Dump(DumpArgs),
+    /// Decodes every block and AMQF filter of the database and reports corruptions.
+    Verify(DatabaseArgs),
+    /// Runs a full compaction on the database. The database must not be in use.
+    Compact(DatabaseArgs),
+    /// Compares the entries of two databases.
+    Diff(DiffArgs),
+    /// Creates a consistent snapshot of the database in a new directory.
+    Snapshot(SnapshotArgs),
+}
+
+#[derive(Args)]
+struct DatabaseArgs {
+    /// Path to the TurboPersistence directory
+    path: PathBuf,
+}
+
+#[derive(Args)]
+struct GetArgs {
+    /// Path to the TurboPersistence directory
+    path: PathBuf,
+    /// The key family
+    family: usize,
+    /// The key encoded as hex
+    key: String,
+}
+
+#[derive(Args)]
+struct DumpArgs {
+    /// Path to the TurboPersistence directory
+    path: PathBuf,
+    /// The key family
+    family: usize,
+    /// Only print the value size instead of the value
+    #[arg(long)]
+    sizes_only: bool,
+}
+
+#[derive(Args)]
+struct DiffArgs {
+    /// Path to the first TurboPersistence directory
+    a: PathBuf,
+    /// Path to the second TurboPersistence directory
+    b: PathBuf,
+    /// Only compare this key family
+    #[arg(long)]
+    family: Option<usize>,
+    /// Only print the number of differences per family
+    #[arg(long)]
+    summary: bool,
+}
+
+#[derive(Args)]
+struct SnapshotArgs {
+    /// Path to the TurboPersistence directory
+    path: PathBuf,
+    /// Path to the snapshot directory, which must not exist or be empty
+    dest: PathBuf,
+    /// Also decode every block of the snapshot, like the `verify` command
+    #[arg(long)]
+    verify: bool,
+}
 
 fn main() -> Result<()> {
-    // Get CLI argument
-    let path = PathBuf::from(
-        std::env::args()
-            .nth(1)
-            .context("Please provide a path to the TurboPersistence directory")?,
-    );
+    let cli = Cli::parse();
+
+    match cli.command {
+        Commands::Info(args) => info(&open_read_only(&args.path)?),
+        Commands::Stats(args) => stats(&open_read_only(&args.path)?),
+        Commands::Get(args) => get(args),
+        Commands::Dump(args) => dump(args),
+        Commands::Verify(args) => verify(&open_read_only(&args.path)?),
+        Commands::Compact(args) => compact(&args.path),
+        Commands::Diff(args) => diff(args),
+        Commands::Snapshot(args) => snapshot(args),
+    }
+}
+
+fn open_read_only(path: &Path) -> Result<Database> {
     if !path.exists() {
         bail!("The provided path does not exist: {}", path.display());
     }
+    TurboPersistence::open_read_only(path.to_path_buf())
+        .with_context(|| format!("Failed to open database at {}", path.display()))
+}
+
+/// Returns the number of key families that are used in the database.
+fn family_count(db: &Database) -> Result<usize> {
+    Ok(db
+        .meta_info()?
+        .iter()
+        .map(|meta_file| meta_file.family as usize + 1)
+        .max()
+        .unwrap_or(0))
+}
 
-    let db: TurboPersistence<SerialScheduler> = TurboPersistence::open_read_only(path)?;
+fn info(db: &Database) -> Result<()> {
     let meta_info = db
         .meta_info()
         .context("Failed to retrieve meta information")?;
@@ -35,7 +147,9 @@ fn main() -> Result<()> {
             amqf_entries,
             sst_size,
             key_compression_dictionary_size,
+            value_compression_dictionary_size,
             block_count,
+            compression,
         } in meta_file.entries
         {
             println!(
@@ -44,11 +158,15 @@ fn main() -> Result<()> {
             );
             println!("    AMQF {amqf_entries} entries = {} KiB", amqf_size / 1024);
             println!(
-                "    {} KiB = {} kiB key compression dict + {block_count} blocks (avg {} \
-                 bytes/block)",
+                "    {} KiB = {} kiB key compression dict + {} kiB value compression dict + \
+                 {block_count} {compression:?} blocks (avg {} bytes/block)",
                 sst_size / 1024,
                 key_compression_dictionary_size / 1024,
-                (sst_size - key_compression_dictionary_size as u64) / block_count as u64
+                value_compression_dictionary_size / 1024,
+                (sst_size
+                    - key_compression_dictionary_size as u64
+                    - value_compression_dictionary_size as u64)
+                    / block_count as u64
             );
         }
         if !meta_file.obsolete_sst_files.is_empty() {
@@ -65,3 +183,341 @@ fn main() -> Result<()> {
     }
     Ok(())
 }
+
+fn stats(db: &Database) -> Result<()> {
+    #[derive(Default)]
+    struct FamilyStats {
+        meta_files: usize,
+        sst_files: usize,
+        sst_size: u64,
+        amqf_size: u64,
+        amqf_entries: usize,
+        blocks: u64,
+    }
+
+    let meta_info = db
+        .meta_info()
+        .context("Failed to retrieve meta information")?;
+    let mut families: Vec<FamilyStats> = Vec::new();
+    for meta_file in meta_info {
+        let family = meta_file.family as usize;
+        if families.len() <= family {
+            families.resize_with(family + 1, Default::default);
+        }
+        let stats = &mut families[family];
+        stats.meta_files += 1;
+        for entry in meta_file.entries {
+            stats.sst_files += 1;
+            stats.sst_size += entry.sst_size;
+            stats.amqf_size += entry.amqf_size as u64;
+            stats.amqf_entries += entry.amqf_entries;
+            stats.blocks += entry.block_count as u64;
+        }
+    }
+    for (family, stats) in families.iter().enumerate() {
+        if stats.meta_files == 0 {
+            continue;
+        }
+        println!("FAMILY {family}");
+        println!(
+            "  {} meta files, {} SST files",
+            stats.meta_files, stats.sst_files
+        );
+        println!(
+            "  {} MiB in {} blocks (avg {} bytes/block)",
+            stats.sst_size / 1024 / 1024,
+            stats.blocks,
+            stats.sst_size / stats.blocks.max(1)
+        );
+        println!(
+            "  AMQF {} entries = {} KiB",
+            stats.amqf_entries,
+            stats.amqf_size / 1024
+        );
+    }
+    let total_size = families.iter().map(|s| s.sst_size).sum::<u64>();
+    println!("TOTAL {} MiB", total_size / 1024 / 1024);
+
+    #[cfg(feature = "stats")]
+    println!("{:#?}", db.statistics());
+    Ok(())
+}
+
+fn get(args: GetArgs) -> Result<()> {
+    let db = open_read_only(&args.path)?;
+    let key = HEXLOWER_PERMISSIVE
+        .decode(args.key.as_bytes())
+        .context("The key is not valid hex")?;
+    match db.get(args.family, &key.as_slice())? {
+        Some(value) => {
+            println!("{} bytes", value.len());
+            println!("{}", HEXLOWER.encode(&value));
+        }
+        None => {
+            bail!("Key not found in family {}", args.family);
+        }
+    }
+    Ok(())
+}
+
+fn dump(args: DumpArgs) -> Result<()> {
+    let db = open_read_only(&args.path)?;
+    let reader = db.read_family(args.family)?;
+    let mut out = BufWriter::new(stdout().lock());
+    for entry in reader.iter()? {
+        let (key, value) = entry?;
+        let line = if args.sizes_only {
+            serde_json::json!({
+                "key": HEXLOWER.encode(&key),
+                "size": value.len(),
+            })
+        } else {
+            serde_json::json!({
+                "key": HEXLOWER.encode(&key),
+                "value": HEXLOWER.encode(&value),
+            })
+        };
+        serde_json::to_writer(&mut out, &line)?;
+        writeln!(out)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn verify(db: &Database) -> Result<()> {
+    db.verify()?;
+    println!("No corruptions found");
+    Ok(())
+}
+
+fn compact(path: &Path) -> Result<()> {
+    if !path.exists() {
+        bail!("The provided path does not exist: {}", path.display());
+    }
+    let db: Database = TurboPersistence::open(path.to_path_buf())
+        .with_context(|| format!("Failed to open database at {}", path.display()))?;
+    let count_files = |db: &Database| -> Result<(usize, usize)> {
+        let meta_info = db.meta_info()?;
+        let sst_files = meta_info.iter().map(|m| m.entries.len()).sum();
+        Ok((meta_info.len(), sst_files))
+    };
+    let (meta_files, sst_files) = count_files(&db)?;
+    println!("Before: {meta_files} meta files, {sst_files} SST files");
+    db.full_compact().context("Compaction failed")?;
+    let (meta_files, sst_files) = count_files(&db)?;
+    println!("After: {meta_files} meta files, {sst_files} SST files");
+    db.shutdown()?;
+    Ok(())
+}
+
+fn snapshot(args: SnapshotArgs) -> Result<()> {
+    let db = open_read_only(&args.path)?;
+    db.snapshot(&args.dest)
+        .with_context(|| format!("Failed to create snapshot at {}", args.dest.display()))?;
+    db.shutdown()?;
+    if args.verify {
+        verify(&open_read_only(&args.dest)?)?;
+    }
+    println!("Snapshot created at {}", args.dest.display());
+    Ok(())
+}
+
+fn diff(args: DiffArgs) -> Result<()> {
+    let a = open_read_only(&args.a)?;
+    let b = open_read_only(&args.b)?;
+    let families = match args.family {
+        Some(family) => family..family + 1,
+        None => 0..family_count(&a)?.max(family_count(&b)?),
+    };
+    let mut out = BufWriter::new(stdout().lock());
+    let mut total_differences = 0;
+    for family in families {
+        let reader_a = a.read_family(family)?;
+        let reader_b = b.read_family(family)?;
+        let mut differences = 0;
+        diff_family(reader_a.iter()?, reader_b.iter()?, |difference| {
+            differences += 1;
+            if args.summary {
+                return Ok(());
+            }
+            match difference {
+                Difference::OnlyInA(key) => {
+                    writeln!(out, "- {family} {}", HEXLOWER.encode(&key))
+                }
+                Difference::OnlyInB(key) => {
+                    writeln!(out, "+ {family} {}", HEXLOWER.encode(&key))
+                }
+                Difference::Changed(key) => {
+                    writeln!(out, "~ {family} {}", HEXLOWER.encode(&key))
+                }
+            }?;
+            Ok(())
+        })?;
+        if args.summary {
+            writeln!(out, "FAMILY {family}: {differences} differences")?;
+        }
+        total_differences += differences;
+    }
+    out.flush()?;
+    drop(out);
+    if total_differences > 0 {
+        bail!("Databases differ in {total_differences} entries");
+    }
+    Ok(())
+}
+
+enum Difference {
+    OnlyInA(ArcSlice<u8>),
+    OnlyInB(ArcSlice<u8>),
+    Changed(ArcSlice<u8>),
+}
+
+/// Merges two family iterators, which are both sorted by key hash and key, and reports all
+/// differences.
+fn diff_family(
+    mut a: FamilyIter<'_, SerialScheduler>,
+    mut b: FamilyIter<'_, SerialScheduler>,
+    mut report: impl FnMut(Difference) -> Result<()>,
+) -> Result<()> {
+    let mut next_a = a.next().transpose()?;
+    let mut next_b = b.next().transpose()?;
+    loop {
+        let order = match (&next_a, &next_b) {
+            (None, None) => return Ok(()),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some((key_a, _)), Some((key_b, _))) => hash_key(&&**key_a)
+                .cmp(&hash_key(&&**key_b))
+                .then_with(|| (**key_a).cmp(&**key_b)),
+        };
+        match order {
+            Ordering::Less => {
+                let (key, _) = next_a.take().unwrap();
+                report(Difference::OnlyInA(key))?;
+                next_a = a.next().transpose()?;
+            }
+            Ordering::Greater => {
+                let (key, _) = next_b.take().unwrap();
+                report(Difference::OnlyInB(key))?;
+                next_b = b.next().transpose()?;
+            }
+            Ordering::Equal => {
+                let (key, value_a) = next_a.take().unwrap();
+                let (_, value_b) = next_b.take().unwrap();
+                if value_a != value_b {
+                    report(Difference::Changed(key))?;
+                }
+                next_a = a.next().transpose()?;
+                next_b = b.next().transpose()?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn create_database(
+        path: &Path,
+        entries: impl IntoIterator<Item = (u32, Vec<u8>)>,
+    ) -> Result<()> {
+        let db: Database = TurboPersistence::open(path.to_path_buf())?;
+        let batch = db.write_batch::<_, 1>()?;
+        for (key, value) in entries {
+            batch.put(0, key.to_be_bytes().to_vec(), value.into())?;
+        }
+        db.commit_write_batch(batch)?;
+        db.shutdown()?;
+        Ok(())
+    }
+
+    #[test]
+    fn diff_reports_all_differences() -> Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let path_a = tempdir.path().join("a");
+        let path_b = tempdir.path().join("b");
+        let path_c = tempdir.path().join("c");
+        let entries_a = || {
+            [
+                (1, b"one".to_vec()),
+                (2, b"two".to_vec()),
+                (3, b"three".to_vec()),
+            ]
+        };
+        create_database(&path_a, entries_a())?;
+        create_database(
+            &path_b,
+            [
+                (2, b"zwei".to_vec()),
+                (3, b"three".to_vec()),
+                (4, b"four".to_vec()),
+            ],
+        )?;
+        create_database(&path_c, entries_a())?;
+
+        let a = open_read_only(&path_a)?;
+        let b = open_read_only(&path_b)?;
+        let reader_a = a.read_family(0)?;
+        let reader_b = b.read_family(0)?;
+        let mut differences = Vec::new();
+        diff_family(reader_a.iter()?, reader_b.iter()?, |difference| {
+            differences.push(match difference {
+                Difference::OnlyInA(key) => ('-', key.to_vec()),
+                Difference::OnlyInB(key) => ('+', key.to_vec()),
+                Difference::Changed(key) => ('~', key.to_vec()),
+            });
+            Ok(())
+        })?;
+        // The differences are ordered by key hash
+        differences.sort();
+        assert_eq!(
+            differences,
+            [
+                ('+', 4u32.to_be_bytes().to_vec()),
+                ('-', 1u32.to_be_bytes().to_vec()),
+                ('~', 2u32.to_be_bytes().to_vec()),
+            ]
+        );
+
+        let diff_args = |a: &Path, b: &Path| DiffArgs {
+            a: a.to_path_buf(),
+            b: b.to_path_buf(),
+            family: None,
+            summary: true,
+        };
+        let err = diff(diff_args(&path_a, &path_b)).unwrap_err();
+        assert_eq!(err.to_string(), "Databases differ in 3 entries");
+        diff(diff_args(&path_a, &path_c))?;
+        Ok(())
+    }
+
+    #[test]
+    fn verify_detects_corrupted_block() -> Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let path = tempdir.path();
+        create_database(path, (0..1000u32).map(|i| (i, i.to_le_bytes().to_vec())))?;
+        verify(&open_read_only(path)?)?;
+
+        let entry = open_read_only(path)?
+            .meta_info()?
+            .into_iter()
+            .flat_map(|meta_file| meta_file.entries)
+            .next()
+            .context("No SST file has been written")?;
+        // The SST file starts with the 1 byte codec tag and the compression dictionaries. Each
+        // block starts with 4 bytes uncompressed length and 4 bytes checksum.
+        let block_start = 1
+            + usize::from(entry.key_compression_dictionary_size)
+            + usize::from(entry.value_compression_dictionary_size);
+        let sst_path = path.join(format!("{:08}.sst", entry.sequence_number));
+        let mut content = fs::read(&sst_path)?;
+        content[block_start + 8] ^= 0xFF;
+        fs::write(&sst_path, content)?;
+
+        assert!(verify(&open_read_only(path)?).is_err());
+        Ok(())
+    }
+}
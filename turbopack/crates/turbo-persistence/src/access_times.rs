@@ -0,0 +1,51 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::Result;
+use byteorder::{BE, ReadBytesExt, WriteBytesExt};
+use jiff::Timestamp;
+use rustc_hash::FxHashMap;
+
+/// The name of the file that stores the last access time of every SST file.
+///
+/// The file contains a sequence of (u32 SST sequence number, u64 seconds since the unix epoch)
+/// pairs. It is rewritten on every commit and on shutdown. It's not needed for consistency, so a
+/// missing or truncated file only loses the access times. SST files without access time are
+/// considered to be accessed when the database is opened.
+pub const ACCESS_FILE: &str = "ACCESS";
+
+/// Returns the current time in seconds since the unix epoch.
+pub fn now() -> u64 {
+    Timestamp::now().as_second().max(0) as u64
+}
+
+/// Reads the last access times of the SST files. Errors are ignored, since the access times are
+/// only used as hint for eviction.
+pub fn read_access_times(db_path: &Path) -> FxHashMap<u32, u64> {
+    let mut access_times = FxHashMap::default();
+    let Ok(content) = fs::read(db_path.join(ACCESS_FILE)) else {
+        return access_times;
+    };
+    let mut content = &content[..];
+    while let (Ok(seq), Ok(time)) = (content.read_u32::<BE>(), content.read_u64::<BE>()) {
+        access_times.insert(seq, time);
+    }
+    access_times
+}
+
+/// Writes the last access times of the SST files.
+pub fn write_access_times(
+    db_path: &Path,
+    access_times: impl IntoIterator<Item = (u32, u64)>,
+) -> Result<()> {
+    let mut file = BufWriter::new(File::create(db_path.join(ACCESS_FILE))?);
+    for (seq, time) in access_times {
+        file.write_u32::<BE>(seq)?;
+        file.write_u64::<BE>(time)?;
+    }
+    file.flush()?;
+    Ok(())
+}
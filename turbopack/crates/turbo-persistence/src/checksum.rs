@@ -0,0 +1,5 @@
+/// Computes the checksum that is stored alongside blocks, blob files and meta file headers to
+/// detect corruption.
+pub fn checksum(data: &[u8]) -> u32 {
+    twox_hash::XxHash64::oneshot(0, data) as u32
+}
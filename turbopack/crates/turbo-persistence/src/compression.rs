@@ -1,13 +1,70 @@
-use std::{mem::MaybeUninit, sync::Arc};
+use std::{cell::RefCell, mem::MaybeUninit, sync::Arc};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, anyhow, bail};
 use lzzzz::lz4::{ACC_LEVEL_DEFAULT, decompress, decompress_with_dict};
+use zstd::{dict::DecoderDictionary, zstd_safe};
+
+/// The zstd compression level used for blocks.
+const ZSTD_LEVEL: i32 = 3;
+
+/// The codec that is used to compress the blocks of an SST file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlockCompression {
+    /// Fast LZ4 compression. Key and index blocks use the key compression dictionary.
+    #[default]
+    Lz4,
+    /// Zstd compression. Key and index blocks use the key compression dictionary, small value
+    /// blocks use a value compression dictionary trained from the values of the SST file. This
+    /// results in smaller files, but compression and decompression is slower.
+    Zstd,
+}
+
+impl BlockCompression {
+    /// Returns the tag that identifies the codec in the SST file header.
+    pub fn tag(self) -> u8 {
+        match self {
+            BlockCompression::Lz4 => 0,
+            BlockCompression::Zstd => 1,
+        }
+    }
+
+    /// Returns the codec for a tag from the SST file header.
+    pub fn from_tag(tag: u8) -> Result<Self> {
+        Ok(match tag {
+            0 => BlockCompression::Lz4,
+            1 => BlockCompression::Zstd,
+            _ => bail!("Unknown block compression codec {tag}"),
+        })
+    }
+
+    /// Returns true if the codec makes use of a value compression dictionary.
+    pub fn uses_value_dictionary(self) -> bool {
+        matches!(self, BlockCompression::Zstd)
+    }
+}
+
+/// The compression dictionary that is used to decompress a block.
+#[derive(Clone, Copy)]
+pub enum DecompressionDictionary<'l> {
+    None,
+    /// A raw dictionary for LZ4.
+    Lz4(&'l [u8]),
+    /// A digested dictionary for zstd. Digesting is expensive, so this is done once per SST file.
+    Zstd(&'l DecoderDictionary<'static>),
+}
+
+thread_local! {
+    /// Creating a zstd decompression context is expensive, so one is reused per thread.
+    static ZSTD_DECOMPRESSION_CONTEXT: RefCell<zstd_safe::DCtx<'static>> =
+        RefCell::new(zstd_safe::DCtx::create());
+}
 
 #[tracing::instrument(level = "trace", skip_all, name = "decompress database block")]
 pub fn decompress_into_arc(
+    compression: BlockCompression,
     uncompressed_length: u32,
     block: &[u8],
-    compression_dictionary: Option<&[u8]>,
+    dictionary: DecompressionDictionary<'_>,
     _long_term: bool,
 ) -> Result<Arc<[u8]>> {
     // We directly allocate the buffer in an Arc to avoid copying it into an Arc and avoiding
@@ -20,37 +77,114 @@ pub fn decompress_into_arc(
     let mut buffer = unsafe { Arc::from_raw(buffer as *mut [u8]) };
     // Safety: We know that the buffer is not shared yet.
     let decompressed = unsafe { Arc::get_mut_unchecked(&mut buffer) };
-    let bytes_writes = if let Some(dict) = compression_dictionary {
+    let bytes_writes = match (compression, dictionary) {
         // Safety: decompress_with_dict will only write to `decompressed` and not read from it.
-        decompress_with_dict(block, decompressed, dict)?
-    } else {
+        (BlockCompression::Lz4, DecompressionDictionary::Lz4(dict)) => {
+            decompress_with_dict(block, decompressed, dict)?
+        }
         // Safety: decompress will only write to `decompressed` and not read from it.
-        decompress(block, decompressed)?
+        (BlockCompression::Lz4, DecompressionDictionary::None) => decompress(block, decompressed)?,
+        (BlockCompression::Zstd, DecompressionDictionary::Zstd(dict)) => ZSTD_DECOMPRESSION_CONTEXT
+            .with_borrow_mut(|context| {
+                context.decompress_using_ddict(decompressed, block, dict.as_ddict())
+            })
+            .map_err(|code| {
+                anyhow!(
+                    "Zstd decompression failed: {}",
+                    zstd_safe::get_error_name(code)
+                )
+            })?,
+        (BlockCompression::Zstd, DecompressionDictionary::None) => ZSTD_DECOMPRESSION_CONTEXT
+            .with_borrow_mut(|context| context.decompress(decompressed, block))
+            .map_err(|code| {
+                anyhow!(
+                    "Zstd decompression failed: {}",
+                    zstd_safe::get_error_name(code)
+                )
+            })?,
+        (compression, _) => bail!("Invalid compression dictionary for {compression:?}"),
     };
-    assert_eq!(
-        bytes_writes, uncompressed_length as usize,
-        "Decompressed length does not match expected length"
-    );
+    if bytes_writes != uncompressed_length as usize {
+        bail!(
+            "Decompressed length {bytes_writes} does not match expected length \
+             {uncompressed_length}"
+        );
+    }
     // Safety: The buffer is now fully initialized and can be used.
     Ok(buffer)
 }
 
-#[tracing::instrument(level = "trace", skip_all)]
+/// Compresses blocks with a fixed codec and dictionary. The zstd compression context is created
+/// for the first block and reused for all following blocks.
+pub struct BlockCompressor<'l> {
+    compression: BlockCompression,
+    dict: Option<&'l [u8]>,
+    zstd: Option<zstd::bulk::Compressor<'static>>,
+}
+
+impl<'l> BlockCompressor<'l> {
+    pub fn new(compression: BlockCompression, dict: Option<&'l [u8]>) -> Self {
+        Self {
+            compression,
+            dict,
+            zstd: None,
+        }
+    }
+
+    /// Compresses a block and appends the compressed data to the buffer.
+    #[tracing::instrument(level = "trace", skip_all)]
+    pub fn compress_into_buffer(
+        &mut self,
+        block: &[u8],
+        _long_term: bool,
+        buffer: &mut Vec<u8>,
+    ) -> Result<()> {
+        match self.compression {
+            BlockCompression::Lz4 => {
+                // LZ4 compressors are streaming, they would use previous blocks as dictionary
+                let mut compressor = if let Some(dict) = self.dict {
+                    lzzzz::lz4::Compressor::with_dict(dict)
+                } else {
+                    lzzzz::lz4::Compressor::new()
+                }
+                .context("LZ4 compressor creation failed")?;
+                let acc_factor = ACC_LEVEL_DEFAULT;
+                compressor
+                    .next_to_vec(block, buffer, acc_factor)
+                    .context("Compression failed")?;
+            }
+            BlockCompression::Zstd => {
+                let compressor = match &mut self.zstd {
+                    Some(compressor) => compressor,
+                    zstd => zstd.insert(
+                        match self.dict {
+                            Some(dict) if !dict.is_empty() => {
+                                zstd::bulk::Compressor::with_dictionary(ZSTD_LEVEL, dict)
+                            }
+                            _ => zstd::bulk::Compressor::new(ZSTD_LEVEL),
+                        }
+                        .context("Zstd compressor creation failed")?,
+                    ),
+                };
+                let start = buffer.len();
+                buffer.resize(start + zstd_safe::compress_bound(block.len()), 0);
+                let len = compressor
+                    .compress_to_buffer(block, &mut buffer[start..])
+                    .context("Compression failed")?;
+                buffer.truncate(start + len);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compresses a single block and appends the compressed data to the buffer.
 pub fn compress_into_buffer(
+    compression: BlockCompression,
     block: &[u8],
     dict: Option<&[u8]>,
-    _long_term: bool,
+    long_term: bool,
     buffer: &mut Vec<u8>,
 ) -> Result<()> {
-    let mut compressor = if let Some(dict) = dict {
-        lzzzz::lz4::Compressor::with_dict(dict)
-    } else {
-        lzzzz::lz4::Compressor::new()
-    }
-    .context("LZ4 compressor creation failed")?;
-    let acc_factor = ACC_LEVEL_DEFAULT;
-    compressor
-        .next_to_vec(block, buffer, acc_factor)
-        .context("Compression failed")?;
-    Ok(())
+    BlockCompressor::new(compression, dict).compress_into_buffer(block, long_term, buffer)
 }
@@ -1,6 +1,10 @@
 /// Values larger than this become blob files
 pub const MAX_MEDIUM_VALUE_SIZE: usize = 64 * 1024 * 1024;
 
+/// The size of the header of a blob file: 4 bytes uncompressed length and 4 bytes checksum of the
+/// compressed data.
+pub const BLOB_HEADER_SIZE: usize = 8;
+
 /// Values larger than this become separate value blocks
 // Note this must fit into 2 bytes length
 pub const MAX_SMALL_VALUE_SIZE: usize = 64 * 1024 - 1;
@@ -32,3 +36,6 @@ pub const KEY_BLOCK_AVG_SIZE: usize = 16 * 1024;
 /// Maximum RAM bytes for value block cache
 pub const VALUE_BLOCK_CACHE_SIZE: u64 = 300 * 1024 * 1024;
 pub const VALUE_BLOCK_AVG_SIZE: usize = 132000;
+
+/// Percentage of the maximum database size that eviction frees in addition to the exceeding size
+pub const EVICTION_HEADROOM: u64 = 10;
@@ -1,4 +1,5 @@
 use std::{
+    array,
     borrow::Cow,
     collections::HashSet,
     fs::{self, File, OpenOptions, ReadDir},
@@ -6,7 +7,10 @@ use std::{
     mem::swap,
     ops::RangeInclusive,
     path::{Path, PathBuf},
-    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU32, Ordering},
+    },
 };
 
 use anyhow::{Context, Result, bail};
@@ -14,26 +18,32 @@ use byteorder::{BE, ReadBytesExt, WriteBytesExt};
 use jiff::Timestamp;
 use memmap2::Mmap;
 use parking_lot::{Mutex, RwLock};
+use rustc_hash::FxHashMap;
 
 pub use crate::compaction::selector::CompactConfig;
 use crate::{
-    QueryKey,
+    QueryKey, access_times,
+    access_times::{ACCESS_FILE, read_access_times, write_access_times},
     arc_slice::ArcSlice,
+    checksum::checksum,
     compaction::selector::{Compactable, compute_metrics, get_merge_segments},
-    compression::decompress_into_arc,
+    compression::{BlockCompression, DecompressionDictionary, decompress_into_arc},
     constants::{
-        AMQF_AVG_SIZE, AMQF_CACHE_SIZE, DATA_THRESHOLD_PER_COMPACTED_FILE, KEY_BLOCK_AVG_SIZE,
-        KEY_BLOCK_CACHE_SIZE, MAX_ENTRIES_PER_COMPACTED_FILE, VALUE_BLOCK_AVG_SIZE,
-        VALUE_BLOCK_CACHE_SIZE,
+        AMQF_AVG_SIZE, AMQF_CACHE_SIZE, DATA_THRESHOLD_PER_COMPACTED_FILE, EVICTION_HEADROOM,
+        KEY_BLOCK_AVG_SIZE, KEY_BLOCK_CACHE_SIZE, MAX_ENTRIES_PER_COMPACTED_FILE,
+        VALUE_BLOCK_AVG_SIZE, VALUE_BLOCK_CACHE_SIZE,
     },
     key::{StoreKey, hash_key},
-    lookup_entry::{LookupEntry, LookupValue},
+    lookup_entry::{LazyLookupValue, LookupEntry, LookupValue},
     merge_iter::MergeIter,
-    meta_file::{AmqfCache, MetaFile, MetaLookupResult, StaticSortedFileRange},
+    meta_file::{
+        AmqfCache, IncompatibleFormatError, MetaEntry, MetaFile, MetaLookupResult,
+        StaticSortedFileRange,
+    },
     meta_file_builder::MetaFileBuilder,
     parallel_scheduler::ParallelScheduler,
     sst_filter::SstFilter,
-    static_sorted_file::{BlockCache, SstLookupResult},
+    static_sorted_file::{BlockCache, SstLookupResult, StaticSortedFile, StaticSortedFileIter},
     static_sorted_file_builder::{StaticSortedFileBuilderMeta, write_static_stored_file},
     write_batch::{FinishResult, WriteBatch},
 };
@@ -122,6 +132,8 @@ pub struct TurboPersistence<S: ParallelScheduler> {
     key_block_cache: BlockCache,
     /// A cache for decompressed value blocks.
     value_block_cache: BlockCache,
+    /// The configuration of the database.
+    config: DbConfig,
     /// Statistics for the database.
     #[cfg(feature = "stats")]
     stats: TrackedStats,
@@ -135,6 +147,68 @@ struct Inner {
     current_sequence_number: u32,
 }
 
+impl Inner {
+    /// Returns the last access times of all active SST files.
+    fn access_times(&self) -> Vec<(u32, u64)> {
+        self.meta_files
+            .iter()
+            .flat_map(|meta| meta.entries())
+            .map(|entry| (entry.sequence_number(), entry.last_access()))
+            .collect()
+    }
+}
+
+/// The name of the directory where corrupted files are moved to by recovery.
+const CORRUPTED_DIRECTORY: &str = "CORRUPTED";
+
+/// Defines how corrupted files are handled when opening a database.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RecoveryMode {
+    /// Opening fails when a meta file is corrupted. Corrupted SST files are only detected when
+    /// reading from them.
+    #[default]
+    None,
+    /// Corrupted meta files and missing or truncated SST files are moved aside and their key
+    /// ranges are dropped. Reading these keys will return `None`.
+    Quick,
+    /// Like [`RecoveryMode::Quick`], but also verifies the checksums of all blocks in all SST
+    /// files. This reads the whole database.
+    Full,
+}
+
+/// The configuration of a database.
+#[derive(Clone, Debug, Default)]
+pub struct DbConfig {
+    /// How corrupted files are handled when opening the database.
+    pub recovery: RecoveryMode,
+    /// The block compression of new SST files per key family. Families without an entry use
+    /// [`BlockCompression::Lz4`]. Existing SST files keep their compression until they are
+    /// compacted.
+    pub family_block_compression: Vec<BlockCompression>,
+    /// The maximum total size of all SST files in bytes. When it's exceeded, compaction evicts
+    /// the least recently accessed SST files of the [`DbConfig::evictable_families`]. Evicted
+    /// keys read as `None`. `None` disables eviction.
+    pub max_size: Option<u64>,
+    /// The key families whose entries can be evicted when the database exceeds
+    /// [`DbConfig::max_size`].
+    pub evictable_families: Vec<u32>,
+}
+
+impl DbConfig {
+    /// Returns the block compression for new SST files of a key family.
+    fn block_compression(&self, family: u32) -> BlockCompression {
+        self.family_block_compression
+            .get(family as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns true if entries of the key family can be evicted.
+    fn is_evictable(&self, family: u32) -> bool {
+        self.evictable_families.contains(&family)
+    }
+}
+
 pub struct CommitOptions {
     new_meta_files: Vec<(u32, File)>,
     new_sst_files: Vec<(u32, File)>,
@@ -143,6 +217,9 @@ pub struct CommitOptions {
     blob_seq_numbers_to_delete: Vec<u32>,
     sequence_number: u32,
     keys_written: u64,
+    /// The last access times of new SST files. New SST files without an entry are considered to
+    /// be accessed now.
+    sst_access_times: Vec<(u32, u64)>,
 }
 
 impl<S: ParallelScheduler + Default> TurboPersistence<S> {
@@ -159,10 +236,22 @@ impl<S: ParallelScheduler + Default> TurboPersistence<S> {
     pub fn open_read_only(path: PathBuf) -> Result<Self> {
         Self::open_read_only_with_parallel_scheduler(path, Default::default())
     }
+
+    /// Open a TurboPersistence database at the given path and recover from corrupted files. See
+    /// [`RecoveryMode`] for details.
+    pub fn open_with_recovery(path: PathBuf, recovery: RecoveryMode) -> Result<Self> {
+        Self::open_with_recovery_and_parallel_scheduler(path, recovery, Default::default())
+    }
+
+    /// Open a TurboPersistence database at the given path with a custom configuration. See
+    /// [`DbConfig`] for details.
+    pub fn open_with_config(path: PathBuf, config: DbConfig) -> Result<Self> {
+        Self::open_with_config_and_parallel_scheduler(path, config, Default::default())
+    }
 }
 
 impl<S: ParallelScheduler> TurboPersistence<S> {
-    fn new(path: PathBuf, read_only: bool, parallel_scheduler: S) -> Self {
+    fn new(path: PathBuf, read_only: bool, config: DbConfig, parallel_scheduler: S) -> Self {
         Self {
             parallel_scheduler,
             path,
@@ -193,6 +282,7 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                 Default::default(),
                 Default::default(),
             ),
+            config,
             #[cfg(feature = "stats")]
             stats: TrackedStats::default(),
         }
@@ -203,8 +293,37 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
     /// properly. Cleanup only requires to read a few bytes from a few files and to delete
     /// files, so it's fast.
     pub fn open_with_parallel_scheduler(path: PathBuf, parallel_scheduler: S) -> Result<Self> {
-        let mut db = Self::new(path, false, parallel_scheduler);
-        db.open_directory(false)?;
+        Self::open_with_recovery_and_parallel_scheduler(
+            path,
+            RecoveryMode::None,
+            parallel_scheduler,
+        )
+    }
+
+    /// Open a TurboPersistence database at the given path and recover from corrupted files. See
+    /// [`RecoveryMode`] for details.
+    pub fn open_with_recovery_and_parallel_scheduler(
+        path: PathBuf,
+        recovery: RecoveryMode,
+        parallel_scheduler: S,
+    ) -> Result<Self> {
+        let config = DbConfig {
+            recovery,
+            ..Default::default()
+        };
+        Self::open_with_config_and_parallel_scheduler(path, config, parallel_scheduler)
+    }
+
+    /// Open a TurboPersistence database at the given path with a custom configuration. See
+    /// [`DbConfig`] for details.
+    pub fn open_with_config_and_parallel_scheduler(
+        path: PathBuf,
+        config: DbConfig,
+        parallel_scheduler: S,
+    ) -> Result<Self> {
+        let recovery = config.recovery;
+        let mut db = Self::new(path, false, config, parallel_scheduler);
+        db.open_directory(false, recovery)?;
         Ok(db)
     }
 
@@ -214,17 +333,17 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
         path: PathBuf,
         parallel_scheduler: S,
     ) -> Result<Self> {
-        let mut db = Self::new(path, true, parallel_scheduler);
-        db.open_directory(false)?;
+        let mut db = Self::new(path, true, DbConfig::default(), parallel_scheduler);
+        db.open_directory(false, RecoveryMode::None)?;
         Ok(db)
     }
 
     /// Performs the initial check on the database directory.
-    fn open_directory(&mut self, read_only: bool) -> Result<()> {
+    fn open_directory(&mut self, read_only: bool, recovery: RecoveryMode) -> Result<()> {
         match fs::read_dir(&self.path) {
             Ok(entries) => {
                 if !self
-                    .load_directory(entries, read_only)
+                    .load_directory(entries, read_only, recovery)
                     .context("Loading persistence directory failed")?
                 {
                     if read_only {
@@ -262,8 +381,15 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
     }
 
     /// Loads an existing database directory and performs cleanup if necessary.
-    fn load_directory(&mut self, entries: ReadDir, read_only: bool) -> Result<bool> {
+    fn load_directory(
+        &mut self,
+        entries: ReadDir,
+        read_only: bool,
+        recovery: RecoveryMode,
+    ) -> Result<bool> {
         let mut meta_files = Vec::new();
+        let mut sst_files = Vec::new();
+        let mut blob_files = Vec::new();
         let mut current_file = match File::open(self.path.join("CURRENT")) {
             Ok(file) => file,
             Err(e) => {
@@ -323,8 +449,13 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                                 fs::remove_file(&path)?;
                             }
                         }
-                        "blob" | "sst" => {
-                            // ignore blobs and sst, they are read when needed
+                        "sst" => {
+                            // SST files are read when needed, this is only used by recovery
+                            sst_files.push(seq);
+                        }
+                        "blob" => {
+                            // Blob files are read when needed, this is only used by recovery
+                            blob_files.push(seq);
                         }
                         _ => {
                             if !path
@@ -344,6 +475,12 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                     Some("LOG") => {
                         // Ignored, write-only
                     }
+                    Some(ACCESS_FILE) => {
+                        // Read below
+                    }
+                    Some(CORRUPTED_DIRECTORY) => {
+                        // Files moved aside by recovery
+                    }
                     _ => {
                         if !path
                             .file_name()
@@ -358,24 +495,289 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
 
         meta_files.retain(|seq| !deleted_files.contains(seq));
         meta_files.sort_unstable();
-        let mut meta_files = self
+        sst_files.retain(|seq| !deleted_files.contains(seq));
+        blob_files.retain(|seq| !deleted_files.contains(seq));
+        let meta_files = self
             .parallel_scheduler
-            .parallel_map_collect::<_, _, Result<Vec<MetaFile>>>(&meta_files, |&seq| {
-                let meta_file = MetaFile::open(&self.path, seq)?;
-                Ok(meta_file)
-            })?;
+            .parallel_map_collect::<_, _, Vec<_>>(&meta_files, |&seq| {
+                (seq, MetaFile::open(&self.path, seq))
+            });
+        if let Some(error) = meta_files.iter().find_map(|(_, result)| {
+            result
+                .as_ref()
+                .err()?
+                .downcast_ref::<IncompatibleFormatError>()
+                .copied()
+        }) {
+            if read_only {
+                bail!("Unable to open a database written in a different format: {error}");
+            }
+            // The file format changed, so the whole database is discarded instead of treating
+            // every file as corrupted.
+            drop(meta_files);
+            self.discard_incompatible_database(&error)?;
+            return Ok(false);
+        }
+        let mut corrupted_meta_files = Vec::new();
+        let mut meta_files = meta_files
+            .into_iter()
+            .filter_map(|(seq, result)| match result {
+                Ok(meta_file) => Some(Ok(meta_file)),
+                Err(err) if recovery != RecoveryMode::None && !read_only => {
+                    corrupted_meta_files.push((seq, err));
+                    None
+                }
+                Err(err) => Some(Err(err)),
+            })
+            .collect::<Result<Vec<_>>>()?;
 
         let mut sst_filter = SstFilter::new();
         for meta_file in meta_files.iter_mut().rev() {
             sst_filter.apply_filter(meta_file);
         }
 
+        let access_times = read_access_times(&self.path);
+        let now = access_times::now();
+        for entry in meta_files.iter().flat_map(|meta| meta.entries()) {
+            let last_access = access_times.get(&entry.sequence_number()).copied();
+            entry.set_last_access(last_access.unwrap_or(now));
+        }
+
         let inner = self.inner.get_mut();
         inner.meta_files = meta_files;
         inner.current_sequence_number = current;
+
+        if recovery != RecoveryMode::None && !read_only {
+            self.recover(corrupted_meta_files, sst_files, blob_files, recovery)
+                .context("Recovery of corrupted files failed")?;
+        }
         Ok(true)
     }
 
+    /// Deletes all files of a database that was written in an incompatible version of the file
+    /// format. The database is empty afterwards.
+    fn discard_incompatible_database(&mut self, error: &IncompatibleFormatError) -> Result<()> {
+        tracing::info!("Discarding database: {error}");
+        for entry in fs::read_dir(&self.path)? {
+            let path = entry?.path();
+            let is_database_file = match path.extension().and_then(|s| s.to_str()) {
+                Some("meta" | "sst" | "blob" | "del") => true,
+                Some(_) => false,
+                None => matches!(
+                    path.file_stem().and_then(|s| s.to_str()),
+                    Some("CURRENT") | Some(ACCESS_FILE)
+                ),
+            };
+            if is_database_file {
+                fs::remove_file(&path)?;
+            }
+        }
+        let mut log = self.open_log()?;
+        writeln!(log, "Time {}", Timestamp::now())?;
+        writeln!(log, "DISCARDED {error}")?;
+        Ok(())
+    }
+
+    /// Returns the sequence numbers of the blob files that are referenced by the values of an SST
+    /// file.
+    fn blob_references(
+        meta: &MetaFile,
+        entry: &MetaEntry,
+        key_block_cache: &BlockCache,
+        value_block_cache: &BlockCache,
+    ) -> Result<Vec<u32>> {
+        let mut blob_files = Vec::new();
+        for lookup_entry in entry.sst(meta)?.iter(key_block_cache, value_block_cache)? {
+            if let LazyLookupValue::Eager(LookupValue::Blob { sequence_number }) =
+                lookup_entry?.value
+            {
+                blob_files.push(sequence_number);
+            }
+        }
+        Ok(blob_files)
+    }
+
+    /// Checks all SST files for corruption. Corrupted SST and meta files are moved into the
+    /// `CORRUPTED` directory. Their key ranges are dropped, including all older SST files with
+    /// overlapping key ranges, since these might contain outdated values. For a corrupted meta
+    /// file the key ranges are unknown, so all older SST files are dropped.
+    ///
+    /// `sst_files` and `blob_files` are all committed files in the directory. SST files of a
+    /// corrupted meta file and blob files that are no longer referenced by any SST file are
+    /// deleted.
+    fn recover(
+        &mut self,
+        corrupted_meta_files: Vec<(u32, anyhow::Error)>,
+        sst_files: Vec<u32>,
+        blob_files: Vec<u32>,
+        recovery: RecoveryMode,
+    ) -> Result<()> {
+        let _span = tracing::info_span!("recover database").entered();
+        let inner = self.inner.get_mut();
+        let meta_files = &inner.meta_files;
+        let ssts = meta_files
+            .iter()
+            .enumerate()
+            .flat_map(|(meta_index, meta)| {
+                (0..meta.entries().len()).map(move |entry_index| (meta_index, entry_index))
+            })
+            .collect::<Vec<_>>();
+        let sst_errors = self
+            .parallel_scheduler
+            .parallel_map_collect::<_, _, Vec<_>>(&ssts, |&(meta_index, entry_index)| {
+                let meta = &meta_files[meta_index];
+                let entry = &meta.entries()[entry_index];
+                let result = entry.sst(meta).and_then(|sst| {
+                    if recovery == RecoveryMode::Full {
+                        sst.verify_checksums()
+                    } else {
+                        Ok(())
+                    }
+                });
+                result.err()
+            });
+
+        let newest_corrupted_meta_file = corrupted_meta_files.iter().map(|(seq, _)| *seq).max();
+        let mut dropped_ranges: Vec<StaticSortedFileRange> = Vec::new();
+        let mut corrupted_sst_files = Vec::new();
+        let mut dropped_sst_files = Vec::new();
+        // Newer files override older files, so dropping a key range also requires dropping the
+        // overlapping key ranges of all older files.
+        for (&(meta_index, entry_index), error) in ssts.iter().zip(sst_errors).rev() {
+            let meta = &meta_files[meta_index];
+            let entry = &meta.entries()[entry_index];
+            let range = entry.range();
+            let shadowed = newest_corrupted_meta_file
+                .is_some_and(|seq| meta.sequence_number() < seq)
+                || dropped_ranges
+                    .iter()
+                    .any(|dropped| dropped.overlaps(&range));
+            if let Some(error) = error {
+                corrupted_sst_files.push((entry.sequence_number(), error));
+                dropped_ranges.push(range);
+            } else if shadowed {
+                dropped_sst_files.push(entry.sequence_number());
+                dropped_ranges.push(range);
+            }
+        }
+
+        if corrupted_meta_files.is_empty() && corrupted_sst_files.is_empty() {
+            return Ok(());
+        }
+
+        // SST files of a corrupted meta file are not referenced anymore
+        let live_sst_files = meta_files
+            .iter()
+            .flat_map(|meta| meta.entries())
+            .map(|entry| entry.sequence_number())
+            .collect::<HashSet<_>>();
+        let orphaned_sst_files = sst_files
+            .into_iter()
+            .filter(|seq| !live_sst_files.contains(seq))
+            .collect::<Vec<_>>();
+
+        // The blob files referenced by dropped or corrupted SST files are unknown, so all
+        // remaining SST files are read to find the blob files that are still referenced. This is
+        // expensive, but corruption is rare.
+        let remaining_ssts = ssts
+            .iter()
+            .filter(|&&(meta_index, entry_index)| {
+                let seq = meta_files[meta_index].entries()[entry_index].sequence_number();
+                !dropped_sst_files.contains(&seq)
+                    && !corrupted_sst_files
+                        .iter()
+                        .any(|(corrupted, _)| *corrupted == seq)
+            })
+            .collect::<Vec<_>>();
+        let blob_references = self
+            .parallel_scheduler
+            .parallel_map_collect::<_, _, Result<Vec<_>>>(
+                &remaining_ssts,
+                |&&(meta_index, entry_index)| {
+                    let meta = &meta_files[meta_index];
+                    Self::blob_references(
+                        meta,
+                        &meta.entries()[entry_index],
+                        &self.key_block_cache,
+                        &self.value_block_cache,
+                    )
+                },
+            );
+        let orphaned_blob_files = match blob_references {
+            Ok(blob_references) => {
+                let referenced = blob_references
+                    .into_iter()
+                    .flatten()
+                    .collect::<HashSet<_>>();
+                blob_files
+                    .into_iter()
+                    .filter(|seq| !referenced.contains(seq))
+                    .collect::<Vec<_>>()
+            }
+            Err(err) => {
+                // Keeping unreferenced blob files only wastes disk space
+                tracing::warn!("Unable to find unreferenced blob files: {err:#}");
+                Vec::new()
+            }
+        };
+
+        if !corrupted_sst_files.is_empty()
+            || !dropped_sst_files.is_empty()
+            || !orphaned_sst_files.is_empty()
+            || !orphaned_blob_files.is_empty()
+        {
+            // Write a meta file that marks the dropped SST files as obsolete. Committing it will
+            // remove them from the database.
+            let seq = inner.current_sequence_number + 1;
+            let mut meta_file_builder = MetaFileBuilder::new(0);
+            for (seq, _) in corrupted_sst_files.iter() {
+                meta_file_builder.add_obsolete_sst_file(*seq);
+            }
+            for seq in dropped_sst_files.iter() {
+                meta_file_builder.add_obsolete_sst_file(*seq);
+            }
+            let meta_file = meta_file_builder.write(&self.path, seq)?;
+            self.commit(CommitOptions {
+                new_meta_files: vec![(seq, meta_file)],
+                new_sst_files: Vec::new(),
+                new_blob_files: Vec::new(),
+                // Corrupted files are moved aside instead
+                sst_seq_numbers_to_delete: dropped_sst_files
+                    .into_iter()
+                    .chain(orphaned_sst_files)
+                    .collect(),
+                blob_seq_numbers_to_delete: orphaned_blob_files,
+                sequence_number: seq,
+                keys_written: 0,
+                sst_access_times: Vec::new(),
+            })?;
+        }
+
+        // Move the corrupted files aside. This happens after the commit, so a crash in between
+        // will detect the same corruption again on the next start.
+        let corrupted_directory = self.path.join(CORRUPTED_DIRECTORY);
+        fs::create_dir_all(&corrupted_directory)?;
+        let mut log = self.open_log()?;
+        writeln!(log, "Time {}", Timestamp::now())?;
+        let corrupted_files = corrupted_meta_files
+            .iter()
+            .map(|(seq, error)| (format!("{seq:08}.meta"), error))
+            .chain(
+                corrupted_sst_files
+                    .iter()
+                    .map(|(seq, error)| (format!("{seq:08}.sst"), error)),
+            );
+        for (filename, error) in corrupted_files {
+            tracing::warn!("Recovered from corrupted database file {filename}: {error:#}");
+            writeln!(log, "{filename} CORRUPTED {error:#}")?;
+            let path = self.path.join(&filename);
+            if fs::exists(&path)? {
+                fs::rename(&path, corrupted_directory.join(&filename))?;
+            }
+        }
+        Ok(())
+    }
+
     /// Reads and decompresses a blob file. This is not backed by any cache.
     #[tracing::instrument(level = "info", name = "reading database blob", skip_all)]
     fn read_blob(&self, seq: u32) -> Result<ArcSlice<u8>> {
@@ -391,8 +793,18 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
         mmap.advise(memmap2::Advice::Unmergeable)?;
         let mut compressed = &mmap[..];
         let uncompressed_length = compressed.read_u32::<BE>()?;
+        let expected_checksum = compressed.read_u32::<BE>()?;
+        if checksum(compressed) != expected_checksum {
+            bail!("Blob file {seq:08}.blob is corrupted (checksum mismatch)");
+        }
 
-        let buffer = decompress_into_arc(uncompressed_length, compressed, None, true)?;
+        let buffer = decompress_into_arc(
+            BlockCompression::Lz4,
+            uncompressed_length,
+            compressed,
+            DecompressionDictionary::None,
+            true,
+        )?;
         Ok(ArcSlice::from(buffer))
     }
 
@@ -426,6 +838,7 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
             self.path.clone(),
             current,
             self.parallel_scheduler.clone(),
+            array::from_fn(|family| self.config.block_compression(family as u32)),
         ))
     }
 
@@ -465,6 +878,7 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
             blob_seq_numbers_to_delete: vec![],
             sequence_number,
             keys_written,
+            sst_access_times: Vec::new(),
         })?;
         self.active_write_operation.store(false, Ordering::Release);
         Ok(())
@@ -482,6 +896,7 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
             mut blob_seq_numbers_to_delete,
             sequence_number: mut seq,
             keys_written,
+            sst_access_times,
         }: CommitOptions,
     ) -> Result<(), anyhow::Error> {
         let time = Timestamp::now();
@@ -501,6 +916,13 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
             sst_filter.apply_filter(meta_file);
         }
 
+        let sst_access_times = sst_access_times.into_iter().collect::<FxHashMap<_, _>>();
+        let now = access_times::now();
+        for entry in new_meta_files.iter().flat_map(|meta| meta.entries()) {
+            let last_access = sst_access_times.get(&entry.sequence_number()).copied();
+            entry.set_last_access(last_access.unwrap_or(now));
+        }
+
         self.parallel_scheduler.block_in_place(|| {
             for (_, file) in new_sst_files.iter() {
                 file.sync_all()?;
@@ -535,6 +957,7 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
 
         let has_delete_file;
         let mut meta_seq_numbers_to_delete = Vec::new();
+        let access_times;
 
         {
             let mut inner = self.inner.write();
@@ -560,6 +983,7 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                 seq += 1;
             }
             inner.current_sequence_number = seq;
+            access_times = inner.access_times();
         }
 
         self.parallel_scheduler.block_in_place(|| {
@@ -606,6 +1030,10 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                 fs::remove_file(self.path.join(format!("{seq:08}.blob")))?;
             }
 
+            if let Err(err) = write_access_times(&self.path, access_times) {
+                tracing::warn!("Failed to write database access times: {err:#}");
+            }
+
             {
                 let mut log = self.open_log()?;
                 writeln!(log, "Time {time}")?;
@@ -682,12 +1110,27 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
             );
         }
 
+        let result = self.compact_exclusive(compact_config);
+        self.active_write_operation.store(false, Ordering::Release);
+        result
+    }
+
+    /// Runs a (partial) compaction while holding the write lock (`active_write_operation`).
+    fn compact_exclusive(&self, compact_config: &CompactConfig) -> Result<bool> {
+        let eviction = self.evict().context("Failed to evict from database")?;
+        let has_evicted = eviction.is_some();
+        if let Some(eviction) = eviction {
+            self.commit(eviction)
+                .context("Failed to commit the database eviction")?;
+        }
+
         let mut sequence_number;
         let mut new_meta_files = Vec::new();
         let mut new_sst_files = Vec::new();
         let mut sst_seq_numbers_to_delete = Vec::new();
         let mut blob_seq_numbers_to_delete = Vec::new();
         let mut keys_written = 0;
+        let mut sst_access_times = Vec::new();
 
         {
             let inner = self.inner.read();
@@ -700,6 +1143,7 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                 &mut sst_seq_numbers_to_delete,
                 &mut blob_seq_numbers_to_delete,
                 &mut keys_written,
+                &mut sst_access_times,
                 compact_config,
             )
             .context("Failed to compact database")?;
@@ -715,13 +1159,138 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                 blob_seq_numbers_to_delete,
                 sequence_number: *sequence_number.get_mut(),
                 keys_written,
+                sst_access_times,
             })
             .context("Failed to commit the database compaction")?;
         }
 
-        self.active_write_operation.store(false, Ordering::Release);
+        Ok(has_evicted || has_changes)
+    }
+
+    /// Evicts the least recently accessed SST files of the evictable key families when the total
+    /// size of all SST files exceeds [`DbConfig::max_size`]. Evicting an SST file also evicts all
+    /// older SST files with overlapping key ranges, since these might contain outdated values for
+    /// the evicted keys. Returns the commit that removes the evicted SST files and the blob files
+    /// referenced by them.
+    fn evict(&self) -> Result<Option<CommitOptions>> {
+        let Some(max_size) = self.config.max_size else {
+            return Ok(None);
+        };
+        let inner = self.inner.read();
+        // Ordered from oldest to newest
+        let sst_metas = inner
+            .meta_files
+            .iter()
+            .flat_map(|meta| meta.entries().iter().map(move |entry| (meta, entry)))
+            .collect::<Vec<_>>();
+        let ssts = sst_metas
+            .iter()
+            .map(|&(_, entry)| entry)
+            .collect::<Vec<_>>();
+        let total_size = ssts.iter().map(|entry| entry.size()).sum::<u64>();
+        if total_size <= max_size {
+            return Ok(None);
+        }
+        let _span = tracing::info_span!("evict from database").entered();
+
+        // Evict a bit more than necessary to avoid evicting again on the next compaction
+        let target_size = max_size - max_size / 100 * EVICTION_HEADROOM;
+        let mut candidates = (0..ssts.len())
+            .filter(|&index| self.config.is_evictable(ssts[index].range().family))
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|&index| (ssts[index].last_access(), index));
+
+        let mut evicted = vec![false; ssts.len()];
+        let mut size = total_size;
+        for candidate in candidates {
+            if size <= target_size {
+                break;
+            }
+            if evicted[candidate] {
+                continue;
+            }
+            evicted[candidate] = true;
+            size -= ssts[candidate].size();
+            let mut evicted_ranges = vec![ssts[candidate].range()];
+            // Older SST files that were already evicted have already evicted their overlapping
+            // older SST files.
+            for index in (0..candidate).rev() {
+                let range = ssts[index].range();
+                if !evicted[index]
+                    && evicted_ranges
+                        .iter()
+                        .any(|evicted| evicted.overlaps(&range))
+                {
+                    evicted[index] = true;
+                    size -= ssts[index].size();
+                    evicted_ranges.push(range);
+                }
+            }
+        }
+
+        let evicted_ssts = sst_metas
+            .iter()
+            .zip(evicted)
+            .filter(|(_, evicted)| *evicted)
+            .map(|(&sst, _)| sst)
+            .collect::<Vec<_>>();
+        if evicted_ssts.is_empty() {
+            return Ok(None);
+        }
+        let evicted_sst_files = evicted_ssts
+            .iter()
+            .map(|(_, entry)| entry.sequence_number())
+            .collect::<Vec<_>>();
+
+        // A blob file is only referenced by a single SST file, so the blob files of evicted SST
+        // files are unreferenced after the eviction.
+        let evicted_blob_files = self
+            .parallel_scheduler
+            .parallel_map_collect::<_, _, Result<Vec<_>>>(&evicted_ssts, |&(meta, entry)| {
+                Self::blob_references(meta, entry, &self.key_block_cache, &self.value_block_cache)
+            });
+        let evicted_blob_files = match evicted_blob_files {
+            Ok(blob_references) => blob_references.into_iter().flatten().collect::<Vec<_>>(),
+            Err(err) => {
+                // Keeping unreferenced blob files only wastes disk space
+                tracing::warn!("Unable to find blob files of evicted SST files: {err:#}");
+                Vec::new()
+            }
+        };
 
-        Ok(has_changes)
+        // Write a meta file that marks the evicted SST files as obsolete. Committing it will
+        // remove them from the database.
+        let seq = inner.current_sequence_number + 1;
+        let mut meta_file_builder = MetaFileBuilder::new(0);
+        for &sst_seq in evicted_sst_files.iter() {
+            meta_file_builder.add_obsolete_sst_file(sst_seq);
+        }
+        let meta_file = self
+            .parallel_scheduler
+            .block_in_place(|| meta_file_builder.write(&self.path, seq))?;
+
+        let mut log = self.open_log()?;
+        writeln!(
+            log,
+            "Eviction of {} SST files and {} blob files ({} MiB, database size {} MiB exceeds {} \
+             MiB)",
+            evicted_sst_files.len(),
+            evicted_blob_files.len(),
+            (total_size - size) / 1024 / 1024,
+            total_size / 1024 / 1024,
+            max_size / 1024 / 1024
+        )?;
+
+        Ok(Some(CommitOptions {
+            new_meta_files: vec![(seq, meta_file)],
+            new_sst_files: Vec::new(),
+            new_blob_files: Vec::new(),
+            sst_seq_numbers_to_delete: evicted_sst_files,
+            blob_seq_numbers_to_delete: evicted_blob_files,
+            sequence_number: seq,
+            keys_written: 0,
+            sst_access_times: Vec::new(),
+        }))
     }
 
     /// Internal function to perform a compaction.
@@ -734,6 +1303,7 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
         sst_seq_numbers_to_delete: &mut Vec<u32>,
         blob_seq_numbers_to_delete: &mut Vec<u32>,
         keys_written: &mut u64,
+        sst_access_times: &mut Vec<(u32, u64)>,
         compact_config: &CompactConfig,
     ) -> Result<()> {
         if meta_files.is_empty() {
@@ -801,6 +1371,7 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
             sst_seq_numbers_to_delete: Vec<u32>,
             blob_seq_numbers_to_delete: Vec<u32>,
             keys_written: u64,
+            sst_access_times: Vec<(u32, u64)>,
         }
 
         let mut compact_config = compact_config.clone();
@@ -832,6 +1403,7 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                             sst_seq_numbers_to_delete: Vec::new(),
                             blob_seq_numbers_to_delete: Vec::new(),
                             keys_written: 0,
+                            sst_access_times: Vec::new(),
                         });
                     }
 
@@ -860,14 +1432,28 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                         anyhow::Ok(())
                     })?;
 
+                    // A single file can be moved to the new meta file, unless it's in the LZ4-only
+                    // format, which can't be referenced from a meta file of the current format
+                    let can_move = |indices: &[usize]| {
+                        let [index] = indices else {
+                            return false;
+                        };
+                        let sst = &ssts_with_ranges[*index];
+                        !meta_files[sst.meta_index]
+                            .entry(sst.index_in_meta)
+                            .is_lz4_only_format()
+                    };
+
                     // Later we will remove the merged files
                     let sst_seq_numbers_to_delete = merge_jobs
                         .iter()
-                        .filter(|l| l.len() > 1)
+                        .filter(|l| !can_move(l))
                         .flat_map(|l| l.iter().copied())
                         .map(|index| ssts_with_ranges[index].seq)
                         .collect::<Vec<_>>();
 
+                    let compression = self.config.block_compression(family);
+
                     // Merge SST files
                     let span = tracing::trace_span!("merge files");
                     enum PartialMergeResult<'l> {
@@ -875,17 +1461,19 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                             new_sst_files: Vec<(u32, File, StaticSortedFileBuilderMeta<'static>)>,
                             blob_seq_numbers_to_delete: Vec<u32>,
                             keys_written: u64,
+                            last_access: u64,
                         },
                         Move {
                             seq: u32,
                             meta: StaticSortedFileBuilderMeta<'l>,
+                            last_access: u64,
                         },
                     }
                     let merge_result = self
                         .parallel_scheduler
                         .parallel_map_collect_owned::<_, _, Result<Vec<_>>>(merge_jobs, |indices| {
                             let _span = span.clone().entered();
-                            if indices.len() == 1 {
+                            if can_move(&indices) {
                                 // If we only have one file, we can just move it
                                 let index = indices[0];
                                 let meta_index = ssts_with_ranges[index].meta_index;
@@ -899,6 +1487,8 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                                     amqf,
                                     key_compression_dictionary_length: entry
                                         .key_compression_dictionary_length(),
+                                    value_compression_dictionary_length: entry
+                                        .value_compression_dictionary_length(),
                                     block_count: entry.block_count(),
                                     size: entry.size(),
                                     entries: 0,
@@ -906,6 +1496,7 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                                 return Ok(PartialMergeResult::Move {
                                     seq: entry.sequence_number(),
                                     meta,
+                                    last_access: entry.last_access(),
                                 });
                             }
 
@@ -915,6 +1506,7 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                                 total_key_size: usize,
                                 path: &Path,
                                 seq: u32,
+                                compression: BlockCompression,
                             ) -> Result<(u32, File, StaticSortedFileBuilderMeta<'static>)>
                             {
                                 let _span = tracing::trace_span!("write merged sst file").entered();
@@ -923,6 +1515,7 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                                         entries,
                                         total_key_size,
                                         &path.join(format!("{seq:08}.sst")),
+                                        compression,
                                     )
                                 })?;
                                 Ok((seq, file, meta))
@@ -930,6 +1523,18 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
 
                             let mut new_sst_files = Vec::new();
 
+                            // The merged files contain the keys of all source files, so they
+                            // are as recently accessed as the most recently accessed source file
+                            let last_access = indices
+                                .iter()
+                                .map(|&index| {
+                                    let meta_index = ssts_with_ranges[index].meta_index;
+                                    let index_in_meta = ssts_with_ranges[index].index_in_meta;
+                                    meta_files[meta_index].entry(index_in_meta).last_access()
+                                })
+                                .max()
+                                .unwrap_or_default();
+
                             // Iterate all SST files
                             let iters = indices
                                 .iter()
@@ -991,6 +1596,7 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                                                     selected_total_key_size,
                                                     path,
                                                     seq,
+                                                    compression,
                                                 )?);
 
                                                 entries.clear();
@@ -1022,6 +1628,7 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                                     total_key_size,
                                     path,
                                     seq,
+                                    compression,
                                 )?);
                             } else
                             // If we have two sets of entries left, merge them and
@@ -1045,6 +1652,7 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                                     last_entries_total_key_size / 2,
                                     path,
                                     seq1,
+                                    compression,
                                 )?);
 
                                 keys_written += part2.len() as u64;
@@ -1054,12 +1662,14 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                                     last_entries_total_key_size / 2,
                                     path,
                                     seq2,
+                                    compression,
                                 )?);
                             }
                             Ok(PartialMergeResult::Merged {
                                 new_sst_files,
                                 blob_seq_numbers_to_delete,
                                 keys_written,
+                                last_access,
                             })
                         })
                         .with_context(|| {
@@ -1073,6 +1683,7 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                                 new_sst_files,
                                 blob_seq_numbers_to_delete,
                                 keys_written: _,
+                                last_access: _,
                             } = r
                             {
                                 (new_sst_files.len(), blob_seq_numbers_to_delete.len())
@@ -1091,23 +1702,31 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                     let mut meta_file_builder = MetaFileBuilder::new(family);
 
                     let mut keys_written = 0;
+                    let mut sst_access_times = Vec::new();
                     for result in merge_result {
                         match result {
                             PartialMergeResult::Merged {
                                 new_sst_files: merged_new_sst_files,
                                 blob_seq_numbers_to_delete: merged_blob_seq_numbers_to_delete,
                                 keys_written: merged_keys_written,
+                                last_access,
                             } => {
                                 for (seq, file, meta) in merged_new_sst_files {
                                     meta_file_builder.add(seq, meta);
                                     new_sst_files.push((seq, file));
+                                    sst_access_times.push((seq, last_access));
                                 }
                                 blob_seq_numbers_to_delete
                                     .extend(merged_blob_seq_numbers_to_delete);
                                 keys_written += merged_keys_written;
                             }
-                            PartialMergeResult::Move { seq, meta } => {
+                            PartialMergeResult::Move {
+                                seq,
+                                meta,
+                                last_access,
+                            } => {
                                 meta_file_builder.add(seq, meta);
+                                sst_access_times.push((seq, last_access));
                             }
                         }
                     }
@@ -1129,6 +1748,7 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                         sst_seq_numbers_to_delete,
                         blob_seq_numbers_to_delete,
                         keys_written,
+                        sst_access_times,
                     })
                 },
             )?;
@@ -1139,6 +1759,7 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
             sst_seq_numbers_to_delete: mut inner_sst_seq_numbers_to_delete,
             blob_seq_numbers_to_delete: mut inner_blob_seq_numbers_to_delete,
             keys_written: inner_keys_written,
+            sst_access_times: mut inner_sst_access_times,
         } in result
         {
             new_meta_files.extend(inner_new_meta_file);
@@ -1146,6 +1767,7 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
             sst_seq_numbers_to_delete.append(&mut inner_sst_seq_numbers_to_delete);
             blob_seq_numbers_to_delete.append(&mut inner_blob_seq_numbers_to_delete);
             *keys_written += inner_keys_written;
+            sst_access_times.append(&mut inner_sst_access_times);
         }
 
         Ok(())
@@ -1208,6 +1830,232 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
         Ok(None)
     }
 
+    /// Starts a read over all entries of a key family. The returned [`FamilyReader`] is a snapshot
+    /// of the SST files at the time of the call. It doesn't hold a lock, so reads, commits and
+    /// compactions continue to work while it's in use. Blob files that are evicted in the meantime
+    /// can't be read by it anymore.
+    pub fn read_family(&self, family: usize) -> Result<FamilyReader<'_, S>> {
+        let family = family as u32;
+        // Meta files and their entries are ordered from oldest to newest. This order is preserved
+        // by the merge iterator for equal keys, so the last occurrence of a key wins.
+        let inner = self.inner.read();
+        let ssts = inner
+            .meta_files
+            .iter()
+            .filter(|meta| meta.family() == family)
+            .flat_map(|meta| meta.entries().iter().map(move |entry| (meta, entry)))
+            .map(|(meta, entry)| entry.sst(meta).cloned())
+            .collect::<Result<Vec<_>>>()?;
+        drop(inner);
+        Ok(FamilyReader { db: self, ssts })
+    }
+
+    /// Reads and decodes every block and AMQF filter of all active SST files and all referenced
+    /// blob files. Checks that entries are sorted, are in the hash range of their SST file and
+    /// are contained in the AMQF filter. Returns an error describing the first corruption found.
+    pub fn verify(&self) -> Result<()> {
+        let inner = self.inner.read();
+        self.parallel_scheduler
+            .try_parallel_for_each(&inner.meta_files, |meta| {
+                for entry in meta.entries() {
+                    self.verify_sst(meta, entry).with_context(|| {
+                        format!(
+                            "Verification of {:08}.sst referenced from {:08}.meta failed",
+                            entry.sequence_number(),
+                            meta.sequence_number()
+                        )
+                    })?;
+                }
+                anyhow::Ok(())
+            })
+    }
+
+    /// Verifies a single SST file. See [`TurboPersistence::verify`].
+    fn verify_sst(&self, meta: &MetaFile, entry: &MetaEntry) -> Result<()> {
+        let amqf = entry.deserialize_amqf(meta)?;
+        let sst = entry.sst(meta)?;
+        let mut last: Option<(u64, ArcSlice<u8>)> = None;
+        for item in sst.iter(&self.key_block_cache, &self.value_block_cache)? {
+            let LookupEntry { hash, key, value } = item?;
+            if hash_key(&&*key) != hash {
+                bail!("Key hash mismatch for key {key:?}");
+            }
+            if hash < entry.min_hash() || hash > entry.max_hash() {
+                bail!(
+                    "Key hash {hash:016x} out of range {:016x} - {:016x}",
+                    entry.min_hash(),
+                    entry.max_hash()
+                );
+            }
+            if !amqf.contains_fingerprint(hash) {
+                bail!("Key hash {hash:016x} not found in AMQF filter");
+            }
+            if let Some((last_hash, last_key)) = &last
+                && (*last_hash, &**last_key) >= (hash, &*key)
+            {
+                bail!("Entries are not sorted at key {key:?}");
+            }
+            match value {
+                LazyLookupValue::Eager(LookupValue::Blob { sequence_number }) => {
+                    self.read_blob(sequence_number).with_context(|| {
+                        format!("Unable to read blob file {sequence_number:08}.blob")
+                    })?;
+                }
+                LazyLookupValue::Medium {
+                    compression,
+                    uncompressed_size,
+                    block,
+                } => {
+                    decompress_into_arc(
+                        compression,
+                        uncompressed_size,
+                        block,
+                        DecompressionDictionary::None,
+                        true,
+                    )?;
+                }
+                LazyLookupValue::Eager(LookupValue::Slice { .. } | LookupValue::Deleted) => {}
+            }
+            last = Some((hash, key));
+        }
+        Ok(())
+    }
+
+    /// Creates a consistent point-in-time snapshot of the database in `dest`, which must not exist
+    /// or be empty. Committed files are immutable, so the live meta, SST and blob files are hard
+    /// linked into `dest`, falling back to copying when hard links are not supported. This holds
+    /// a read lock on the current set of files, so concurrent commits and compactions wait until
+    /// the snapshot is complete.
+    ///
+    /// The snapshot is opened in read-only mode afterwards to verify that it is a valid database.
+    pub fn snapshot(&self, dest: &Path) -> Result<()> {
+        let _span = tracing::info_span!("snapshot database").entered();
+        fs::create_dir_all(dest)?;
+        if fs::read_dir(dest)?.next().is_some() {
+            bail!("Snapshot destination {} is not empty", dest.display());
+        }
+
+        let inner = self.inner.read();
+        let current = inner.current_sequence_number;
+        let mut files = Vec::new();
+        for meta in inner.meta_files.iter() {
+            files.push(format!("{:08}.meta", meta.sequence_number()));
+            for entry in meta.entries() {
+                files.push(format!("{:08}.sst", entry.sequence_number()));
+            }
+        }
+        let blob_files = self.live_blob_files(current)?;
+
+        let link_or_copy = |filename: &String| -> Result<bool> {
+            let src = self.path.join(filename);
+            let dest = dest.join(filename);
+            match fs::hard_link(&src, &dest) {
+                Ok(()) => Ok(true),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+                Err(_) => match fs::copy(&src, &dest) {
+                    Ok(_) => {
+                        File::open(&dest)?.sync_all()?;
+                        Ok(true)
+                    }
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+                    Err(err) => Err(err.into()),
+                },
+            }
+        };
+        self.parallel_scheduler
+            .try_parallel_for_each(&files, |filename| {
+                if !link_or_copy(filename)? {
+                    bail!("Live file {filename} is missing");
+                }
+                anyhow::Ok(())
+            })
+            .context("Unable to snapshot database files")?;
+        // Blob files might be unused and deleted by a commit that finished concurrently. These
+        // are not referenced by the current state and can be skipped.
+        self.parallel_scheduler
+            .try_parallel_for_each(&blob_files, |filename| {
+                link_or_copy(filename)?;
+                anyhow::Ok(())
+            })
+            .context("Unable to snapshot database blob files")?;
+        drop(inner);
+
+        // The CURRENT file is written last, so an incomplete snapshot is never a valid database
+        let mut current_file = File::create(dest.join("CURRENT"))?;
+        current_file.write_u32::<BE>(current)?;
+        current_file.sync_all()?;
+        drop(current_file);
+
+        self.verify_snapshot(dest, current, files.len())
+            .with_context(|| format!("Verification of snapshot {} failed", dest.display()))
+    }
+
+    /// Returns the file names of all blob files that are committed and not deleted.
+    fn live_blob_files(&self, current: u32) -> Result<Vec<String>> {
+        let mut blob_files = Vec::new();
+        let mut deleted_files = HashSet::new();
+        for entry in fs::read_dir(&self.path)? {
+            let path = entry?.path();
+            let Some(ext) = path.extension().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let Some(seq) = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            if seq > current {
+                continue;
+            }
+            match ext {
+                "blob" => blob_files.push(seq),
+                "del" => {
+                    let mut content = &*fs::read(&path)?;
+                    while !content.is_empty() {
+                        deleted_files.insert(content.read_u32::<BE>()?);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(blob_files
+            .into_iter()
+            .filter(|seq| !deleted_files.contains(seq))
+            .map(|seq| format!("{seq:08}.blob"))
+            .collect())
+    }
+
+    /// Opens a snapshot in read-only mode and checks that it contains the expected files.
+    fn verify_snapshot(&self, dest: &Path, current: u32, file_count: usize) -> Result<()> {
+        let snapshot = Self::open_read_only_with_parallel_scheduler(
+            dest.to_path_buf(),
+            self.parallel_scheduler.clone(),
+        )?;
+        let inner = snapshot.inner.read();
+        if inner.current_sequence_number != current {
+            bail!(
+                "Snapshot has sequence number {} instead of {current}",
+                inner.current_sequence_number
+            );
+        }
+        let snapshot_file_count = inner
+            .meta_files
+            .iter()
+            .map(|meta| meta.entries().len() + 1)
+            .sum::<usize>();
+        if snapshot_file_count != file_count {
+            bail!("Snapshot has {snapshot_file_count} live files instead of {file_count}");
+        }
+        for meta in inner.meta_files.iter() {
+            for entry in meta.entries() {
+                entry.sst(meta)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Returns database statistics.
     #[cfg(feature = "stats")]
     pub fn statistics(&self) -> Statistics {
@@ -1242,7 +2090,7 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                     .iter()
                     .map(|entry| {
                         let amqf = entry.raw_amqf(meta_file.amqf_data());
-                        MetaFileEntryInfo {
+                        Ok(MetaFileEntryInfo {
                             sequence_number: entry.sequence_number(),
                             min_hash: entry.min_hash(),
                             max_hash: entry.max_hash(),
@@ -1251,28 +2099,122 @@ impl<S: ParallelScheduler> TurboPersistence<S> {
                             amqf_entries: amqf.len(),
                             key_compression_dictionary_size: entry
                                 .key_compression_dictionary_length(),
+                            value_compression_dictionary_size: entry
+                                .value_compression_dictionary_length(),
                             block_count: entry.block_count(),
-                        }
+                            compression: entry.sst(meta_file)?.compression(),
+                        })
                     })
-                    .collect();
-                MetaFileInfo {
+                    .collect::<Result<_>>()?;
+                Ok(MetaFileInfo {
                     sequence_number: meta_file.sequence_number(),
                     family: meta_file.family(),
                     obsolete_sst_files: meta_file.obsolete_sst_files().to_vec(),
                     entries,
-                }
+                })
             })
-            .collect())
+            .collect::<Result<_>>()?)
     }
 
     /// Shuts down the database. This will print statistics if the `print_stats` feature is enabled.
     pub fn shutdown(&self) -> Result<()> {
         #[cfg(feature = "print_stats")]
         println!("{:#?}", self.statistics());
+        if !self.read_only {
+            // Persist the access times of reads since the last commit
+            let access_times = self.inner.read().access_times();
+            write_access_times(&self.path, access_times)?;
+        }
         Ok(())
     }
 }
 
+/// A consistent view on all entries of a single key family. Created by
+/// [`TurboPersistence::read_family`].
+pub struct FamilyReader<'a, S: ParallelScheduler> {
+    db: &'a TurboPersistence<S>,
+    /// The SST files of the family ordered from oldest to newest.
+    ssts: Vec<Arc<StaticSortedFile>>,
+}
+
+impl<S: ParallelScheduler> FamilyReader<'_, S> {
+    /// Iterates all live entries of the family in hash order. Each key is only returned once with
+    /// its most recent value. Deleted keys are skipped.
+    pub fn iter(&self) -> Result<FamilyIter<'_, S>> {
+        let iters = self
+            .ssts
+            .iter()
+            .map(|sst| sst.iter(&self.db.key_block_cache, &self.db.value_block_cache))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(FamilyIter {
+            db: self.db,
+            iter: MergeIter::new(iters.into_iter())?,
+            pending: None,
+        })
+    }
+}
+
+/// An iterator over the live entries of a key family. Yields `(key, value)` pairs.
+pub struct FamilyIter<'l, S: ParallelScheduler> {
+    db: &'l TurboPersistence<S>,
+    iter: MergeIter<'l, StaticSortedFileIter<'l>>,
+    /// The entry that has been read ahead while looking for newer versions of the previous key.
+    pending: Option<LookupEntry<'l>>,
+}
+
+impl<S: ParallelScheduler> FamilyIter<'_, S> {
+    fn next_internal(&mut self) -> Result<Option<(ArcSlice<u8>, ArcSlice<u8>)>> {
+        loop {
+            let mut entry = match self.pending.take() {
+                Some(entry) => entry,
+                None => match self.iter.next().transpose()? {
+                    Some(entry) => entry,
+                    None => return Ok(None),
+                },
+            };
+            // Skip over all older versions of the same key and keep the most recent one
+            loop {
+                match self.iter.next().transpose()? {
+                    Some(next) if next.hash == entry.hash && next.key == entry.key => {
+                        entry = next;
+                    }
+                    next => {
+                        self.pending = next;
+                        break;
+                    }
+                }
+            }
+            let value = match entry.value {
+                LazyLookupValue::Eager(LookupValue::Deleted) => continue,
+                LazyLookupValue::Eager(LookupValue::Slice { value }) => value,
+                LazyLookupValue::Eager(LookupValue::Blob { sequence_number }) => {
+                    self.db.read_blob(sequence_number)?
+                }
+                LazyLookupValue::Medium {
+                    compression,
+                    uncompressed_size,
+                    block,
+                } => ArcSlice::from(decompress_into_arc(
+                    compression,
+                    uncompressed_size,
+                    block,
+                    DecompressionDictionary::None,
+                    true,
+                )?),
+            };
+            return Ok(Some((entry.key, value)));
+        }
+    }
+}
+
+impl<S: ParallelScheduler> Iterator for FamilyIter<'_, S> {
+    type Item = Result<(ArcSlice<u8>, ArcSlice<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_internal().transpose()
+    }
+}
+
 pub struct MetaFileInfo {
     pub sequence_number: u32,
     pub family: u32,
@@ -1288,5 +2230,7 @@ pub struct MetaFileEntryInfo {
     pub amqf_entries: usize,
     pub sst_size: u64,
     pub key_compression_dictionary_size: u16,
+    pub value_compression_dictionary_size: u16,
     pub block_count: u16,
+    pub compression: BlockCompression,
 }
@@ -4,7 +4,9 @@
 #![feature(sync_unsafe_cell)]
 #![feature(iter_collect_into)]
 
+mod access_times;
 mod arc_slice;
+mod checksum;
 mod collector;
 mod collector_entry;
 mod compaction;
@@ -27,8 +29,12 @@ mod write_batch;
 mod tests;
 
 pub use arc_slice::ArcSlice;
-pub use db::{CompactConfig, MetaFileEntryInfo, MetaFileInfo, TurboPersistence};
-pub use key::{KeyBase, QueryKey, StoreKey};
+pub use compression::BlockCompression;
+pub use db::{
+    CompactConfig, DbConfig, FamilyIter, FamilyReader, MetaFileEntryInfo, MetaFileInfo,
+    RecoveryMode, TurboPersistence,
+};
+pub use key::{KeyBase, QueryKey, StoreKey, hash_key};
 pub use parallel_scheduler::{ParallelScheduler, SerialScheduler};
 pub use value_buf::ValueBuffer;
 pub use write_batch::WriteBatch;
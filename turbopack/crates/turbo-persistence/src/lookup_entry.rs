@@ -1,5 +1,6 @@
 use crate::{
     ArcSlice,
+    compression::BlockCompression,
     constants::MAX_SMALL_VALUE_SIZE,
     static_sorted_file_builder::{Entry, EntryValue},
 };
@@ -20,6 +21,7 @@ pub enum LazyLookupValue<'l> {
     Eager(LookupValue),
     /// A medium sized value that is still compressed.
     Medium {
+        compression: BlockCompression,
         uncompressed_size: u32,
         block: &'l [u8],
     },
@@ -76,9 +78,11 @@ impl Entry for LookupEntry<'_> {
                 blob: *sequence_number,
             },
             LazyLookupValue::Medium {
+                compression,
                 uncompressed_size,
                 block,
             } => EntryValue::MediumCompressed {
+                compression: *compression,
                 uncompressed_size: *uncompressed_size,
                 block,
             },
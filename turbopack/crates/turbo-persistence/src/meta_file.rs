@@ -1,24 +1,57 @@
 use std::{
     fs::File,
     hash::BuildHasherDefault,
-    io::{BufReader, Seek},
     ops::Deref,
     path::{Path, PathBuf},
-    sync::{Arc, OnceLock},
+    sync::{
+        Arc, OnceLock,
+        atomic::{AtomicU64, Ordering},
+    },
 };
 
 use anyhow::{Context, Result, bail};
 use byteorder::{BE, ReadBytesExt};
 use either::Either;
-use memmap2::{Mmap, MmapOptions};
+use memmap2::Mmap;
 use quick_cache::sync::GuardResult;
 use rustc_hash::FxHasher;
 
 use crate::{
-    QueryKey,
+    QueryKey, access_times,
+    checksum::checksum,
     static_sorted_file::{BlockCache, SstLookupResult, StaticSortedFile, StaticSortedFileMetaData},
 };
 
+/// The magic number at the start of every meta file. It also identifies the version of the file
+/// format.
+pub const META_FILE_MAGIC: u32 = 0xFE4ADA4D;
+/// The magic number of the LZ4-only format. Its SST files have no header and its entries have no
+/// value compression dictionary. It's still readable, compaction rewrites its SST files.
+pub const LZ4_ONLY_META_FILE_MAGIC: u32 = 0xFE4ADA4B;
+/// The magic numbers of all versions of the file format share these bytes. The last byte is the
+/// version.
+const META_FILE_MAGIC_PREFIX: u32 = 0xFE4ADA00;
+
+/// The error returned when a meta file was written in a version of the file format that can't be
+/// read, e.g. before blocks had checksums. The whole database needs to be discarded in this case.
+#[derive(Clone, Copy, Debug)]
+pub struct IncompatibleFormatError {
+    pub magic: u32,
+}
+
+impl std::fmt::Display for IncompatibleFormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Incompatible file format version {:02x} (expected {:02x})",
+            self.magic & 0xFF,
+            META_FILE_MAGIC & 0xFF
+        )
+    }
+}
+
+impl std::error::Error for IncompatibleFormatError {}
+
 #[derive(Clone, Default)]
 pub struct AmqfWeighter;
 
@@ -48,11 +81,16 @@ pub struct MetaEntry {
     /// The offset of the end of the AMQF data in the the meta file relative to the end of the
     /// header.
     end_of_amqf_data_offset: u32,
+    /// The checksum of the AMQF data.
+    amqf_checksum: u32,
     /// The AMQF filter of this file. This is only used if the range is very large. Smaller ranges
     /// use the AMQF cache instead.
     amqf: OnceLock<qfilter::Filter>,
-    /// The static sorted file that is lazily loaded
-    sst: OnceLock<StaticSortedFile>,
+    /// The static sorted file that is lazily loaded. It's shared with readers that outlive the
+    /// current set of meta files.
+    sst: OnceLock<Arc<StaticSortedFile>>,
+    /// The time of the last read access in seconds since the unix epoch.
+    last_access: AtomicU64,
 }
 
 impl MetaEntry {
@@ -64,6 +102,24 @@ impl MetaEntry {
         self.size
     }
 
+    /// Returns the time of the last read access in seconds since the unix epoch.
+    pub fn last_access(&self) -> u64 {
+        self.last_access.load(Ordering::Relaxed)
+    }
+
+    pub fn set_last_access(&self, time: u64) {
+        self.last_access.store(time, Ordering::Relaxed);
+    }
+
+    /// Records a read access to the SST file.
+    pub fn touch(&self) {
+        let now = access_times::now();
+        // Avoid writing the shared cache line when the time hasn't changed
+        if self.last_access.load(Ordering::Relaxed) < now {
+            self.last_access.store(now, Ordering::Relaxed);
+        }
+    }
+
     pub fn amqf_size(&self) -> u32 {
         self.end_of_amqf_data_offset - self.start_of_amqf_data_offset
     }
@@ -76,6 +132,13 @@ impl MetaEntry {
 
     pub fn deserialize_amqf(&self, meta: &MetaFile) -> Result<qfilter::Filter> {
         let amqf = self.raw_amqf(meta.amqf_data());
+        if checksum(amqf) != self.amqf_checksum {
+            bail!(
+                "AMQF checksum mismatch in {:08}.meta for {:08}.sst",
+                meta.sequence_number,
+                self.sequence_number()
+            );
+        }
         pot::from_slice(amqf).with_context(|| {
             format!(
                 "Failed to deserialize AMQF from {:08}.meta for {:08}.sst",
@@ -112,14 +175,26 @@ impl MetaEntry {
         })
     }
 
-    pub fn sst(&self, meta: &MetaFile) -> Result<&StaticSortedFile> {
+    pub fn sst(&self, meta: &MetaFile) -> Result<&Arc<StaticSortedFile>> {
         self.sst.get_or_try_init(|| {
-            StaticSortedFile::open(&meta.db_path, self.sst_data.clone()).with_context(|| {
-                format!(
-                    "Unable to open static sorted file referenced from {:08}.meta",
-                    meta.sequence_number()
-                )
-            })
+            let sst = StaticSortedFile::open(&meta.db_path, self.sst_data.clone()).with_context(
+                || {
+                    format!(
+                        "Unable to open static sorted file referenced from {:08}.meta",
+                        meta.sequence_number()
+                    )
+                },
+            )?;
+            if sst.size() != self.size {
+                bail!(
+                    "Static sorted file {:08}.sst has size {} but {:08}.meta expects {}",
+                    self.sequence_number(),
+                    sst.size(),
+                    meta.sequence_number(),
+                    self.size
+                );
+            }
+            Ok(Arc::new(sst))
         })
     }
 
@@ -144,9 +219,19 @@ impl MetaEntry {
         self.sst_data.key_compression_dictionary_length
     }
 
+    pub fn value_compression_dictionary_length(&self) -> u16 {
+        self.sst_data.value_compression_dictionary_length
+    }
+
     pub fn block_count(&self) -> u16 {
         self.sst_data.block_count
     }
+
+    /// Returns true if the SST file was written in the LZ4-only format. It can't be referenced
+    /// from a meta file of the current format, so it needs to be rewritten instead of moved.
+    pub fn is_lz4_only_format(&self) -> bool {
+        self.sst_data.lz4_only_format
+    }
 }
 
 /// The result of a lookup operation.
@@ -170,6 +255,15 @@ pub struct StaticSortedFileRange {
     pub max_hash: u64,
 }
 
+impl StaticSortedFileRange {
+    /// Returns true if both ranges are in the same key family and their hash ranges overlap.
+    pub fn overlaps(&self, other: &StaticSortedFileRange) -> bool {
+        self.family == other.family
+            && self.min_hash <= other.max_hash
+            && other.min_hash <= self.max_hash
+    }
+}
+
 pub struct MetaFile {
     /// The database path
     db_path: PathBuf,
@@ -185,6 +279,8 @@ pub struct MetaFile {
     obsolete_sst_files: Vec<u32>,
     /// The memory mapped file.
     mmap: Mmap,
+    /// The offset of the AMQF data in the memory mapped file.
+    amqf_data_offset: usize,
 }
 
 impl MetaFile {
@@ -198,47 +294,74 @@ impl MetaFile {
     }
 
     fn open_internal(db_path: PathBuf, sequence_number: u32, path: &Path) -> Result<Self> {
-        let mut file = BufReader::new(File::open(path)?);
-        let magic = file.read_u32::<BE>()?;
-        if magic != 0xFE4ADA4A {
-            bail!("Invalid magic number");
-        }
-        let family = file.read_u32::<BE>()?;
-        let obsolete_count = file.read_u32::<BE>()?;
+        let file = File::open(path)?;
+        let mmap = unsafe { Mmap::map(&file)? };
+        #[cfg(unix)]
+        mmap.advise(memmap2::Advice::Random)?;
+        let mut header = &mmap[..];
+        let magic = header.read_u32::<BE>()?;
+        let lz4_only_format = match magic {
+            META_FILE_MAGIC => false,
+            LZ4_ONLY_META_FILE_MAGIC => true,
+            _ if magic & 0xFFFFFF00 == META_FILE_MAGIC_PREFIX => {
+                return Err(IncompatibleFormatError { magic }.into());
+            }
+            _ => bail!("Invalid magic number"),
+        };
+        let family = header.read_u32::<BE>()?;
+        let obsolete_count = header.read_u32::<BE>()?;
         let mut obsolete_sst_files = Vec::with_capacity(obsolete_count as usize);
         for _ in 0..obsolete_count {
-            let obsolete_sst = file.read_u32::<BE>()?;
+            let obsolete_sst = header.read_u32::<BE>()?;
             obsolete_sst_files.push(obsolete_sst);
         }
-        let count = file.read_u32::<BE>()?;
+        let count = header.read_u32::<BE>()?;
         let mut entries = Vec::with_capacity(count as usize);
         let mut start_of_amqf_data_offset = 0;
         for _ in 0..count {
+            let sequence_number = header.read_u32::<BE>()?;
+            let key_compression_dictionary_length = header.read_u16::<BE>()?;
+            let value_compression_dictionary_length = if lz4_only_format {
+                0
+            } else {
+                header.read_u16::<BE>()?
+            };
+            let block_count = header.read_u16::<BE>()?;
             let entry = MetaEntry {
                 sst_data: StaticSortedFileMetaData {
-                    sequence_number: file.read_u32::<BE>()?,
-                    key_compression_dictionary_length: file.read_u16::<BE>()?,
-                    block_count: file.read_u16::<BE>()?,
+                    sequence_number,
+                    key_compression_dictionary_length,
+                    value_compression_dictionary_length,
+                    block_count,
+                    lz4_only_format,
                 },
                 family,
-                min_hash: file.read_u64::<BE>()?,
-                max_hash: file.read_u64::<BE>()?,
-                size: file.read_u64::<BE>()?,
+                min_hash: header.read_u64::<BE>()?,
+                max_hash: header.read_u64::<BE>()?,
+                size: header.read_u64::<BE>()?,
                 start_of_amqf_data_offset,
-                end_of_amqf_data_offset: file.read_u32::<BE>()?,
+                end_of_amqf_data_offset: header.read_u32::<BE>()?,
+                amqf_checksum: header.read_u32::<BE>()?,
                 amqf: OnceLock::new(),
                 sst: OnceLock::new(),
+                last_access: AtomicU64::new(0),
             };
             start_of_amqf_data_offset = entry.end_of_amqf_data_offset;
             entries.push(entry);
         }
-        let offset = file.stream_position()?;
-        let file = file.into_inner();
-        let mut options = MmapOptions::new();
-        options.offset(offset);
-        let mmap = unsafe { options.map(&file)? };
-        #[cfg(unix)]
-        mmap.advise(memmap2::Advice::Random)?;
+        let header_len = mmap.len() - header.len();
+        let expected_checksum = header.read_u32::<BE>()?;
+        let actual_checksum = checksum(&mmap[..header_len]);
+        if actual_checksum != expected_checksum {
+            bail!(
+                "Header checksum mismatch (expected {expected_checksum:08x}, actual \
+                 {actual_checksum:08x})"
+            );
+        }
+        let amqf_data_offset = header_len + size_of::<u32>();
+        if mmap.len() - amqf_data_offset < start_of_amqf_data_offset as usize {
+            bail!("File is truncated");
+        }
         let file = Self {
             db_path,
             sequence_number,
@@ -247,6 +370,7 @@ impl MetaFile {
             obsolete_entries: Vec::new(),
             obsolete_sst_files,
             mmap,
+            amqf_data_offset,
         };
         Ok(file)
     }
@@ -269,7 +393,7 @@ impl MetaFile {
     }
 
     pub fn amqf_data(&self) -> &[u8] {
-        &self.mmap
+        &self.mmap[self.amqf_data_offset..]
     }
 
     pub fn retain_entries(&mut self, mut predicate: impl FnMut(u32) -> bool) -> bool {
@@ -326,6 +450,7 @@ impl MetaFile {
                     .sst(self)?
                     .lookup(key_hash, key, key_block_cache, value_block_cache)?;
             if !matches!(result, SstLookupResult::NotFound) {
+                entry.touch();
                 return Ok(MetaLookupResult::SstLookup(result));
             }
         }
@@ -7,7 +7,10 @@ use std::{
 use anyhow::{Context, Result};
 use byteorder::{BE, WriteBytesExt};
 
-use crate::static_sorted_file_builder::StaticSortedFileBuilderMeta;
+use crate::{
+    checksum::checksum, meta_file::META_FILE_MAGIC,
+    static_sorted_file_builder::StaticSortedFileBuilderMeta,
+};
 
 pub struct MetaFileBuilder<'a> {
     family: u32,
@@ -42,30 +45,36 @@ impl<'a> MetaFileBuilder<'a> {
     }
 
     fn write_internal(mut self, file: &Path) -> io::Result<File> {
-        let mut file = BufWriter::new(File::create(file)?);
-        file.write_u32::<BE>(0xFE4ADA4A)?; // Magic number
-        file.write_u32::<BE>(self.family)?;
+        // The header is collected in a buffer first, since it's followed by its checksum
+        let mut header = Vec::new();
+        header.write_u32::<BE>(META_FILE_MAGIC)?;
+        header.write_u32::<BE>(self.family)?;
 
         self.obsolete_sst_files.sort();
-        file.write_u32::<BE>(self.obsolete_sst_files.len() as u32)?;
+        header.write_u32::<BE>(self.obsolete_sst_files.len() as u32)?;
         for obsolete_sst in &self.obsolete_sst_files {
-            file.write_u32::<BE>(*obsolete_sst)?;
+            header.write_u32::<BE>(*obsolete_sst)?;
         }
 
-        file.write_u32::<BE>(self.entries.len() as u32)?;
+        header.write_u32::<BE>(self.entries.len() as u32)?;
 
         let mut amqf_offset = 0;
         for (sequence_number, sst) in &self.entries {
-            file.write_u32::<BE>(*sequence_number)?;
-            file.write_u16::<BE>(sst.key_compression_dictionary_length)?;
-            file.write_u16::<BE>(sst.block_count)?;
-            file.write_u64::<BE>(sst.min_hash)?;
-            file.write_u64::<BE>(sst.max_hash)?;
-            file.write_u64::<BE>(sst.size)?;
+            header.write_u32::<BE>(*sequence_number)?;
+            header.write_u16::<BE>(sst.key_compression_dictionary_length)?;
+            header.write_u16::<BE>(sst.value_compression_dictionary_length)?;
+            header.write_u16::<BE>(sst.block_count)?;
+            header.write_u64::<BE>(sst.min_hash)?;
+            header.write_u64::<BE>(sst.max_hash)?;
+            header.write_u64::<BE>(sst.size)?;
             amqf_offset += sst.amqf.len();
-            file.write_u32::<BE>(amqf_offset as u32)?;
+            header.write_u32::<BE>(amqf_offset as u32)?;
+            header.write_u32::<BE>(checksum(&sst.amqf))?;
         }
 
+        let mut file = BufWriter::new(File::create(file)?);
+        file.write_all(&header)?;
+        file.write_u32::<BE>(checksum(&header))?;
         for (_, sst) in &self.entries {
             file.write_all(&sst.amqf)?;
         }
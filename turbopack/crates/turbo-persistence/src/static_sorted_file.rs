@@ -4,6 +4,7 @@ use std::{
     hash::BuildHasherDefault,
     ops::Range,
     path::{Path, PathBuf},
+    sync::OnceLock,
 };
 
 use anyhow::{Context, Result, bail};
@@ -11,14 +12,23 @@ use byteorder::{BE, ReadBytesExt};
 use memmap2::Mmap;
 use quick_cache::sync::GuardResult;
 use rustc_hash::FxHasher;
+use zstd::dict::DecoderDictionary;
 
 use crate::{
     QueryKey,
     arc_slice::ArcSlice,
-    compression::decompress_into_arc,
+    checksum::checksum,
+    compression::{BlockCompression, DecompressionDictionary, decompress_into_arc},
     lookup_entry::{LazyLookupValue, LookupEntry, LookupValue},
 };
 
+/// The size of the SST file header: 1 byte codec tag of the blocks.
+pub const SST_HEADER_SIZE: usize = 1;
+
+/// The size of the header in front of every block: 4 bytes uncompressed length and 4 bytes
+/// checksum of the compressed data.
+pub const BLOCK_HEADER_SIZE: usize = 8;
+
 /// The block header for an index block.
 pub const BLOCK_TYPE_INDEX: u8 = 0;
 /// The block header for a key block.
@@ -65,8 +75,13 @@ pub struct StaticSortedFileMetaData {
     pub sequence_number: u32,
     /// The length of the key compression dictionary.
     pub key_compression_dictionary_length: u16,
+    /// The length of the value compression dictionary.
+    pub value_compression_dictionary_length: u16,
     /// The number of blocks in the SST file.
     pub block_count: u16,
+    /// The file was written in the LZ4-only format, which has no file header and no value
+    /// compression dictionary. All blocks are LZ4 compressed.
+    pub lz4_only_format: bool,
 }
 
 impl StaticSortedFileMetaData {
@@ -75,14 +90,30 @@ impl StaticSortedFileMetaData {
         sst_len - (bc * size_of::<u32>())
     }
 
+    /// Returns the size of the file header, which is missing in the LZ4-only format.
+    pub fn header_size(&self) -> usize {
+        if self.lz4_only_format {
+            0
+        } else {
+            SST_HEADER_SIZE
+        }
+    }
+
     pub fn blocks_start(&self) -> usize {
         let k: usize = self.key_compression_dictionary_length.into();
-        k
+        let v: usize = self.value_compression_dictionary_length.into();
+        self.header_size() + k + v
     }
 
     pub fn key_compression_dictionary_range(&self) -> Range<usize> {
-        let start = 0;
-        let end: usize = self.key_compression_dictionary_length.into();
+        let start = self.header_size();
+        let end = start + usize::from(self.key_compression_dictionary_length);
+        start..end
+    }
+
+    pub fn value_compression_dictionary_range(&self) -> Range<usize> {
+        let start = self.header_size() + usize::from(self.key_compression_dictionary_length);
+        let end = start + usize::from(self.value_compression_dictionary_length);
         start..end
     }
 }
@@ -93,6 +124,12 @@ pub struct StaticSortedFile {
     meta: StaticSortedFileMetaData,
     /// The memory mapped file.
     mmap: Mmap,
+    /// The codec used to compress the blocks, read from the file header if there is one.
+    compression: BlockCompression,
+    /// The digested zstd key compression dictionary, created on first use.
+    zstd_key_dictionary: OnceLock<DecoderDictionary<'static>>,
+    /// The digested zstd value compression dictionary, created on first use.
+    zstd_value_dictionary: OnceLock<DecoderDictionary<'static>>,
 }
 
 impl StaticSortedFile {
@@ -107,6 +144,13 @@ impl StaticSortedFile {
 
     fn open_internal(path: PathBuf, meta: StaticSortedFileMetaData) -> Result<Self> {
         let mmap = unsafe { Mmap::map(&File::open(&path)?)? };
+        if mmap.len() < meta.blocks_start() + meta.block_count as usize * size_of::<u32>() {
+            bail!(
+                "File is truncated ({} bytes is too small for {} blocks)",
+                mmap.len(),
+                meta.block_count
+            );
+        }
         #[cfg(unix)]
         mmap.advise(memmap2::Advice::Random)?;
         #[cfg(unix)]
@@ -114,10 +158,39 @@ impl StaticSortedFile {
             let offset = meta.block_offsets_start(mmap.len());
             let _ = mmap.advise_range(memmap2::Advice::Sequential, offset, mmap.len() - offset);
         }
-        let file = Self { meta, mmap };
+        let compression = if meta.lz4_only_format {
+            BlockCompression::Lz4
+        } else {
+            BlockCompression::from_tag(mmap[0])?
+        };
+        let file = Self {
+            meta,
+            mmap,
+            compression,
+            zstd_key_dictionary: OnceLock::new(),
+            zstd_value_dictionary: OnceLock::new(),
+        };
         Ok(file)
     }
 
+    /// Returns the codec used to compress the blocks of this file.
+    pub fn compression(&self) -> BlockCompression {
+        self.compression
+    }
+
+    /// Returns the size of the file in bytes.
+    pub fn size(&self) -> u64 {
+        self.mmap.len() as u64
+    }
+
+    /// Checks the checksums of all blocks in this file without decompressing them.
+    pub fn verify_checksums(&self) -> Result<()> {
+        for block_index in 0..self.meta.block_count {
+            self.get_compressed_block(block_index)?;
+        }
+        Ok(())
+    }
+
     /// Iterate over all entries in this file in sorted order.
     pub fn iter<'l>(
         &'l self,
@@ -317,21 +390,40 @@ impl StaticSortedFile {
 
     /// Reads a key block from the file.
     fn read_key_block(&self, block_index: u16) -> Result<ArcSlice<u8>> {
-        self.read_block(
-            block_index,
-            Some(&self.mmap[self.meta.key_compression_dictionary_range()]),
-            false,
-        )
+        let dict = &self.mmap[self.meta.key_compression_dictionary_range()];
+        let dict = match self.compression {
+            BlockCompression::Lz4 => DecompressionDictionary::Lz4(dict),
+            BlockCompression::Zstd => self.zstd_dictionary(&self.zstd_key_dictionary, dict),
+        };
+        self.read_block(block_index, dict, false)
     }
 
     /// Reads a value block from the file.
     fn read_small_value_block(&self, block_index: u16) -> Result<ArcSlice<u8>> {
-        self.read_block(block_index, None, false)
+        let dict = &self.mmap[self.meta.value_compression_dictionary_range()];
+        let dict = match self.compression {
+            _ if dict.is_empty() => DecompressionDictionary::None,
+            BlockCompression::Lz4 => DecompressionDictionary::Lz4(dict),
+            BlockCompression::Zstd => self.zstd_dictionary(&self.zstd_value_dictionary, dict),
+        };
+        self.read_block(block_index, dict, false)
     }
 
     /// Reads a value block from the file.
     fn read_value_block(&self, block_index: u16) -> Result<ArcSlice<u8>> {
-        self.read_block(block_index, None, true)
+        self.read_block(block_index, DecompressionDictionary::None, true)
+    }
+
+    /// Returns the digested zstd dictionary, digesting it on first use.
+    fn zstd_dictionary<'l>(
+        &self,
+        cell: &'l OnceLock<DecoderDictionary<'static>>,
+        dict: &[u8],
+    ) -> DecompressionDictionary<'l> {
+        if dict.is_empty() {
+            return DecompressionDictionary::None;
+        }
+        DecompressionDictionary::Zstd(cell.get_or_init(|| DecoderDictionary::copy(dict)))
     }
 
     /// Reads a block from the file.
@@ -339,15 +431,16 @@ impl StaticSortedFile {
     fn read_block(
         &self,
         block_index: u16,
-        compression_dictionary: Option<&[u8]>,
+        dictionary: DecompressionDictionary<'_>,
         long_term: bool,
     ) -> Result<ArcSlice<u8>> {
         let (uncompressed_length, block) = self.get_compressed_block(block_index)?;
 
         let buffer = decompress_into_arc(
+            self.compression,
             uncompressed_length,
             block,
-            compression_dictionary,
+            dictionary,
             long_term,
         )?;
         Ok(ArcSlice::from(buffer))
@@ -402,14 +495,36 @@ impl StaticSortedFile {
                 self.meta.blocks_start()
             );
         }
+        if block_end > self.mmap.len() || block_start + BLOCK_HEADER_SIZE > block_end {
+            bail!(
+                "Corrupted file seq:{} block:{} has invalid range {} - {} (file size {})",
+                self.meta.sequence_number,
+                block_index,
+                block_start,
+                block_end,
+                self.mmap.len()
+            );
+        }
         #[cfg(unix)]
         let _ = self.mmap.advise_range(
             memmap2::Advice::Sequential,
             block_start,
             block_end - block_start,
         );
-        let uncompressed_length = (&self.mmap[block_start..block_start + 4]).read_u32::<BE>()?;
-        let block = &self.mmap[block_start + 4..block_end];
+        let mut header = &self.mmap[block_start..block_start + BLOCK_HEADER_SIZE];
+        let uncompressed_length = header.read_u32::<BE>()?;
+        let expected_checksum = header.read_u32::<BE>()?;
+        let block = &self.mmap[block_start + BLOCK_HEADER_SIZE..block_end];
+        let actual_checksum = checksum(block);
+        if actual_checksum != expected_checksum {
+            bail!(
+                "Corrupted file seq:{} block:{} checksum mismatch (expected {:08x}, actual {:08x})",
+                self.meta.sequence_number,
+                block_index,
+                expected_checksum,
+                actual_checksum
+            );
+        }
         Ok((uncompressed_length, block))
     }
 }
@@ -498,6 +613,7 @@ impl<'l> StaticSortedFileIter<'l> {
                     let block = val.read_u16::<BE>()?;
                     let (uncompressed_size, block) = self.this.get_compressed_block(block)?;
                     LazyLookupValue::Medium {
+                        compression: self.this.compression,
                         uncompressed_size,
                         block,
                     }
@@ -1,8 +1,9 @@
 use std::{
     borrow::Cow,
-    cmp::min,
+    cmp::{max, min},
     fs::File,
     io::{BufWriter, Seek, Write},
+    mem::take,
     path::Path,
 };
 
@@ -10,10 +11,13 @@ use anyhow::{Context, Result};
 use byteorder::{BE, ByteOrder, WriteBytesExt};
 
 use crate::{
-    compression::compress_into_buffer,
+    checksum::checksum,
+    compression::{
+        BlockCompression, BlockCompressor, DecompressionDictionary, decompress_into_arc,
+    },
     static_sorted_file::{
-        BLOCK_TYPE_INDEX, BLOCK_TYPE_KEY, KEY_BLOCK_ENTRY_TYPE_BLOB, KEY_BLOCK_ENTRY_TYPE_DELETED,
-        KEY_BLOCK_ENTRY_TYPE_MEDIUM, KEY_BLOCK_ENTRY_TYPE_SMALL,
+        BLOCK_HEADER_SIZE, BLOCK_TYPE_INDEX, BLOCK_TYPE_KEY, KEY_BLOCK_ENTRY_TYPE_BLOB,
+        KEY_BLOCK_ENTRY_TYPE_DELETED, KEY_BLOCK_ENTRY_TYPE_MEDIUM, KEY_BLOCK_ENTRY_TYPE_SMALL,
     },
 };
 
@@ -38,6 +42,13 @@ const KEY_COMPRESSION_SAMPLES_SIZE: usize = 256 * 1024;
 /// The minimum bytes that should be selected as keys samples. Below that no compression dictionary
 /// is used.
 const MIN_KEY_COMPRESSION_SAMPLES_SIZE: usize = 1024;
+/// The maximum compression dictionary size for small value blocks
+const VALUE_COMPRESSION_DICTIONARY_SIZE: usize = 64 * 1024 - 1;
+/// The maximum bytes that should be selected as value samples to create a compression dictionary
+const VALUE_COMPRESSION_SAMPLES_SIZE: usize = 1024 * 1024;
+/// The minimum total size of small values in an SST file. Below that no value compression
+/// dictionary is used, since the dictionary would make up a large part of the file.
+const MIN_VALUE_SIZE_FOR_COMPRESSION_DICTIONARY: usize = 64 * 1024;
 /// The bytes that are used per key entry for a sample.
 const COMPRESSION_DICTIONARY_SAMPLE_PER_ENTRY: usize = 100;
 /// The minimum bytes that are used per key entry for a sample.
@@ -65,6 +76,7 @@ pub enum EntryValue<'l> {
     Medium { value: &'l [u8] },
     /// Medium-sized value. They are stored in their own value block. Precompressed.
     MediumCompressed {
+        compression: BlockCompression,
         uncompressed_size: u32,
         block: &'l [u8],
     },
@@ -84,6 +96,8 @@ pub struct StaticSortedFileBuilderMeta<'a> {
     pub amqf: Cow<'a, [u8]>,
     /// The key compression dictionary
     pub key_compression_dictionary_length: u16,
+    /// The value compression dictionary
+    pub value_compression_dictionary_length: u16,
     /// The number of blocks in the SST file
     pub block_count: u16,
     /// The file size of the SST file
@@ -96,6 +110,7 @@ pub fn write_static_stored_file<E: Entry>(
     entries: &[E],
     total_key_size: usize,
     file: &Path,
+    compression: BlockCompression,
 ) -> Result<(StaticSortedFileBuilderMeta<'static>, File)> {
     debug_assert!(entries.iter().map(|e| e.key_hash()).is_sorted());
 
@@ -105,10 +120,18 @@ pub fn write_static_stored_file<E: Entry>(
     // We use a shared buffer for all operations to avoid excessive allocations
     let mut buffer = Vec::with_capacity(capacity);
 
+    file.write_u8(compression.tag())?;
     let key_dict = compute_key_compression_dictionary(entries, total_key_size, &mut buffer)?;
     file.write_all(&key_dict)?;
+    let value_dict = if compression.uses_value_dictionary() {
+        compute_value_compression_dictionary(entries, &mut buffer)?
+    } else {
+        Vec::new()
+    };
+    file.write_all(&value_dict)?;
 
-    let mut block_writer = BlockWriter::new(&mut file, &mut buffer);
+    let mut block_writer =
+        BlockWriter::new(&mut file, &mut buffer, compression, &key_dict, &value_dict);
 
     // Another shared buffer for the uncompressed blocks
     // The existing shared buffer will be used for compressed blocks
@@ -121,7 +144,6 @@ pub fn write_static_stored_file<E: Entry>(
     let amqf = write_key_blocks_and_compute_amqf(
         entries,
         &value_locations,
-        &key_dict,
         &mut block_writer,
         &mut buffer,
     )
@@ -139,6 +161,7 @@ pub fn write_static_stored_file<E: Entry>(
         max_hash,
         amqf: Cow::Owned(amqf),
         key_compression_dictionary_length: key_dict.len().try_into().unwrap(),
+        value_compression_dictionary_length: value_dict.len().try_into().unwrap(),
         block_count,
         size: file.stream_position()?,
         entries: entries.len() as u64,
@@ -204,18 +227,87 @@ fn compute_key_compression_dictionary<E: Entry>(
     Ok(result)
 }
 
+/// Computes a compression dictionary from samples of the small values of all entries
+#[tracing::instrument(level = "trace", skip_all)]
+fn compute_value_compression_dictionary<E: Entry>(
+    entries: &[E],
+    buffer: &mut Vec<u8>,
+) -> Result<Vec<u8>> {
+    let total_value_size = entries
+        .iter()
+        .map(|entry| match entry.value() {
+            EntryValue::Small { value } => value.len(),
+            _ => 0,
+        })
+        .sum::<usize>();
+    if total_value_size < MIN_VALUE_SIZE_FOR_COMPRESSION_DICTIONARY {
+        return Ok(Vec::new());
+    }
+    let value_compression_samples_size = min(VALUE_COMPRESSION_SAMPLES_SIZE, total_value_size / 4);
+    let value_compression_dictionary_size =
+        min(VALUE_COMPRESSION_DICTIONARY_SIZE, total_value_size / 16);
+    let mut sample_sizes = Vec::new();
+
+    // Take samples from all over the file
+    let step = max(1, total_value_size / value_compression_samples_size);
+    for entry in entries.iter().step_by(step) {
+        let EntryValue::Small { value } = entry.value() else {
+            continue;
+        };
+        let value_remaining = value_compression_samples_size - buffer.len();
+        if value_remaining < MIN_COMPRESSION_DICTIONARY_SAMPLE_PER_ENTRY {
+            break;
+        }
+        if value.len() >= MIN_COMPRESSION_DICTIONARY_SAMPLE_PER_ENTRY {
+            let used_len = min(value_remaining, value.len());
+            sample_sizes.push(used_len);
+            buffer.extend_from_slice(&value[..used_len]);
+        }
+    }
+    let result = if sample_sizes.len() > 5 {
+        // Training fails when the samples are not suitable. Compression still works without a
+        // dictionary in that case.
+        zstd::dict::from_continuous(buffer, &sample_sizes, value_compression_dictionary_size)
+            .unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    buffer.clear();
+    Ok(result)
+}
+
 struct BlockWriter<'l> {
     buffer: &'l mut Vec<u8>,
     block_offsets: Vec<u32>,
     writer: &'l mut BufWriter<File>,
+    compression: BlockCompression,
+    /// Compressor for key and index blocks, using the key compression dictionary.
+    key_compressor: BlockCompressor<'l>,
+    /// Compressor for small value blocks, using the value compression dictionary.
+    small_value_compressor: BlockCompressor<'l>,
+    /// Compressor for medium value blocks, without dictionary.
+    value_compressor: BlockCompressor<'l>,
 }
 
 impl<'l> BlockWriter<'l> {
-    fn new(writer: &'l mut BufWriter<File>, buffer: &'l mut Vec<u8>) -> Self {
+    fn new(
+        writer: &'l mut BufWriter<File>,
+        buffer: &'l mut Vec<u8>,
+        compression: BlockCompression,
+        key_dict: &'l [u8],
+        value_dict: &'l [u8],
+    ) -> Self {
         Self {
             buffer,
             block_offsets: Vec::new(),
             writer,
+            compression,
+            key_compressor: BlockCompressor::new(compression, Some(key_dict)),
+            small_value_compressor: BlockCompressor::new(
+                compression,
+                (!value_dict.is_empty()).then_some(value_dict),
+            ),
+            value_compressor: BlockCompressor::new(compression, None),
         }
     }
 
@@ -234,54 +326,46 @@ impl<'l> BlockWriter<'l> {
     }
 
     #[tracing::instrument(level = "trace", skip_all)]
-    fn write_key_block(&mut self, block: &[u8], dict: &[u8]) -> Result<()> {
-        self.write_block(block, Some(dict), false)
+    fn write_key_block(&mut self, block: &[u8]) -> Result<()> {
+        self.write_block(block, BlockKind::Key, false)
             .context("Failed to write key block")
     }
 
     #[tracing::instrument(level = "trace", skip_all)]
-    fn write_index_block(&mut self, block: &[u8], dict: &[u8]) -> Result<()> {
-        self.write_block(block, Some(dict), false)
+    fn write_index_block(&mut self, block: &[u8]) -> Result<()> {
+        self.write_block(block, BlockKind::Key, false)
             .context("Failed to write index block")
     }
 
     #[tracing::instrument(level = "trace", skip_all)]
     fn write_small_value_block(&mut self, block: &[u8]) -> Result<()> {
-        self.write_block(block, None, false)
+        self.write_block(block, BlockKind::SmallValue, false)
             .context("Failed to write small value block")
     }
 
     #[tracing::instrument(level = "trace", skip_all)]
     fn write_value_block(&mut self, block: &[u8]) -> Result<()> {
-        self.write_block(block, None, true)
+        self.write_block(block, BlockKind::Value, true)
             .context("Failed to write value block")
     }
 
-    fn write_block(&mut self, block: &[u8], dict: Option<&[u8]>, long_term: bool) -> Result<()> {
+    fn write_block(&mut self, block: &[u8], kind: BlockKind, long_term: bool) -> Result<()> {
         let uncompressed_size = block.len().try_into().unwrap();
-        self.compress_block_into_buffer(block, dict, long_term)?;
-        let len = (self.buffer.len() + 4).try_into().unwrap();
-        let offset = self
-            .block_offsets
-            .last()
-            .copied()
-            .unwrap_or_default()
-            .checked_add(len)
-            .expect("Block offset overflow");
-        self.block_offsets.push(offset);
-
-        self.writer
-            .write_u32::<BE>(uncompressed_size)
-            .context("Failed to write uncompressed size")?;
-        self.writer
-            .write_all(self.buffer)
-            .context("Failed to write compressed block")?;
-        self.buffer.clear();
-        Ok(())
+        let compressor = match kind {
+            BlockKind::Key => &mut self.key_compressor,
+            BlockKind::SmallValue => &mut self.small_value_compressor,
+            BlockKind::Value => &mut self.value_compressor,
+        };
+        compressor.compress_into_buffer(block, long_term, self.buffer)?;
+        let mut buffer = take(self.buffer);
+        let result = self.write_compressed_block(uncompressed_size, &buffer);
+        buffer.clear();
+        *self.buffer = buffer;
+        result
     }
 
     fn write_compressed_block(&mut self, uncompressed_size: u32, block: &[u8]) -> Result<()> {
-        let len = (block.len() + 4).try_into().unwrap();
+        let len = (block.len() + BLOCK_HEADER_SIZE).try_into().unwrap();
         let offset = self
             .block_offsets
             .last()
@@ -294,21 +378,22 @@ impl<'l> BlockWriter<'l> {
         self.writer
             .write_u32::<BE>(uncompressed_size)
             .context("Failed to write uncompressed size")?;
+        self.writer
+            .write_u32::<BE>(checksum(block))
+            .context("Failed to write block checksum")?;
         self.writer
             .write_all(block)
             .context("Failed to write compressed block")?;
         Ok(())
     }
+}
 
-    /// Compresses a block with a compression dictionary.
-    fn compress_block_into_buffer(
-        &mut self,
-        block: &[u8],
-        dict: Option<&[u8]>,
-        long_term: bool,
-    ) -> Result<()> {
-        compress_into_buffer(block, dict, long_term, self.buffer)
-    }
+/// Selects the compression dictionary of a block.
+#[derive(Clone, Copy)]
+enum BlockKind {
+    Key,
+    SmallValue,
+    Value,
 }
 
 /// Splits the values of the entries into blocks and writes them to the writer.
@@ -353,12 +438,26 @@ fn write_value_blocks(
                 writer.write_value_block(value)?;
             }
             EntryValue::MediumCompressed {
+                compression,
                 uncompressed_size,
                 block,
             } => {
                 let block_index = writer.next_block_index();
                 value_locations.push((block_index, 0));
-                writer.write_compressed_block(uncompressed_size, block)?;
+                if compression == writer.compression {
+                    writer.write_compressed_block(uncompressed_size, block)?;
+                } else {
+                    // The block was compressed with a different codec and needs to be
+                    // recompressed
+                    let value = decompress_into_arc(
+                        compression,
+                        uncompressed_size,
+                        block,
+                        DecompressionDictionary::None,
+                        true,
+                    )?;
+                    writer.write_value_block(&value)?;
+                }
             }
             EntryValue::Deleted | EntryValue::Large { .. } => {
                 value_locations.push((0, 0));
@@ -387,7 +486,6 @@ fn write_value_blocks(
 fn write_key_blocks_and_compute_amqf(
     entries: &[impl Entry],
     value_locations: &[(u16, u32)],
-    key_compression_dictionary: &[u8],
     writer: &mut BlockWriter<'_>,
     buffer: &mut Vec<u8>,
 ) -> Result<Vec<u8>> {
@@ -454,7 +552,7 @@ fn write_key_blocks_and_compute_amqf(
                 writer.next_block_index(),
             ));
             block.finish();
-            writer.write_key_block(buffer, key_compression_dictionary)?;
+            writer.write_key_block(buffer)?;
             buffer.clear();
             current_block_size = 0;
             current_block_start = i;
@@ -476,7 +574,7 @@ fn write_key_blocks_and_compute_amqf(
             writer.next_block_index(),
         ));
         block.finish();
-        writer.write_key_block(buffer, key_compression_dictionary)?;
+        writer.write_key_block(buffer)?;
         buffer.clear();
     }
 
@@ -494,7 +592,7 @@ fn write_key_blocks_and_compute_amqf(
     }
     let _ = writer.next_block_index();
     index_block.finish();
-    writer.write_index_block(buffer, key_compression_dictionary)?;
+    writer.write_index_block(buffer)?;
     buffer.clear();
 
     Ok(pot::to_vec(&filter).expect("AMQF serialization failed"))
@@ -1,12 +1,18 @@
-use std::{fs, time::Instant};
+use std::{collections::BTreeMap, fs, path::Path, time::Instant};
 
 use anyhow::Result;
+use byteorder::{BE, ReadBytesExt, WriteBytesExt};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
 use crate::{
+    access_times,
+    checksum::checksum,
+    compression::BlockCompression,
     constants::MAX_MEDIUM_VALUE_SIZE,
-    db::{CompactConfig, TurboPersistence},
+    db::{CompactConfig, DbConfig, RecoveryMode, TurboPersistence},
+    meta_file::{LZ4_ONLY_META_FILE_MAGIC, META_FILE_MAGIC},
     parallel_scheduler::ParallelScheduler,
+    static_sorted_file::SST_HEADER_SIZE,
     write_batch::WriteBatch,
 };
 
@@ -836,3 +842,692 @@ fn merge_file_removal() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn iterate_family() -> Result<()> {
+    let tempdir = tempfile::tempdir()?;
+    let path = tempdir.path();
+
+    fn collect(
+        db: &TurboPersistence<RayonParallelScheduler>,
+        family: usize,
+    ) -> Result<BTreeMap<Vec<u8>, Vec<u8>>> {
+        let reader = db.read_family(family)?;
+        let mut result = BTreeMap::new();
+        for entry in reader.iter()? {
+            let (key, value) = entry?;
+            let old = result.insert(key.to_vec(), value.to_vec());
+            assert!(old.is_none(), "Key {key:?} returned twice");
+        }
+        Ok(result)
+    }
+
+    let mut expected = BTreeMap::new();
+    {
+        let db = TurboPersistence::open_with_parallel_scheduler(
+            path.to_path_buf(),
+            RayonParallelScheduler,
+        )?;
+        let b = db.write_batch::<_, 2>()?;
+        for i in 0..1000u32 {
+            let key = i.to_be_bytes().to_vec();
+            b.put(0, key.clone(), key.clone().into())?;
+            b.put(1, key.clone(), vec![1].into())?;
+            expected.insert(key.clone(), key);
+        }
+        // Medium sized value
+        b.put(0, vec![1, 2, 3, 4, 5], vec![42; 100 * 1024].into())?;
+        expected.insert(vec![1, 2, 3, 4, 5], vec![42; 100 * 1024]);
+        db.commit_write_batch(b)?;
+
+        let b = db.write_batch::<_, 2>()?;
+        for i in (0..1000u32).step_by(3) {
+            let key = i.to_be_bytes().to_vec();
+            b.put(0, key.clone(), vec![3].into())?;
+            expected.insert(key, vec![3]);
+        }
+        for i in (0..1000u32).step_by(5) {
+            let key = i.to_be_bytes().to_vec();
+            b.delete(0, key.clone())?;
+            expected.remove(&key);
+        }
+        db.commit_write_batch(b)?;
+
+        assert_eq!(collect(&db, 0)?, expected);
+        assert_eq!(collect(&db, 1)?.len(), 1000);
+        assert!(collect(&db, 2)?.is_empty());
+
+        // Reads, commits and compactions are possible while the family is iterated
+        let reader = db.read_family(0)?;
+        let mut iter = reader.iter()?;
+        let (key, value) = iter.next().unwrap()?;
+        assert_eq!(db.get(0, &&*key)?.as_deref(), Some(&*value));
+        let b = db.write_batch::<_, 2>()?;
+        b.put(0, key.to_vec(), vec![4].into())?;
+        db.commit_write_batch(b)?;
+        db.full_compact()?;
+        assert_eq!(db.get(0, &&*key)?.as_deref(), Some(&[4u8][..]));
+        // The reader still returns the entries from the time it was created
+        let mut remaining = BTreeMap::from([(key.to_vec(), value.to_vec())]);
+        for entry in iter {
+            let (key, value) = entry?;
+            remaining.insert(key.to_vec(), value.to_vec());
+        }
+        assert_eq!(remaining, expected);
+        drop(reader);
+        expected.insert(key.to_vec(), vec![4]);
+
+        db.full_compact()?;
+        assert_eq!(collect(&db, 0)?, expected);
+        db.shutdown()?;
+    }
+
+    {
+        let db = TurboPersistence::open_read_only_with_parallel_scheduler(
+            path.to_path_buf(),
+            RayonParallelScheduler,
+        )?;
+        assert_eq!(collect(&db, 0)?, expected);
+        assert_eq!(collect(&db, 1)?.len(), 1000);
+        db.verify()?;
+        db.shutdown()?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn recover_corrupted_sst_file() -> Result<()> {
+    let tempdir = tempfile::tempdir()?;
+    let path = tempdir.path();
+
+    let corrupted_sst;
+    {
+        let db = TurboPersistence::open_with_parallel_scheduler(
+            path.to_path_buf(),
+            RayonParallelScheduler,
+        )?;
+        let b = db.write_batch::<_, 2>()?;
+        for i in 0..1000u32 {
+            let key = i.to_be_bytes().to_vec();
+            b.put(0, key.clone(), key.clone().into())?;
+            b.put(1, key.clone(), key.clone().into())?;
+        }
+        db.commit_write_batch(b)?;
+
+        let b = db.write_batch::<_, 2>()?;
+        for i in (0..1000u32).step_by(3) {
+            let key = i.to_be_bytes().to_vec();
+            b.put(0, key, vec![3].into())?;
+        }
+        db.commit_write_batch(b)?;
+
+        // Meta files are ordered from newest to oldest
+        let meta_info = db.meta_info()?;
+        let newest_meta = meta_info.iter().find(|meta| meta.family == 0).unwrap();
+        corrupted_sst = newest_meta.entries[0].sequence_number;
+        db.shutdown()?;
+    }
+
+    // Truncate the SST file of the second write batch
+    let sst_path = path.join(format!("{corrupted_sst:08}.sst"));
+    let len = fs::metadata(&sst_path)?.len();
+    fs::File::options()
+        .write(true)
+        .open(&sst_path)?
+        .set_len(len / 2)?;
+
+    // Without recovery the corruption is only detected when reading
+    {
+        let db = TurboPersistence::open_read_only_with_parallel_scheduler(
+            path.to_path_buf(),
+            RayonParallelScheduler,
+        )?;
+        assert!(db.verify().is_err());
+        db.shutdown()?;
+    }
+
+    {
+        let db = TurboPersistence::open_with_recovery_and_parallel_scheduler(
+            path.to_path_buf(),
+            RecoveryMode::Quick,
+            RayonParallelScheduler,
+        )?;
+        // Keys of the corrupted file are gone, outdated values must not become visible
+        for i in 0..1000u32 {
+            let key = i.to_be_bytes();
+            let value = db.get(0, &key.as_slice())?;
+            if i % 3 == 0 {
+                assert!(value.is_none(), "Key {i} should be dropped");
+            } else if let Some(value) = value {
+                assert_eq!(&*value, &key);
+            }
+        }
+        // Other families are not affected
+        for i in 0..1000u32 {
+            let key = i.to_be_bytes();
+            let value = db.get(1, &key.as_slice())?;
+            assert_eq!(value.as_deref(), Some(&key[..]));
+        }
+        db.verify()?;
+        db.shutdown()?;
+    }
+
+    assert!(!sst_path.exists());
+    assert!(
+        path.join("CORRUPTED")
+            .join(format!("{corrupted_sst:08}.sst"))
+            .exists()
+    );
+
+    // The recovery is persisted
+    {
+        let db = TurboPersistence::open_with_parallel_scheduler(
+            path.to_path_buf(),
+            RayonParallelScheduler,
+        )?;
+        db.verify()?;
+        db.shutdown()?;
+    }
+
+    Ok(())
+}
+
+fn files_with_extension(path: &Path, extension: &str) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == extension) {
+            files.push(path.file_name().unwrap().to_string_lossy().into_owned());
+        }
+    }
+    Ok(files)
+}
+
+#[test]
+fn recover_corrupted_meta_file() -> Result<()> {
+    let tempdir = tempfile::tempdir()?;
+    let path = tempdir.path();
+
+    let corrupted_meta;
+    let orphaned_sst;
+    {
+        let db = TurboPersistence::open_with_parallel_scheduler(
+            path.to_path_buf(),
+            RayonParallelScheduler,
+        )?;
+        let b = db.write_batch::<_, 1>()?;
+        for i in 0..1000u32 {
+            let key = i.to_be_bytes().to_vec();
+            b.put(0, key.clone(), key.into())?;
+        }
+        db.commit_write_batch(b)?;
+
+        let b = db.write_batch::<_, 1>()?;
+        b.put(
+            0,
+            vec![1, 2, 3, 4, 5],
+            vec![42; MAX_MEDIUM_VALUE_SIZE + 1].into(),
+        )?;
+        db.commit_write_batch(b)?;
+
+        let meta_info = db.meta_info()?;
+        corrupted_meta = meta_info[0].sequence_number;
+        orphaned_sst = meta_info[0].entries[0].sequence_number;
+        db.shutdown()?;
+    }
+    assert_eq!(files_with_extension(path, "blob")?.len(), 1);
+
+    // Flip a byte in the header of the newest meta file
+    let meta_path = path.join(format!("{corrupted_meta:08}.meta"));
+    let mut content = fs::read(&meta_path)?;
+    content[8] ^= 0xFF;
+    fs::write(&meta_path, content)?;
+
+    {
+        let db = TurboPersistence::open_with_recovery_and_parallel_scheduler(
+            path.to_path_buf(),
+            RecoveryMode::Quick,
+            RayonParallelScheduler,
+        )?;
+        // The key ranges of the corrupted meta file are unknown, so all older data is dropped
+        assert!(db.get(0, &[1u8, 2, 3, 4, 5].as_slice())?.is_none());
+        assert!(db.get(0, &0u32.to_be_bytes().as_slice())?.is_none());
+        db.verify()?;
+        db.shutdown()?;
+    }
+
+    assert!(
+        path.join("CORRUPTED")
+            .join(format!("{corrupted_meta:08}.meta"))
+            .exists()
+    );
+    // Files of the corrupted meta file are not orphaned
+    assert!(!path.join(format!("{orphaned_sst:08}.sst")).exists());
+    assert!(files_with_extension(path, "sst")?.is_empty());
+    assert!(files_with_extension(path, "blob")?.is_empty());
+
+    Ok(())
+}
+
+/// Rewrites a database into the LZ4-only format, which has no SST file header and no value
+/// compression dictionary length in the meta file entries.
+fn convert_to_lz4_only_format(path: &Path) -> Result<()> {
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("sst") => {
+                let content = fs::read(&path)?;
+                assert_eq!(content[0], BlockCompression::Lz4.tag());
+                fs::write(&path, &content[SST_HEADER_SIZE..])?;
+            }
+            Some("meta") => {
+                let content = fs::read(&path)?;
+                let mut input = &content[..];
+                let mut header = Vec::new();
+                assert_eq!(input.read_u32::<BE>()?, META_FILE_MAGIC);
+                header.write_u32::<BE>(LZ4_ONLY_META_FILE_MAGIC)?;
+                // Family
+                header.write_u32::<BE>(input.read_u32::<BE>()?)?;
+                let obsolete_count = input.read_u32::<BE>()?;
+                header.write_u32::<BE>(obsolete_count)?;
+                for _ in 0..obsolete_count {
+                    header.write_u32::<BE>(input.read_u32::<BE>()?)?;
+                }
+                let count = input.read_u32::<BE>()?;
+                header.write_u32::<BE>(count)?;
+                for _ in 0..count {
+                    // Sequence number and key compression dictionary length
+                    header.write_u32::<BE>(input.read_u32::<BE>()?)?;
+                    header.write_u16::<BE>(input.read_u16::<BE>()?)?;
+                    // LZ4 doesn't use a value compression dictionary
+                    assert_eq!(input.read_u16::<BE>()?, 0);
+                    // Block count, min hash and max hash
+                    header.write_u16::<BE>(input.read_u16::<BE>()?)?;
+                    header.write_u64::<BE>(input.read_u64::<BE>()?)?;
+                    header.write_u64::<BE>(input.read_u64::<BE>()?)?;
+                    // The SST file is smaller without the header
+                    header.write_u64::<BE>(input.read_u64::<BE>()? - SST_HEADER_SIZE as u64)?;
+                    // End of the AMQF data and AMQF checksum
+                    header.write_u32::<BE>(input.read_u32::<BE>()?)?;
+                    header.write_u32::<BE>(input.read_u32::<BE>()?)?;
+                }
+                // The header checksum is followed by the AMQF data
+                input.read_u32::<BE>()?;
+                let mut content = header;
+                let header_checksum = checksum(&content);
+                content.write_u32::<BE>(header_checksum)?;
+                content.extend_from_slice(input);
+                fs::write(&path, content)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn read_lz4_only_format() -> Result<()> {
+    let tempdir = tempfile::tempdir()?;
+    let path = tempdir.path();
+
+    // Keys 0..500 are only in the first batch, keys 500..1500 are overridden by the second one
+    let expected_value = |i: u32| {
+        let mut value = i.to_be_bytes().to_vec();
+        value.push(if i < 500 { 0 } else { 1 });
+        value
+    };
+    let check = |db: &TurboPersistence<RayonParallelScheduler>| -> Result<()> {
+        for i in 0..1500u32 {
+            let key = i.to_be_bytes();
+            assert_eq!(
+                db.get(0, &key.as_slice())?.as_deref(),
+                Some(&expected_value(i)[..])
+            );
+        }
+        Ok(())
+    };
+
+    {
+        let db = TurboPersistence::open_with_parallel_scheduler(
+            path.to_path_buf(),
+            RayonParallelScheduler,
+        )?;
+        for batch in 0..2u8 {
+            let b = db.write_batch::<_, 1>()?;
+            let start = u32::from(batch) * 500;
+            for i in start..start + 1000 {
+                let mut value = i.to_be_bytes().to_vec();
+                value.push(batch);
+                b.put(0, i.to_be_bytes().to_vec(), value.into())?;
+            }
+            db.commit_write_batch(b)?;
+        }
+        db.shutdown()?;
+    }
+
+    convert_to_lz4_only_format(path)?;
+
+    {
+        let db = TurboPersistence::open_read_only_with_parallel_scheduler(
+            path.to_path_buf(),
+            RayonParallelScheduler,
+        )?;
+        check(&db)?;
+        db.verify()?;
+        db.shutdown()?;
+    }
+
+    {
+        let db = TurboPersistence::open_with_parallel_scheduler(
+            path.to_path_buf(),
+            RayonParallelScheduler,
+        )?;
+        check(&db)?;
+
+        // New files are written in the current format next to the old ones
+        let b = db.write_batch::<_, 1>()?;
+        b.put(0, 1500u32.to_be_bytes().to_vec(), vec![42].into())?;
+        db.commit_write_batch(b)?;
+
+        // Compaction rewrites the old files
+        db.full_compact()?;
+        check(&db)?;
+        db.shutdown()?;
+    }
+
+    {
+        let db = TurboPersistence::open_read_only_with_parallel_scheduler(
+            path.to_path_buf(),
+            RayonParallelScheduler,
+        )?;
+        check(&db)?;
+        assert_eq!(
+            db.get(0, &1500u32.to_be_bytes().as_slice())?.as_deref(),
+            Some(&[42u8][..])
+        );
+        db.verify()?;
+        db.shutdown()?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn snapshot() -> Result<()> {
+    let tempdir = tempfile::tempdir()?;
+    let path = tempdir.path().join("db");
+    let snapshot_path = tempdir.path().join("snapshot");
+
+    let db = TurboPersistence::open_with_parallel_scheduler(path.clone(), RayonParallelScheduler)?;
+    let b = db.write_batch::<_, 1>()?;
+    for i in 0..1000u32 {
+        let key = i.to_be_bytes().to_vec();
+        b.put(0, key.clone(), key.into())?;
+    }
+    // Blob file
+    b.put(
+        0,
+        vec![1, 2, 3, 4, 5],
+        vec![42; MAX_MEDIUM_VALUE_SIZE + 1].into(),
+    )?;
+    db.commit_write_batch(b)?;
+
+    db.snapshot(&snapshot_path)?;
+    assert!(db.snapshot(&snapshot_path).is_err());
+
+    // Changes after the snapshot are not visible in the snapshot
+    let b = db.write_batch::<_, 1>()?;
+    for i in 0..1000u32 {
+        let key = i.to_be_bytes().to_vec();
+        b.put(0, key, vec![0].into())?;
+    }
+    b.delete(0, vec![1, 2, 3, 4, 5])?;
+    db.commit_write_batch(b)?;
+    db.full_compact()?;
+    db.shutdown()?;
+    drop(db);
+
+    let snapshot = TurboPersistence::open_read_only_with_parallel_scheduler(
+        snapshot_path,
+        RayonParallelScheduler,
+    )?;
+    for i in 0..1000u32 {
+        let key = i.to_be_bytes();
+        let value = snapshot.get(0, &key.as_slice())?;
+        assert_eq!(value.as_deref(), Some(&key[..]));
+    }
+    let value = snapshot.get(0, &[1u8, 2, 3, 4, 5].as_slice())?;
+    assert_eq!(
+        value.as_deref(),
+        Some(&vec![42; MAX_MEDIUM_VALUE_SIZE + 1][..])
+    );
+    snapshot.verify()?;
+    snapshot.shutdown()?;
+
+    Ok(())
+}
+
+#[test]
+fn block_compression() -> Result<()> {
+    let tempdir = tempfile::tempdir()?;
+    let path = tempdir.path();
+
+    fn value(i: u32) -> Vec<u8> {
+        format!("{{\"task\":{i},\"name\":\"value number {}\"}}", i % 17).into_bytes()
+    }
+    fn check(db: &TurboPersistence<RayonParallelScheduler>) -> Result<()> {
+        for i in 0..10000u32 {
+            let key = i.to_be_bytes();
+            let result = db.get(0, &key.as_slice())?;
+            assert_eq!(result.as_deref(), Some(&value(i)[..]));
+        }
+        let result = db.get(0, &[1u8, 2, 3, 4, 5].as_slice())?;
+        assert_eq!(result.as_deref(), Some(&vec![42; 100 * 1024][..]));
+        db.verify()
+    }
+    fn compressions(
+        db: &TurboPersistence<RayonParallelScheduler>,
+    ) -> Result<Vec<BlockCompression>> {
+        Ok(db
+            .meta_info()?
+            .iter()
+            .flat_map(|meta| meta.entries.iter().map(|entry| entry.compression))
+            .collect())
+    }
+
+    {
+        let db = TurboPersistence::open_with_config_and_parallel_scheduler(
+            path.to_path_buf(),
+            DbConfig {
+                family_block_compression: vec![BlockCompression::Zstd],
+                ..Default::default()
+            },
+            RayonParallelScheduler,
+        )?;
+        let b = db.write_batch::<_, 1>()?;
+        for i in 0..10000u32 {
+            b.put(0, i.to_be_bytes().to_vec(), value(i).into())?;
+        }
+        // Medium sized value
+        b.put(0, vec![1, 2, 3, 4, 5], vec![42; 100 * 1024].into())?;
+        db.commit_write_batch(b)?;
+        assert!(
+            db.meta_info()?
+                .iter()
+                .flat_map(|meta| meta.entries.iter())
+                .any(|entry| entry.value_compression_dictionary_size > 0)
+        );
+        assert!(
+            compressions(&db)?
+                .iter()
+                .all(|c| *c == BlockCompression::Zstd)
+        );
+        check(&db)?;
+        db.shutdown()?;
+    }
+
+    // Zstd files are still readable and are converted to LZ4 by compaction
+    {
+        let db = TurboPersistence::open_with_parallel_scheduler(
+            path.to_path_buf(),
+            RayonParallelScheduler,
+        )?;
+        check(&db)?;
+        let b = db.write_batch::<_, 1>()?;
+        b.put(0, 0u32.to_be_bytes().to_vec(), value(0).into())?;
+        db.commit_write_batch(b)?;
+        db.full_compact()?;
+        assert!(
+            compressions(&db)?
+                .iter()
+                .all(|c| *c == BlockCompression::Lz4)
+        );
+        check(&db)?;
+        db.shutdown()?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn evict_least_recently_accessed() -> Result<()> {
+    let tempdir = tempfile::tempdir()?;
+    let path = tempdir.path();
+
+    fn key(family: u32, i: u32) -> Vec<u8> {
+        [family.to_be_bytes(), i.to_be_bytes()].concat()
+    }
+    fn value(i: u32) -> Vec<u8> {
+        vec![i as u8; 100]
+    }
+
+    let total_size;
+    {
+        let db = TurboPersistence::open_with_parallel_scheduler(
+            path.to_path_buf(),
+            RayonParallelScheduler,
+        )?;
+        let b = db.write_batch::<_, 3>()?;
+        for i in 0..100u32 {
+            b.put(0, key(0, i), value(i).into())?;
+        }
+        for i in 0..10000u32 {
+            b.put(1, key(1, i), value(i).into())?;
+            b.put(2, key(2, i), value(i).into())?;
+        }
+        db.commit_write_batch(b)?;
+        // Overwrite some keys of family 1
+        let b = db.write_batch::<_, 3>()?;
+        for i in 0..100u32 {
+            b.put(1, key(1, i), value(i + 1).into())?;
+        }
+        db.commit_write_batch(b)?;
+        db.shutdown()?;
+
+        let meta_info = db.meta_info()?;
+        total_size = meta_info
+            .iter()
+            .flat_map(|meta| meta.entries.iter())
+            .map(|entry| entry.sst_size)
+            .sum::<u64>();
+        // The overwriting SST files of family 1 are the least recently accessed, followed by
+        // family 2. The first SST files of family 1 were accessed most recently, but they need to
+        // be evicted together with the overwriting SST files, since they contain outdated values.
+        let newest_meta_file = meta_info[0].sequence_number;
+        let now = access_times::now();
+        let times = meta_info.iter().flat_map(|meta| {
+            meta.entries.iter().map(|entry| {
+                let time = match meta.family {
+                    1 if meta.sequence_number == newest_meta_file => 0,
+                    2 => 1,
+                    _ => now,
+                };
+                (entry.sequence_number, time)
+            })
+        });
+        access_times::write_access_times(path, times)?;
+    }
+
+    let db = TurboPersistence::open_with_config_and_parallel_scheduler(
+        path.to_path_buf(),
+        DbConfig {
+            max_size: Some(total_size - 1),
+            evictable_families: vec![1, 2],
+            ..Default::default()
+        },
+        RayonParallelScheduler,
+    )?;
+    assert!(db.compact(&CompactConfig {
+        max_merge_segment_count: 0,
+        ..Default::default()
+    })?);
+    for i in 0..100u32 {
+        let result = db.get(0, &key(0, i).as_slice())?;
+        assert_eq!(result.as_deref(), Some(&value(i)[..]));
+    }
+    for i in 0..10000u32 {
+        assert_eq!(db.get(1, &key(1, i).as_slice())?, None);
+        let result = db.get(2, &key(2, i).as_slice())?;
+        assert_eq!(result.as_deref(), Some(&value(i)[..]));
+    }
+    db.shutdown()?;
+
+    // The database is below the maximum size now
+    let db = TurboPersistence::open_with_config_and_parallel_scheduler(
+        path.to_path_buf(),
+        DbConfig {
+            max_size: Some(total_size - 1),
+            evictable_families: vec![1, 2],
+            ..Default::default()
+        },
+        RayonParallelScheduler,
+    )?;
+    assert!(!db.compact(&CompactConfig {
+        max_merge_segment_count: 0,
+        ..Default::default()
+    })?);
+    db.shutdown()?;
+
+    Ok(())
+}
+
+#[test]
+fn evict_blob_files() -> Result<()> {
+    let tempdir = tempfile::tempdir()?;
+    let path = tempdir.path();
+
+    {
+        let db = TurboPersistence::open_with_parallel_scheduler(
+            path.to_path_buf(),
+            RayonParallelScheduler,
+        )?;
+        let b = db.write_batch::<_, 2>()?;
+        b.put(0, vec![0], vec![0].into())?;
+        b.put(1, vec![1], vec![42; MAX_MEDIUM_VALUE_SIZE + 1].into())?;
+        db.commit_write_batch(b)?;
+        db.shutdown()?;
+    }
+    assert_eq!(files_with_extension(path, "blob")?.len(), 1);
+
+    // Evict all SST files of family 1
+    let db = TurboPersistence::open_with_config_and_parallel_scheduler(
+        path.to_path_buf(),
+        DbConfig {
+            max_size: Some(1),
+            evictable_families: vec![1],
+            ..Default::default()
+        },
+        RayonParallelScheduler,
+    )?;
+    assert!(db.compact(&CompactConfig {
+        max_merge_segment_count: 0,
+        ..Default::default()
+    })?);
+    assert_eq!(db.get(0, &[0u8])?.as_deref(), Some(&[0u8][..]));
+    assert_eq!(db.get(1, &[1u8])?, None);
+    db.shutdown()?;
+    assert!(files_with_extension(path, "blob")?.is_empty());
+
+    Ok(())
+}
@@ -8,7 +8,7 @@ use std::{
 };
 
 use anyhow::{Context, Result};
-use byteorder::{BE, WriteBytesExt};
+use byteorder::{BE, ByteOrder};
 use either::Either;
 use parking_lot::Mutex;
 use smallvec::SmallVec;
@@ -16,10 +16,11 @@ use thread_local::ThreadLocal;
 
 use crate::{
     ValueBuffer,
+    checksum::checksum,
     collector::Collector,
     collector_entry::CollectorEntry,
-    compression::compress_into_buffer,
-    constants::{MAX_MEDIUM_VALUE_SIZE, THREAD_LOCAL_SIZE_SHIFT},
+    compression::{BlockCompression, compress_into_buffer},
+    constants::{BLOB_HEADER_SIZE, MAX_MEDIUM_VALUE_SIZE, THREAD_LOCAL_SIZE_SHIFT},
     key::StoreKey,
     meta_file_builder::MetaFileBuilder,
     parallel_scheduler::ParallelScheduler,
@@ -81,13 +82,20 @@ pub struct WriteBatch<K: StoreKey + Send, S: ParallelScheduler, const FAMILIES:
     /// The list of new SST files that have been created.
     /// Tuple of (sequence number, file).
     new_sst_files: Mutex<Vec<(u32, File)>>,
+    /// The block compression for each family.
+    block_compression: [BlockCompression; FAMILIES],
 }
 
 impl<K: StoreKey + Send + Sync, S: ParallelScheduler, const FAMILIES: usize>
     WriteBatch<K, S, FAMILIES>
 {
     /// Creates a new write batch for a database.
-    pub(crate) fn new(path: PathBuf, current: u32, parallel_scheduler: S) -> Self {
+    pub(crate) fn new(
+        path: PathBuf,
+        current: u32,
+        parallel_scheduler: S,
+        block_compression: [BlockCompression; FAMILIES],
+    ) -> Self {
         const {
             assert!(FAMILIES <= usize_from_u32(u32::MAX));
         };
@@ -100,6 +108,7 @@ impl<K: StoreKey + Send + Sync, S: ParallelScheduler, const FAMILIES: usize>
                 .map(|_| Mutex::new(GlobalCollectorState::Unsharded(Collector::new()))),
             meta_collectors: [(); FAMILIES].map(|_| Mutex::new(Vec::new())),
             new_sst_files: Mutex::new(Vec::new()),
+            block_compression,
         }
     }
 
@@ -388,10 +397,13 @@ impl<K: StoreKey + Send + Sync, S: ParallelScheduler, const FAMILIES: usize>
     #[tracing::instrument(level = "trace", skip(self, value), fields(value_len = value.len()))]
     fn create_blob(&self, value: &[u8]) -> Result<(u32, File)> {
         let seq = self.current_sequence_number.fetch_add(1, Ordering::SeqCst) + 1;
-        let mut buffer = Vec::new();
-        buffer.write_u32::<BE>(value.len() as u32)?;
-        compress_into_buffer(value, None, true, &mut buffer)
+        // The header (uncompressed length and checksum) is filled in after compression
+        let mut buffer = vec![0; BLOB_HEADER_SIZE];
+        compress_into_buffer(BlockCompression::Lz4, value, None, true, &mut buffer)
             .context("Compression of value for blob file failed")?;
+        let blob_checksum = checksum(&buffer[BLOB_HEADER_SIZE..]);
+        BE::write_u32(&mut buffer[0..4], value.len() as u32);
+        BE::write_u32(&mut buffer[4..8], blob_checksum);
 
         let file = self.db_path.join(format!("{seq:08}.blob"));
         let mut file = File::create(&file).context("Unable to create blob file")?;
@@ -413,9 +425,12 @@ impl<K: StoreKey + Send + Sync, S: ParallelScheduler, const FAMILIES: usize>
         let seq = self.current_sequence_number.fetch_add(1, Ordering::SeqCst) + 1;
 
         let path = self.db_path.join(format!("{seq:08}.sst"));
+        let compression = self.block_compression[usize_from_u32(family)];
         let (meta, file) = self
             .parallel_scheduler
-            .block_in_place(|| write_static_stored_file(entries, total_key_size, &path))
+            .block_in_place(|| {
+                write_static_stored_file(entries, total_key_size, &path, compression)
+            })
             .with_context(|| format!("Unable to write SST file {seq:08}.sst"))?;
 
         #[cfg(feature = "verify_sst_content")]
@@ -438,7 +453,9 @@ impl<K: StoreKey + Send + Sync, S: ParallelScheduler, const FAMILIES: usize>
                 StaticSortedFileMetaData {
                     sequence_number: seq,
                     key_compression_dictionary_length: meta.key_compression_dictionary_length,
+                    value_compression_dictionary_length: meta.value_compression_dictionary_length,
                     block_count: meta.block_count,
+                    lz4_only_format: false,
                 },
             )?;
             let cache2 = BlockCache::with(
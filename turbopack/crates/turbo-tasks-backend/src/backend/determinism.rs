@@ -0,0 +1,257 @@
+use std::{
+    fmt::{self, Display},
+    hash::BuildHasherDefault,
+    mem::take,
+};
+
+use auto_hash_map::AutoMap;
+use parking_lot::Mutex;
+use rustc_hash::{FxHashMap, FxHashSet, FxHasher};
+use serde::Serialize;
+use turbo_tasks::{
+    CellId, FxDashMap, SharedReference, TaskId, TypedSharedReference, ValueTypeId,
+    backend::CellContent, registry,
+};
+
+pub type CellCounters = AutoMap<ValueTypeId, u32, BuildHasherDefault<FxHasher>, 8>;
+
+/// The number of violations that are kept until they are taken.
+const MAX_VIOLATIONS: usize = 100;
+
+/// The state of a task that is recomputed to verify its determinism.
+struct Verification {
+    /// The cell contents of the first execution.
+    cells: FxHashMap<CellId, TypedSharedReference>,
+    /// The cell counters of the first execution. The recomputation is completed with these, so it
+    /// neither removes cells of the first execution nor keeps cells that only it has created.
+    cell_counters: CellCounters,
+    divergent_cells: Vec<DivergentCell>,
+    output_diverged: bool,
+}
+
+/// Verifies that tasks are deterministic by recomputing them with the same inputs and comparing
+/// the cell contents of both executions.
+///
+/// Cell contents are compared by their [`DeterministicHash`], so cells of other value types can't
+/// be verified. The recomputation doesn't change the task: its cells and output are kept from the
+/// first execution and dependent tasks are not invalidated.
+///
+/// [`DeterministicHash`]: turbo_tasks_hash::DeterministicHash
+pub struct DeterminismVerifier {
+    /// The fraction of completed tasks that is verified, between 0 and 1.
+    sample_rate: f64,
+    /// Tasks that are currently recomputed.
+    in_progress: FxDashMap<TaskId, Verification>,
+    /// Value types that have been reported as not verifiable.
+    unverifiable_types: Mutex<FxHashSet<ValueTypeId>>,
+    violations: Mutex<Vec<DeterminismViolation>>,
+}
+
+impl DeterminismVerifier {
+    pub fn new(sample_rate: f64) -> Self {
+        Self {
+            sample_rate,
+            in_progress: Default::default(),
+            unverifiable_types: Default::default(),
+            violations: Default::default(),
+        }
+    }
+
+    /// Decides whether a task that has just completed should be recomputed for verification.
+    pub fn select(&self) -> bool {
+        self.sample_rate >= 1.0 || rand::random::<f64>() < self.sample_rate
+    }
+
+    /// Starts the verification of a task. The task must be recomputed afterwards.
+    pub fn start(
+        &self,
+        task_id: TaskId,
+        cells: FxHashMap<CellId, TypedSharedReference>,
+        cell_counters: CellCounters,
+    ) {
+        self.in_progress.insert(
+            task_id,
+            Verification {
+                cells,
+                cell_counters,
+                divergent_cells: Vec::new(),
+                output_diverged: false,
+            },
+        );
+    }
+
+    /// Stops the verification of a task, e.g. when it has been invalidated, since the inputs of
+    /// the recomputation are no longer the same.
+    pub fn cancel(&self, task_id: TaskId) {
+        self.in_progress.remove(&task_id);
+    }
+
+    /// Returns the cell counters of the first execution when the task is recomputed for
+    /// verification.
+    pub fn first_cell_counters(&self, task_id: TaskId) -> Option<CellCounters> {
+        self.in_progress
+            .get(&task_id)
+            .map(|verification| verification.cell_counters.clone())
+    }
+
+    /// Records that a recomputed task returned a different output than the first execution.
+    pub fn output_diverged(&self, task_id: TaskId) {
+        if let Some(mut verification) = self.in_progress.get_mut(&task_id) {
+            verification.output_diverged = true;
+        }
+    }
+
+    /// Compares a cell update of a recomputed task with the first execution. Returns `true` when
+    /// the update should be skipped. The content of the first execution is kept, so the
+    /// verification doesn't affect the result of the build.
+    pub fn compare_cell(&self, task_id: TaskId, cell: CellId, content: &CellContent) -> bool {
+        let Some(mut verification) = self.in_progress.get_mut(&task_id) else {
+            return false;
+        };
+        let first = verification.cells.get(&cell);
+        let second = content.0.as_ref();
+        match (first, second) {
+            (Some(first), Some(second)) => {
+                let value_type = registry::get_value_type(cell.type_id);
+                let (Some(first_hash), Some(second_hash)) = (
+                    value_type.any_deterministic_hash(&first.reference.0),
+                    value_type.any_deterministic_hash(&second.0),
+                ) else {
+                    if self.unverifiable_types.lock().insert(cell.type_id) {
+                        tracing::warn!(
+                            "{} doesn't implement DeterministicHash, so its cells are not \
+                             verified for determinism",
+                            value_type.name
+                        );
+                    }
+                    return true;
+                };
+                if first_hash == second_hash {
+                    return true;
+                }
+            }
+            (None, None) => return true,
+            // The cell has only been created or cleared in one of the executions
+            _ => {}
+        }
+        let divergent_cell = DivergentCell::new(cell, first.map(|first| &first.reference), second);
+        // A cell that didn't exist in the first execution needs to be written, as the task might
+        // read it. It's removed again when the recomputation completes.
+        let skip_update = first.is_some();
+        verification.divergent_cells.push(divergent_cell);
+        skip_update
+    }
+
+    /// Finishes the verification of a task. Returns `None` when the task hasn't been recomputed
+    /// for verification, and the violation when it is not deterministic.
+    pub fn finish(
+        &self,
+        task_id: TaskId,
+        cell_counters: &CellCounters,
+        describe_task: impl FnOnce() -> String,
+    ) -> Option<Result<(), DeterminismViolation>> {
+        let (_, mut verification) = self.in_progress.remove(&task_id)?;
+        // Cells that are no longer created by the recomputation
+        for (cell, first) in &verification.cells {
+            if cell_counters
+                .get(&cell.type_id)
+                .is_none_or(|count| cell.index >= *count)
+            {
+                let divergent_cell = DivergentCell::new(*cell, Some(&first.reference), None);
+                verification.divergent_cells.push(divergent_cell);
+            }
+        }
+        if verification.divergent_cells.is_empty() && !verification.output_diverged {
+            return Some(Ok(()));
+        }
+        let mut divergent_cells = take(&mut verification.divergent_cells);
+        divergent_cells.sort_by_key(|cell| (cell.value_type, cell.index));
+        let violation = DeterminismViolation {
+            task: describe_task(),
+            output_diverged: verification.output_diverged,
+            divergent_cells,
+        };
+        let mut violations = self.violations.lock();
+        if violations.len() < MAX_VIOLATIONS {
+            violations.push(violation.clone());
+        }
+        Some(Err(violation))
+    }
+
+    /// Returns the violations that have been found since the last call. Only the first
+    /// [`MAX_VIOLATIONS`] are kept.
+    pub fn take_violations(&self) -> Vec<DeterminismViolation> {
+        take(&mut *self.violations.lock())
+    }
+}
+
+fn format_cell(type_id: ValueTypeId, content: &SharedReference) -> String {
+    let Some(serializable) = registry::get_value_type(type_id).any_as_serializable(&content.0)
+    else {
+        return "<not serializable>".to_string();
+    };
+    serde_json::to_string(serializable)
+        .unwrap_or_else(|err| format!("<not representable as JSON: {err}>"))
+}
+
+/// A cell that has different contents when the task is executed again with the same inputs.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DivergentCell {
+    pub value_type: &'static str,
+    pub index: u32,
+    /// The content of the first execution, or `None` when the cell hasn't been created.
+    pub first: Option<String>,
+    /// The content of the second execution, or `None` when the cell hasn't been created.
+    pub second: Option<String>,
+}
+
+impl DivergentCell {
+    fn new(
+        cell: CellId,
+        first: Option<&SharedReference>,
+        second: Option<&SharedReference>,
+    ) -> Self {
+        Self {
+            value_type: registry::get_value_type(cell.type_id).name,
+            index: cell.index,
+            first: first.map(|first| format_cell(cell.type_id, first)),
+            second: second.map(|second| format_cell(cell.type_id, second)),
+        }
+    }
+}
+
+/// A task that produced different cell contents when it has been executed again with the same
+/// inputs.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeterminismViolation {
+    /// The description of the task, including the function name.
+    pub task: String,
+    /// Whether the task returned a different output, e.g. an error or another cell.
+    pub output_diverged: bool,
+    pub divergent_cells: Vec<DivergentCell>,
+}
+
+impl Display for DeterminismViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} is not deterministic:", self.task)?;
+        if self.output_diverged {
+            writeln!(f, "  output")?;
+        }
+        for cell in &self.divergent_cells {
+            writeln!(f, "  {} cell #{}", cell.value_type, cell.index)?;
+            writeln!(
+                f,
+                "    first:  {}",
+                cell.first.as_deref().unwrap_or("<not created>")
+            )?;
+            writeln!(
+                f,
+                "    second: {}",
+                cell.second.as_deref().unwrap_or("<not created>")
+            )?;
+        }
+        Ok(())
+    }
+}
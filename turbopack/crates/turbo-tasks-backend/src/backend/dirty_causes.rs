@@ -0,0 +1,340 @@
+use std::{
+    collections::hash_map::Entry,
+    fmt::{self, Display},
+    mem::take,
+    sync::Arc,
+};
+
+use parking_lot::Mutex;
+use rustc_hash::FxHashMap;
+use serde::Serialize;
+use turbo_tasks::{FxDashMap, TaskId, registry};
+
+use crate::backend::operation::TaskDirtyCause;
+
+/// Chains are cut off after this many links.
+const MAX_CHAIN_LENGTH: usize = 100;
+
+#[derive(Clone)]
+struct RecordedCause {
+    /// The task that has been made dirty.
+    task_id: TaskId,
+    cause: TaskDirtyCause,
+    /// The reason passed to `invalidate_with_reason` for [`TaskDirtyCause::Invalidator`].
+    reason: Option<Arc<str>>,
+    /// The cause of the upstream task at the time this cause has been recorded. The upstream task
+    /// might have been made dirty again since then.
+    upstream: Option<Arc<RecordedCause>>,
+    /// The number of links in the chain ending with this cause.
+    chain_length: usize,
+}
+
+impl RecordedCause {
+    /// The task that caused this task to become dirty.
+    fn upstream_task_id(&self) -> Option<TaskId> {
+        match self.cause {
+            TaskDirtyCause::CellChange { task_id, .. }
+            | TaskDirtyCause::CellRemoved { task_id, .. }
+            | TaskDirtyCause::OutputChange { task_id }
+            | TaskDirtyCause::CollectiblesChange { task_id, .. } => Some(task_id),
+            TaskDirtyCause::InitialDirty
+            | TaskDirtyCause::Invalidator
+            | TaskDirtyCause::Unknown => None,
+        }
+    }
+}
+
+/// Records why tasks have been made dirty, so re-executions can be traced back to the invalidation
+/// that caused them, e.g. a file change.
+#[derive(Default)]
+pub struct DirtyCauses {
+    /// The cause for every task that is dirty or executing. It's removed once the task has
+    /// completed, after its dependents have been made dirty.
+    causes: FxDashMap<TaskId, Arc<RecordedCause>>,
+    /// The causes of tasks that have been re-executed since the last call to
+    /// [`DirtyCauses::take_summary`].
+    reexecuted: Mutex<FxHashMap<TaskId, Arc<RecordedCause>>>,
+}
+
+impl DirtyCauses {
+    pub fn record(&self, task_id: TaskId, cause: TaskDirtyCause) {
+        let mut recorded = RecordedCause {
+            task_id,
+            cause,
+            reason: None,
+            upstream: None,
+            chain_length: 1,
+        };
+        if let Some(upstream_task_id) = recorded.upstream_task_id()
+            && let Some(upstream) = self.causes.get(&upstream_task_id)
+            && upstream.chain_length < MAX_CHAIN_LENGTH
+        {
+            recorded.chain_length = upstream.chain_length + 1;
+            recorded.upstream = Some(upstream.clone());
+        }
+        self.causes.insert(task_id, Arc::new(recorded));
+    }
+
+    /// Attaches the reason to a task that has been invalidated directly.
+    pub fn record_reason(&self, task_id: TaskId, reason: String) {
+        if let Some(mut recorded) = self.causes.get_mut(&task_id)
+            && matches!(recorded.cause, TaskDirtyCause::Invalidator)
+        {
+            *recorded = Arc::new(RecordedCause {
+                reason: Some(reason.into()),
+                ..(**recorded).clone()
+            });
+        }
+    }
+
+    /// Called when a task starts executing. Only executions of tasks that have been made dirty
+    /// are tracked as re-executions.
+    pub fn task_execution_started(&self, task_id: TaskId) {
+        if let Some(recorded) = self.causes.get(&task_id)
+            && !matches!(recorded.cause, TaskDirtyCause::InitialDirty)
+        {
+            self.reexecuted.lock().insert(task_id, recorded.clone());
+        }
+    }
+
+    /// Called when a task has completed and is no longer dirty.
+    pub fn task_execution_completed(&self, task_id: TaskId) {
+        self.causes.remove(&task_id);
+    }
+
+    /// Follows the recorded causes from the task back to the invalidation that caused it. Returns
+    /// `None` when the task is neither dirty nor has been re-executed since the last call to
+    /// [`DirtyCauses::take_summary`].
+    pub fn chain(
+        &self,
+        task_id: TaskId,
+        describe_task: &impl Fn(TaskId) -> String,
+    ) -> Option<TaskDirtyChain> {
+        let recorded = match self.causes.get(&task_id) {
+            Some(recorded) => recorded.clone(),
+            None => self.reexecuted.lock().get(&task_id)?.clone(),
+        };
+        Some(chain(&recorded, describe_task))
+    }
+
+    /// Groups all re-executions since the last call by their root cause, most frequent first.
+    pub fn take_summary(
+        &self,
+        describe_task: &impl Fn(TaskId) -> String,
+    ) -> Vec<ReexecutionSummary> {
+        let reexecuted = take(&mut *self.reexecuted.lock());
+        let mut summaries: FxHashMap<String, ReexecutionSummary> = FxHashMap::default();
+        for recorded in reexecuted.into_values() {
+            let chain = chain(&recorded, describe_task);
+            match summaries.entry(chain.root_cause.clone()) {
+                Entry::Occupied(mut entry) => {
+                    let summary = entry.get_mut();
+                    summary.task_count += 1;
+                    if chain.links.len() > summary.longest_chain.links.len() {
+                        summary.longest_chain = chain;
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(ReexecutionSummary {
+                        task_count: 1,
+                        longest_chain: chain,
+                    });
+                }
+            }
+        }
+        let mut summaries: Vec<_> = summaries.into_values().collect();
+        summaries.sort_by(|a, b| b.task_count.cmp(&a.task_count));
+        summaries
+    }
+}
+
+fn chain(
+    recorded: &Arc<RecordedCause>,
+    describe_task: &impl Fn(TaskId) -> String,
+) -> TaskDirtyChain {
+    let mut links = Vec::new();
+    let mut root_cause = None;
+    let mut current = Some(recorded);
+    while let Some(recorded) = current {
+        if let Some(reason) = &recorded.reason {
+            root_cause = Some(reason.to_string());
+        }
+        links.push(TaskDirtyChainLink {
+            task: describe_task(recorded.task_id),
+            cause: describe_cause(recorded),
+        });
+        current = recorded.upstream.as_ref();
+        if current.is_none()
+            && let Some(upstream_task_id) = recorded.upstream_task_id()
+        {
+            // The upstream task wasn't dirty or the chain has been cut off
+            links.push(TaskDirtyChainLink {
+                task: describe_task(upstream_task_id),
+                cause: "no recorded cause".to_string(),
+            });
+        }
+    }
+    let root_cause = root_cause.unwrap_or_else(|| {
+        let root = links.last().unwrap();
+        format!("{} ({})", root.cause, root.task)
+    });
+    TaskDirtyChain { root_cause, links }
+}
+
+/// Describes how a task has been made dirty.
+fn describe_cause(recorded: &RecordedCause) -> String {
+    match recorded.cause {
+        TaskDirtyCause::InitialDirty => "executed for the first time".to_string(),
+        TaskDirtyCause::CellChange { value_type, .. } => format!(
+            "read a changed {} cell",
+            registry::get_value_type(value_type).name
+        ),
+        TaskDirtyCause::CellRemoved { value_type, .. } => format!(
+            "read a removed {} cell",
+            registry::get_value_type(value_type).name
+        ),
+        TaskDirtyCause::OutputChange { .. } => "read the changed output".to_string(),
+        TaskDirtyCause::CollectiblesChange {
+            collectible_type, ..
+        } => format!(
+            "read changed {} collectibles",
+            registry::get_trait(collectible_type).name
+        ),
+        TaskDirtyCause::Invalidator => recorded
+            .reason
+            .as_deref()
+            .map_or_else(|| "invalidated".to_string(), |reason| reason.to_string()),
+        TaskDirtyCause::Unknown => "invalidated".to_string(),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskDirtyChainLink {
+    /// The description of the task.
+    pub task: String,
+    /// How the task has been made dirty by the next task in the chain, e.g. by reading a changed
+    /// cell of it.
+    pub cause: String,
+}
+
+/// Explains why a task has been re-executed. The first link is the re-executed task, the last
+/// link is the task that has been invalidated directly.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskDirtyChain {
+    /// The invalidation at the end of the chain, e.g. a file change.
+    pub root_cause: String,
+    pub links: Vec<TaskDirtyChainLink>,
+}
+
+impl TaskDirtyChain {
+    /// Writes the tasks from the root cause to the re-executed task.
+    fn fmt_tasks(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, link) in self.links.iter().rev().enumerate() {
+            if i > 0 {
+                write!(f, " → ")?;
+            }
+            write!(f, "{}", link.task)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for TaskDirtyChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.root_cause)?;
+        self.fmt_tasks(f)
+    }
+}
+
+/// All re-executions with the same root cause.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReexecutionSummary {
+    pub task_count: usize,
+    /// The longest chain from the root cause to one of the re-executed tasks.
+    pub longest_chain: TaskDirtyChain,
+}
+
+impl Display for ReexecutionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} re-ran {} tasks via ",
+            self.longest_chain.root_cause, self.task_count
+        )?;
+        self.longest_chain.fmt_tasks(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: u32) -> TaskId {
+        TaskId::try_from(id).unwrap()
+    }
+
+    fn describe_task(task_id: TaskId) -> String {
+        format!("task {}", *task_id)
+    }
+
+    #[test]
+    fn test_chain_to_invalidation() {
+        let causes = DirtyCauses::default();
+        causes.record(task(1), TaskDirtyCause::Invalidator);
+        causes.record_reason(task(1), "a.txt changed".to_string());
+        causes.record(task(2), TaskDirtyCause::OutputChange { task_id: task(1) });
+        causes.record(task(3), TaskDirtyCause::OutputChange { task_id: task(2) });
+        causes.record(task(4), TaskDirtyCause::InitialDirty);
+
+        let chain = causes.chain(task(3), &describe_task).unwrap();
+        assert_eq!(chain.root_cause, "a.txt changed");
+        assert_eq!(chain.links.len(), 3);
+        assert_eq!(chain.to_string(), "a.txt changed: task 1 → task 2 → task 3");
+        assert!(causes.chain(task(5), &describe_task).is_none());
+
+        for id in 1..=4 {
+            causes.task_execution_started(task(id));
+        }
+        let summary = causes.take_summary(&describe_task);
+        assert_eq!(summary.len(), 1);
+        assert_eq!(summary[0].task_count, 3);
+        assert_eq!(summary[0].longest_chain.links.len(), 3);
+        assert!(causes.take_summary(&describe_task).is_empty());
+    }
+
+    #[test]
+    fn test_chain_keeps_cause_at_time_of_invalidation() {
+        let causes = DirtyCauses::default();
+        causes.record(task(1), TaskDirtyCause::Invalidator);
+        causes.record_reason(task(1), "a.txt changed".to_string());
+        causes.record(task(2), TaskDirtyCause::OutputChange { task_id: task(1) });
+        // The upstream task is invalidated again before the task re-executes
+        causes.record(task(1), TaskDirtyCause::Invalidator);
+        causes.record_reason(task(1), "b.txt changed".to_string());
+
+        let chain = causes.chain(task(2), &describe_task).unwrap();
+        assert_eq!(chain.to_string(), "a.txt changed: task 1 → task 2");
+    }
+
+    #[test]
+    fn test_causes_are_removed_on_completion() {
+        let causes = DirtyCauses::default();
+        causes.record(task(1), TaskDirtyCause::InitialDirty);
+        causes.task_execution_started(task(1));
+        causes.task_execution_completed(task(1));
+        assert!(causes.chain(task(1), &describe_task).is_none());
+        assert!(causes.causes.is_empty());
+
+        causes.record(task(1), TaskDirtyCause::Invalidator);
+        causes.task_execution_started(task(1));
+        causes.task_execution_completed(task(1));
+        assert!(causes.causes.is_empty());
+        // Re-executions are kept until the next summary
+        assert!(causes.chain(task(1), &describe_task).is_some());
+        assert_eq!(causes.take_summary(&describe_task).len(), 1);
+        assert!(causes.chain(task(1), &describe_task).is_none());
+    }
+}
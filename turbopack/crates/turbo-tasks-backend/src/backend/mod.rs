@@ -1,6 +1,9 @@
+mod determinism;
+mod dirty_causes;
 mod dynamic_storage;
 mod operation;
 mod storage;
+mod wait_graph;
 
 use std::{
     borrow::Cow,
@@ -26,37 +29,49 @@ use smallvec::{SmallVec, smallvec};
 use tokio::time::{Duration, Instant};
 use tracing::{Span, field::Empty, info_span, trace_span};
 use turbo_tasks::{
-    CellId, FxDashMap, FxIndexMap, KeyValuePair, RawVc, ReadCellOptions, ReadConsistency,
-    ReadOutputOptions, ReadTracking, SessionId, TRANSIENT_TASK_BIT, TaskExecutionReason, TaskId,
-    TraitTypeId, TurboTasksBackendApi, ValueTypeId,
+    CellId, FxDashMap, FxIndexMap, FxIndexSet, InvalidationReason, KeyValuePair, RawVc,
+    ReadCellOptions, ReadConsistency, ReadOutputOptions, ReadTracking, SessionId,
+    TRANSIENT_TASK_BIT, TaskExecutionReason, TaskId, TraitTypeId, TurboTasksBackendApi,
+    ValueTypeId,
     backend::{
-        Backend, CachedTaskType, CellContent, TaskExecutionSpec, TransientTaskRoot,
+        Backend, CachedTaskType, CellContent, TaskCycleError, TaskExecutionSpec, TransientTaskRoot,
         TransientTaskType, TurboTasksExecutionError, TypedCellContent,
     },
     event::{Event, EventListener},
-    message_queue::TimingEvent,
-    registry::get_value_type,
+    message_queue::{DiagnosticEvent, Severity, TimingEvent},
+    registry::{get_trait, get_value_type},
+    task_graph::{
+        TaskGraph, TaskGraphCollectible, TaskGraphEdge, TaskGraphEdgeKind, TaskGraphNode,
+    },
     task_statistics::TaskStatisticsApi,
     trace::TraceRawVcs,
     turbo_tasks,
     util::{IdFactoryWithReuse, good_chunk_size},
 };
+use turbo_tasks_malloc::TurboMalloc;
 
-pub use self::{operation::AnyOperation, storage::TaskDataCategory};
-#[cfg(feature = "trace_task_dirty")]
-use crate::backend::operation::TaskDirtyCause;
+pub use self::{
+    determinism::{DeterminismViolation, DivergentCell},
+    dirty_causes::{ReexecutionSummary, TaskDirtyChain, TaskDirtyChainLink},
+    operation::AnyOperation,
+    storage::TaskDataCategory,
+};
 use crate::{
     backend::{
+        determinism::DeterminismVerifier,
+        dirty_causes::DirtyCauses,
         operation::{
             AggregatedDataUpdate, AggregationUpdateJob, AggregationUpdateQueue,
             CleanupOldEdgesOperation, ConnectChildOperation, ExecuteContext, ExecuteContextImpl,
-            Operation, OutdatedEdge, TaskGuard, connect_children, get_aggregation_number,
-            get_uppers, is_root_node, make_task_dirty_internal, prepare_new_children,
+            Operation, OutdatedEdge, TaskDirtyCause, TaskGuard, connect_children,
+            get_aggregation_number, get_uppers, is_root_node, make_task_dirty_internal,
+            prepare_new_children,
         },
         storage::{
             InnerStorageSnapshot, Storage, count, get, get_many, get_mut, get_mut_or_insert_with,
             iter_many, remove,
         },
+        wait_graph::WaitGraph,
     },
     backing_storage::BackingStorage,
     data::{
@@ -64,6 +79,7 @@ use crate::{
         CachedDataItemValueRef, CellRef, CollectibleRef, CollectiblesRef, DirtyState,
         InProgressCellState, InProgressState, InProgressStateInner, OutputValue, RootType,
     },
+    database::db_invalidation::invalidation_reasons,
     utils::{
         bi_map::BiMap, chunked_vec::ChunkedVec, dash_map_drop_contents::drop_contents,
         ptr_eq_arc::PtrEqArc, shard_amount::compute_shard_amount, sharded::Sharded, swap_retain,
@@ -139,6 +155,28 @@ pub struct BackendOptions {
 
     /// Avoid big preallocations for faster startup. Should only be used for testing purposes.
     pub small_preallocation: bool,
+
+    /// Memory budget in bytes, measured by `turbo-tasks-malloc`.
+    ///
+    /// When exceeded, tasks that haven't been accessed recently and are fully persisted are
+    /// dropped from memory. They are restored from the backing storage on next access. Only has an
+    /// effect with [`StorageMode::ReadWrite`].
+    pub memory_limit: Option<usize>,
+
+    /// Records why tasks have been made dirty, so re-executions can be traced back to the
+    /// invalidation that caused them. See [`TurboTasksBackend::task_dirty_chain`].
+    pub track_dirty_causes: bool,
+
+    /// Recomputes this fraction (between 0 and 1) of successfully completed tasks with the same
+    /// inputs and reports tasks that produce different cells or outputs as warning
+    /// [`DiagnosticEvent`]s. This is a debugging aid for non-deterministic functions. See
+    /// [`TurboTasksBackend::take_determinism_violations`].
+    pub verify_determinism: Option<f64>,
+
+    /// Detects dependency cycles between tasks that wait for each other and fails the reads that
+    /// would close a cycle with a [`TaskCycleError`] instead of waiting forever. This tracks every
+    /// wait for an in-progress task, so it has a cost.
+    pub detect_task_cycles: bool,
 }
 
 impl Default for BackendOptions {
@@ -149,6 +187,10 @@ impl Default for BackendOptions {
             storage_mode: Some(StorageMode::ReadWrite),
             num_workers: None,
             small_preallocation: false,
+            memory_limit: None,
+            track_dirty_causes: false,
+            verify_determinism: None,
+            detect_task_cycles: false,
         }
     }
 }
@@ -184,6 +226,11 @@ struct TurboTasksBackendInner<B: BackingStorage> {
     /// When true, the backing_storage has data that is not in the local storage.
     local_is_partial: AtomicBool,
 
+    /// Incremented when tasks are evicted from memory. Read transactions that have been started
+    /// before might not contain the latest persisted data of the evicted tasks, so they must not
+    /// be used to restore them.
+    evictions: AtomicUsize,
+
     /// Number of executing operations + Highest bit is set when snapshot is
     /// requested. When that bit is set, operations should pause until the
     /// snapshot is completed. When the bit is set and in progress counter
@@ -211,6 +258,13 @@ struct TurboTasksBackendInner<B: BackingStorage> {
 
     task_statistics: TaskStatisticsApi,
 
+    dirty_causes: Option<DirtyCauses>,
+
+    determinism_verifier: Option<DeterminismVerifier>,
+
+    /// Detects dependency cycles between tasks that wait for each other.
+    wait_graph: Option<WaitGraph>,
+
     backing_storage: B,
 
     #[cfg(feature = "verify_aggregation_graph")]
@@ -228,6 +282,46 @@ impl<B: BackingStorage> TurboTasksBackend<B> {
     pub fn backing_storage(&self) -> &B {
         &self.0.backing_storage
     }
+
+    /// Explains why a task has been re-executed by following the recorded causes back to the
+    /// invalidation, e.g. a file change. Requires [`BackendOptions::track_dirty_causes`].
+    pub fn task_dirty_chain(&self, task_id: TaskId) -> Option<TaskDirtyChain> {
+        let dirty_causes = self.0.dirty_causes.as_ref()?;
+        dirty_causes.chain(task_id, &|task_id| self.0.get_task_description(task_id))
+    }
+
+    /// Returns all re-executions since the last call, grouped by the invalidation that caused
+    /// them. This explains what an update has spent its time on. Requires
+    /// [`BackendOptions::track_dirty_causes`].
+    pub fn take_reexecution_summary(&self) -> Vec<ReexecutionSummary> {
+        let Some(dirty_causes) = &self.0.dirty_causes else {
+            return Vec::new();
+        };
+        dirty_causes.take_summary(&|task_id| self.0.get_task_description(task_id))
+    }
+
+    /// Persists all changes to the backing storage right away, instead of waiting for the next
+    /// periodic snapshot. Returns `false` when persisting is disabled or has failed. Blocks until
+    /// all in-progress operations have been suspended.
+    pub fn snapshot_now(&self) -> bool {
+        self.0.should_persist() && self.0.snapshot().is_some()
+    }
+
+    /// Drops tasks from memory that haven't been accessed since the last call and have no
+    /// unpersisted changes, like when [`BackendOptions::memory_limit`] is exceeded. They are
+    /// restored from the backing storage on next access. Returns the number of evicted tasks.
+    pub fn evict_cold_tasks_now(&self) -> usize {
+        self.0.evict_cold_tasks()
+    }
+
+    /// Returns all tasks that have been found to be non-deterministic since the last call.
+    /// Requires [`BackendOptions::verify_determinism`].
+    pub fn take_determinism_violations(&self) -> Vec<DeterminismViolation> {
+        let Some(determinism_verifier) = &self.0.determinism_verifier else {
+            return Vec::new();
+        };
+        determinism_verifier.take_violations()
+    }
 }
 
 impl<B: BackingStorage> TurboTasksBackendInner<B> {
@@ -238,6 +332,9 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             options.active_tracking = false;
         }
         let small_preallocation = options.small_preallocation;
+        let dirty_causes = options.track_dirty_causes.then(DirtyCauses::default);
+        let determinism_verifier = options.verify_determinism.map(DeterminismVerifier::new);
+        let wait_graph = options.detect_task_cycles.then(WaitGraph::default);
         let next_task_id = backing_storage
             .next_free_task_id()
             .expect("Failed to get task id");
@@ -259,6 +356,7 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             task_cache: BiMap::new(),
             transient_tasks: FxDashMap::default(),
             local_is_partial: AtomicBool::new(next_task_id != TaskId::MIN),
+            evictions: AtomicUsize::new(0),
             storage: Storage::new(shard_amount, small_preallocation),
             in_progress_operations: AtomicUsize::new(0),
             snapshot_request: Mutex::new(SnapshotRequest::new()),
@@ -272,6 +370,9 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             #[cfg(feature = "verify_aggregation_graph")]
             is_idle: AtomicBool::new(false),
             task_statistics: TaskStatisticsApi::default(),
+            dirty_causes,
+            determinism_verifier,
+            wait_graph,
             backing_storage,
             #[cfg(feature = "verify_aggregation_graph")]
             root_tasks: Default::default(),
@@ -291,17 +392,19 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
 
     /// # Safety
     ///
-    /// `tx` must be a transaction from this TurboTasksBackendInner instance.
+    /// `tx` must be a transaction from this TurboTasksBackendInner instance. `tx_evictions` is the
+    /// value of [`Self::evictions`] when `tx` has been started.
     unsafe fn execute_context_with_tx<'e, 'tx>(
         &'e self,
         tx: Option<&'e B::ReadTransaction<'tx>>,
+        tx_evictions: usize,
         turbo_tasks: &'e dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
     ) -> impl ExecuteContext<'e> + use<'e, 'tx, B>
     where
         'tx: 'e,
     {
         // Safety: `tx` is from `self`.
-        unsafe { ExecuteContextImpl::new_with_tx(self, tx, turbo_tasks) }
+        unsafe { ExecuteContextImpl::new_with_tx(self, tx, tx_evictions, turbo_tasks) }
     }
 
     fn suspending_requested(&self) -> bool {
@@ -374,6 +477,84 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         self.options.storage_mode.is_some()
     }
 
+    /// Handles a failed read of the task cache or the meta data of a task like a cache miss.
+    /// Aggregation and edges to other tasks are lost in this case, so the persistent cache is
+    /// invalidated to start from a clean cache next time.
+    fn handle_corrupted_cache(&self, error: anyhow::Error) {
+        tracing::warn!(
+            "Failed to read from the persistent cache (corrupted database or bug), it will be \
+             invalidated: {error:?}"
+        );
+        if let Err(err) = self
+            .backing_storage
+            .invalidate(invalidation_reasons::CORRUPTED)
+        {
+            tracing::warn!("Failed to invalidate the persistent cache: {err:?}");
+        }
+    }
+
+    /// Handles a failed read of the data of a single task. Only this task is affected: its data is
+    /// dropped and it's recomputed, which recreates its cells and dependencies. The rest of the
+    /// persistent cache stays valid.
+    fn handle_corrupted_task_data(&self, error: anyhow::Error) {
+        tracing::warn!(
+            "Failed to read task data from the persistent cache (corrupted database or bug), the \
+             task will be recomputed: {error:?}"
+        );
+    }
+
+    fn is_over_memory_limit(&self) -> bool {
+        self.options
+            .memory_limit
+            .is_some_and(|limit| TurboMalloc::memory_usage() > limit)
+    }
+
+    /// Drops cold tasks from memory when the memory limit is exceeded. Must be called after a
+    /// snapshot, as only tasks without unpersisted changes are evicted.
+    fn evict_if_over_memory_limit(&self) {
+        if self.is_over_memory_limit() {
+            self.evict_cold_tasks();
+        }
+    }
+
+    /// Drops tasks from memory that haven't been accessed since the last call and are fully
+    /// persisted. Returns the number of evicted tasks.
+    ///
+    /// All operations are suspended while evicting, like for a snapshot, so no task is evicted
+    /// while an operation restores or modifies it.
+    fn evict_cold_tasks(&self) -> usize {
+        if !self.should_persist() {
+            return 0;
+        }
+        let span = info_span!("evict cold tasks", evicted = Empty).entered();
+        let mut snapshot_request = self.snapshot_request.lock();
+        if snapshot_request.snapshot_requested {
+            // A snapshot is in progress, it suspends the operations on its own
+            return 0;
+        }
+        snapshot_request.snapshot_requested = true;
+        let active_operations = self
+            .in_progress_operations
+            .fetch_or(SNAPSHOT_REQUESTED_BIT, Ordering::Relaxed);
+        if active_operations != 0 {
+            self.operations_suspended
+                .wait_while(&mut snapshot_request, |_| {
+                    self.in_progress_operations.load(Ordering::Relaxed) != SNAPSHOT_REQUESTED_BIT
+                });
+        }
+        // Evicted tasks need to be restored from the backing storage on next access.
+        self.local_is_partial.store(true, Ordering::Release);
+        self.evictions.fetch_add(1, Ordering::AcqRel);
+        let evicted = self.storage.evict_cold_tasks();
+        snapshot_request.snapshot_requested = false;
+        self.in_progress_operations
+            .fetch_sub(SNAPSHOT_REQUESTED_BIT, Ordering::Relaxed);
+        self.snapshot_completed.notify_all();
+        drop(snapshot_request);
+        span.record("evicted", evicted);
+        evicted
+    }
+
     fn should_track_dependencies(&self) -> bool {
         self.options.dependency_tracking
     }
@@ -435,12 +616,13 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
     unsafe fn connect_child_with_tx<'l, 'tx: 'l>(
         &'l self,
         tx: Option<&'l B::ReadTransaction<'tx>>,
+        tx_evictions: usize,
         parent_task: Option<TaskId>,
         child_task: TaskId,
         turbo_tasks: &'l dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
     ) {
         operation::ConnectChildOperation::run(parent_task, child_task, unsafe {
-            self.execute_context_with_tx(tx, turbo_tasks)
+            self.execute_context_with_tx(tx, tx_evictions, turbo_tasks)
         });
     }
 
@@ -507,21 +689,28 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             ctx: &impl ExecuteContext<'_>,
         ) -> Option<std::result::Result<std::result::Result<RawVc, EventListener>, anyhow::Error>>
         {
-            match get!(task, InProgress) {
-                Some(InProgressState::Scheduled { done_event, .. }) => Some(Ok(Err(
-                    listen_to_done_event(this, reader, tracking, done_event),
-                ))),
+            let done_event = match get!(task, InProgress) {
+                Some(InProgressState::Scheduled { done_event, .. }) => done_event,
                 Some(InProgressState::InProgress(box InProgressStateInner {
                     done_event, ..
-                })) => Some(Ok(Err(listen_to_done_event(
-                    this, reader, tracking, done_event,
-                )))),
-                Some(InProgressState::Canceled) => Some(Err(anyhow::anyhow!(
-                    "{} was canceled",
-                    ctx.get_task_description(task.id())
-                ))),
-                None => None,
+                })) => done_event,
+                Some(InProgressState::Canceled) => {
+                    return Some(Err(anyhow::anyhow!(
+                        "{} was canceled",
+                        ctx.get_task_description(task.id())
+                    )));
+                }
+                None => return None,
+            };
+            if let Some(reader) = reader
+                && let Some(wait_graph) = &this.wait_graph
+                && let Some(cycle) = wait_graph.wait(reader, task.id())
+            {
+                return Some(Err(this.task_cycle_error(&cycle)));
             }
+            Some(Ok(Err(listen_to_done_event(
+                this, reader, tracking, done_event,
+            ))))
         }
 
         if matches!(options.consistency, ReadConsistency::Strong) {
@@ -764,6 +953,13 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             }
         };
 
+        if let Some(reader) = reader
+            && let Some(wait_graph) = &self.wait_graph
+            && let Some(cycle) = wait_graph.wait(reader, task_id)
+        {
+            return Err(self.task_cycle_error(&cycle));
+        }
+
         // Output doesn't exist. We need to schedule the task to compute it.
         let (item, listener) = CachedDataItem::new_scheduled_with_listener(
             TaskExecutionReason::OutputNotAvailable,
@@ -962,7 +1158,10 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             && let Some(task_type) = unsafe {
                 self.backing_storage
                     .reverse_lookup_task_cache(None, task_id)
-                    .expect("Failed to lookup task type")
+                    .unwrap_or_else(|err| {
+                        self.handle_corrupted_cache(err);
+                        None
+                    })
             }
         {
             let _ = self.task_cache.try_insert(task_type.clone(), task_id);
@@ -971,6 +1170,23 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         None
     }
 
+    /// Describes a task by its function name and arguments.
+    fn get_task_description_with_arguments(&self, task_id: TaskId) -> String {
+        match self.lookup_task_type(task_id) {
+            Some(task_type) => format!("{task_type}({})", format_task_arguments(&task_type)),
+            None => self.get_task_description(task_id),
+        }
+    }
+
+    fn task_cycle_error(&self, cycle: &[TaskId]) -> anyhow::Error {
+        anyhow::Error::new(TaskCycleError {
+            cycle: cycle
+                .iter()
+                .map(|&task_id| self.get_task_description_with_arguments(task_id).into())
+                .collect(),
+        })
+    }
+
     fn get_task_desc_fn(&self, task_id: TaskId) -> impl Fn() -> String + Send + Sync + 'static {
         let task_type = self.lookup_task_type(task_id);
         move || {
@@ -1329,6 +1545,7 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
 
         let check_backing_storage =
             self.should_restore() && self.local_is_partial.load(Ordering::Acquire);
+        let tx_evictions = self.evictions.load(Ordering::Acquire);
         let tx = check_backing_storage
             .then(|| self.backing_storage.start_read_transaction())
             .flatten();
@@ -1339,7 +1556,10 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                     .then(|| {
                         self.backing_storage
                             .forward_lookup_task_cache(tx.as_ref(), &task_type)
-                            .expect("Failed to lookup task id")
+                            .unwrap_or_else(|err| {
+                                self.handle_corrupted_cache(err);
+                                None
+                            })
                     })
                     .flatten()
             } {
@@ -1370,7 +1590,9 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         };
 
         // Safety: `tx` is a valid transaction from `self.backend.backing_storage`.
-        unsafe { self.connect_child_with_tx(tx.as_ref(), parent_task, task_id, turbo_tasks) };
+        unsafe {
+            self.connect_child_with_tx(tx.as_ref(), tx_evictions, parent_task, task_id, turbo_tasks)
+        };
 
         task_id
     }
@@ -1515,7 +1737,6 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         }
         operation::InvalidateOperation::run(
             smallvec![task_id],
-            #[cfg(feature = "trace_task_dirty")]
             TaskDirtyCause::Invalidator,
             self.execute_context(turbo_tasks),
         );
@@ -1531,7 +1752,6 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         }
         operation::InvalidateOperation::run(
             tasks.iter().copied().collect(),
-            #[cfg(feature = "trace_task_dirty")]
             TaskDirtyCause::Unknown,
             self.execute_context(turbo_tasks),
         );
@@ -1547,7 +1767,6 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         }
         operation::InvalidateOperation::run(
             tasks.iter().copied().collect(),
-            #[cfg(feature = "trace_task_dirty")]
             TaskDirtyCause::Unknown,
             self.execute_context(turbo_tasks),
         );
@@ -1595,6 +1814,9 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         task.add_new(CachedDataItem::InProgress {
             value: InProgressState::Canceled,
         });
+        if let Some(wait_graph) = &self.wait_graph {
+            wait_graph.task_finished(task_id);
+        }
     }
 
     fn try_start_task_execution(
@@ -1626,6 +1848,19 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                 return None;
             };
             execution_reason = reason;
+            if let Some(dirty_causes) = &self.dirty_causes {
+                dirty_causes.task_execution_started(task_id);
+            }
+            if let Some(wait_graph) = &self.wait_graph {
+                // A previous execution might have been waiting for other tasks
+                wait_graph.task_execution_started(task_id);
+            }
+            if let Some(determinism_verifier) = &self.determinism_verifier
+                && task.has_key(&CachedDataItemKey::Dirty {})
+            {
+                // The inputs might have changed since the task has been selected for verification
+                determinism_verifier.cancel(task_id);
+            }
             task.add_new(CachedDataItem::InProgress {
                 value: InProgressState::InProgress(Box::new(InProgressStateInner {
                     stale: false,
@@ -1761,18 +1996,29 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
 
         self.track_task_duration(task_id, duration);
 
+        // Stateful tasks and tasks with invalidators depend on external state, so they are not
+        // expected to be deterministic.
+        let can_verify_determinism = result.is_ok() && !stateful && !has_invalidator;
+
+        // A recomputation for verification is completed with the cell counters of the first
+        // execution, so it doesn't change the cells of the task
+        let first_cell_counters = self
+            .determinism_verifier
+            .as_ref()
+            .and_then(|determinism_verifier| determinism_verifier.first_cell_counters(task_id));
+
         let Some(TaskExecutionCompletePrepareResult {
             new_children,
             mut removed_data,
             is_now_immutable,
-            new_output,
-            output_dependent_tasks,
+            mut new_output,
+            mut output_dependent_tasks,
         }) = self.task_execution_completed_prepare(
             &mut ctx,
             &span,
             task_id,
             result,
-            cell_counters,
+            first_cell_counters.as_ref().unwrap_or(cell_counters),
             stateful,
             has_invalidator,
         )
@@ -1781,6 +2027,16 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             return true;
         };
 
+        // A recomputation for verification keeps the output of the first execution, so dependent
+        // tasks are not invalidated
+        if first_cell_counters.is_some()
+            && let Some(determinism_verifier) = &self.determinism_verifier
+            && new_output.take().is_some()
+        {
+            determinism_verifier.output_diverged(task_id);
+            output_dependent_tasks.clear();
+        }
+
         // When restoring from filesystem cache the following might not be executed (since we can
         // suspend in `CleanupOldEdgesOperation`), but that's ok as the task is still dirty and
         // would be executed again.
@@ -1821,6 +2077,15 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
 
         self.task_execution_completed_cleanup(&mut ctx, task_id);
 
+        if self.determinism_verifier.is_some() {
+            self.task_execution_completed_verify_determinism(
+                &mut ctx,
+                task_id,
+                cell_counters,
+                can_verify_determinism,
+            );
+        }
+
         false
     }
 
@@ -1850,7 +2115,7 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         let &mut InProgressState::InProgress(box InProgressStateInner {
             stale,
             ref mut new_children,
-            session_dependent,
+            ref mut session_dependent,
             ..
         }) = in_progress
         else {
@@ -1892,6 +2157,15 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             return None;
         }
 
+        // A cycle error is only valid for the current session, as the cycle might depend on the
+        // order of execution. So it's not persisted as the output of the task.
+        if let Some(wait_graph) = &self.wait_graph
+            && wait_graph.take_cycle_reader(task_id)
+        {
+            *session_dependent = true;
+        }
+        let session_dependent = *session_dependent;
+
         // take the children from the task to process them
         let mut new_children = take(new_children);
 
@@ -2009,8 +2283,10 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                         {
                             return Some(OutdatedEdge::RemovedCellDependent {
                                 task_id: task,
-                                #[cfg(feature = "trace_task_dirty")]
-                                value_type_id: cell.type_id,
+                                cause: TaskDirtyCause::CellRemoved {
+                                    value_type: cell.type_id,
+                                    task_id,
+                                },
                             });
                         }
                         None
@@ -2126,7 +2402,6 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                 dependent,
                 dependent_task_id,
                 true,
-                #[cfg(feature = "trace_task_dirty")]
                 TaskDirtyCause::OutputChange { task_id },
                 &mut queue,
                 ctx,
@@ -2151,7 +2426,6 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                     child_task,
                     child_id,
                     false,
-                    #[cfg(feature = "trace_task_dirty")]
                     TaskDirtyCause::InitialDirty,
                     &mut queue,
                     ctx,
@@ -2268,6 +2542,12 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             return true;
         }
 
+        // Dependents have been made dirty already, so the cause is no longer needed. This happens
+        // while holding the task lock, so a new cause can't be recorded in the meantime.
+        if let Some(dirty_causes) = &self.dirty_causes {
+            dirty_causes.task_execution_completed(task_id);
+        }
+
         // Set the output if it has changed
         let mut old_content = None;
         if let Some(value) = new_output {
@@ -2353,6 +2633,11 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             None
         };
 
+        // Before releasing the task, as a new execution could be awaited afterwards
+        if let Some(wait_graph) = &self.wait_graph {
+            wait_graph.task_finished(task_id);
+        }
+
         drop(task);
         drop(old_content);
 
@@ -2376,6 +2661,55 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         drop(task);
     }
 
+    /// Reports the result when the task has been recomputed for verification. Otherwise it might
+    /// select the task for verification and schedule a recomputation.
+    fn task_execution_completed_verify_determinism(
+        &self,
+        ctx: &mut impl ExecuteContext<'_>,
+        task_id: TaskId,
+        cell_counters: &AutoMap<ValueTypeId, u32, BuildHasherDefault<FxHasher>, 8>,
+        can_verify: bool,
+    ) {
+        let Some(determinism_verifier) = &self.determinism_verifier else {
+            return;
+        };
+        match determinism_verifier
+            .finish(task_id, cell_counters, || ctx.get_task_description(task_id))
+        {
+            Some(Ok(())) => return,
+            Some(Err(violation)) => {
+                tracing::warn!("{violation}");
+                turbo_tasks().send_compilation_event(Arc::new(DiagnosticEvent::new(
+                    Severity::Warning,
+                    violation.to_string(),
+                )));
+                return;
+            }
+            None => {}
+        }
+        // Root and once tasks can't be recomputed with the same inputs
+        if !can_verify
+            || self.transient_tasks.contains_key(&task_id)
+            || !determinism_verifier.select()
+        {
+            return;
+        }
+        let mut task = ctx.task(task_id, TaskDataCategory::All);
+        if task.has_key(&CachedDataItemKey::Dirty {})
+            || task.has_key(&CachedDataItemKey::InProgress {})
+        {
+            return;
+        }
+        let cells = iter_many!(task, CellData { cell } value => (cell, value.clone())).collect();
+        determinism_verifier.start(task_id, cells, cell_counters.clone());
+        // Recompute the task without making it dirty, so dependent tasks are not invalidated
+        task.add_new(CachedDataItem::new_scheduled(
+            TaskExecutionReason::VerifyDeterminism,
+            || self.get_task_desc_fn(task_id),
+        ));
+        ctx.schedule_task(task);
+    }
+
     fn run_backend_job<'a>(
         self: &'a Arc<Self>,
         job: TurboTasksBackendJob,
@@ -2392,6 +2726,7 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                         const FIRST_SNAPSHOT_WAIT: Duration = Duration::from_secs(300);
                         const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(120);
                         const IDLE_TIMEOUT: Duration = Duration::from_secs(2);
+                        const MEMORY_CHECK_INTERVAL: Duration = Duration::from_secs(10);
 
                         let (time, mut reason) =
                             if matches!(job, TurboTasksBackendJob::InitialSnapshot) {
@@ -2435,6 +2770,16 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                                             break;
                                         }
                                     },
+                                    _ = tokio::time::sleep(MEMORY_CHECK_INTERVAL),
+                                        if self.options.memory_limit.is_some() =>
+                                    {
+                                        // Evicting requires a snapshot first, since only
+                                        // persisted tasks can be evicted.
+                                        if self.is_over_memory_limit() {
+                                            reason = "memory limit";
+                                            break;
+                                        }
+                                    },
                                 }
                             }
                         }
@@ -2444,6 +2789,7 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                         let snapshot = this.snapshot();
                         if let Some((snapshot_start, new_data)) = snapshot {
                             last_snapshot = snapshot_start;
+                            self.evict_if_over_memory_limit();
                             if new_data {
                                 continue;
                             }
@@ -2654,6 +3000,11 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         content: CellContent,
         turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
     ) {
+        if let Some(determinism_verifier) = &self.determinism_verifier
+            && determinism_verifier.compare_cell(task_id, cell, &content)
+        {
+            return;
+        }
         operation::UpdateCellOperation::run(
             task_id,
             cell,
@@ -2815,6 +3166,109 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         }
     }
 
+    fn task_graph(
+        &self,
+        root: TaskId,
+        max_depth: usize,
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+    ) -> TaskGraph {
+        let mut ctx = self.execute_context(turbo_tasks);
+        let mut graph = TaskGraph {
+            root: Some(root),
+            ..Default::default()
+        };
+        let mut visited = FxHashSet::default();
+        // Tasks that are only referenced by a dependency edge or beyond the depth limit
+        let mut unexpanded = FxIndexSet::default();
+        let mut queue = std::collections::VecDeque::new();
+        visited.insert(root);
+        queue.push_back((root, 0));
+        while let Some((task_id, depth)) = queue.pop_front() {
+            let task = ctx.task(task_id, TaskDataCategory::All);
+            let expanded = depth < max_depth;
+            graph.tasks.push(self.task_graph_node(&task, expanded));
+            let mut edges = iter_many!(task, OutputDependency { target } => {
+                (target, TaskGraphEdgeKind::OutputDependency)
+            })
+            .chain(iter_many!(task, CellDependency { target } => {
+                (target.task, TaskGraphEdgeKind::CellDependency {
+                    value_type: get_value_type(target.cell.type_id).name.to_string(),
+                    index: target.cell.index,
+                })
+            }))
+            .chain(iter_many!(task, CollectiblesDependency { target } => {
+                (target.task, TaskGraphEdgeKind::CollectiblesDependency {
+                    collectible_type: get_trait(target.collectible_type).name.to_string(),
+                })
+            }))
+            .collect::<Vec<_>>();
+            if expanded {
+                edges.extend(iter_many!(task, Child { task } => (task, TaskGraphEdgeKind::Child)));
+            }
+            drop(task);
+            for (target, kind) in edges {
+                if matches!(kind, TaskGraphEdgeKind::Child) {
+                    if visited.insert(target) {
+                        unexpanded.shift_remove(&target);
+                        queue.push_back((target, depth + 1));
+                    }
+                } else if !visited.contains(&target) {
+                    unexpanded.insert(target);
+                }
+                graph.edges.push(TaskGraphEdge {
+                    from: task_id,
+                    to: target,
+                    kind,
+                });
+            }
+        }
+        for task_id in unexpanded {
+            if !visited.contains(&task_id) {
+                let task = ctx.task(task_id, TaskDataCategory::All);
+                graph.tasks.push(self.task_graph_node(&task, false));
+            }
+        }
+        graph
+    }
+
+    fn task_graph_node(&self, task: &impl TaskGuard, expanded: bool) -> TaskGraphNode {
+        let task_id = task.id();
+        let task_type = self.lookup_task_type(task_id);
+        let function = task_type.as_ref().map_or_else(
+            || self.get_task_description(task_id),
+            |task_type| task_type.to_string(),
+        );
+        let arguments = task_type.map(|task_type| format_task_arguments(&task_type));
+        let mut collectibles: FxIndexMap<TraitTypeId, i32> = FxIndexMap::default();
+        for (collectible, count) in
+            iter_many!(task, Collectible { collectible } value => (collectible, *value))
+        {
+            if count > 0 {
+                *collectibles
+                    .entry(collectible.collectible_type)
+                    .or_default() += count;
+            }
+        }
+        TaskGraphNode {
+            id: task_id,
+            function,
+            arguments,
+            dirty: get!(task, Dirty).is_some_and(|dirty| dirty.get(self.session_id)),
+            in_progress: get!(task, InProgress).is_some(),
+            aggregation_number: get_aggregation_number(task),
+            dirty_containers: get!(task, AggregatedDirtyContainerCount)
+                .map_or(0, |count| count.get(self.session_id)),
+            collectibles: collectibles
+                .into_iter()
+                .map(|(collectible_type, count)| TaskGraphCollectible {
+                    collectible_type: get_trait(collectible_type).name.to_string(),
+                    count,
+                })
+                .collect(),
+            expanded,
+        }
+    }
+
     #[cfg(feature = "verify_aggregation_graph")]
     fn verify_aggregation_graph(
         &self,
@@ -3109,6 +3563,18 @@ impl<B: BackingStorage> Backend for TurboTasksBackend<B> {
         self.0.invalidate_task(task_id, turbo_tasks);
     }
 
+    fn invalidate_task_with_reason(
+        &self,
+        task_id: TaskId,
+        reason: &dyn InvalidationReason,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+        self.0.invalidate_task(task_id, turbo_tasks);
+        if let Some(dirty_causes) = &self.0.dirty_causes {
+            dirty_causes.record_reason(task_id, reason.to_string());
+        }
+    }
+
     fn invalidate_tasks(&self, tasks: &[TaskId], turbo_tasks: &dyn TurboTasksBackendApi<Self>) {
         self.0.invalidate_tasks(tasks, turbo_tasks);
     }
@@ -3133,6 +3599,10 @@ impl<B: BackingStorage> Backend for TurboTasksBackend<B> {
         self.0.get_task_description(task)
     }
 
+    fn get_task_description_with_arguments(&self, task: TaskId) -> String {
+        self.0.get_task_description_with_arguments(task)
+    }
+
     fn task_execution_canceled(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi<Self>) {
         self.0.task_execution_canceled(task, turbo_tasks)
     }
@@ -3303,6 +3773,15 @@ impl<B: BackingStorage> Backend for TurboTasksBackend<B> {
         self.0.dispose_root_task(task_id, turbo_tasks);
     }
 
+    fn task_graph(
+        &self,
+        root: TaskId,
+        max_depth: usize,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) -> TaskGraph {
+        self.0.task_graph(root, max_depth, turbo_tasks)
+    }
+
     fn task_statistics(&self) -> &TaskStatisticsApi {
         &self.0.task_statistics
     }
@@ -3399,6 +3878,22 @@ impl fmt::Display for DebugTraceTransientTask {
 }
 
 // from https://github.com/tokio-rs/tokio/blob/29cd6ec1ec6f90a7ee1ad641c03e0e00badbcb0e/tokio/src/time/instant.rs#L57-L63
+/// Formats the `self` argument and the arguments of a task for debugging. Long arguments are cut
+/// off to keep the output readable.
+fn format_task_arguments(task_type: &CachedTaskType) -> String {
+    const MAX_ARGUMENTS_LENGTH: usize = 200;
+
+    let mut arguments = match &task_type.this {
+        Some(this) => format!("self: {this:?}, {:?}", task_type.arg),
+        None => format!("{:?}", task_type.arg),
+    };
+    if let Some((index, _)) = arguments.char_indices().nth(MAX_ARGUMENTS_LENGTH) {
+        arguments.truncate(index);
+        arguments.push('…');
+    }
+    arguments
+}
+
 fn far_future() -> Instant {
     // Roughly 30 years from now.
     // API does not provide a way to obtain max `Instant`
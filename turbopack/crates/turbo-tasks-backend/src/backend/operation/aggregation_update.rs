@@ -22,12 +22,12 @@ use smallvec::{SmallVec, smallvec};
 use tracing::{span::Span, trace_span};
 use turbo_tasks::{FxIndexMap, SessionId, TaskExecutionReason, TaskId};
 
-#[cfg(feature = "trace_task_dirty")]
-use crate::backend::operation::invalidate::TaskDirtyCause;
 use crate::{
     backend::{
         TaskDataCategory, get_mut, get_mut_or_insert_with,
-        operation::{ExecuteContext, Operation, TaskGuard, invalidate::make_task_dirty},
+        operation::{
+            ExecuteContext, Operation, TaskDirtyCause, TaskGuard, invalidate::make_task_dirty,
+        },
         storage::{count, get, get_many, iter_many, remove, update, update_count},
     },
     data::{
@@ -173,8 +173,8 @@ pub enum AggregationUpdateJob {
     /// Invalidates tasks that are dependent on a collectible type.
     InvalidateDueToCollectiblesChange {
         task_ids: TaskIdVec,
-        #[cfg(feature = "trace_task_dirty")]
-        collectible_type: turbo_tasks::TraitTypeId,
+        #[cfg_attr(not(feature = "trace_task_dirty"), serde(skip))]
+        cause: TaskDirtyCause,
     },
     /// Increases the active counter of the task
     #[serde(skip)]
@@ -389,8 +389,10 @@ impl AggregatedDataUpdate {
                 if !dependent.is_empty() {
                     queue.push(AggregationUpdateJob::InvalidateDueToCollectiblesChange {
                         task_ids: dependent,
-                        #[cfg(feature = "trace_task_dirty")]
-                        collectible_type: ty,
+                        cause: TaskDirtyCause::CollectiblesChange {
+                            collectible_type: ty,
+                            task_id: collectible.cell.task,
+                        },
                     })
                 }
             }
@@ -865,19 +867,9 @@ impl AggregationUpdateQueue {
                 }) => {
                     self.aggregated_data_update(upper_ids, ctx, update);
                 }
-                AggregationUpdateJob::InvalidateDueToCollectiblesChange {
-                    task_ids,
-                    #[cfg(feature = "trace_task_dirty")]
-                    collectible_type,
-                } => {
+                AggregationUpdateJob::InvalidateDueToCollectiblesChange { task_ids, cause } => {
                     for task_id in task_ids {
-                        make_task_dirty(
-                            task_id,
-                            #[cfg(feature = "trace_task_dirty")]
-                            TaskDirtyCause::CollectiblesChange { collectible_type },
-                            self,
-                            ctx,
-                        );
+                        make_task_dirty(task_id, cause, self, ctx);
                     }
                 }
                 AggregationUpdateJob::DecreaseActiveCount { task } => {
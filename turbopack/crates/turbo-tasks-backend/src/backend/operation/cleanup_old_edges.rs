@@ -5,13 +5,11 @@ use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 use turbo_tasks::TaskId;
 
-#[cfg(feature = "trace_task_dirty")]
-use crate::backend::operation::invalidate::TaskDirtyCause;
 use crate::{
     backend::{
         TaskDataCategory, get, get_many,
         operation::{
-            AggregatedDataUpdate, ExecuteContext, Operation, TaskGuard,
+            AggregatedDataUpdate, ExecuteContext, Operation, TaskDirtyCause, TaskGuard,
             aggregation_update::{
                 AggregationUpdateJob, AggregationUpdateQueue, InnerOfUppersLostFollowersJob,
                 get_aggregation_number, get_uppers, is_aggregating_node,
@@ -47,8 +45,8 @@ pub enum OutdatedEdge {
     CollectiblesDependency(CollectiblesRef),
     RemovedCellDependent {
         task_id: TaskId,
-        #[cfg(feature = "trace_task_dirty")]
-        value_type_id: turbo_tasks::ValueTypeId,
+        #[cfg_attr(not(feature = "trace_task_dirty"), serde(skip))]
+        cause: TaskDirtyCause,
     },
 }
 
@@ -150,8 +148,10 @@ impl Operation for CleanupOldEdgesOperation {
                                     queue.push(
                                         AggregationUpdateJob::InvalidateDueToCollectiblesChange {
                                             task_ids,
-                                            #[cfg(feature = "trace_task_dirty")]
-                                            collectible_type: ty,
+                                            cause: TaskDirtyCause::CollectiblesChange {
+                                                collectible_type: ty,
+                                                task_id,
+                                            },
                                         },
                                     );
                                 }
@@ -218,19 +218,10 @@ impl Operation for CleanupOldEdgesOperation {
                                 }
                             }
                             OutdatedEdge::RemovedCellDependent {
-                                task_id,
-                                #[cfg(feature = "trace_task_dirty")]
-                                value_type_id,
+                                task_id: dependent_task_id,
+                                cause,
                             } => {
-                                make_task_dirty(
-                                    task_id,
-                                    #[cfg(feature = "trace_task_dirty")]
-                                    TaskDirtyCause::CellRemoved {
-                                        value_type: value_type_id,
-                                    },
-                                    queue,
-                                    ctx,
-                                );
+                                make_task_dirty(dependent_task_id, cause, queue, ctx);
                             }
                         }
                     }
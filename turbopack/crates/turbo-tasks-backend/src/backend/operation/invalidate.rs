@@ -24,7 +24,7 @@ use crate::{
 pub enum InvalidateOperation {
     MakeDirty {
         task_ids: SmallVec<[TaskId; 4]>,
-        #[cfg(feature = "trace_task_dirty")]
+        #[cfg_attr(not(feature = "trace_task_dirty"), serde(skip))]
         cause: TaskDirtyCause,
     },
     AggregationUpdate {
@@ -37,15 +37,10 @@ pub enum InvalidateOperation {
 impl InvalidateOperation {
     pub fn run(
         task_ids: SmallVec<[TaskId; 4]>,
-        #[cfg(feature = "trace_task_dirty")] cause: TaskDirtyCause,
+        cause: TaskDirtyCause,
         mut ctx: impl ExecuteContext,
     ) {
-        InvalidateOperation::MakeDirty {
-            task_ids,
-            #[cfg(feature = "trace_task_dirty")]
-            cause,
-        }
-        .execute(&mut ctx)
+        InvalidateOperation::MakeDirty { task_ids, cause }.execute(&mut ctx)
     }
 }
 
@@ -54,20 +49,10 @@ impl Operation for InvalidateOperation {
         loop {
             ctx.operation_suspend_point(&self);
             match self {
-                InvalidateOperation::MakeDirty {
-                    task_ids,
-                    #[cfg(feature = "trace_task_dirty")]
-                    cause,
-                } => {
+                InvalidateOperation::MakeDirty { task_ids, cause } => {
                     let mut queue = AggregationUpdateQueue::new();
                     for task_id in task_ids {
-                        make_task_dirty(
-                            task_id,
-                            #[cfg(feature = "trace_task_dirty")]
-                            cause,
-                            &mut queue,
-                            ctx,
-                        );
+                        make_task_dirty(task_id, cause, &mut queue, ctx);
                     }
                     if queue.is_empty() {
                         self = InvalidateOperation::Done
@@ -89,23 +74,31 @@ impl Operation for InvalidateOperation {
     }
 }
 
-#[cfg(feature = "trace_task_dirty")]
-#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+/// Why a task has been made dirty. The `task_id` fields refer to the upstream task that caused
+/// it, e.g. the task that owns the changed cell.
+///
+/// Causes are only serialized into persisted operations with the `trace_task_dirty` feature, and
+/// are restored as [`TaskDirtyCause::Unknown`] otherwise.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
 pub enum TaskDirtyCause {
     InitialDirty,
     CellChange {
         value_type: turbo_tasks::ValueTypeId,
+        task_id: TaskId,
     },
     CellRemoved {
         value_type: turbo_tasks::ValueTypeId,
+        task_id: TaskId,
     },
     OutputChange {
         task_id: TaskId,
     },
     CollectiblesChange {
         collectible_type: turbo_tasks::TraitTypeId,
+        task_id: TaskId,
     },
     Invalidator,
+    #[default]
     Unknown,
 }
 
@@ -132,14 +125,14 @@ impl<'e, E: ExecuteContext<'e>> std::fmt::Display for TaskDirtyCauseInContext<'_
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.cause {
             TaskDirtyCause::InitialDirty => write!(f, "initial dirty"),
-            TaskDirtyCause::CellChange { value_type } => {
+            TaskDirtyCause::CellChange { value_type, .. } => {
                 write!(
                     f,
                     "{} cell changed",
                     turbo_tasks::registry::get_value_type(*value_type).name
                 )
             }
-            TaskDirtyCause::CellRemoved { value_type } => {
+            TaskDirtyCause::CellRemoved { value_type, .. } => {
                 write!(
                     f,
                     "{} cell removed",
@@ -153,7 +146,9 @@ impl<'e, E: ExecuteContext<'e>> std::fmt::Display for TaskDirtyCauseInContext<'_
                     self.ctx.get_task_description(*task_id)
                 )
             }
-            TaskDirtyCause::CollectiblesChange { collectible_type } => {
+            TaskDirtyCause::CollectiblesChange {
+                collectible_type, ..
+            } => {
                 write!(
                     f,
                     "{} collectible changed",
@@ -168,7 +163,7 @@ impl<'e, E: ExecuteContext<'e>> std::fmt::Display for TaskDirtyCauseInContext<'_
 
 pub fn make_task_dirty(
     task_id: TaskId,
-    #[cfg(feature = "trace_task_dirty")] cause: TaskDirtyCause,
+    cause: TaskDirtyCause,
     queue: &mut AggregationUpdateQueue,
     ctx: &mut impl ExecuteContext,
 ) {
@@ -178,22 +173,14 @@ pub fn make_task_dirty(
 
     let task = ctx.task(task_id, TaskDataCategory::Meta);
 
-    make_task_dirty_internal(
-        task,
-        task_id,
-        true,
-        #[cfg(feature = "trace_task_dirty")]
-        cause,
-        queue,
-        ctx,
-    );
+    make_task_dirty_internal(task, task_id, true, cause, queue, ctx);
 }
 
 pub fn make_task_dirty_internal(
     mut task: impl TaskGuard,
     task_id: TaskId,
     make_stale: bool,
-    #[cfg(feature = "trace_task_dirty")] cause: TaskDirtyCause,
+    cause: TaskDirtyCause,
     queue: &mut AggregationUpdateQueue,
     ctx: &mut impl ExecuteContext,
 ) {
@@ -229,6 +216,7 @@ pub fn make_task_dirty_internal(
         )
         .entered();
         *stale = true;
+        ctx.record_dirty_cause(task_id, cause);
     }
     let old = task.insert(CachedDataItem::Dirty {
         value: DirtyState {
@@ -279,6 +267,7 @@ pub fn make_task_dirty_internal(
         cause = %TaskDirtyCauseInContext::new(&cause, ctx)
     )
     .entered();
+    ctx.record_dirty_cause(task_id, cause);
 
     let should_schedule = {
         let aggregated_update = dirty_container.update_with_dirty_state(&DirtyState {
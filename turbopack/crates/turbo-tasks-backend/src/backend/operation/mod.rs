@@ -14,7 +14,9 @@ use std::{
 };
 
 use serde::{Deserialize, Serialize};
-use turbo_tasks::{FxIndexMap, KeyValuePair, SessionId, TaskId, TurboTasksBackendApi};
+use turbo_tasks::{
+    FxIndexMap, KeyValuePair, SessionId, TaskExecutionReason, TaskId, TurboTasksBackendApi,
+};
 
 use crate::{
     backend::{
@@ -70,6 +72,7 @@ pub trait ExecuteContext<'e>: Sized {
     fn get_task_description(&self, task_id: TaskId) -> String;
     fn should_track_dependencies(&self) -> bool;
     fn should_track_activeness(&self) -> bool;
+    fn record_dirty_cause(&self, task_id: TaskId, cause: TaskDirtyCause);
 }
 
 pub trait ChildExecuteContext<'e>: Send + Sized {
@@ -85,6 +88,8 @@ where
     turbo_tasks: &'e dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
     _operation_guard: Option<OperationGuard<'e, B>>,
     transaction: TransactionState<'e, 'tx, B>,
+    /// The value of [`TurboTasksBackendInner::evictions`] when `transaction` has been started.
+    transaction_evictions: usize,
 }
 
 impl<'e, 'tx, B: BackingStorage> ExecuteContextImpl<'e, 'tx, B>
@@ -100,12 +105,16 @@ where
             turbo_tasks,
             _operation_guard: Some(backend.start_operation()),
             transaction: TransactionState::None,
+            transaction_evictions: 0,
         }
     }
 
+    /// `transaction_evictions` is the value of [`TurboTasksBackendInner::evictions`] when
+    /// `transaction` has been started.
     pub(super) unsafe fn new_with_tx(
         backend: &'e TurboTasksBackendInner<B>,
         transaction: Option<&'e B::ReadTransaction<'tx>>,
+        transaction_evictions: usize,
         turbo_tasks: &'e dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
     ) -> Self {
         Self {
@@ -113,20 +122,29 @@ where
             turbo_tasks,
             _operation_guard: Some(backend.start_operation()),
             transaction: TransactionState::Borrowed(transaction),
+            transaction_evictions,
         }
     }
 
+    /// Reads the data of a task from the backing storage. Returns `None` when the data of the
+    /// task is corrupted. The task needs to be recomputed in this case.
     fn restore_task_data(
         &mut self,
         task_id: TaskId,
         category: TaskDataCategory,
-    ) -> Vec<CachedDataItem> {
-        if matches!(self.transaction, TransactionState::None) {
+    ) -> Option<Vec<CachedDataItem>> {
+        // Evictions only happen while all operations are suspended, so the task can't be evicted
+        // between this check and adding the restored data to it.
+        let evictions = self.backend.evictions.load(Ordering::Acquire);
+        // An evicted task might have been persisted after the transaction has been started
+        if matches!(self.transaction, TransactionState::None)
+            || self.transaction_evictions != evictions
+        {
             let check_backing_storage = self.backend.should_restore()
                 && self.backend.local_is_partial.load(Ordering::Acquire);
             if !check_backing_storage {
                 // If we don't need to restore, we can just return an empty vector
-                return Vec::new();
+                return Some(Vec::new());
             }
             let tx = self.backend.backing_storage.start_read_transaction();
             let tx = tx.map(|tx| {
@@ -134,6 +152,7 @@ where
                 unsafe { transmute::<B::ReadTransaction<'_>, B::ReadTransaction<'tx>>(tx) }
             });
             self.transaction = TransactionState::Owned(tx);
+            self.transaction_evictions = evictions;
         }
         let tx = match &self.transaction {
             TransactionState::None => unreachable!(),
@@ -147,14 +166,45 @@ where
                 .lookup_data(tx, task_id, category)
         };
         match result {
-            Ok(data) => data,
+            Ok(data) => Some(data),
             Err(e) => {
                 let task_name = self.backend.get_task_description(task_id);
-                panic!(
-                    "Failed to restore task data (corrupted database or bug): {:?}",
-                    e.context(format!("{category:?} for {task_name} ({task_id}))"))
-                )
+                let error = e.context(format!("{category:?} for {task_name} ({task_id})"));
+                match category {
+                    // The data of a task is recreated by executing the task again
+                    TaskDataCategory::Data => {
+                        self.backend.handle_corrupted_task_data(error);
+                        None
+                    }
+                    // Aggregation and edges to other tasks can't be recovered
+                    _ => {
+                        self.backend.handle_corrupted_cache(error);
+                        Some(Vec::new())
+                    }
+                }
+            }
+        }
+    }
+
+    /// Adds the restored data to a task, or schedules the task for recomputation when its data
+    /// was corrupted.
+    fn add_restored_task_data(
+        &self,
+        task: &mut StorageWriteGuard<'_>,
+        task_id: TaskId,
+        items: Option<Vec<CachedDataItem>>,
+    ) {
+        let Some(items) = items else {
+            if task.add(CachedDataItem::new_scheduled(
+                TaskExecutionReason::CellNotAvailable,
+                || self.backend.get_task_desc_fn(task_id),
+            )) {
+                self.turbo_tasks.schedule(task_id);
             }
+            return;
+        };
+        for item in items {
+            task.add(item);
         }
     }
 }
@@ -193,9 +243,7 @@ where
                         let items = self.restore_task_data(task_id, category);
                         task = self.backend.storage.access_mut(task_id);
                         if !task.state().is_restored(category) {
-                            for item in items {
-                                task.add(item);
-                            }
+                            self.add_restored_task_data(&mut task, task_id, items);
                             task.state_mut().set_restored(category);
                         }
                     }
@@ -244,15 +292,11 @@ where
                 task1 = t1;
                 task2 = t2;
                 if !task1.state().is_restored(category) {
-                    for item in items1.unwrap() {
-                        task1.add(item);
-                    }
+                    self.add_restored_task_data(&mut task1, task_id1, items1.unwrap());
                     task1.state_mut().set_restored(category);
                 }
                 if !task2.state().is_restored(category) {
-                    for item in items2.unwrap() {
-                        task2.add(item);
-                    }
+                    self.add_restored_task_data(&mut task2, task_id2, items2.unwrap());
                     task2.state_mut().set_restored(category);
                 }
             }
@@ -314,6 +358,12 @@ where
     fn should_track_activeness(&self) -> bool {
         self.backend.should_track_activeness()
     }
+
+    fn record_dirty_cause(&self, task_id: TaskId, cause: TaskDirtyCause) {
+        if let Some(dirty_causes) = &self.backend.dirty_causes {
+            dirty_causes.record(task_id, cause);
+        }
+    }
 }
 
 struct ChildExecuteContextImpl<'e, B: BackingStorage> {
@@ -328,6 +378,7 @@ impl<'e, B: BackingStorage> ChildExecuteContext<'e> for ChildExecuteContextImpl<
             turbo_tasks: self.turbo_tasks,
             _operation_guard: None,
             transaction: TransactionState::None,
+            transaction_evictions: 0,
         }
     }
 }
@@ -631,8 +682,6 @@ impl_operation!(UpdateCell update_cell::UpdateCellOperation);
 impl_operation!(CleanupOldEdges cleanup_old_edges::CleanupOldEdgesOperation);
 impl_operation!(AggregationUpdate aggregation_update::AggregationUpdateQueue);
 
-#[cfg(feature = "trace_task_dirty")]
-pub use self::invalidate::TaskDirtyCause;
 pub use self::{
     aggregation_update::{
         AggregatedDataUpdate, AggregationUpdateJob, get_aggregation_number, get_uppers,
@@ -640,7 +689,7 @@ pub use self::{
     },
     cleanup_old_edges::OutdatedEdge,
     connect_children::connect_children,
-    invalidate::make_task_dirty_internal,
+    invalidate::{TaskDirtyCause, make_task_dirty_internal},
     prepare_new_children::prepare_new_children,
     update_collectible::UpdateCollectibleOperation,
 };
@@ -4,13 +4,11 @@ use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 use turbo_tasks::{CellId, TaskId, TypedSharedReference, backend::CellContent};
 
-#[cfg(feature = "trace_task_dirty")]
-use crate::backend::operation::invalidate::TaskDirtyCause;
 use crate::{
     backend::{
         TaskDataCategory,
         operation::{
-            AggregationUpdateQueue, ExecuteContext, Operation, TaskGuard,
+            AggregationUpdateQueue, ExecuteContext, Operation, TaskDirtyCause, TaskGuard,
             invalidate::make_task_dirty_internal,
         },
         storage::{get_many, remove},
@@ -158,9 +156,9 @@ impl Operation for UpdateCellOperation {
                             dependent,
                             dependent_task_id,
                             true,
-                            #[cfg(feature = "trace_task_dirty")]
                             TaskDirtyCause::CellChange {
                                 value_type: cell_ref.cell.type_id,
+                                task_id: cell_ref.task,
                             },
                             queue,
                             ctx,
@@ -8,7 +8,7 @@ use crate::{
         TaskDataCategory, get_many,
         operation::{
             AggregatedDataUpdate, AggregationUpdateJob, AggregationUpdateQueue, ExecuteContext,
-            Operation, get_aggregation_number, is_root_node,
+            Operation, TaskDirtyCause, get_aggregation_number, is_root_node,
         },
         storage::{get, update_count},
     },
@@ -81,8 +81,10 @@ impl UpdateCollectibleOperation {
                 if !dependent.is_empty() {
                     queue.push(AggregationUpdateJob::InvalidateDueToCollectiblesChange {
                         task_ids: dependent,
-                        #[cfg(feature = "trace_task_dirty")]
-                        collectible_type: ty,
+                        cause: TaskDirtyCause::CollectiblesChange {
+                            collectible_type: ty,
+                            task_id,
+                        },
                     })
                 }
             }
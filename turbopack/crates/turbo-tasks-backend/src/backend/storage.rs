@@ -102,6 +102,8 @@ bitfield! {
     pub data_snapshot, set_data_snapshot: 5;
     /// Prefetched dependencies
     pub prefetched, set_prefetched: 6;
+    /// Item was accessed since the last eviction pass.
+    pub accessed, set_accessed: 7;
 }
 
 impl InnerStorageState {
@@ -779,10 +781,11 @@ impl Storage {
     }
 
     pub fn access_mut(&self, key: TaskId) -> StorageWriteGuard<'_> {
-        let inner = match self.map.entry(key) {
+        let mut inner = match self.map.entry(key) {
             dashmap::mapref::entry::Entry::Occupied(e) => e.into_ref(),
             dashmap::mapref::entry::Entry::Vacant(e) => e.insert(Box::new(InnerStorage::new())),
         };
+        inner.state_mut().set_accessed(true);
         StorageWriteGuard {
             storage: self,
             inner: inner.into(),
@@ -794,7 +797,10 @@ impl Storage {
         key1: TaskId,
         key2: TaskId,
     ) -> (StorageWriteGuard<'_>, StorageWriteGuard<'_>) {
-        let (a, b) = get_multiple_mut(&self.map, key1, key2, || Box::new(InnerStorage::new()));
+        let (mut a, mut b) =
+            get_multiple_mut(&self.map, key1, key2, || Box::new(InnerStorage::new()));
+        a.state_mut().set_accessed(true);
+        b.state_mut().set_accessed(true);
         (
             StorageWriteGuard {
                 storage: self,
@@ -807,6 +813,44 @@ impl Storage {
         )
     }
 
+    /// Drops tasks from memory that haven't been accessed since the last call and are fully
+    /// persisted. They will be restored from the backing storage on next access. Returns the
+    /// number of evicted tasks.
+    ///
+    /// Tasks with unpersisted modifications or transient state (e.g. in progress or active tasks)
+    /// are kept, so the restored state is identical to the evicted one and the aggregation and
+    /// dirty tracking invariants are not affected.
+    ///
+    /// Nothing is evicted while a snapshot is in progress, since the snapshot still reads the
+    /// tasks.
+    pub fn evict_cold_tasks(&self) -> usize {
+        if self.snapshot_mode() {
+            return 0;
+        }
+        let mut evicted = 0;
+        self.map.retain(|task_id, inner| {
+            let state = inner.state_mut();
+            // Give recently accessed tasks a second chance
+            if state.accessed() {
+                state.set_accessed(false);
+                return true;
+            }
+            if task_id.is_transient() || state.any_modified() || state.any_snapshot() {
+                return true;
+            }
+            if inner
+                .iter_all()
+                .any(|(key, value)| !key.is_persistent() || !value.is_persistent())
+            {
+                return true;
+            }
+            evicted += 1;
+            false
+        });
+        self.map.shrink_to_fit();
+        evicted
+    }
+
     pub fn drop_contents(&self) {
         drop_contents(&self.map);
         drop_contents(&self.modified);
@@ -0,0 +1,171 @@
+use std::collections::VecDeque;
+
+use parking_lot::Mutex;
+use rustc_hash::{FxHashMap, FxHashSet};
+use turbo_tasks::TaskId;
+
+#[derive(Default)]
+struct WaitGraphInner {
+    /// The tasks that a task is waiting for.
+    waiting_for: FxHashMap<TaskId, FxHashSet<TaskId>>,
+    /// The tasks that are waiting for a task.
+    waited_by: FxHashMap<TaskId, FxHashSet<TaskId>>,
+    /// The tasks whose current execution has read a task that would have closed a cycle.
+    cycle_readers: FxHashSet<TaskId>,
+}
+
+/// Tracks which tasks are waiting for the output of other tasks that are in progress, to detect
+/// dependency cycles. Without it, tasks in a cycle would wait for each other forever.
+///
+/// The wait of a task ends when the awaited task finishes or when the waiting task starts a new
+/// execution, so edges of previous executions can't form a cycle.
+#[derive(Default)]
+pub struct WaitGraph {
+    inner: Mutex<WaitGraphInner>,
+}
+
+impl WaitGraph {
+    /// Records that `reader` waits for `task` to finish. Returns the cycle instead when `task`
+    /// is already waiting for `reader`, directly or through other tasks. The cycle starts with
+    /// `reader`, every task waits for the next one and the last one waits for `reader`.
+    pub fn wait(&self, reader: TaskId, task: TaskId) -> Option<Vec<TaskId>> {
+        let mut inner = self.inner.lock();
+        if reader == task {
+            inner.cycle_readers.insert(reader);
+            return Some(vec![reader]);
+        }
+        if let Some(cycle) = inner.find_path(task, reader) {
+            inner.cycle_readers.insert(reader);
+            return Some(cycle);
+        }
+        inner.waiting_for.entry(reader).or_default().insert(task);
+        inner.waited_by.entry(task).or_default().insert(reader);
+        None
+    }
+
+    /// Called when a task starts a new execution. Waits of the previous execution have ended.
+    pub fn task_execution_started(&self, task: TaskId) {
+        let mut inner = self.inner.lock();
+        inner.cycle_readers.remove(&task);
+        inner.remove_waits_of(task);
+    }
+
+    /// Returns whether the current execution of the task has been failed with a cycle error.
+    pub fn take_cycle_reader(&self, task: TaskId) -> bool {
+        self.inner.lock().cycle_readers.remove(&task)
+    }
+
+    /// Called when a task has finished or has been canceled. Tasks that have been waiting for it
+    /// continue, and the task no longer waits for other tasks.
+    pub fn task_finished(&self, task: TaskId) {
+        let mut inner = self.inner.lock();
+        let inner = &mut *inner;
+        if let Some(readers) = inner.waited_by.remove(&task) {
+            for reader in readers {
+                remove_edge(&mut inner.waiting_for, reader, task);
+            }
+        }
+        inner.cycle_readers.remove(&task);
+        inner.remove_waits_of(task);
+    }
+}
+
+impl WaitGraphInner {
+    fn remove_waits_of(&mut self, task: TaskId) {
+        if let Some(tasks) = self.waiting_for.remove(&task) {
+            for waited_for in tasks {
+                remove_edge(&mut self.waited_by, waited_for, task);
+            }
+        }
+    }
+
+    /// Finds the shortest path of waiting tasks from `from` to `to`. The path starts with `to`,
+    /// followed by `from` and ends with the task that waits for `to`.
+    fn find_path(&self, from: TaskId, to: TaskId) -> Option<Vec<TaskId>> {
+        let mut parents = FxHashMap::default();
+        let mut queue = VecDeque::from([from]);
+        let mut visited = FxHashSet::from_iter([from]);
+        while let Some(task) = queue.pop_front() {
+            let Some(waiting_for) = self.waiting_for.get(&task) else {
+                continue;
+            };
+            for &next in waiting_for {
+                if next == to {
+                    let mut path = vec![task];
+                    let mut current = task;
+                    while let Some(&parent) = parents.get(&current) {
+                        path.push(parent);
+                        current = parent;
+                    }
+                    path.push(to);
+                    path.reverse();
+                    return Some(path);
+                }
+                if visited.insert(next) {
+                    parents.insert(next, task);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+}
+
+fn remove_edge(edges: &mut FxHashMap<TaskId, FxHashSet<TaskId>>, from: TaskId, to: TaskId) {
+    if let Some(targets) = edges.get_mut(&from) {
+        targets.remove(&to);
+        if targets.is_empty() {
+            edges.remove(&from);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: u32) -> TaskId {
+        TaskId::try_from(id).unwrap()
+    }
+
+    #[test]
+    fn test_detect_cycle() {
+        let graph = WaitGraph::default();
+        assert_eq!(graph.wait(task(1), task(1)), Some(vec![task(1)]));
+        assert_eq!(graph.wait(task(1), task(2)), None);
+        assert_eq!(graph.wait(task(2), task(3)), None);
+        assert_eq!(graph.wait(task(4), task(1)), None);
+        assert_eq!(
+            graph.wait(task(3), task(1)),
+            Some(vec![task(3), task(1), task(2)])
+        );
+        // The edge of the detected cycle is not recorded
+        assert_eq!(graph.wait(task(1), task(3)), None);
+    }
+
+    #[test]
+    fn test_finished_task_breaks_cycle() {
+        let graph = WaitGraph::default();
+        assert_eq!(graph.wait(task(1), task(2)), None);
+        assert_eq!(graph.wait(task(2), task(3)), None);
+        graph.task_finished(task(3));
+        assert_eq!(graph.wait(task(3), task(1)), None);
+        graph.task_finished(task(2));
+        assert_eq!(graph.wait(task(2), task(3)), None);
+        assert!(graph.inner.lock().waited_by.get(&task(2)).is_none());
+    }
+
+    #[test]
+    fn test_new_execution_ends_waits() {
+        let graph = WaitGraph::default();
+        assert_eq!(graph.wait(task(1), task(2)), None);
+        // The next execution of task 1 doesn't wait for task 2 anymore
+        graph.task_execution_started(task(1));
+        assert_eq!(graph.wait(task(2), task(1)), None);
+        assert!(!graph.take_cycle_reader(task(2)));
+
+        assert!(graph.wait(task(1), task(2)).is_some());
+        assert!(graph.take_cycle_reader(task(1)));
+        assert!(!graph.take_cycle_reader(task(1)));
+    }
+}
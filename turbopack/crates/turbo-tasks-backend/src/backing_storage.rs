@@ -8,6 +8,7 @@ use turbo_tasks::{SessionId, TaskId, backend::CachedTaskType};
 use crate::{
     backend::{AnyOperation, TaskDataCategory},
     data::CachedDataItem,
+    database::invalidation_log::InvalidationLogEntry,
     utils::chunked_vec::ChunkedVec,
 };
 
@@ -33,6 +34,10 @@ pub trait BackingStorage: BackingStorageSealed {
     /// [`KeyValueDatabase::shutdown`]: crate::database::key_value_database::KeyValueDatabase::shutdown
     /// [`invalidate_db`]: crate::database::db_invalidation::invalidate_db
     fn invalidate(&self, reason_code: &str) -> Result<()>;
+
+    /// Returns the log of past cache invalidations and version changes, oldest first. This explains
+    /// to users why a build didn't use the cache.
+    fn invalidation_log(&self) -> Vec<InvalidationLogEntry>;
 }
 
 /// Private methods used by [`BackingStorage`]. This trait is `pub` (because of the sealed-trait
@@ -103,6 +108,10 @@ where
     fn invalidate(&self, reason_code: &str) -> Result<()> {
         either::for_both!(self, this => this.invalidate(reason_code))
     }
+
+    fn invalidation_log(&self) -> Vec<InvalidationLogEntry> {
+        either::for_both!(self, this => this.invalidation_log())
+    }
 }
 
 impl<L, R> BackingStorageSealed for Either<L, R>
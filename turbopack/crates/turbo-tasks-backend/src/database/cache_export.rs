@@ -0,0 +1,885 @@
+//! A portable file format for the persistent cache.
+//!
+//! The database stores task data keyed by `TaskId`s, which are only meaningful within a single
+//! database. An export identifies every task by its serialized task type, which is the stable
+//! identity of a task. All `TaskId`s in the exported items are rewritten to the export index of the
+//! referenced task, which is its position in the export starting at 1. The importer assigns new
+//! `TaskId`s, so an export can be merged into any database of the same version. `TaskId`s nested in
+//! values are rewritten while deserializing them, see [`deserialize_with_task_id_mapping`].
+//!
+//! Exports are stored in a directory, one file per cache version, similar to a remote cache.
+//!
+//! **File format**
+//!
+//! All integers are little endian `u32`. Byte strings and lists are prefixed with their length.
+//!
+//! - magic number and format version
+//! - cache version (the git describe output of the exporting build)
+//! - project root path of the exporting machine
+//! - session id
+//! - a list of entries, each starting with a `u8` tag. The list is terminated by a tag of 0.
+//!   - task (tag 1): serialized task type, export indices of all referenced tasks, serialized meta
+//!     items, data items and cell data. Empty byte strings mean that there are no items.
+//!   - incomplete task (tag 2): serialized task type. The items of the task couldn't be exported,
+//!     e.g. because they reference a task that has never been persisted.
+//!
+//! Tasks and incomplete tasks are numbered in the order of the export.
+
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    fmt,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use byteorder::{LE, ReadBytesExt, WriteBytesExt};
+use serde::{
+    Deserialize, Deserializer,
+    de::{self, DeserializeSeed, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor},
+};
+use turbo_tasks::TaskId;
+
+const MAGIC: &[u8; 4] = b"TTCE";
+const FORMAT_VERSION: u32 = 3;
+
+const ENTRY_END: u8 = 0;
+const ENTRY_TASK: u8 = 1;
+const ENTRY_INCOMPLETE_TASK: u8 = 2;
+
+/// Returns the path of the export file for a cache version in an export directory.
+pub fn export_file_path(dir: &Path, version: &str) -> PathBuf {
+    dir.join(format!("{version}.cache"))
+}
+
+/// The global information stored at the start of an export.
+pub struct ExportHeader {
+    /// The cache version of the exporting build. Only exports of the same version can be
+    /// imported, since the serialization format might differ between versions.
+    pub version: String,
+    /// The project root path on the exporting machine. Paths starting with it are rebased on
+    /// import.
+    pub root: String,
+    pub session_id: u32,
+}
+
+/// A single task of an export. All `TaskId`s in the items are export indices.
+pub struct ExportedTask {
+    pub task_type: Vec<u8>,
+    /// The export indices of all tasks that are referenced by the items.
+    pub references: Vec<u32>,
+    pub meta: Vec<u8>,
+    pub data: Vec<u8>,
+    pub cells: Vec<u8>,
+}
+
+pub enum ExportEntry {
+    Task(ExportedTask),
+    /// A task whose items couldn't be exported. It's never imported, but keeps its export index.
+    /// The task type is empty when it couldn't be exported either.
+    IncompleteTask {
+        task_type: Vec<u8>,
+    },
+}
+
+pub struct CacheExportWriter {
+    writer: BufWriter<File>,
+}
+
+impl CacheExportWriter {
+    pub fn create(path: &Path, header: &ExportHeader) -> Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_u32::<LE>(FORMAT_VERSION)?;
+        write_bytes(&mut writer, header.version.as_bytes())?;
+        write_bytes(&mut writer, header.root.as_bytes())?;
+        writer.write_u32::<LE>(header.session_id)?;
+        Ok(Self { writer })
+    }
+
+    pub fn write_entry(&mut self, entry: &ExportEntry) -> Result<()> {
+        let writer = &mut self.writer;
+        match entry {
+            ExportEntry::Task(task) => {
+                writer.write_u8(ENTRY_TASK)?;
+                write_bytes(writer, &task.task_type)?;
+                write_u32s(writer, &task.references)?;
+                write_bytes(writer, &task.meta)?;
+                write_bytes(writer, &task.data)?;
+                write_bytes(writer, &task.cells)?;
+            }
+            ExportEntry::IncompleteTask { task_type } => {
+                writer.write_u8(ENTRY_INCOMPLETE_TASK)?;
+                write_bytes(writer, task_type)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<()> {
+        self.writer.write_u8(ENTRY_END)?;
+        let file = self.writer.into_inner()?;
+        file.sync_all()?;
+        Ok(())
+    }
+}
+
+pub struct CacheExportReader {
+    reader: BufReader<File>,
+}
+
+impl CacheExportReader {
+    pub fn open(path: &Path) -> Result<(Self, ExportHeader)> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            bail!("Not a cache export file");
+        }
+        let format_version = reader.read_u32::<LE>()?;
+        if format_version != FORMAT_VERSION {
+            bail!("Unsupported cache export format version {format_version}");
+        }
+        let header = ExportHeader {
+            version: String::from_utf8(read_bytes(&mut reader)?)
+                .context("Invalid cache version")?,
+            root: String::from_utf8(read_bytes(&mut reader)?).context("Invalid root path")?,
+            session_id: reader.read_u32::<LE>()?,
+        };
+        Ok((Self { reader }, header))
+    }
+
+    pub fn next_entry(&mut self) -> Result<Option<ExportEntry>> {
+        let reader = &mut self.reader;
+        Ok(Some(match reader.read_u8()? {
+            ENTRY_END => return Ok(None),
+            ENTRY_TASK => ExportEntry::Task(ExportedTask {
+                task_type: read_bytes(reader)?,
+                references: read_u32s(reader)?,
+                meta: read_bytes(reader)?,
+                data: read_bytes(reader)?,
+                cells: read_bytes(reader)?,
+            }),
+            ENTRY_INCOMPLETE_TASK => ExportEntry::IncompleteTask {
+                task_type: read_bytes(reader)?,
+            },
+            tag => bail!("Invalid cache export entry {tag}"),
+        }))
+    }
+}
+
+fn write_bytes(writer: &mut impl Write, bytes: &[u8]) -> Result<()> {
+    writer.write_u32::<LE>(bytes.len().try_into()?)?;
+    writer.write_all(bytes)?;
+    Ok(())
+}
+
+fn read_bytes(reader: &mut impl Read) -> Result<Vec<u8>> {
+    let len = reader.read_u32::<LE>()?;
+    let mut bytes = vec![0; len as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn write_u32s(writer: &mut impl Write, values: &[u32]) -> Result<()> {
+    writer.write_u32::<LE>(values.len().try_into()?)?;
+    for &value in values {
+        writer.write_u32::<LE>(value)?;
+    }
+    Ok(())
+}
+
+fn read_u32s(reader: &mut impl Read) -> Result<Vec<u32>> {
+    let len = reader.read_u32::<LE>()?;
+    (0..len).map(|_| Ok(reader.read_u32::<LE>()?)).collect()
+}
+
+/// Rebases all paths in a serialized value from the project root `from` to `to`. Only strings
+/// that are a path inside of `from` are rebased, other strings that merely contain it, e.g. file
+/// contents, are kept. Returns `None` when the value doesn't contain such a path.
+///
+/// The result is not necessarily the canonical serialization of the value. It needs to be
+/// deserialized into the concrete type and serialized again before storing it.
+pub fn rebase_paths(
+    bytes: &[u8],
+    from: &str,
+    to: &str,
+    config: &pot::Config,
+) -> Result<Option<Vec<u8>>> {
+    let from = from.trim_end_matches(['/', '\\']);
+    let to = to.trim_end_matches(['/', '\\']);
+    if from.is_empty()
+        || !bytes
+            .windows(from.len())
+            .any(|window| window == from.as_bytes())
+    {
+        return Ok(None);
+    }
+    let mut value: pot::Value<'_> = config.deserialize(bytes).context("Unable to read value")?;
+    if !rebase_value(&mut value, from, to) {
+        return Ok(None);
+    }
+    Ok(Some(
+        config.serialize(&value).context("Unable to write value")?,
+    ))
+}
+
+fn rebase_value(value: &mut pot::Value<'_>, from: &str, to: &str) -> bool {
+    match value {
+        pot::Value::String(string) => {
+            let Some(rest) = string.strip_prefix(from) else {
+                return false;
+            };
+            if !rest.is_empty() && !rest.starts_with(['/', '\\']) {
+                return false;
+            }
+            *string = Cow::Owned(format!("{to}{rest}"));
+            true
+        }
+        pot::Value::Sequence(items) => items.iter_mut().fold(false, |changed, item| {
+            rebase_value(item, from, to) | changed
+        }),
+        pot::Value::Mappings(mappings) => {
+            mappings.iter_mut().fold(false, |changed, (key, value)| {
+                rebase_value(key, from, to) | rebase_value(value, from, to) | changed
+            })
+        }
+        _ => false,
+    }
+}
+
+/// The mapping that is applied to deserialized `TaskId`s. Returning `None` fails the
+/// deserialization.
+type TaskIdMapping<'m> = RefCell<&'m mut dyn FnMut(TaskId) -> Option<TaskId>>;
+
+/// The serde names of the newtype structs that wrap a `TaskId`.
+const TASK_ID_NEWTYPE_STRUCTS: &[&str] = &["Invalidator", "SerializationInvalidator"];
+
+/// Deserializes a value with the `TaskId` of every [`RawVc`] and invalidator in it passed through
+/// `mapping`. When `mapping` returns `None`, the deserialization fails.
+///
+/// Values only reference tasks via these types, e.g. in a serialized [`ResolvedVc`] of a cell value
+/// or a task argument. `TaskId`s that are stored directly, e.g. in the task items, are not mapped
+/// and need to be rewritten after the deserialization.
+///
+/// [`RawVc`]: turbo_tasks::RawVc
+/// [`ResolvedVc`]: turbo_tasks::ResolvedVc
+pub fn deserialize_with_task_id_mapping<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
+    deserializer: D,
+    mapping: &mut dyn FnMut(TaskId) -> Option<TaskId>,
+) -> Result<T, D::Error> {
+    let mapping = RefCell::new(mapping);
+    T::deserialize(MapTaskIds {
+        inner: deserializer,
+        mapping: &mapping,
+    })
+}
+
+/// Wraps a [`Deserializer`] and everything it passes to the visitors, so that every [`RawVc`] and
+/// invalidator of the deserialized value can be intercepted.
+///
+/// [`RawVc`]: turbo_tasks::RawVc
+struct MapTaskIds<'a, 'm, T> {
+    inner: T,
+    mapping: &'a TaskIdMapping<'m>,
+}
+
+impl<'a, 'm, T> MapTaskIds<'a, 'm, T> {
+    fn wrap<U>(&self, inner: U) -> MapTaskIds<'a, 'm, U> {
+        MapTaskIds {
+            inner,
+            mapping: self.mapping,
+        }
+    }
+}
+
+macro_rules! forward_deserialize {
+    ($($method:ident($($arg:ident: $ty:ty),*)),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(
+                self,
+                $($arg: $ty,)*
+                visitor: V,
+            ) -> Result<V::Value, Self::Error> {
+                let visitor = self.wrap(visitor);
+                self.inner.$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for MapTaskIds<'_, '_, D> {
+    type Error = D::Error;
+
+    forward_deserialize! {
+        deserialize_any(),
+        deserialize_bool(),
+        deserialize_i8(),
+        deserialize_i16(),
+        deserialize_i32(),
+        deserialize_i64(),
+        deserialize_i128(),
+        deserialize_u8(),
+        deserialize_u16(),
+        deserialize_u32(),
+        deserialize_u64(),
+        deserialize_u128(),
+        deserialize_f32(),
+        deserialize_f64(),
+        deserialize_char(),
+        deserialize_str(),
+        deserialize_string(),
+        deserialize_bytes(),
+        deserialize_byte_buf(),
+        deserialize_option(),
+        deserialize_unit(),
+        deserialize_unit_struct(name: &'static str),
+        deserialize_seq(),
+        deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_map(),
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+        deserialize_identifier(),
+        deserialize_ignored_any(),
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        if TASK_ID_NEWTYPE_STRUCTS.contains(&name) {
+            let visitor = TaskIdNewtypeVisitor(self.wrap(visitor));
+            self.inner.deserialize_newtype_struct(name, visitor)
+        } else {
+            let visitor = self.wrap(visitor);
+            self.inner.deserialize_newtype_struct(name, visitor)
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        if name == "RawVc" {
+            let visitor = RawVcVisitor(self.wrap(visitor));
+            self.inner.deserialize_enum(name, variants, visitor)
+        } else {
+            let visitor = self.wrap(visitor);
+            self.inner.deserialize_enum(name, variants, visitor)
+        }
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+macro_rules! forward_visit {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method<E: de::Error>(self, v: $ty) -> Result<Self::Value, E> {
+                self.inner.$method(v)
+            }
+        )*
+    };
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for MapTaskIds<'_, '_, V> {
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.expecting(formatter)
+    }
+
+    forward_visit! {
+        visit_bool(bool),
+        visit_i8(i8),
+        visit_i16(i16),
+        visit_i32(i32),
+        visit_i64(i64),
+        visit_i128(i128),
+        visit_u8(u8),
+        visit_u16(u16),
+        visit_u32(u32),
+        visit_u64(u64),
+        visit_u128(u128),
+        visit_f32(f32),
+        visit_f64(f64),
+        visit_char(char),
+        visit_str(&str),
+        visit_borrowed_str(&'de str),
+        visit_string(String),
+        visit_bytes(&[u8]),
+        visit_borrowed_bytes(&'de [u8]),
+        visit_byte_buf(Vec<u8>),
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.inner.visit_none()
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.inner.visit_unit()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let deserializer = self.wrap(deserializer);
+        self.inner.visit_some(deserializer)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        let deserializer = self.wrap(deserializer);
+        self.inner.visit_newtype_struct(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        let seq = self.wrap(seq);
+        self.inner.visit_seq(seq)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        let map = self.wrap(map);
+        self.inner.visit_map(map)
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        let data = self.wrap(data);
+        self.inner.visit_enum(data)
+    }
+}
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for MapTaskIds<'_, '_, S> {
+    type Value = S::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let deserializer = self.wrap(deserializer);
+        self.inner.deserialize(deserializer)
+    }
+}
+
+impl<'de, A: SeqAccess<'de>> SeqAccess<'de> for MapTaskIds<'_, '_, A> {
+    type Error = A::Error;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> Result<Option<S::Value>, Self::Error> {
+        let seed = self.wrap(seed);
+        self.inner.next_element_seed(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for MapTaskIds<'_, '_, A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        let seed = self.wrap(seed);
+        self.inner.next_key_seed(seed)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let seed = self.wrap(seed);
+        self.inner.next_value_seed(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, 'm, 'de, A: EnumAccess<'de>> EnumAccess<'de> for MapTaskIds<'a, 'm, A> {
+    type Error = A::Error;
+    type Variant = MapTaskIds<'a, 'm, A::Variant>;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Self::Variant), Self::Error> {
+        let mapping = self.mapping;
+        let (value, variant) = self.inner.variant_seed(seed)?;
+        Ok((
+            value,
+            MapTaskIds {
+                inner: variant,
+                mapping,
+            },
+        ))
+    }
+}
+
+impl<'de, A: VariantAccess<'de>> VariantAccess<'de> for MapTaskIds<'_, '_, A> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        self.inner.unit_variant()
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<S::Value, Self::Error> {
+        let seed = self.wrap(seed);
+        self.inner.newtype_variant_seed(seed)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let visitor = self.wrap(visitor);
+        self.inner.tuple_variant(len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let visitor = self.wrap(visitor);
+        self.inner.struct_variant(fields, visitor)
+    }
+}
+
+/// Visits a [`RawVc`]. `RawVc::TaskOutput(TaskId)` is the only newtype variant and
+/// `RawVc::TaskCell(TaskId, CellId)` the only tuple variant with two fields. The fields of
+/// `RawVc::LocalOutput` don't contain a `TaskId`.
+///
+/// [`RawVc`]: turbo_tasks::RawVc
+struct RawVcVisitor<'a, 'm, T>(MapTaskIds<'a, 'm, T>);
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for RawVcVisitor<'_, '_, V> {
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.0.inner.expecting(formatter)
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        let data = RawVcVisitor(self.0.wrap(data));
+        self.0.inner.visit_enum(data)
+    }
+}
+
+impl<'a, 'm, 'de, A: EnumAccess<'de>> EnumAccess<'de> for RawVcVisitor<'a, 'm, A> {
+    type Error = A::Error;
+    type Variant = RawVcVisitor<'a, 'm, A::Variant>;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, Self::Variant), Self::Error> {
+        let mapping = self.0.mapping;
+        let (value, variant) = self.0.inner.variant_seed(seed)?;
+        Ok((
+            value,
+            RawVcVisitor(MapTaskIds {
+                inner: variant,
+                mapping,
+            }),
+        ))
+    }
+}
+
+impl<'de, A: VariantAccess<'de>> VariantAccess<'de> for RawVcVisitor<'_, '_, A> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        self.0.inner.unit_variant()
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<S::Value, Self::Error> {
+        let seed = TaskIdSeed(self.0.wrap(seed));
+        self.0.inner.newtype_variant_seed(seed)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        if len == 2 {
+            let visitor = TaskCellVisitor(self.0.wrap(visitor));
+            self.0.inner.tuple_variant(len, visitor)
+        } else {
+            let visitor = self.0.wrap(visitor);
+            self.0.inner.tuple_variant(len, visitor)
+        }
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        let visitor = self.0.wrap(visitor);
+        self.0.inner.struct_variant(fields, visitor)
+    }
+}
+
+/// Visits the fields of `RawVc::TaskCell`, where the first field is the `TaskId`.
+struct TaskCellVisitor<'a, 'm, T>(MapTaskIds<'a, 'm, T>);
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for TaskCellVisitor<'_, '_, V> {
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.0.inner.expecting(formatter)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        let seq = TaskCellFields {
+            fields: self.0.wrap(seq),
+            index: 0,
+        };
+        self.0.inner.visit_seq(seq)
+    }
+}
+
+struct TaskCellFields<'a, 'm, A> {
+    fields: MapTaskIds<'a, 'm, A>,
+    index: usize,
+}
+
+impl<'de, A: SeqAccess<'de>> SeqAccess<'de> for TaskCellFields<'_, '_, A> {
+    type Error = A::Error;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> Result<Option<S::Value>, Self::Error> {
+        self.index += 1;
+        if self.index == 1 {
+            let seed = TaskIdSeed(self.fields.wrap(seed));
+            self.fields.inner.next_element_seed(seed)
+        } else {
+            self.fields.next_element_seed(seed)
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        SeqAccess::size_hint(&self.fields)
+    }
+}
+
+/// Visits a newtype struct that wraps a `TaskId`.
+struct TaskIdNewtypeVisitor<'a, 'm, T>(MapTaskIds<'a, 'm, T>);
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for TaskIdNewtypeVisitor<'_, '_, V> {
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.0.inner.expecting(formatter)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        let deserializer = TaskIdDeserializer(self.0.wrap(deserializer));
+        self.0.inner.visit_newtype_struct(deserializer)
+    }
+}
+
+/// Deserializes a value that is a `TaskId`.
+struct TaskIdSeed<'a, 'm, S>(MapTaskIds<'a, 'm, S>);
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for TaskIdSeed<'_, '_, S> {
+    type Value = S::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let deserializer = TaskIdDeserializer(self.0.wrap(deserializer));
+        self.0.inner.deserialize(deserializer)
+    }
+}
+
+/// Deserializes a `TaskId` and passes it through the mapping.
+struct TaskIdDeserializer<'a, 'm, D>(MapTaskIds<'a, 'm, D>);
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for TaskIdDeserializer<'_, '_, D> {
+    type Error = D::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let task_id = TaskId::deserialize(self.0.inner)?;
+        let mapped = (*self.0.mapping.borrow_mut())(task_id)
+            .ok_or_else(|| de::Error::custom(format!("{task_id} has no mapping")))?;
+        visitor.visit_u32(*mapped)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier
+        ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use tempfile::TempDir;
+    use turbo_tasks::{CellId, ExecutionId, LocalTaskId, RawVc, TaskPersistence, VcValueType};
+
+    use super::*;
+
+    const CONFIG: pot::Config = pot::Config::new().compatibility(pot::Compatibility::V4);
+
+    #[test]
+    fn test_roundtrip() -> Result<()> {
+        let tmp_dir = TempDir::new()?;
+        let path = export_file_path(tmp_dir.path(), "mock-version");
+        let mut writer = CacheExportWriter::create(
+            &path,
+            &ExportHeader {
+                version: "mock-version".to_string(),
+                root: "/project".to_string(),
+                session_id: 7,
+            },
+        )?;
+        writer.write_entry(&ExportEntry::Task(ExportedTask {
+            task_type: vec![1; 3],
+            references: vec![2],
+            meta: vec![4],
+            data: Vec::new(),
+            cells: vec![5, 6],
+        }))?;
+        writer.write_entry(&ExportEntry::IncompleteTask {
+            task_type: vec![2; 3],
+        })?;
+        writer.finish()?;
+
+        let (mut reader, header) = CacheExportReader::open(&path)?;
+        assert_eq!(header.version, "mock-version");
+        assert_eq!(header.root, "/project");
+        assert_eq!(header.session_id, 7);
+        let Some(ExportEntry::Task(task)) = reader.next_entry()? else {
+            panic!("expected a task");
+        };
+        assert_eq!(task.task_type, vec![1; 3]);
+        assert_eq!(task.references, vec![2]);
+        assert_eq!(task.meta, vec![4]);
+        assert!(task.data.is_empty());
+        assert_eq!(task.cells, vec![5, 6]);
+        let Some(ExportEntry::IncompleteTask { task_type }) = reader.next_entry()? else {
+            panic!("expected an incomplete task");
+        };
+        assert_eq!(task_type, vec![2; 3]);
+        assert!(reader.next_entry()?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rebase_paths() -> Result<()> {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Item {
+            path: String,
+            names: Vec<String>,
+            content: String,
+            count: u32,
+        }
+
+        let item = Item {
+            path: "/old/project/src/index.js".to_string(),
+            names: vec![
+                "/old/project".to_string(),
+                "/old/project-other/a.js".to_string(),
+                "b.js".to_string(),
+            ],
+            content: "import '/old/project/a.js'".to_string(),
+            count: 42,
+        };
+        let bytes = CONFIG.serialize(&item)?;
+        assert!(rebase_paths(&bytes, "/other", "/new", &CONFIG)?.is_none());
+
+        let rebased = rebase_paths(&bytes, "/old/project/", "/new/checkout", &CONFIG)?.unwrap();
+        let rebased: Item = CONFIG.deserialize(&rebased)?;
+        assert_eq!(
+            rebased,
+            Item {
+                path: "/new/checkout/src/index.js".to_string(),
+                names: vec![
+                    "/new/checkout".to_string(),
+                    "/old/project-other/a.js".to_string(),
+                    "b.js".to_string()
+                ],
+                content: "import '/old/project/a.js'".to_string(),
+                count: 42,
+            }
+        );
+
+        // Only contains the root as part of a string
+        let bytes = CONFIG.serialize(&vec!["import '/old/project/a.js'".to_string()])?;
+        assert!(rebase_paths(&bytes, "/old/project", "/new", &CONFIG)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_deserialize_with_task_id_mapping() -> Result<()> {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Value {
+            output: RawVc,
+            cell: Option<RawVc>,
+            local: RawVc,
+            count: u32,
+        }
+
+        let task = |id: u32| TaskId::try_from(id).unwrap();
+        let cell = CellId {
+            type_id: <u32 as VcValueType>::get_value_type_id(),
+            index: 3,
+        };
+        let local = RawVc::LocalOutput(
+            ExecutionId::try_from(4)?,
+            LocalTaskId::try_from(5)?,
+            TaskPersistence::Persistent,
+        );
+        let bytes = CONFIG.serialize(&Value {
+            output: RawVc::TaskOutput(task(1)),
+            cell: Some(RawVc::TaskCell(task(2), cell)),
+            local,
+            count: 6,
+        })?;
+
+        let mapped: Value = deserialize_with_task_id_mapping(
+            &mut pot::de::SymbolList::new().deserializer_for_slice(&bytes)?,
+            &mut |id: TaskId| TaskId::try_from(*id + 10).ok(),
+        )?;
+        assert_eq!(
+            mapped,
+            Value {
+                output: RawVc::TaskOutput(task(11)),
+                cell: Some(RawVc::TaskCell(task(12), cell)),
+                local,
+                count: 6,
+            }
+        );
+
+        // A task without a mapping fails the deserialization
+        let result: Result<Value, _> = deserialize_with_task_id_mapping(
+            &mut pot::de::SymbolList::new().deserializer_for_slice(&bytes)?,
+            &mut |id: TaskId| (*id != 2).then_some(id),
+        );
+        assert!(result.is_err());
+        Ok(())
+    }
+}
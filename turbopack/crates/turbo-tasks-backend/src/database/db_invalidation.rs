@@ -8,6 +8,8 @@ use std::{
 use anyhow::Context;
 use serde::{Deserialize, Serialize};
 
+use crate::database::invalidation_log::{CacheVersion, INVALIDATION_LOG, LAST_VERSION_FILE};
+
 const INVALIDATION_MARKER: &str = "__turbo_tasks_invalidated_db";
 
 const EXPLANATION: &str = "The cache database has been invalidated. The existence of this file \
@@ -51,6 +53,12 @@ pub enum StartupCacheState {
         /// [`BackingStorage::invalidate`]: crate::BackingStorage::invalidate
         reason_code: Option<String>,
     },
+    /// The cache of a different version has been found, so a cold build is needed. See
+    /// [`read_invalidation_log`][crate::database::invalidation_log::read_invalidation_log].
+    VersionChanged {
+        previous: CacheVersion,
+        current: CacheVersion,
+    },
 }
 
 /// Common invalidation reason codes. The application or libraries it uses may choose to use these
@@ -62,6 +70,9 @@ pub mod invalidation_reasons {
     /// Indicates that the user explicitly clicked a button or ran a command that invalidates the
     /// cache.
     pub const USER_REQUEST: &str = concat!(module_path!(), "::USER_REQUEST");
+    /// Used by the backend when data read from the database is corrupted. The affected reads are
+    /// treated as cache misses.
+    pub const CORRUPTED: &str = concat!(module_path!(), "::CORRUPTED");
 }
 
 /// Atomically create an invalidation marker.
@@ -162,10 +173,14 @@ fn cleanup_db_inner(base_path: &Path) -> io::Result<()> {
         return Ok(());
     };
 
-    // delete everything except the invalidation marker
+    // delete everything except the invalidation marker and the files that explain invalidations
     for entry in contents {
         let entry = entry?;
-        if entry.file_name() != INVALIDATION_MARKER {
+        let file_name = entry.file_name();
+        if file_name != INVALIDATION_MARKER
+            && file_name != INVALIDATION_LOG
+            && file_name != LAST_VERSION_FILE
+        {
             if entry.file_type()?.is_dir() {
                 fs::remove_dir_all(entry.path())?;
             } else {
@@ -0,0 +1,312 @@
+use std::hash::BuildHasher;
+
+use anyhow::{Result, bail};
+use parking_lot::Mutex;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use rustc_hash::FxBuildHasher;
+
+use crate::database::{
+    key_value_database::{KeySpace, KeyValueDatabase},
+    write_batch::{
+        BaseWriteBatch, ConcurrentWriteBatch, SerialWriteBatch, WriteBatch, WriteBuffer,
+    },
+};
+
+/// Configures which faults are injected by a [`FaultInjectionLayer`]. All rates are probabilities
+/// between `0.0` and `1.0`. The default configuration doesn't inject any faults.
+#[derive(Debug, Clone, Default)]
+pub struct FaultInjectionConfig {
+    /// The seed for all random decisions. The same seed leads to the same sequence of faults, as
+    /// long as the database is accessed in the same order.
+    pub seed: u64,
+    /// The probability that a read returns an I/O error.
+    pub read_error_rate: f64,
+    /// The probability that a commit fails without applying any changes.
+    pub commit_error_rate: f64,
+    /// The probability that a commit only applies a random subset of its writes and then fails.
+    /// This simulates a database without atomic commits.
+    pub partial_commit_rate: f64,
+    /// The probability that the process is aborted after all key spaces have been flushed, but
+    /// before the commit. Tests using this should run the build in a child process.
+    pub crash_rate: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CommitFault {
+    None,
+    Error,
+    /// Applies only the writes selected by the seed and fails afterwards.
+    Partial {
+        seed: u64,
+    },
+    Crash,
+}
+
+struct Faults {
+    config: FaultInjectionConfig,
+    rng: Mutex<StdRng>,
+}
+
+impl Faults {
+    fn new(config: FaultInjectionConfig) -> Self {
+        Self {
+            rng: Mutex::new(StdRng::seed_from_u64(config.seed)),
+            config,
+        }
+    }
+
+    fn should_fail_read(&self) -> bool {
+        self.config.read_error_rate > 0.0
+            && self.rng.lock().random_bool(self.config.read_error_rate)
+    }
+
+    fn next_commit_fault(&self) -> CommitFault {
+        let mut rng = self.rng.lock();
+        let roll: f64 = rng.random();
+        let config = &self.config;
+        if roll < config.commit_error_rate {
+            CommitFault::Error
+        } else if roll < config.commit_error_rate + config.partial_commit_rate {
+            CommitFault::Partial { seed: rng.random() }
+        } else if roll < config.commit_error_rate + config.partial_commit_rate + config.crash_rate {
+            CommitFault::Crash
+        } else {
+            CommitFault::None
+        }
+    }
+}
+
+/// Decides if a write is applied by a partial commit. This only depends on the seed and the key,
+/// so it doesn't depend on the order of concurrent writes.
+fn is_write_applied(fault: CommitFault, key_space: KeySpace, key: &[u8]) -> bool {
+    match fault {
+        CommitFault::Partial { seed } => {
+            FxBuildHasher.hash_one((seed, key_space as u8, key)) & 1 == 0
+        }
+        _ => true,
+    }
+}
+
+/// A database layer that injects faults into reads and commits of the underlying database. It's
+/// used to test that the persistent cache recovers from failures, e.g. that restarting after
+/// [`check_db_invalidation_and_cleanup`][crate::database::db_invalidation::check_db_invalidation_and_cleanup]
+/// always leads to a correct build.
+pub struct FaultInjectionLayer<T: KeyValueDatabase> {
+    database: T,
+    faults: Faults,
+}
+
+impl<T: KeyValueDatabase> FaultInjectionLayer<T> {
+    pub fn new(database: T, config: FaultInjectionConfig) -> Self {
+        Self {
+            database,
+            faults: Faults::new(config),
+        }
+    }
+}
+
+impl<T: KeyValueDatabase> KeyValueDatabase for FaultInjectionLayer<T> {
+    type ReadTransaction<'l>
+        = T::ReadTransaction<'l>
+    where
+        Self: 'l;
+
+    fn is_empty(&self) -> bool {
+        self.database.is_empty()
+    }
+
+    fn begin_read_transaction(&self) -> Result<Self::ReadTransaction<'_>> {
+        self.database.begin_read_transaction()
+    }
+
+    type ValueBuffer<'l>
+        = T::ValueBuffer<'l>
+    where
+        Self: 'l;
+
+    fn get<'l, 'db: 'l>(
+        &'l self,
+        transaction: &'l Self::ReadTransaction<'db>,
+        key_space: KeySpace,
+        key: &[u8],
+    ) -> Result<Option<Self::ValueBuffer<'l>>> {
+        if self.faults.should_fail_read() {
+            bail!("Injected read error in {key_space:?}");
+        }
+        self.database.get(transaction, key_space, key)
+    }
+
+    type SerialWriteBatch<'l>
+        = FaultInjectionWriteBatch<T::SerialWriteBatch<'l>>
+    where
+        Self: 'l;
+
+    type ConcurrentWriteBatch<'l>
+        = FaultInjectionWriteBatch<T::ConcurrentWriteBatch<'l>>
+    where
+        Self: 'l;
+
+    fn write_batch(
+        &self,
+    ) -> Result<WriteBatch<'_, Self::SerialWriteBatch<'_>, Self::ConcurrentWriteBatch<'_>>> {
+        let fault = self.faults.next_commit_fault();
+        Ok(match self.database.write_batch()? {
+            WriteBatch::Serial(batch) => {
+                WriteBatch::serial(FaultInjectionWriteBatch { batch, fault })
+            }
+            WriteBatch::Concurrent(batch, _) => {
+                WriteBatch::concurrent(FaultInjectionWriteBatch { batch, fault })
+            }
+        })
+    }
+
+    fn prevent_writes(&self) {
+        self.database.prevent_writes()
+    }
+
+    fn shutdown(&self) -> Result<()> {
+        self.database.shutdown()
+    }
+}
+
+pub struct FaultInjectionWriteBatch<B> {
+    batch: B,
+    fault: CommitFault,
+}
+
+impl<'a, B: BaseWriteBatch<'a>> BaseWriteBatch<'a> for FaultInjectionWriteBatch<B> {
+    type ValueBuffer<'l>
+        = B::ValueBuffer<'l>
+    where
+        Self: 'l,
+        'a: 'l;
+
+    fn get<'l>(&'l self, key_space: KeySpace, key: &[u8]) -> Result<Option<Self::ValueBuffer<'l>>>
+    where
+        'a: 'l,
+    {
+        self.batch.get(key_space, key)
+    }
+
+    fn commit(self) -> Result<()> {
+        match self.fault {
+            CommitFault::None => self.batch.commit(),
+            // Dropping the batch discards all writes
+            CommitFault::Error => bail!("Injected commit error"),
+            CommitFault::Partial { .. } => {
+                self.batch.commit()?;
+                bail!("Injected partial commit")
+            }
+            CommitFault::Crash => {
+                tracing::error!("Injected crash before commit");
+                std::process::abort()
+            }
+        }
+    }
+}
+
+impl<'a, B: SerialWriteBatch<'a>> SerialWriteBatch<'a> for FaultInjectionWriteBatch<B> {
+    fn put(
+        &mut self,
+        key_space: KeySpace,
+        key: WriteBuffer<'_>,
+        value: WriteBuffer<'_>,
+    ) -> Result<()> {
+        if !is_write_applied(self.fault, key_space, &key) {
+            return Ok(());
+        }
+        self.batch.put(key_space, key, value)
+    }
+
+    fn delete(&mut self, key_space: KeySpace, key: WriteBuffer<'_>) -> Result<()> {
+        if !is_write_applied(self.fault, key_space, &key) {
+            return Ok(());
+        }
+        self.batch.delete(key_space, key)
+    }
+
+    fn flush(&mut self, key_space: KeySpace) -> Result<()> {
+        self.batch.flush(key_space)
+    }
+}
+
+impl<'a, B: ConcurrentWriteBatch<'a>> ConcurrentWriteBatch<'a> for FaultInjectionWriteBatch<B> {
+    fn put(&self, key_space: KeySpace, key: WriteBuffer<'_>, value: WriteBuffer<'_>) -> Result<()> {
+        if !is_write_applied(self.fault, key_space, &key) {
+            return Ok(());
+        }
+        self.batch.put(key_space, key, value)
+    }
+
+    fn delete(&self, key_space: KeySpace, key: WriteBuffer<'_>) -> Result<()> {
+        if !is_write_applied(self.fault, key_space, &key) {
+            return Ok(());
+        }
+        self.batch.delete(key_space, key)
+    }
+
+    unsafe fn flush(&self, key_space: KeySpace) -> Result<()> {
+        unsafe { self.batch.flush(key_space) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit_faults(config: FaultInjectionConfig) -> Vec<CommitFault> {
+        let faults = Faults::new(config);
+        (0..100).map(|_| faults.next_commit_fault()).collect()
+    }
+
+    #[test]
+    fn test_no_faults_by_default() {
+        let faults = Faults::new(FaultInjectionConfig::default());
+        assert!((0..100).all(|_| !faults.should_fail_read()));
+        assert!(
+            commit_faults(FaultInjectionConfig::default())
+                .into_iter()
+                .all(|fault| fault == CommitFault::None)
+        );
+    }
+
+    #[test]
+    fn test_faults_are_deterministic() {
+        let config = FaultInjectionConfig {
+            seed: 42,
+            commit_error_rate: 0.2,
+            partial_commit_rate: 0.2,
+            crash_rate: 0.2,
+            ..Default::default()
+        };
+        let faults = commit_faults(config.clone());
+        assert_eq!(faults, commit_faults(config.clone()));
+        assert!(faults.contains(&CommitFault::None));
+        assert!(faults.contains(&CommitFault::Error));
+        assert!(faults.contains(&CommitFault::Crash));
+
+        let different_seed = commit_faults(FaultInjectionConfig { seed: 43, ..config });
+        assert_ne!(faults, different_seed);
+    }
+
+    #[test]
+    fn test_partial_commit_applies_subset() {
+        let fault = CommitFault::Partial { seed: 7 };
+        let applied = (0u32..1000)
+            .filter(|i| is_write_applied(fault, KeySpace::TaskData, &i.to_le_bytes()))
+            .count();
+        assert!(applied > 0 && applied < 1000);
+        for i in 0u32..1000 {
+            let key = i.to_le_bytes();
+            assert_eq!(
+                is_write_applied(fault, KeySpace::TaskData, &key),
+                is_write_applied(fault, KeySpace::TaskData, &key)
+            );
+            assert!(is_write_applied(
+                CommitFault::None,
+                KeySpace::TaskData,
+                &key
+            ));
+        }
+    }
+}
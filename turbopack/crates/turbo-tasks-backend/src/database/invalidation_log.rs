@@ -0,0 +1,235 @@
+use std::{
+    fmt::{self, Display},
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, ErrorKind, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+
+use crate::{GitVersionInfo, database::db_invalidation::invalidation_reasons};
+
+/// A JSON lines file in the base path of the cache, with one [`InvalidationLogEntry`] per line.
+/// It's kept when the cache is cleaned up, so users can find out afterwards why a build was cold.
+pub(crate) const INVALIDATION_LOG: &str = "__turbo_tasks_invalidation_log";
+
+/// Stores the [`CacheVersion`] of the last startup, so we can report what changed.
+pub(crate) const LAST_VERSION_FILE: &str = "__turbo_tasks_last_version";
+
+/// Only the most recent entries are kept.
+const MAX_LOG_ENTRIES: usize = 50;
+
+/// The version information a cache has been created with. See [`GitVersionInfo`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CacheVersion {
+    pub describe: String,
+    pub dirty: bool,
+}
+
+impl From<&GitVersionInfo<'_>> for CacheVersion {
+    fn from(version_info: &GitVersionInfo<'_>) -> Self {
+        Self {
+            describe: version_info.describe.to_string(),
+            dirty: version_info.dirty,
+        }
+    }
+}
+
+impl Display for CacheVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe)?;
+        if self.dirty {
+            write!(f, " (dirty)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Why the cache couldn't be used on startup.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum InvalidationReason {
+    /// The cache has been invalidated by [`crate::BackingStorage::invalidate`] and has been
+    /// deleted on startup.
+    Invalidated { reason_code: Option<String> },
+    /// The version of the build changed, so a different cache directory is used.
+    VersionChanged {
+        previous: CacheVersion,
+        current: CacheVersion,
+    },
+}
+
+impl Display for InvalidationReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidationReason::Invalidated { reason_code } => {
+                write!(f, "The cache has been deleted")?;
+                match reason_code.as_deref() {
+                    Some(invalidation_reasons::PANIC) => {
+                        write!(f, " because an internal error has been detected")
+                    }
+                    Some(invalidation_reasons::USER_REQUEST) => {
+                        write!(f, " as the result of a user request")
+                    }
+                    Some(invalidation_reasons::CORRUPTED) => {
+                        write!(f, " because corrupted data has been read from it")
+                    }
+                    Some(reason_code) => write!(f, " (reason: {reason_code})"),
+                    None => write!(f, " for an unknown reason"),
+                }
+            }
+            InvalidationReason::VersionChanged { previous, current } => {
+                write!(f, "The version changed from {previous} to {current}")?;
+                if current.dirty && !previous.dirty {
+                    write!(f, ", the git repository is dirty")?;
+                } else if current.describe != previous.describe {
+                    write!(f, ", caches are not shared between versions")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InvalidationLogEntry {
+    /// Seconds since the unix epoch.
+    pub timestamp: i64,
+    #[serde(flatten)]
+    pub reason: InvalidationReason,
+}
+
+impl Display for InvalidationLogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match Timestamp::from_second(self.timestamp) {
+            Ok(timestamp) => write!(f, "[{timestamp}] {}", self.reason),
+            Err(_) => write!(f, "{}", self.reason),
+        }
+    }
+}
+
+/// Reads all entries of the invalidation log, oldest first. Unreadable entries are skipped.
+pub fn read_invalidation_log(base_path: &Path) -> Vec<InvalidationLogEntry> {
+    let Ok(file) = File::open(base_path.join(INVALIDATION_LOG)) else {
+        return Vec::new();
+    };
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect()
+}
+
+/// Appends an entry to the invalidation log and returns it.
+///
+/// This should be run with the base (non-versioned) path.
+pub(crate) fn append_invalidation_log(
+    base_path: &Path,
+    reason: InvalidationReason,
+) -> Result<InvalidationLogEntry> {
+    let entry = InvalidationLogEntry {
+        timestamp: Timestamp::now().as_second(),
+        reason,
+    };
+    let mut entries = read_invalidation_log(base_path);
+    entries.push(entry.clone());
+    let skip = entries.len().saturating_sub(MAX_LOG_ENTRIES);
+    write_json_lines(&base_path.join(INVALIDATION_LOG), &entries[skip..])
+        .context("Failed to write the invalidation log")?;
+    Ok(entry)
+}
+
+/// Compares the version info with the one from the last startup and stores the new one. Returns
+/// the [`InvalidationReason`] if the version changed.
+pub(crate) fn check_version_change(
+    base_path: &Path,
+    version_info: &GitVersionInfo,
+) -> Result<Option<InvalidationReason>> {
+    let path = base_path.join(LAST_VERSION_FILE);
+    let current = CacheVersion::from(version_info);
+    let previous = match fs::read(&path) {
+        // a corrupted file is treated like a missing one
+        Ok(content) => serde_json::from_slice::<CacheVersion>(&content).ok(),
+        Err(err) if err.kind() == ErrorKind::NotFound => None,
+        Err(err) => return Err(err).context("Failed to read the last cache version"),
+    };
+    if previous.as_ref() == Some(&current) {
+        return Ok(None);
+    }
+    fs::create_dir_all(base_path)?;
+    fs::write(&path, serde_json::to_vec(&current)?)
+        .context("Failed to write the last cache version")?;
+    Ok(previous.map(|previous| InvalidationReason::VersionChanged { previous, current }))
+}
+
+fn write_json_lines(path: &Path, entries: &[InvalidationLogEntry]) -> Result<()> {
+    let temp_path = path.with_extension("tmp");
+    let mut writer = BufWriter::new(File::create(&temp_path)?);
+    for entry in entries {
+        serde_json::to_writer(&mut writer, entry)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    drop(writer);
+    fs::rename(temp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_version_change_is_logged() -> Result<()> {
+        let tmp_dir = TempDir::new()?;
+        let base_path = tmp_dir.path();
+        let version = |describe, dirty| GitVersionInfo { describe, dirty };
+
+        assert!(check_version_change(base_path, &version("v1", false))?.is_none());
+        assert!(check_version_change(base_path, &version("v1", false))?.is_none());
+
+        let reason = check_version_change(base_path, &version("v2", false))?.unwrap();
+        let InvalidationReason::VersionChanged { previous, current } = &reason else {
+            panic!("expected a version change, got {reason:?}");
+        };
+        assert_eq!(previous.describe, "v1");
+        assert_eq!(current.describe, "v2");
+
+        append_invalidation_log(base_path, reason)?;
+        append_invalidation_log(
+            base_path,
+            InvalidationReason::Invalidated {
+                reason_code: Some(invalidation_reasons::USER_REQUEST.to_string()),
+            },
+        )?;
+        let log = read_invalidation_log(base_path);
+        assert_eq!(log.len(), 2);
+        assert!(log[0].to_string().contains("from v1 to v2"));
+        assert!(log[1].to_string().contains("user request"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_log_is_truncated() -> Result<()> {
+        let tmp_dir = TempDir::new()?;
+        for i in 0..MAX_LOG_ENTRIES + 5 {
+            append_invalidation_log(
+                tmp_dir.path(),
+                InvalidationReason::Invalidated {
+                    reason_code: Some(i.to_string()),
+                },
+            )?;
+        }
+        let log = read_invalidation_log(tmp_dir.path());
+        assert_eq!(log.len(), MAX_LOG_ENTRIES);
+        assert!(matches!(
+            &log[0].reason,
+            InvalidationReason::Invalidated { reason_code: Some(code) } if code == "5"
+        ));
+        Ok(())
+    }
+}
@@ -11,6 +11,9 @@ pub enum KeySpace {
     TaskData = 2,
     ForwardTaskCache = 3,
     ReverseTaskCache = 4,
+    /// The cell data of tasks. It's stored separately from the other task data, since it can be
+    /// evicted. Missing cell data is recomputed on demand.
+    TaskCells = 5,
 }
 
 pub trait KeyValueDatabase {
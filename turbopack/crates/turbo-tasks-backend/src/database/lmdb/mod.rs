@@ -20,6 +20,7 @@ pub struct LmbdKeyValueDatabase {
     meta_db: Database,
     forward_task_cache_db: Database,
     reverse_task_cache_db: Database,
+    cells_db: Database,
 }
 
 impl LmbdKeyValueDatabase {
@@ -38,7 +39,7 @@ impl LmbdKeyValueDatabase {
                     | EnvironmentFlags::NO_TLS,
             )
             .set_max_readers((available_parallelism().map_or(16, |v| v.get()) * 8) as u32)
-            .set_max_dbs(5)
+            .set_max_dbs(6)
             .set_map_size(MAP_SIZE)
             .open(path)?;
         let infra_db = env.create_db(Some("infra"), DatabaseFlags::INTEGER_KEY)?;
@@ -48,6 +49,7 @@ impl LmbdKeyValueDatabase {
             env.create_db(Some("forward_task_cache"), DatabaseFlags::empty())?;
         let reverse_task_cache_db =
             env.create_db(Some("reverse_task_cache"), DatabaseFlags::INTEGER_KEY)?;
+        let cells_db = env.create_db(Some("cells"), DatabaseFlags::INTEGER_KEY)?;
         Ok(LmbdKeyValueDatabase {
             env,
             infra_db,
@@ -55,6 +57,7 @@ impl LmbdKeyValueDatabase {
             meta_db,
             forward_task_cache_db,
             reverse_task_cache_db,
+            cells_db,
         })
     }
 
@@ -65,6 +68,7 @@ impl LmbdKeyValueDatabase {
             KeySpace::TaskData => self.data_db,
             KeySpace::ForwardTaskCache => self.forward_task_cache_db,
             KeySpace::ReverseTaskCache => self.reverse_task_cache_db,
+            KeySpace::TaskCells => self.cells_db,
         }
     }
 }
@@ -93,6 +97,7 @@ impl KeyValueDatabase for LmbdKeyValueDatabase {
             KeySpace::TaskData => self.data_db,
             KeySpace::ForwardTaskCache => self.forward_task_cache_db,
             KeySpace::ReverseTaskCache => self.reverse_task_cache_db,
+            KeySpace::TaskCells => self.cells_db,
         };
 
         let value = match extended_key::get(transaction, db, key) {
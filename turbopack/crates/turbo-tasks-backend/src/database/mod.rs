@@ -1,9 +1,12 @@
 #[cfg(feature = "lmdb")]
 mod by_key_space;
+pub mod cache_export;
 pub mod db_invalidation;
 pub mod db_versioning;
+pub mod fault_injection;
 #[cfg(feature = "lmdb")]
 pub mod fresh_db_optimization;
+pub mod invalidation_log;
 pub mod key_value_database;
 #[cfg(feature = "lmdb")]
 pub mod lmdb;
@@ -1,5 +1,6 @@
 use std::{
     cmp::max,
+    env,
     path::PathBuf,
     sync::Arc,
     thread::available_parallelism,
@@ -9,7 +10,8 @@ use std::{
 use anyhow::{Ok, Result};
 use parking_lot::Mutex;
 use turbo_persistence::{
-    ArcSlice, CompactConfig, KeyBase, StoreKey, TurboPersistence, ValueBuffer,
+    ArcSlice, BlockCompression, CompactConfig, DbConfig, KeyBase, RecoveryMode, StoreKey,
+    TurboPersistence, ValueBuffer,
 };
 use turbo_tasks::{JoinHandle, message_queue::TimingEvent, spawn, turbo_tasks};
 
@@ -22,6 +24,8 @@ use crate::database::{
 mod parallel_scheduler;
 
 const MB: u64 = 1024 * 1024;
+/// The number of key families in the database. See [`KeySpace`].
+const FAMILIES: usize = 6;
 const COMPACT_CONFIG: CompactConfig = CompactConfig {
     min_merge_count: 3,
     optimal_merge_count: 8,
@@ -40,8 +44,32 @@ pub struct TurboKeyValueDatabase {
 }
 
 impl TurboKeyValueDatabase {
+    /// Opens the database.
+    ///
+    /// The on-disk size of the database can be limited with the `TURBO_ENGINE_MAX_CACHE_SIZE_MB`
+    /// environment variable. When the limit is exceeded, compaction evicts the least recently
+    /// accessed cell data, which is recomputed on demand.
     pub fn new(versioned_path: PathBuf, is_ci: bool, is_short_session: bool) -> Result<Self> {
-        let db = Arc::new(TurboPersistence::open(versioned_path)?);
+        let mut family_block_compression = vec![BlockCompression::Lz4; FAMILIES];
+        // Task data makes up most of the cache size and compresses well with zstd
+        family_block_compression[KeySpace::TaskMeta as usize] = BlockCompression::Zstd;
+        family_block_compression[KeySpace::TaskData as usize] = BlockCompression::Zstd;
+        family_block_compression[KeySpace::TaskCells as usize] = BlockCompression::Zstd;
+        let max_size = env::var("TURBO_ENGINE_MAX_CACHE_SIZE_MB")
+            .ok()
+            .and_then(|value| value.parse::<u64>().ok())
+            .map(|value| value * MB);
+        let config = DbConfig {
+            // Corrupted files only lose the affected cache entries instead of failing the whole
+            // cache. Checking all block checksums would be too expensive for startup.
+            recovery: RecoveryMode::Quick,
+            family_block_compression,
+            max_size,
+            // Only cell data can be recomputed. All other task data is needed to invalidate tasks
+            // correctly.
+            evictable_families: vec![KeySpace::TaskCells as u32],
+        };
+        let db = Arc::new(TurboPersistence::open_with_config(versioned_path, config)?);
         Ok(Self {
             db: db.clone(),
             compact_join_handle: Mutex::new(None),
@@ -151,7 +179,8 @@ fn do_compact(
 }
 
 pub struct TurboWriteBatch<'a> {
-    batch: turbo_persistence::WriteBatch<WriteBuffer<'static>, TurboTasksParallelScheduler, 5>,
+    batch:
+        turbo_persistence::WriteBatch<WriteBuffer<'static>, TurboTasksParallelScheduler, FAMILIES>,
     db: &'a Arc<TurboPersistence<TurboTasksParallelScheduler>>,
     compact_join_handle: Option<&'a Mutex<Option<JoinHandle<Result<()>>>>>,
 }
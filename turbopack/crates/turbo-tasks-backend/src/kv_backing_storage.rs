@@ -1,11 +1,12 @@
 use std::{
     borrow::Borrow,
-    env,
-    path::PathBuf,
+    env, fs,
+    path::{Path, PathBuf},
     sync::{Arc, LazyLock, Mutex, PoisonError, Weak},
 };
 
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Context, Result, anyhow, bail};
+use rustc_hash::{FxHashMap, FxHashSet};
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 use turbo_tasks::{
@@ -19,10 +20,18 @@ use crate::{
     GitVersionInfo,
     backend::{AnyOperation, TaskDataCategory},
     backing_storage::{BackingStorage, BackingStorageSealed},
-    data::CachedDataItem,
+    data::{CachedDataItem, CellRef, CollectiblesRef, OutputValue},
     database::{
+        cache_export::{
+            CacheExportReader, CacheExportWriter, ExportEntry, ExportHeader, ExportedTask,
+            deserialize_with_task_id_mapping, export_file_path, rebase_paths,
+        },
         db_invalidation::{StartupCacheState, check_db_invalidation_and_cleanup, invalidate_db},
         db_versioning::handle_db_versioning,
+        invalidation_log::{
+            InvalidationLogEntry, InvalidationReason, append_invalidation_log,
+            check_version_change, read_invalidation_log,
+        },
         key_value_database::{KeySpace, KeyValueDatabase},
         write_batch::{
             BaseWriteBatch, ConcurrentWriteBatch, SerialWriteBatch, WriteBatch, WriteBatchRef,
@@ -36,6 +45,15 @@ use crate::{
 const POT_CONFIG: pot::Config = pot::Config::new().compatibility(pot::Compatibility::V4);
 
 fn pot_serialize_small_vec<T: Serialize>(value: &T) -> pot::Result<SmallVec<[u8; 16]>> {
+    let mut output = SmallVec::new();
+    pot_serialize_into_small_vec(value, &mut output)?;
+    Ok(output)
+}
+
+fn pot_serialize_into_small_vec<T: Serialize>(
+    value: &T,
+    output: &mut SmallVec<[u8; 16]>,
+) -> pot::Result<()> {
     struct SmallVecWrite<'l>(&'l mut SmallVec<[u8; 16]>);
     impl std::io::Write for SmallVecWrite<'_> {
         #[inline]
@@ -56,9 +74,7 @@ fn pot_serialize_small_vec<T: Serialize>(value: &T) -> pot::Result<SmallVec<[u8;
         }
     }
 
-    let mut output = SmallVec::new();
-    POT_CONFIG.serialize_into(value, SmallVecWrite(&mut output))?;
-    Ok(output)
+    POT_CONFIG.serialize_into(value, SmallVecWrite(output))
 }
 
 fn pot_ser_symbol_map() -> pot::ser::SymbolMap {
@@ -73,6 +89,10 @@ const META_KEY_OPERATIONS: u32 = 0;
 const META_KEY_NEXT_FREE_TASK_ID: u32 = 1;
 const META_KEY_SESSION_ID: u32 = 2;
 
+/// Set in the first byte of a [`KeySpace::TaskData`] value when the task has cell data in
+/// [`KeySpace::TaskCells`]. Tasks without cells don't have an entry there.
+const TASK_ITEMS_HAS_CELLS: u8 = 1;
+
 struct IntKey([u8; 4]);
 
 impl IntKey {
@@ -163,8 +183,29 @@ impl<T: KeyValueDatabase> KeyValueDatabaseBackingStorage<T> {
     where
         T: Send + Sync + 'static,
     {
-        let startup_cache_state = check_db_invalidation_and_cleanup(&base_path)
+        let mut startup_cache_state = check_db_invalidation_and_cleanup(&base_path)
             .context("Failed to check database invalidation and cleanup")?;
+        // The invalidation log is only informational, so failures to update it are ignored
+        let version_change = check_version_change(&base_path, version_info).unwrap_or(None);
+        if let StartupCacheState::Invalidated { reason_code } = &startup_cache_state {
+            let _ = append_invalidation_log(
+                &base_path,
+                InvalidationReason::Invalidated {
+                    reason_code: reason_code.clone(),
+                },
+            );
+        } else if let StartupCacheState::Cached = startup_cache_state
+            && let Some(InvalidationReason::VersionChanged { previous, current }) = version_change
+        {
+            let _ = append_invalidation_log(
+                &base_path,
+                InvalidationReason::VersionChanged {
+                    previous: previous.clone(),
+                    current: current.clone(),
+                },
+            );
+            startup_cache_state = StartupCacheState::VersionChanged { previous, current };
+        }
         let versioned_path = handle_db_versioning(&base_path, version_info, is_ci)
             .context("Failed to handle database versioning")?;
         let database = (database)(versioned_path).context("Failed to open database")?;
@@ -197,6 +238,394 @@ impl<T: KeyValueDatabase> KeyValueDatabaseBackingStorage<T> {
         };
         Ok((backing_storage, startup_cache_state))
     }
+
+    /// Exports the persistent cache into `dir`, so that it can be imported into another database
+    /// with [`Self::import_cache`], e.g. on another checkout of the same version.
+    ///
+    /// All `TaskId`s are rewritten to export indices, so the export doesn't depend on the task ids
+    /// of this database. `root` is the project root path. Paths inside of it will be rebased to the
+    /// root of the importing project.
+    ///
+    /// This should only be called after the backend has been stopped. It fails when the database
+    /// contains uncompleted operations.
+    pub fn export_cache(
+        &self,
+        dir: &Path,
+        version_info: &GitVersionInfo,
+        root: &str,
+    ) -> Result<()> {
+        if version_info.dirty {
+            bail!("Unable to export the cache of a dirty git repository");
+        }
+        let _span = tracing::info_span!("export cache").entered();
+        let database = &self.inner.database;
+        let tx = database.begin_read_transaction()?;
+        let get = |key_space: KeySpace, key: u32| -> Result<Vec<u8>> {
+            Ok(database
+                .get(&tx, key_space, IntKey::new(key).as_ref())?
+                .map(|bytes| bytes.borrow().to_vec())
+                .unwrap_or_default())
+        };
+        let next_free_task_id = get(KeySpace::Infra, META_KEY_NEXT_FREE_TASK_ID)?;
+        if next_free_task_id.is_empty() {
+            bail!("Unable to export an empty cache");
+        }
+        let next_free_task_id = as_u32(&*next_free_task_id)?;
+        let session_id = get(KeySpace::Infra, META_KEY_SESSION_ID)?;
+        let session_id = if session_id.is_empty() {
+            0
+        } else {
+            as_u32(&*session_id)?
+        };
+        // The `TaskId`s of uncompleted operations can't be rewritten, see
+        // `deserialize_with_task_id_mapping`. There are none after the backend has been stopped.
+        let operations = get(KeySpace::Infra, META_KEY_OPERATIONS)?;
+        if !operations.is_empty()
+            && !deserialize_with_good_error::<Vec<AnyOperation>>(&operations)?.is_empty()
+        {
+            bail!("Unable to export a cache with uncompleted operations");
+        }
+
+        // Tasks that have been allocated, but never persisted, are not exported
+        let mut export_indices = FxHashMap::default();
+        for task_id in 1..next_free_task_id {
+            if database
+                .get(
+                    &tx,
+                    KeySpace::ReverseTaskCache,
+                    IntKey::new(task_id).as_ref(),
+                )?
+                .is_some()
+            {
+                let index = export_indices.len() as u32 + 1;
+                export_indices.insert(TaskId::try_from(task_id)?, index);
+            }
+        }
+
+        fs::create_dir_all(dir)?;
+        let path = export_file_path(dir, version_info.describe);
+        let mut writer = CacheExportWriter::create(
+            &path,
+            &ExportHeader {
+                version: version_info.describe.to_string(),
+                root: root.to_string(),
+                session_id,
+            },
+        )
+        .with_context(|| format!("Unable to create cache export {}", path.display()))?;
+        let mut incomplete_tasks = 0;
+        for task_id in (1..next_free_task_id).map(TaskId::try_from) {
+            let task_id = task_id?;
+            if !export_indices.contains_key(&task_id) {
+                continue;
+            }
+            let task_type = get(KeySpace::ReverseTaskCache, *task_id)?;
+            let mut references = Vec::new();
+            let mut to_export_index = |task_id: TaskId| {
+                let index = *export_indices.get(&task_id)?;
+                references.push(index);
+                TaskId::try_from(index).ok()
+            };
+            let task = (|| -> Result<_> {
+                let task_type = map_task_ids_in_task_type(&task_type, &mut to_export_index)?;
+                let mut export_items = |key_space| -> Result<_> {
+                    let bytes = get(key_space, *task_id)?;
+                    if bytes.is_empty() {
+                        return Ok((0, Vec::new()));
+                    }
+                    let (flags, items) = split_task_items(&bytes)?;
+                    let items = map_task_ids_in_items(task_id, items, &mut to_export_index)?;
+                    Ok((flags, items.to_vec()))
+                };
+                let (_, meta) = export_items(KeySpace::TaskMeta)?;
+                let (flags, data) = export_items(KeySpace::TaskData)?;
+                let cells = if flags & TASK_ITEMS_HAS_CELLS != 0 {
+                    let bytes = get(KeySpace::TaskCells, *task_id)?;
+                    map_task_ids_in_items(task_id, &bytes, &mut to_export_index)?.to_vec()
+                } else {
+                    Vec::new()
+                };
+                Ok((task_type, meta, data, cells))
+            })();
+            match task {
+                Ok((task_type, meta, data, cells)) => {
+                    references.sort_unstable();
+                    references.dedup();
+                    writer.write_entry(&ExportEntry::Task(ExportedTask {
+                        task_type,
+                        references,
+                        meta,
+                        data,
+                        cells,
+                    }))?;
+                }
+                Err(err) => {
+                    tracing::debug!("Unable to export {task_id}: {err:?}");
+                    incomplete_tasks += 1;
+                    // Other tasks might still reference it, so it keeps its export index
+                    writer.write_entry(&ExportEntry::IncompleteTask {
+                        task_type: map_task_ids_in_task_type(&task_type, &mut |task_id| {
+                            TaskId::try_from(*export_indices.get(&task_id)?).ok()
+                        })
+                        .unwrap_or_default(),
+                    })?;
+                }
+            }
+        }
+
+        writer.finish()?;
+        if incomplete_tasks > 0 {
+            tracing::warn!(
+                "{incomplete_tasks} tasks have been exported without data, since their data \
+                 couldn't be exported"
+            );
+        }
+        Ok(())
+    }
+
+    /// Imports a cache export created by [`Self::export_cache`] from `dir`. Returns `false` if
+    /// there is no export for the current version.
+    ///
+    /// The tasks of the export get new `TaskId`s. Tasks that already exist in this database are not
+    /// imported, neither are tasks that couldn't be exported or rebased. Since tasks reference each
+    /// other, e.g. as dependencies, all tasks that are connected to such a task are skipped too.
+    /// This keeps the task graph consistent, but it might skip large parts of the export when it
+    /// overlaps with this database.
+    ///
+    /// When the export has been created for a different project root, all paths inside of the old
+    /// root are rebased to `root`.
+    ///
+    /// This should only be called when the database is not in use by a backend.
+    pub fn import_cache(
+        &self,
+        dir: &Path,
+        version_info: &GitVersionInfo,
+        root: &str,
+    ) -> Result<bool> {
+        if version_info.dirty {
+            return Ok(false);
+        }
+        let path = export_file_path(dir, version_info.describe);
+        if !path.exists() {
+            return Ok(false);
+        }
+        let _span = tracing::info_span!("import cache").entered();
+        let (mut reader, header) = CacheExportReader::open(&path)
+            .with_context(|| format!("Unable to open cache export {}", path.display()))?;
+        if header.version != version_info.describe {
+            bail!(
+                "Cache export {} has been created by version {}",
+                path.display(),
+                header.version
+            );
+        }
+        let rebase = (header.root != root).then_some((header.root.as_str(), root));
+        let database = &self.inner.database;
+        let tx = database.begin_read_transaction()?;
+
+        // Finds the tasks that can be imported. Tasks that reference each other are in the same
+        // component and are either all imported or all skipped.
+        struct ImportedTask {
+            /// The serialized task type when it doesn't reference other tasks. Otherwise the task
+            /// type depends on the new `TaskId`s of the referenced tasks.
+            task_type: Option<Vec<u8>>,
+            importable: bool,
+        }
+        let mut tasks: Vec<ImportedTask> = Vec::new();
+        let mut edges: Vec<(u32, u32)> = Vec::new();
+        while let Some(entry) = reader.next_entry()? {
+            let index = tasks.len() as u32 + 1;
+            match entry {
+                ExportEntry::Task(task) => {
+                    let mut has_references = false;
+                    let result = (|| -> Result<_> {
+                        let task_type = import_task_type(&task.task_type, rebase, &mut |id| {
+                            has_references = true;
+                            Some(id)
+                        })?;
+                        let task_id = TaskId::try_from(index)?;
+                        for bytes in [&task.meta, &task.data, &task.cells] {
+                            import_items(task_id, bytes, rebase, &mut Some::<TaskId>)?;
+                        }
+                        Ok(task_type)
+                    })();
+                    edges.extend(task.references.iter().map(|&reference| (index, reference)));
+                    tasks.push(match result {
+                        // A task type that references other tasks can't exist in this database
+                        // unless the referenced tasks exist too. In that case the referenced
+                        // tasks aren't importable, which skips the whole component.
+                        Ok(task_type) if !has_references => {
+                            let exists = database
+                                .get(&tx, KeySpace::ForwardTaskCache, &task_type)?
+                                .is_some();
+                            ImportedTask {
+                                task_type: Some(task_type),
+                                importable: !exists,
+                            }
+                        }
+                        Ok(_) => ImportedTask {
+                            task_type: None,
+                            importable: true,
+                        },
+                        Err(err) => {
+                            tracing::debug!("Unable to import task {index}: {err:?}");
+                            ImportedTask {
+                                task_type: None,
+                                importable: false,
+                            }
+                        }
+                    });
+                }
+                ExportEntry::IncompleteTask { .. } => tasks.push(ImportedTask {
+                    task_type: None,
+                    importable: false,
+                }),
+            }
+        }
+
+        let mut components = (0..=tasks.len() as u32).collect::<Vec<_>>();
+        let mut skipped_components = FxHashSet::default();
+        for (index, reference) in edges {
+            if reference == 0 || reference as usize > tasks.len() {
+                skipped_components.insert(find_component(&mut components, index));
+                continue;
+            }
+            let a = find_component(&mut components, index);
+            let b = find_component(&mut components, reference);
+            components[a as usize] = b;
+        }
+        for (index, task) in (1..).zip(&tasks) {
+            if !task.importable {
+                skipped_components.insert(find_component(&mut components, index));
+            }
+        }
+        let mut next_free_task_id = self
+            .inner
+            .get_infra_u32(META_KEY_NEXT_FREE_TASK_ID)?
+            .unwrap_or(*TaskId::MIN);
+        let mut new_task_ids: Vec<Option<TaskId>> = vec![None; tasks.len() + 1];
+        for index in 1..=tasks.len() as u32 {
+            if !skipped_components.contains(&find_component(&mut components, index)) {
+                new_task_ids[index as usize] = Some(TaskId::try_from(next_free_task_id)?);
+                next_free_task_id += 1;
+            }
+        }
+        let imported_tasks = new_task_ids.iter().flatten().count();
+        let skipped_tasks = tasks.len() - imported_tasks;
+        if skipped_tasks > 0 {
+            tracing::warn!(
+                "{skipped_tasks} of {} tasks of the cache export have been skipped, since they or \
+                 connected tasks already exist or couldn't be imported",
+                tasks.len()
+            );
+        }
+        if imported_tasks == 0 {
+            return Ok(false);
+        }
+
+        let session_id = self
+            .inner
+            .get_infra_u32(META_KEY_SESSION_ID)?
+            .unwrap_or(0)
+            .max(header.session_id);
+        drop(tx);
+
+        let (mut reader, _) = CacheExportReader::open(&path)?;
+        let mut to_new_task_id = |task_id: TaskId| new_task_ids.get(*task_id as usize).copied()?;
+        let mut batch = database.write_batch()?;
+        let mut task_type_bytes = Vec::new();
+        let mut index = 0;
+        while let Some(entry) = reader.next_entry()? {
+            let task = match entry {
+                ExportEntry::Task(task) => task,
+                ExportEntry::IncompleteTask { .. } => {
+                    index += 1;
+                    continue;
+                }
+            };
+            index += 1;
+            let Some(task_id) = new_task_ids[index] else {
+                continue;
+            };
+            let key = IntKey::new(*task_id);
+            let task_type = match &tasks[index - 1].task_type {
+                Some(task_type) => task_type,
+                None => {
+                    task_type_bytes =
+                        import_task_type(&task.task_type, rebase, &mut to_new_task_id)
+                            .with_context(|| {
+                                format!("Unable to import the task type of {task_id}")
+                            })?;
+                    &task_type_bytes
+                }
+            };
+            batch.put(
+                KeySpace::ForwardTaskCache,
+                WriteBuffer::Borrowed(task_type),
+                WriteBuffer::Borrowed(key.as_ref()),
+            )?;
+            batch.put(
+                KeySpace::ReverseTaskCache,
+                WriteBuffer::Borrowed(key.as_ref()),
+                WriteBuffer::Borrowed(task_type),
+            )?;
+            let cells = import_items(task_id, &task.cells, rebase, &mut to_new_task_id)?;
+            let data_flags = if cells.is_empty() {
+                0
+            } else {
+                TASK_ITEMS_HAS_CELLS
+            };
+            for (key_space, bytes, flags) in [
+                (KeySpace::TaskMeta, &task.meta, 0),
+                (KeySpace::TaskData, &task.data, data_flags),
+            ] {
+                if bytes.is_empty() && flags == 0 {
+                    continue;
+                }
+                let items = import_items(task_id, bytes, rebase, &mut to_new_task_id)?;
+                let mut value = SmallVec::<[u8; 16]>::from_slice(&[flags]);
+                if items.is_empty() {
+                    pot_serialize_into_small_vec(&Vec::<CachedDataItem>::new(), &mut value)?;
+                } else {
+                    value.extend_from_slice(&items);
+                }
+                batch.put(
+                    key_space,
+                    WriteBuffer::Borrowed(key.as_ref()),
+                    WriteBuffer::SmallVec(value),
+                )?;
+            }
+            if !cells.is_empty() {
+                batch.put(
+                    KeySpace::TaskCells,
+                    WriteBuffer::Borrowed(key.as_ref()),
+                    WriteBuffer::SmallVec(cells),
+                )?;
+            }
+        }
+        for (key, value) in [
+            (META_KEY_NEXT_FREE_TASK_ID, next_free_task_id.to_le_bytes()),
+            (META_KEY_SESSION_ID, session_id.to_le_bytes()),
+        ] {
+            batch.put(
+                KeySpace::Infra,
+                WriteBuffer::Borrowed(IntKey::new(key).as_ref()),
+                WriteBuffer::Borrowed(&value),
+            )?;
+        }
+        for key_space in [
+            KeySpace::Infra,
+            KeySpace::TaskMeta,
+            KeySpace::TaskData,
+            KeySpace::TaskCells,
+            KeySpace::ForwardTaskCache,
+            KeySpace::ReverseTaskCache,
+        ] {
+            batch.flush(key_space)?;
+        }
+        batch.commit()?;
+        Ok(true)
+    }
 }
 
 impl<T: KeyValueDatabase> KeyValueDatabaseBackingStorageInner<T> {
@@ -254,6 +683,14 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
     fn invalidate(&self, reason_code: &str) -> Result<()> {
         self.inner.invalidate(reason_code)
     }
+
+    fn invalidation_log(&self) -> Vec<InvalidationLogEntry> {
+        self.inner
+            .base_path
+            .as_deref()
+            .map(read_invalidation_log)
+            .unwrap_or_default()
+    }
 }
 
 impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorageSealed
@@ -328,7 +765,7 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorageSealed
                     process_task_data(snapshots, Some(batch))?;
                     let span = tracing::trace_span!("flush task data").entered();
                     parallel::try_for_each(
-                        &[KeySpace::TaskMeta, KeySpace::TaskData],
+                        &[KeySpace::TaskMeta, KeySpace::TaskData, KeySpace::TaskCells],
                         |&key_space| {
                             let _span = span.clone().entered();
                             // Safety: We already finished all processing of the task data and task
@@ -414,22 +851,44 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorageSealed
                         let key = IntKey::new(*task_id);
                         let key = key.as_ref();
                         if let Some(meta) = meta {
+                            let (items, _) = split_serialized_items(&meta);
                             batch
-                                .put(KeySpace::TaskMeta, WriteBuffer::Borrowed(key), meta)
+                                .put(
+                                    KeySpace::TaskMeta,
+                                    WriteBuffer::Borrowed(key),
+                                    WriteBuffer::Borrowed(items),
+                                )
                                 .with_context(|| {
                                     anyhow!("Unable to write meta items for {task_id}")
                                 })?;
                         }
                         if let Some(data) = data {
+                            let (items, cells) = split_serialized_items(&data);
                             batch
-                                .put(KeySpace::TaskData, WriteBuffer::Borrowed(key), data)
+                                .put(
+                                    KeySpace::TaskData,
+                                    WriteBuffer::Borrowed(key),
+                                    WriteBuffer::Borrowed(items),
+                                )
                                 .with_context(|| {
                                     anyhow!("Unable to write data items for {task_id}")
                                 })?;
+                            if !cells.is_empty() {
+                                batch
+                                    .put(
+                                        KeySpace::TaskCells,
+                                        WriteBuffer::Borrowed(key),
+                                        WriteBuffer::Borrowed(cells),
+                                    )
+                                    .with_context(|| {
+                                        anyhow!("Unable to write cell data for {task_id}")
+                                    })?;
+                            }
                         }
                     }
                     batch.flush(KeySpace::TaskMeta)?;
                     batch.flush(KeySpace::TaskData)?;
+                    batch.flush(KeySpace::TaskCells)?;
                 }
 
                 let mut next_task_id = get_next_free_task_id::<
@@ -562,6 +1021,7 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorageSealed
             task_id: TaskId,
             category: TaskDataCategory,
         ) -> Result<Vec<CachedDataItem>> {
+            let key = IntKey::new(*task_id);
             let Some(bytes) = database.get(
                 tx,
                 match category {
@@ -569,12 +1029,20 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorageSealed
                     TaskDataCategory::Data => KeySpace::TaskData,
                     TaskDataCategory::All => unreachable!(),
                 },
-                IntKey::new(*task_id).as_ref(),
+                key.as_ref(),
             )?
             else {
                 return Ok(Vec::new());
             };
-            let result: Vec<CachedDataItem> = deserialize_with_good_error(bytes.borrow())?;
+            let (flags, items) = split_task_items(bytes.borrow())?;
+            let mut result: Vec<CachedDataItem> = deserialize_with_good_error(items)?;
+            if flags & TASK_ITEMS_HAS_CELLS != 0 {
+                // The cell data might have been evicted. It will be recomputed when needed.
+                if let Some(bytes) = database.get(tx, KeySpace::TaskCells, key.as_ref())? {
+                    let cells: Vec<CachedDataItem> = deserialize_with_good_error(bytes.borrow())?;
+                    result.extend(cells);
+                }
+            }
             Ok(result)
         }
         inner
@@ -716,18 +1184,27 @@ where
                 let key = IntKey::new(*task_id);
                 let key = key.as_ref();
                 if let Some(meta) = meta {
+                    let (items, _) = split_serialized_items(&meta);
                     batch.put(
                         KeySpace::TaskMeta,
                         WriteBuffer::Borrowed(key),
-                        WriteBuffer::SmallVec(meta),
+                        WriteBuffer::Borrowed(items),
                     )?;
                 }
                 if let Some(data) = data {
+                    let (items, cells) = split_serialized_items(&data);
                     batch.put(
                         KeySpace::TaskData,
                         WriteBuffer::Borrowed(key),
-                        WriteBuffer::SmallVec(data),
+                        WriteBuffer::Borrowed(items),
                     )?;
+                    if !cells.is_empty() {
+                        batch.put(
+                            KeySpace::TaskCells,
+                            WriteBuffer::Borrowed(key),
+                            WriteBuffer::Borrowed(cells),
+                        )?;
+                    }
                 }
             } else {
                 // Store the new task data
@@ -743,13 +1220,58 @@ where
     })
 }
 
+/// Serializes the items of a task. The cell data is serialized separately, since it's stored in
+/// the evictable [`KeySpace::TaskCells`]. The result starts with the length of the task items as
+/// u32, followed by the task items and the serialized cell data, which is empty when the task has
+/// no cells. The task items are a flags byte (see [`TASK_ITEMS_HAS_CELLS`]) followed by the
+/// serialized items. Use [`split_serialized_items`] to split it.
 fn serialize(task: TaskId, data: &Vec<CachedDataItem>) -> Result<SmallVec<[u8; 16]>> {
-    Ok(match pot_serialize_small_vec(data) {
+    let (cells, items): (Vec<_>, Vec<_>) = data
+        .iter()
+        .partition(|item| matches!(item, CachedDataItem::CellData { .. }));
+    let flags = if cells.is_empty() {
+        0
+    } else {
+        TASK_ITEMS_HAS_CELLS
+    };
+    let mut output = SmallVec::from_slice(&[0, 0, 0, 0, flags]);
+    serialize_items(task, items, &mut output)?;
+    let items_len = (output.len() - 4) as u32;
+    output[..4].copy_from_slice(&items_len.to_le_bytes());
+    if !cells.is_empty() {
+        serialize_items(task, cells, &mut output)?;
+    }
+    Ok(output)
+}
+
+/// Splits the result of [`serialize`] into the task items and the serialized cell data.
+fn split_serialized_items(bytes: &[u8]) -> (&[u8], &[u8]) {
+    let (items_len, bytes) = bytes.split_at(4);
+    let items_len = u32::from_le_bytes(items_len.try_into().unwrap());
+    bytes.split_at(items_len as usize)
+}
+
+/// Splits a [`KeySpace::TaskMeta`] or [`KeySpace::TaskData`] value into the flags and the
+/// serialized items.
+fn split_task_items(bytes: &[u8]) -> Result<(u8, &[u8])> {
+    let Some((&flags, items)) = bytes.split_first() else {
+        bail!("Task items are empty");
+    };
+    Ok((flags, items))
+}
+
+fn serialize_items(
+    task: TaskId,
+    mut data: Vec<&CachedDataItem>,
+    output: &mut SmallVec<[u8; 16]>,
+) -> Result<()> {
+    let start = output.len();
+    match pot_serialize_into_small_vec(&data, output) {
         #[cfg(not(feature = "verify_serialization"))]
-        Ok(value) => value,
+        Ok(()) => {}
         _ => {
+            output.truncate(start);
             let mut error = Ok(());
-            let mut data = data.clone();
             data.retain(|item| {
                 let mut buf = Vec::<u8>::new();
                 let mut symbol_map = pot_ser_symbol_map();
@@ -783,10 +1305,138 @@ fn serialize(task: TaskId, data: &Vec<CachedDataItem>) -> Result<SmallVec<[u8; 1
             });
             error?;
 
-            pot_serialize_small_vec(&data)
+            pot_serialize_into_small_vec(&data, output)
                 .with_context(|| anyhow!("Unable to serialize data items for {task}: {data:#?}"))?
         }
-    })
+    }
+    Ok(())
+}
+
+/// Deserializes a task type with all `TaskId`s passed through `mapping` and serializes it again.
+fn map_task_ids_in_task_type(
+    bytes: &[u8],
+    mapping: &mut dyn FnMut(TaskId) -> Option<TaskId>,
+) -> Result<Vec<u8>> {
+    let task_type: CachedTaskType = deserialize_with_task_id_mapping(
+        &mut pot_de_symbol_list().deserializer_for_slice(bytes)?,
+        mapping,
+    )
+    .context("Unable to map task ids in task type")?;
+    let mut output = Vec::new();
+    serialize_task_type(&task_type, &mut output, None)?;
+    Ok(output)
+}
+
+/// Deserializes task items with all `TaskId`s passed through `mapping` and serializes them again.
+fn map_task_ids_in_items(
+    task: TaskId,
+    bytes: &[u8],
+    mapping: &mut dyn FnMut(TaskId) -> Option<TaskId>,
+) -> Result<SmallVec<[u8; 16]>> {
+    if bytes.is_empty() {
+        return Ok(SmallVec::new());
+    }
+    let mut items: Vec<CachedDataItem> = deserialize_with_task_id_mapping(
+        &mut pot_de_symbol_list().deserializer_for_slice(bytes)?,
+        &mut *mapping,
+    )
+    .with_context(|| anyhow!("Unable to map task ids in data items for {task}"))?;
+    for item in &mut items {
+        map_task_ids_in_item(item, mapping)
+            .with_context(|| anyhow!("Unable to map task ids in data items for {task}"))?;
+    }
+    let mut output = SmallVec::new();
+    serialize_items(task, items.iter().collect(), &mut output)?;
+    Ok(output)
+}
+
+/// Passes the `TaskId`s that are stored directly in a task item through `mapping`. The `TaskId`s in
+/// cell values are mapped when deserializing them.
+fn map_task_ids_in_item(
+    item: &mut CachedDataItem,
+    mapping: &mut dyn FnMut(TaskId) -> Option<TaskId>,
+) -> Option<()> {
+    let task = match item {
+        CachedDataItem::Output {
+            value: OutputValue::Cell(CellRef { task, .. }) | OutputValue::Output(task),
+        }
+        | CachedDataItem::Child { task, .. }
+        | CachedDataItem::OutputDependency { target: task, .. }
+        | CachedDataItem::CellDependency {
+            target: CellRef { task, .. },
+            ..
+        }
+        | CachedDataItem::CollectiblesDependency {
+            target: CollectiblesRef { task, .. },
+            ..
+        }
+        | CachedDataItem::OutputDependent { task, .. }
+        | CachedDataItem::CellDependent { task, .. }
+        | CachedDataItem::CollectiblesDependent { task, .. }
+        | CachedDataItem::Follower { task, .. }
+        | CachedDataItem::Upper { task, .. }
+        | CachedDataItem::AggregatedDirtyContainer { task, .. } => task,
+        CachedDataItem::Collectible { collectible, .. }
+        | CachedDataItem::AggregatedCollectible { collectible, .. } => &mut collectible.cell.task,
+        CachedDataItem::Output {
+            value: OutputValue::Error(_),
+        }
+        | CachedDataItem::Dirty { .. }
+        | CachedDataItem::CellData { .. }
+        | CachedDataItem::CellTypeMaxIndex { .. }
+        | CachedDataItem::AggregationNumber { .. }
+        | CachedDataItem::AggregatedDirtyContainerCount { .. }
+        | CachedDataItem::Stateful { .. }
+        | CachedDataItem::HasInvalidator { .. }
+        | CachedDataItem::Immutable { .. }
+        | CachedDataItem::Activeness { .. }
+        | CachedDataItem::InProgress { .. }
+        | CachedDataItem::InProgressCell { .. }
+        | CachedDataItem::OutdatedCollectible { .. }
+        | CachedDataItem::OutdatedOutputDependency { .. }
+        | CachedDataItem::OutdatedCellDependency { .. }
+        | CachedDataItem::OutdatedCollectiblesDependency { .. } => return Some(()),
+    };
+    *task = mapping(*task)?;
+    Some(())
+}
+
+/// Rebases the paths of an exported task type and maps its `TaskId`s.
+fn import_task_type(
+    bytes: &[u8],
+    rebase: Option<(&str, &str)>,
+    mapping: &mut dyn FnMut(TaskId) -> Option<TaskId>,
+) -> Result<Vec<u8>> {
+    let rebased = match rebase {
+        Some((from, to)) => rebase_paths(bytes, from, to, &POT_CONFIG)?,
+        None => None,
+    };
+    map_task_ids_in_task_type(rebased.as_deref().unwrap_or(bytes), mapping)
+}
+
+/// Rebases the paths of exported task items and maps their `TaskId`s.
+fn import_items(
+    task: TaskId,
+    bytes: &[u8],
+    rebase: Option<(&str, &str)>,
+    mapping: &mut dyn FnMut(TaskId) -> Option<TaskId>,
+) -> Result<SmallVec<[u8; 16]>> {
+    let rebased = match rebase {
+        Some((from, to)) => rebase_paths(bytes, from, to, &POT_CONFIG)?,
+        None => None,
+    };
+    map_task_ids_in_items(task, rebased.as_deref().unwrap_or(bytes), mapping)
+}
+
+/// Finds the representative of the component of `index` in a union-find structure.
+fn find_component(components: &mut [u32], mut index: u32) -> u32 {
+    while components[index as usize] != index {
+        let parent = components[index as usize];
+        // Path halving
+        components[index as usize] = components[parent as usize];
+        index = parent;
+    }
+    index
 }
 
 fn deserialize_with_good_error<'de, T: Deserialize<'de>>(data: &'de [u8]) -> Result<T> {
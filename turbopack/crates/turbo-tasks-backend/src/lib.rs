@@ -15,12 +15,23 @@ use std::path::Path;
 
 use anyhow::Result;
 
-use crate::database::{noop_kv::NoopKvDb, turbo::TurboKeyValueDatabase};
+use crate::database::{
+    fault_injection::FaultInjectionLayer, noop_kv::NoopKvDb, turbo::TurboKeyValueDatabase,
+};
 pub use crate::{
-    backend::{BackendOptions, StorageMode, TurboTasksBackend},
+    backend::{
+        BackendOptions, DeterminismViolation, DivergentCell, ReexecutionSummary, StorageMode,
+        TaskDirtyChain, TaskDirtyChainLink, TurboTasksBackend,
+    },
     backing_storage::BackingStorage,
     database::{
-        db_invalidation, db_invalidation::StartupCacheState, db_versioning::GitVersionInfo,
+        db_invalidation,
+        db_invalidation::StartupCacheState,
+        db_versioning::GitVersionInfo,
+        fault_injection::FaultInjectionConfig,
+        invalidation_log::{
+            CacheVersion, InvalidationLogEntry, InvalidationReason, read_invalidation_log,
+        },
     },
     kv_backing_storage::KeyValueDatabaseBackingStorage,
 };
@@ -93,6 +104,31 @@ pub fn turbo_backing_storage(
     )
 }
 
+pub type FaultInjectingBackingStorage =
+    KeyValueDatabaseBackingStorage<FaultInjectionLayer<TurboKeyValueDatabase>>;
+
+/// Creates a [`turbo_backing_storage`] that injects faults into reads and commits, as configured
+/// by [`FaultInjectionConfig`]. This is only intended for crash-consistency tests and fuzzing.
+pub fn fault_injecting_backing_storage(
+    base_path: &Path,
+    version_info: &GitVersionInfo,
+    is_ci: bool,
+    is_short_session: bool,
+    config: FaultInjectionConfig,
+) -> Result<(FaultInjectingBackingStorage, StartupCacheState)> {
+    KeyValueDatabaseBackingStorage::open_versioned_on_disk(
+        base_path.to_owned(),
+        version_info,
+        is_ci,
+        |path| {
+            Ok(FaultInjectionLayer::new(
+                TurboKeyValueDatabase::new(path, is_ci, is_short_session)?,
+                config,
+            ))
+        },
+    )
+}
+
 pub type NoopBackingStorage = KeyValueDatabaseBackingStorage<NoopKvDb>;
 
 /// Creates an no-op in-memory `BackingStorage` to be passed to [`TurboTasksBackend::new`].
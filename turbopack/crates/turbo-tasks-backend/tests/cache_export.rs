@@ -0,0 +1,71 @@
+#![feature(arbitrary_self_types)]
+#![feature(arbitrary_self_types_pointers)]
+#![allow(clippy::needless_return)] // tokio macro-generated code doesn't respect this
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::Result;
+use turbo_rcstr::RcStr;
+use turbo_tasks::Vc;
+
+use crate::test_utils::{TestTurboTasks, VERSION_INFO, create_turbo_tasks, open_storage};
+
+mod test_utils;
+
+static EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+async fn run_compute(tt: &TestTurboTasks, root: &str, value: u32) -> Result<u32> {
+    let root = RcStr::from(root);
+    turbo_tasks::run_once(tt.clone(), async move { Ok(*compute(root, value).await?) }).await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn export_and_import() -> Result<()> {
+    let export_dir = tempfile::tempdir()?;
+    let db_a = tempfile::tempdir()?;
+    let db_b = tempfile::tempdir()?;
+
+    let tt = create_turbo_tasks(open_storage(db_a.path()).0);
+    assert_eq!(run_compute(&tt, "/a/project", 3).await?, 3 + 9);
+    tt.stop_and_wait().await;
+    tt.backend()
+        .backing_storage()
+        .export_cache(export_dir.path(), &VERSION_INFO, "/a/project")?;
+    drop(tt);
+
+    // The importing database already contains other tasks
+    let tt = create_turbo_tasks(open_storage(db_b.path()).0);
+    assert_eq!(run_compute(&tt, "/b/project", 5).await?, 5 + 9);
+    tt.stop_and_wait().await;
+    drop(tt);
+
+    let storage = open_storage(db_b.path()).0;
+    assert!(storage.import_cache(export_dir.path(), &VERSION_INFO, "/b/project")?);
+    let tt = create_turbo_tasks(storage);
+    EXECUTIONS.store(0, Ordering::SeqCst);
+    // The paths in the task arguments have been rebased to the new root
+    assert_eq!(run_compute(&tt, "/b/project", 3).await?, 3 + 9);
+    assert_eq!(run_compute(&tt, "/b/project", 5).await?, 5 + 9);
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 0);
+    tt.stop_and_wait().await;
+    drop(tt);
+
+    // Tasks that already exist are not imported again
+    let storage = open_storage(db_b.path()).0;
+    assert!(!storage.import_cache(export_dir.path(), &VERSION_INFO, "/b/project")?);
+    Ok(())
+}
+
+#[turbo_tasks::function]
+async fn compute(root: RcStr, value: u32) -> Result<Vc<u32>> {
+    EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    let path = RcStr::from(format!("{root}/src/{value}.js"));
+    Ok(Vc::cell(value + *path_length(root, path).await?))
+}
+
+/// The length of `path` relative to `root`
+#[turbo_tasks::function]
+fn path_length(root: RcStr, path: RcStr) -> Vc<u32> {
+    EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    Vc::cell((path.len() - root.len()) as u32)
+}
@@ -0,0 +1,134 @@
+#![feature(arbitrary_self_types)]
+#![feature(arbitrary_self_types_pointers)]
+#![allow(clippy::needless_return)] // tokio macro-generated code doesn't respect this
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::{Context, Result, bail};
+use futures::future::try_join_all;
+use turbo_persistence::{SerialScheduler, TurboPersistence};
+use turbo_tasks::Vc;
+use turbo_tasks_backend::StartupCacheState;
+
+use crate::test_utils::{TestTurboTasks, open_storage};
+
+mod test_utils;
+
+/// Enough tasks that their data is spread over multiple blocks.
+const TASKS: u32 = 20_000;
+
+/// The key family of the task data in the database.
+const TASK_DATA_FAMILY: u32 = 2;
+
+static EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+fn create_turbo_tasks(path: &Path) -> (TestTurboTasks, StartupCacheState) {
+    let (storage, startup_cache_state) = open_storage(path);
+    (test_utils::create_turbo_tasks(storage), startup_cache_state)
+}
+
+async fn read_all(tt: &TestTurboTasks) -> Result<()> {
+    tt.run_once(async move {
+        let values = try_join_all(
+            (0..TASKS).map(|i| async move { value_operation(i).read_strongly_consistent().await }),
+        )
+        .await?;
+        for (i, value) in (0..TASKS).zip(values) {
+            assert_eq!(*value, i * 2);
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// Returns the directory of the database in the cache directory.
+fn find_database(path: &Path) -> Result<PathBuf> {
+    if path.join("CURRENT").exists() {
+        return Ok(path.to_path_buf());
+    }
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        if path.is_dir()
+            && let Ok(database) = find_database(&path)
+        {
+            return Ok(database);
+        }
+    }
+    bail!("No database found in {}", path.display())
+}
+
+/// Corrupts the first value block of the SST file with the most blocks in the task data family.
+fn corrupt_task_data_block(cache_dir: &Path) -> Result<()> {
+    let path = find_database(cache_dir)?;
+    let db = TurboPersistence::<SerialScheduler>::open_read_only(path.clone())?;
+    let entry = db
+        .meta_info()?
+        .into_iter()
+        .filter(|meta| meta.family == TASK_DATA_FAMILY)
+        .flat_map(|meta| meta.entries)
+        .max_by_key(|entry| entry.block_count)
+        .context("No task data has been persisted")?;
+    drop(db);
+
+    // The SST file starts with the 1 byte codec tag and the compression dictionaries. The value
+    // blocks come first, each with 4 bytes uncompressed length and 4 bytes checksum in front of
+    // the compressed data.
+    let blocks_start = 1
+        + usize::from(entry.key_compression_dictionary_size)
+        + usize::from(entry.value_compression_dictionary_size);
+    let sst_path = path.join(format!("{:08}.sst", entry.sequence_number));
+    let mut content = fs::read(&sst_path)?;
+    content[blocks_start + 8] ^= 0xFF;
+    fs::write(&sst_path, content)?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn corrupted_task_data_only_recomputes_affected_tasks() -> Result<()> {
+    let cache_dir = tempfile::tempdir()?;
+
+    let (tt, _) = create_turbo_tasks(cache_dir.path());
+    read_all(&tt).await?;
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), TASKS as usize);
+    tt.stop_and_wait().await;
+    drop(tt);
+
+    corrupt_task_data_block(cache_dir.path())?;
+
+    // Only the tasks with data in the corrupted block are recomputed, all other tasks still come
+    // from the cache
+    let (tt, startup_cache_state) = create_turbo_tasks(cache_dir.path());
+    assert!(matches!(startup_cache_state, StartupCacheState::Cached));
+    EXECUTIONS.store(0, Ordering::SeqCst);
+    read_all(&tt).await?;
+    let executions = EXECUTIONS.load(Ordering::SeqCst);
+    assert!(
+        executions > 0,
+        "no task data has been read from the corrupted block"
+    );
+    assert!(
+        executions < TASKS as usize,
+        "all tasks have been recomputed"
+    );
+    tt.stop_and_wait().await;
+    drop(tt);
+
+    // The cache hasn't been invalidated and the recomputed tasks have been persisted again
+    let (tt, startup_cache_state) = create_turbo_tasks(cache_dir.path());
+    assert!(matches!(startup_cache_state, StartupCacheState::Cached));
+    EXECUTIONS.store(0, Ordering::SeqCst);
+    read_all(&tt).await?;
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 0);
+    tt.stop_and_wait().await;
+    Ok(())
+}
+
+#[turbo_tasks::function(operation)]
+fn value_operation(i: u32) -> Vc<u32> {
+    EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    Vc::cell(i * 2)
+}
@@ -0,0 +1,115 @@
+#![feature(arbitrary_self_types)]
+#![feature(arbitrary_self_types_pointers)]
+#![allow(clippy::needless_return)] // tokio macro-generated code doesn't respect this
+
+use std::{
+    env,
+    path::Path,
+    process::Command,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::Result;
+use turbo_tasks::{ResolvedVc, State, Vc};
+use turbo_tasks_backend::{FaultInjectingBackingStorage, FaultInjectionConfig};
+
+use crate::test_utils::open_fault_injecting_storage;
+
+mod test_utils;
+
+/// When set, the test runs the crashing sessions in the cache directory given by the variable.
+const CHILD_ENV: &str = "TURBO_TASKS_CRASH_CONSISTENCY_CHILD";
+
+static EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+type TestTurboTasks = test_utils::TestTurboTasks<FaultInjectingBackingStorage>;
+
+fn create_turbo_tasks(path: &Path, config: FaultInjectionConfig) -> TestTurboTasks {
+    test_utils::create_turbo_tasks(open_fault_injecting_storage(path, config))
+}
+
+/// Sets the input, if given, and returns the input and the output computed from it.
+async fn run(tt: &TestTurboTasks, value: Option<u32>) -> Result<(u32, u32)> {
+    tt.run_once(async move {
+        let input = input_operation().resolve_strongly_consistent().await?;
+        if let Some(value) = value {
+            input.await?.value.set(value);
+        }
+        let input_value = *input.await?.value.get_untracked();
+        let output = *compute_operation(input).read_strongly_consistent().await?;
+        Ok((input_value, output))
+    })
+    .await
+}
+
+/// Persists a first session, and aborts the process at the first commit of a second session that
+/// changes the input.
+async fn crashing_sessions(path: &Path) -> Result<()> {
+    let tt = create_turbo_tasks(path, FaultInjectionConfig::default());
+    run(&tt, Some(1)).await?;
+    tt.stop_and_wait().await;
+    drop(tt);
+
+    let tt = create_turbo_tasks(
+        path,
+        FaultInjectionConfig {
+            crash_rate: 1.0,
+            ..Default::default()
+        },
+    );
+    run(&tt, Some(2)).await?;
+    tt.stop_and_wait().await;
+    unreachable!("the commit should have aborted the process");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn crash_before_commit_then_restart() -> Result<()> {
+    if let Some(path) = env::var_os(CHILD_ENV) {
+        return crashing_sessions(Path::new(&path)).await;
+    }
+
+    let cache_dir = tempfile::tempdir()?;
+    let status = Command::new(env::current_exe()?)
+        .args(["crash_before_commit_then_restart", "--exact", "--nocapture"])
+        .env(CHILD_ENV, cache_dir.path())
+        .status()?;
+    assert!(!status.success(), "the child process should have crashed");
+
+    // The restarted session sees the state of the last successful commit, and everything computed
+    // from it is consistent with it
+    let tt = create_turbo_tasks(cache_dir.path(), FaultInjectionConfig::default());
+    EXECUTIONS.store(0, Ordering::SeqCst);
+    let (input, output) = run(&tt, None).await?;
+    assert_eq!(input, 1);
+    assert_eq!(output, input * 10);
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 0);
+
+    let (input, output) = run(&tt, Some(3)).await?;
+    assert_eq!((input, output), (3, 30));
+    tt.stop_and_wait().await;
+    Ok(())
+}
+
+#[turbo_tasks::value]
+struct Input {
+    value: State<u32>,
+}
+
+#[turbo_tasks::function(operation)]
+fn input_operation() -> Vc<Input> {
+    Input {
+        value: State::new(0),
+    }
+    .cell()
+}
+
+#[turbo_tasks::function(operation)]
+fn compute_operation(input: ResolvedVc<Input>) -> Vc<u32> {
+    compute(*input)
+}
+
+#[turbo_tasks::function]
+async fn compute(input: Vc<Input>) -> Result<Vc<u32>> {
+    EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    Ok(Vc::cell(*input.await?.value.get() * 10))
+}
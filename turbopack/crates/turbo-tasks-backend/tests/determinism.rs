@@ -0,0 +1,72 @@
+#![feature(arbitrary_self_types)]
+#![feature(arbitrary_self_types_pointers)]
+#![allow(clippy::needless_return)] // tokio macro-generated code doesn't respect this
+
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+use anyhow::Result;
+use turbo_tasks::{TurboTasks, Vc};
+use turbo_tasks_backend::{BackendOptions, TurboTasksBackend, noop_backing_storage};
+
+static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn non_deterministic_tasks_are_reported() -> Result<()> {
+    let tt = TurboTasks::new(TurboTasksBackend::new(
+        BackendOptions {
+            verify_determinism: Some(1.0),
+            ..Default::default()
+        },
+        noop_backing_storage(),
+    ));
+    let read = || tt.run_once(async { Ok((*plus_one().await?, *constant().await?)) });
+    assert_eq!(read().await?, (2, 42));
+
+    let mut violations = Vec::new();
+    for _ in 0..100 {
+        violations.extend(tt.backend().take_determinism_violations());
+        if !violations.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(violations.len(), 1);
+    let violation = &violations[0];
+    assert!(violation.task.contains("next_count"));
+    assert!(!violation.output_diverged);
+    // The recomputation creates an additional cell
+    assert_eq!(violation.divergent_cells.len(), 2);
+    assert_eq!(violation.divergent_cells[0].first.as_deref(), Some("1"));
+    assert_eq!(violation.divergent_cells[0].second.as_deref(), Some("2"));
+    assert_eq!(violation.divergent_cells[1].first, None);
+
+    // The recomputation kept the cells of the first execution and didn't invalidate dependents
+    assert_eq!(read().await?, (2, 42));
+
+    tt.stop_and_wait().await;
+    assert!(tt.backend().take_determinism_violations().is_empty());
+    Ok(())
+}
+
+#[turbo_tasks::function]
+fn next_count() -> Vc<u32> {
+    let count = COUNTER.fetch_add(1, Ordering::SeqCst) + 1;
+    let result = Vc::cell(count);
+    for _ in 1..count {
+        let _ = Vc::<u32>::cell(0);
+    }
+    result
+}
+
+#[turbo_tasks::function]
+async fn plus_one() -> Result<Vc<u32>> {
+    Ok(Vc::cell(*next_count().await? + 1))
+}
+
+#[turbo_tasks::function]
+fn constant() -> Vc<u32> {
+    Vc::cell(42)
+}
@@ -0,0 +1,91 @@
+#![feature(arbitrary_self_types)]
+#![feature(arbitrary_self_types_pointers)]
+#![allow(clippy::needless_return)] // tokio macro-generated code doesn't respect this
+
+use std::{
+    fmt::{self, Display},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU32, Ordering},
+    },
+};
+
+use anyhow::Result;
+use turbo_tasks::{InvalidationReason, Invalidator, TurboTasks, Vc, get_invalidator};
+use turbo_tasks_backend::{
+    BackendOptions, NoopBackingStorage, TurboTasksBackend, noop_backing_storage,
+};
+
+static INPUT: AtomicU32 = AtomicU32::new(1);
+static INVALIDATOR: Mutex<Option<Invalidator>> = Mutex::new(None);
+
+type TestTurboTasks = Arc<TurboTasks<TurboTasksBackend<NoopBackingStorage>>>;
+
+#[derive(PartialEq, Eq, Hash)]
+struct InputChanged;
+
+impl Display for InputChanged {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "input.txt changed")
+    }
+}
+
+impl InvalidationReason for InputChanged {}
+
+async fn read_output(tt: &TestTurboTasks) -> Result<u32> {
+    tt.run_once(async move { Ok(*double_operation().read_strongly_consistent().await?) })
+        .await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn reexecutions_are_traced_to_invalidation() -> Result<()> {
+    let tt = TurboTasks::new(TurboTasksBackend::new(
+        BackendOptions {
+            track_dirty_causes: true,
+            ..Default::default()
+        },
+        noop_backing_storage(),
+    ));
+    assert_eq!(read_output(&tt).await?, 2);
+    // Initial executions are not re-executions
+    assert!(tt.backend().take_reexecution_summary().is_empty());
+
+    INPUT.store(2, Ordering::SeqCst);
+    let invalidator = INVALIDATOR.lock().unwrap().take().unwrap();
+    invalidator.invalidate_with_reason(InputChanged);
+    assert_eq!(read_output(&tt).await?, 4);
+
+    let summary = tt.backend().take_reexecution_summary();
+    assert_eq!(summary.len(), 1);
+    let summary = &summary[0];
+    assert_eq!(summary.longest_chain.root_cause, "input.txt changed");
+    assert!(summary.task_count >= 2);
+    let links = &summary.longest_chain.links;
+    assert!(links.len() >= 2);
+    assert!(links.last().unwrap().task.contains("read_input"));
+    assert!(links.iter().any(|link| link.task.contains("double")));
+    assert!(summary.to_string().starts_with("input.txt changed re-ran"));
+
+    // Reading again doesn't re-execute anything
+    assert_eq!(read_output(&tt).await?, 4);
+    assert!(tt.backend().take_reexecution_summary().is_empty());
+
+    tt.stop_and_wait().await;
+    Ok(())
+}
+
+#[turbo_tasks::function]
+fn read_input() -> Vc<u32> {
+    *INVALIDATOR.lock().unwrap() = get_invalidator();
+    Vc::cell(INPUT.load(Ordering::SeqCst))
+}
+
+#[turbo_tasks::function]
+async fn double() -> Result<Vc<u32>> {
+    Ok(Vc::cell(*read_input().await? * 2))
+}
+
+#[turbo_tasks::function(operation)]
+fn double_operation() -> Vc<u32> {
+    double()
+}
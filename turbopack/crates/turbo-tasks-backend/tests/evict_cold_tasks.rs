@@ -0,0 +1,132 @@
+#![feature(arbitrary_self_types)]
+#![feature(arbitrary_self_types_pointers)]
+#![allow(clippy::needless_return)] // tokio macro-generated code doesn't respect this
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+use anyhow::Result;
+use turbo_tasks::{ResolvedVc, State, Vc};
+
+use crate::test_utils::{TestTurboTasks, create_turbo_tasks, open_storage};
+
+mod test_utils;
+
+const INPUTS: u32 = 8;
+
+static EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+async fn set_input(tt: &TestTurboTasks, index: u32, value: u32) -> Result<()> {
+    tt.run_once(async move {
+        let inputs = inputs_operation().resolve_strongly_consistent().await?;
+        inputs.await?.values[index as usize].set(value);
+        Ok(())
+    })
+    .await
+}
+
+async fn read_sum(tt: &TestTurboTasks) -> Result<u32> {
+    tt.run_once(async move {
+        let inputs = inputs_operation().resolve_strongly_consistent().await?;
+        Ok(*sum_operation(inputs).read_strongly_consistent().await?)
+    })
+    .await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn evicted_tasks_are_restored() -> Result<()> {
+    let cache_dir = tempfile::tempdir()?;
+    let tt = create_turbo_tasks(open_storage(cache_dir.path()).0);
+    for index in 0..INPUTS {
+        set_input(&tt, index, index).await?;
+    }
+    assert_eq!(read_sum(&tt).await?, (0..INPUTS).sum::<u32>());
+
+    let backend_tt = tt.clone();
+    let evicted = tokio::task::spawn_blocking(move || {
+        assert!(backend_tt.backend().snapshot_now());
+        // Tasks accessed since the last eviction get a second chance
+        backend_tt.backend().evict_cold_tasks_now();
+        backend_tt.backend().evict_cold_tasks_now()
+    })
+    .await?;
+    assert!(evicted > 0);
+
+    // The evicted tasks are restored instead of recomputed
+    EXECUTIONS.store(0, Ordering::SeqCst);
+    assert_eq!(read_sum(&tt).await?, (0..INPUTS).sum::<u32>());
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 0);
+
+    // Dependencies of restored tasks are still tracked
+    set_input(&tt, 3, 100).await?;
+    assert_eq!(read_sum(&tt).await?, (0..INPUTS).sum::<u32>() - 3 + 100);
+    assert!(EXECUTIONS.load(Ordering::SeqCst) > 0);
+
+    // Evicting concurrently with executions and restores
+    let stop = Arc::new(AtomicBool::new(false));
+    let evict_thread = thread::spawn({
+        let tt = tt.clone();
+        let stop = stop.clone();
+        move || {
+            while !stop.load(Ordering::Relaxed) {
+                tt.backend().snapshot_now();
+                tt.backend().evict_cold_tasks_now();
+            }
+        }
+    });
+    let mut inputs = (0..INPUTS).collect::<Vec<_>>();
+    inputs[3] = 100;
+    for step in 0..200 {
+        let index = step % INPUTS;
+        inputs[index as usize] = step;
+        set_input(&tt, index, step).await?;
+        assert_eq!(
+            read_sum(&tt).await?,
+            inputs.iter().sum::<u32>(),
+            "step {step}"
+        );
+    }
+    stop.store(true, Ordering::Relaxed);
+    evict_thread.join().unwrap();
+    tt.stop_and_wait().await;
+    Ok(())
+}
+
+#[turbo_tasks::value]
+struct Inputs {
+    values: Vec<State<u32>>,
+}
+
+#[turbo_tasks::function(operation)]
+fn inputs_operation() -> Vc<Inputs> {
+    Inputs {
+        values: (0..INPUTS).map(|_| State::new(0)).collect(),
+    }
+    .cell()
+}
+
+#[turbo_tasks::function(operation)]
+fn sum_operation(inputs: ResolvedVc<Inputs>) -> Vc<u32> {
+    sum(*inputs)
+}
+
+#[turbo_tasks::function]
+async fn read_input(inputs: Vc<Inputs>, index: u32) -> Result<Vc<u32>> {
+    EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    Ok(Vc::cell(*inputs.await?.values[index as usize].get()))
+}
+
+#[turbo_tasks::function]
+async fn sum(inputs: Vc<Inputs>) -> Result<Vc<u32>> {
+    EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    let mut sum = 0;
+    for index in 0..INPUTS {
+        sum += *read_input(inputs, index).await?;
+    }
+    Ok(Vc::cell(sum))
+}
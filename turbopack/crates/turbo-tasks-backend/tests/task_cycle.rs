@@ -0,0 +1,39 @@
+#![feature(arbitrary_self_types)]
+#![feature(arbitrary_self_types_pointers)]
+#![allow(clippy::needless_return)] // tokio macro-generated code doesn't respect this
+
+use anyhow::Result;
+use turbo_tasks::{TurboTasks, Vc, backend::TaskCycleError};
+use turbo_tasks_backend::{BackendOptions, TurboTasksBackend, noop_backing_storage};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_cycle_fails_with_error() {
+    let tt = TurboTasks::new(TurboTasksBackend::new(
+        BackendOptions {
+            detect_task_cycles: true,
+            ..Default::default()
+        },
+        noop_backing_storage(),
+    ));
+    let result = tt.run_once(async { Ok(*ping(3).await?) }).await;
+
+    let error = result.unwrap_err();
+    let cycle = TaskCycleError::find(&error).expect("Expected a TaskCycleError");
+    assert_eq!(cycle.cycle.len(), 2, "{cycle}");
+    assert!(cycle.cycle.iter().any(|task| task.contains("ping")));
+    assert!(cycle.cycle.iter().any(|task| task.contains("pong")));
+    // The arguments are included
+    assert!(cycle.cycle.iter().all(|task| task.contains('3')));
+
+    tt.stop_and_wait().await;
+}
+
+#[turbo_tasks::function]
+async fn ping(value: u32) -> Result<Vc<u32>> {
+    Ok(Vc::cell(*pong(value).await? + 1))
+}
+
+#[turbo_tasks::function]
+async fn pong(value: u32) -> Result<Vc<u32>> {
+    Ok(Vc::cell(*ping(value).await?))
+}
@@ -0,0 +1,92 @@
+#![feature(arbitrary_self_types)]
+#![feature(arbitrary_self_types_pointers)]
+#![allow(clippy::needless_return)] // tokio macro-generated code doesn't respect this
+
+use std::sync::{
+    Mutex,
+    atomic::{AtomicU32, Ordering},
+};
+
+use anyhow::Result;
+use turbo_tasks::{
+    Invalidator, ReadConsistency, TaskPriority, TurboTasks, Vc, get_invalidator,
+    test_helpers::current_task_priority_for_testing,
+};
+use turbo_tasks_backend::{BackendOptions, TurboTasksBackend, noop_backing_storage};
+
+static INPUT: AtomicU32 = AtomicU32::new(1);
+static INVALIDATOR: Mutex<Option<Invalidator>> = Mutex::new(None);
+/// The priorities `read_input` and `double` have been executed with.
+static PRIORITIES: Mutex<Vec<(&str, TaskPriority)>> = Mutex::new(Vec::new());
+
+fn take_priorities() -> Vec<(&'static str, TaskPriority)> {
+    std::mem::take(&mut *PRIORITIES.lock().unwrap())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn priority_is_inherited_and_kept_for_invalidations() -> Result<()> {
+    let tt = TurboTasks::new(TurboTasksBackend::new(
+        BackendOptions::default(),
+        noop_backing_storage(),
+    ));
+    let root = tt.spawn_root_task_with_priority(TaskPriority::Interactive, || async {
+        Ok(Vc::<u32>::cell(*double().await?))
+    });
+    tt.wait_task_completion(root, ReadConsistency::Strong)
+        .await?;
+    assert_eq!(
+        take_priorities(),
+        [
+            ("double", TaskPriority::Interactive),
+            ("read_input", TaskPriority::Interactive),
+        ]
+    );
+
+    // Invalidations from outside of a task, like a file watcher, keep the priority
+    INPUT.store(2, Ordering::SeqCst);
+    let invalidator = INVALIDATOR.lock().unwrap().take().unwrap();
+    invalidator.invalidate();
+    tt.wait_task_completion(root, ReadConsistency::Strong)
+        .await?;
+    assert_eq!(
+        take_priorities(),
+        [
+            ("read_input", TaskPriority::Interactive),
+            ("double", TaskPriority::Interactive),
+        ]
+    );
+
+    // Reads outside of the root use the default priority
+    tt.run_once(async { Ok(*triple().await?) }).await?;
+    assert_eq!(take_priorities(), [("triple", TaskPriority::Normal)]);
+
+    tt.dispose_root_task(root);
+    tt.stop_and_wait().await;
+    Ok(())
+}
+
+fn record_priority(name: &'static str) {
+    PRIORITIES
+        .lock()
+        .unwrap()
+        .push((name, current_task_priority_for_testing()));
+}
+
+#[turbo_tasks::function]
+fn read_input() -> Vc<u32> {
+    record_priority("read_input");
+    *INVALIDATOR.lock().unwrap() = get_invalidator();
+    Vc::cell(INPUT.load(Ordering::SeqCst))
+}
+
+#[turbo_tasks::function]
+async fn double() -> Result<Vc<u32>> {
+    record_priority("double");
+    Ok(Vc::cell(*read_input().await? * 2))
+}
+
+#[turbo_tasks::function]
+async fn triple() -> Result<Vc<u32>> {
+    record_priority("triple");
+    Ok(Vc::cell(*read_input().await? * 3))
+}
@@ -0,0 +1,44 @@
+//! Setup of a [`TurboTasks`] instance with a persistent cache, shared by the integration tests.
+#![allow(dead_code)] // not every test uses every helper
+
+use std::{path::Path, sync::Arc};
+
+use turbo_tasks::TurboTasks;
+use turbo_tasks_backend::{
+    BackendOptions, BackingStorage, DefaultBackingStorage, FaultInjectingBackingStorage,
+    FaultInjectionConfig, GitVersionInfo, StartupCacheState, TurboTasksBackend,
+    default_backing_storage, fault_injecting_backing_storage,
+};
+
+pub const VERSION_INFO: GitVersionInfo = GitVersionInfo {
+    describe: "test-unversioned",
+    dirty: false,
+};
+
+pub type TestTurboTasks<B = DefaultBackingStorage> = Arc<TurboTasks<TurboTasksBackend<B>>>;
+
+pub fn create_turbo_tasks<B: BackingStorage>(storage: B) -> TestTurboTasks<B> {
+    TurboTasks::new(TurboTasksBackend::new(
+        BackendOptions {
+            num_workers: Some(2),
+            small_preallocation: true,
+            ..Default::default()
+        },
+        storage,
+    ))
+}
+
+/// Opens the persistent cache in `path` as a short session.
+pub fn open_storage(path: &Path) -> (DefaultBackingStorage, StartupCacheState) {
+    default_backing_storage(path, &VERSION_INFO, false, true).unwrap()
+}
+
+/// Like [`open_storage`], but injects the faults configured by `config`.
+pub fn open_fault_injecting_storage(
+    path: &Path,
+    config: FaultInjectionConfig,
+) -> FaultInjectingBackingStorage {
+    fault_injecting_backing_storage(path, &VERSION_INFO, false, true, config)
+        .unwrap()
+        .0
+}
@@ -0,0 +1,536 @@
+use std::{
+    collections::BTreeMap,
+    io::{Cursor, Read},
+};
+
+use anyhow::{Context, Result, bail};
+use flate2::read::GzDecoder;
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use turbo_rcstr::RcStr;
+use turbo_tasks::{NonLocalValue, ResolvedVc, ValueToString, Vc, trace::TraceRawVcs};
+use turbo_unix_path::{get_parent_path, join_path, normalize_path};
+
+use crate::{
+    File, FileContent, FileMeta, FileSystem, FileSystemPath, LinkContent, LinkType, Permissions,
+    RawDirectoryContent, RawDirectoryEntry,
+    link_resolution::{LinkTarget, resolve_links},
+};
+
+/// A read-only [`FileSystem`] with the content of a zip, tar or gzipped tar archive, e.g. a
+/// package from Yarn's zip cache or a vendored tarball, so it can be resolved into without
+/// extracting it.
+///
+/// The format is detected from the content of the archive. The archive is read with
+/// [`FileSystemPath::read`], so changing it invalidates all reads. A missing archive behaves like
+/// an empty filesystem without a root directory.
+#[turbo_tasks::value]
+pub struct ArchiveFileSystem {
+    name: RcStr,
+    archive: FileSystemPath,
+}
+
+#[turbo_tasks::value_impl]
+impl ArchiveFileSystem {
+    #[turbo_tasks::function]
+    pub fn new(name: RcStr, archive: FileSystemPath) -> Vc<Self> {
+        ArchiveFileSystem { name, archive }.cell()
+    }
+
+    /// The entries of the archive, which are shared by all reads. Every file is stored in a cell
+    /// of its own, so changing the archive only invalidates the reads of the changed files.
+    #[turbo_tasks::function]
+    async fn entries(&self) -> Result<Vc<ArchiveEntries>> {
+        let content = self.archive.read().await?;
+        let FileContent::Content(file) = &*content else {
+            return Ok(ArchiveEntries::default().cell());
+        };
+        let parsed = ParsedArchive::parse(&file.content().to_bytes()).with_context(|| {
+            format!("failed to read the archive of the {} filesystem", self.name)
+        })?;
+        let entries = parsed
+            .entries
+            .into_iter()
+            .map(|(path, entry)| {
+                let entry = match entry {
+                    ArchiveEntry::File(file) => {
+                        ArchiveEntry::File(FileContent::Content(file).resolved_cell())
+                    }
+                    ArchiveEntry::Link(target) => ArchiveEntry::Link(target),
+                    ArchiveEntry::Directory(children) => ArchiveEntry::Directory(children),
+                };
+                (path, entry)
+            })
+            .collect();
+        Ok(ArchiveEntries { entries }.cell())
+    }
+}
+
+impl ArchiveFileSystem {
+    /// The path of the archive that the entries are read from.
+    pub fn archive(&self) -> &FileSystemPath {
+        &self.archive
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl FileSystem for ArchiveFileSystem {
+    #[turbo_tasks::function]
+    async fn read(self: Vc<Self>, fs_path: FileSystemPath) -> Result<Vc<FileContent>> {
+        let entries = self.entries().await?;
+        Ok(match entries.get(&fs_path.path, true) {
+            Some(ArchiveEntry::File(content)) => **content,
+            _ => FileContent::NotFound.cell(),
+        })
+    }
+
+    #[turbo_tasks::function]
+    async fn read_link(self: Vc<Self>, fs_path: FileSystemPath) -> Result<Vc<LinkContent>> {
+        let entries = self.entries().await?;
+        Ok(entries.read_link(&fs_path.path).cell())
+    }
+
+    #[turbo_tasks::function]
+    async fn raw_read_dir(
+        self: Vc<Self>,
+        fs_path: FileSystemPath,
+    ) -> Result<Vc<RawDirectoryContent>> {
+        let entries = self.entries().await?;
+        Ok(match entries.get(&fs_path.path, true) {
+            Some(ArchiveEntry::Directory(children)) => {
+                RawDirectoryContent::new(children.clone().into_iter().collect())
+            }
+            _ => RawDirectoryContent::not_found(),
+        })
+    }
+
+    #[turbo_tasks::function]
+    fn write(&self, _fs_path: FileSystemPath, _content: Vc<FileContent>) -> Result<Vc<()>> {
+        bail!(
+            "Writing is not possible to the archive filesystem {}",
+            self.name
+        )
+    }
+
+    #[turbo_tasks::function]
+    fn write_link(&self, _fs_path: FileSystemPath, _target: Vc<LinkContent>) -> Result<Vc<()>> {
+        bail!(
+            "Writing is not possible to the archive filesystem {}",
+            self.name
+        )
+    }
+
+    #[turbo_tasks::function]
+    async fn metadata(self: Vc<Self>, fs_path: FileSystemPath) -> Result<Vc<FileMeta>> {
+        let entries = self.entries().await?;
+        match entries.get(&fs_path.path, true) {
+            Some(ArchiveEntry::File(content)) => match &*(*content).await? {
+                FileContent::Content(file) => Ok(file.meta.clone().cell()),
+                FileContent::NotFound => bail!("archive entries always have content"),
+            },
+            Some(ArchiveEntry::Directory(_)) => Ok(FileMeta::default().cell()),
+            _ => bail!(
+                "reading metadata for {}: not found",
+                fs_path.value_to_string().await?
+            ),
+        }
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ValueToString for ArchiveFileSystem {
+    #[turbo_tasks::function]
+    fn to_string(&self) -> Vc<RcStr> {
+        Vc::cell(self.name.clone())
+    }
+}
+
+/// An entry of an archive. The content of files is a [`File`] while the archive is parsed and a
+/// cell afterwards.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TraceRawVcs, NonLocalValue)]
+enum ArchiveEntry<F = ResolvedVc<FileContent>> {
+    File(F),
+    /// The target of a symlink, as stored in the archive.
+    Link(RcStr),
+    /// The names and kinds of the entries in the directory.
+    Directory(BTreeMap<RcStr, RawDirectoryEntry>),
+}
+
+impl<F> ArchiveEntry<F> {
+    fn kind(&self) -> RawDirectoryEntry {
+        match self {
+            ArchiveEntry::File(_) => RawDirectoryEntry::File,
+            ArchiveEntry::Link(_) => RawDirectoryEntry::Symlink,
+            ArchiveEntry::Directory(_) => RawDirectoryEntry::Directory,
+        }
+    }
+}
+
+#[turbo_tasks::value]
+#[derive(Default)]
+struct ArchiveEntries {
+    /// The entries by their normalized path. Contains the root directory unless the archive is
+    /// missing.
+    #[turbo_tasks(debug_ignore)]
+    entries: FxHashMap<RcStr, ArchiveEntry>,
+}
+
+impl ArchiveEntries {
+    fn get(&self, path: &str, follow_last_link: bool) -> Option<&ArchiveEntry> {
+        let resolved = self.resolve_path(path, follow_last_link)?;
+        self.entries.get(&*resolved)
+    }
+
+    /// Resolves the symlinks in `path`. Returns `None` when a link leaves the archive or there are
+    /// too many links.
+    fn resolve_path(&self, path: &str, follow_last_link: bool) -> Option<String> {
+        resolve_links(path, follow_last_link, |path| {
+            match self.entries.get(path) {
+                // Absolute links point outside of the archive
+                Some(ArchiveEntry::Link(target)) if target.starts_with('/') => {
+                    Some(LinkTarget::Invalid)
+                }
+                Some(ArchiveEntry::Link(target)) => Some(LinkTarget::Relative(target)),
+                _ => None,
+            }
+        })
+    }
+
+    fn read_link(&self, path: &str) -> LinkContent {
+        let Some(resolved) = self.resolve_path(path, false) else {
+            return LinkContent::NotFound;
+        };
+        let Some(ArchiveEntry::Link(target)) = self.entries.get(&*resolved) else {
+            return LinkContent::NotFound;
+        };
+        // Absolute links point outside of the archive
+        if target.starts_with('/') || join_path(get_parent_path(&resolved), target).is_none() {
+            return LinkContent::Invalid;
+        }
+        let mut link_type = LinkType::empty();
+        if matches!(self.get(&resolved, true), Some(ArchiveEntry::Directory(_))) {
+            link_type |= LinkType::DIRECTORY;
+        }
+        LinkContent::Link {
+            target: target.clone(),
+            link_type,
+        }
+    }
+}
+
+/// The entries of an archive while it is parsed.
+struct ParsedArchive {
+    entries: FxHashMap<RcStr, ArchiveEntry<File>>,
+}
+
+impl ParsedArchive {
+    fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut archive = ParsedArchive {
+            entries: FxHashMap::from_iter([(
+                RcStr::default(),
+                ArchiveEntry::Directory(BTreeMap::new()),
+            )]),
+        };
+        if bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"PK\x05\x06") {
+            archive.read_zip(bytes)?;
+        } else if bytes.starts_with(&[0x1f, 0x8b]) {
+            archive.read_tar(GzDecoder::new(bytes))?;
+        } else {
+            archive.read_tar(bytes)?;
+        }
+        Ok(archive)
+    }
+
+    fn read_zip(&mut self, bytes: &[u8]) -> Result<()> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+        for index in 0..archive.len() {
+            let mut entry = archive.by_index(index)?;
+            let Some(path) = entry_path(entry.name()) else {
+                continue;
+            };
+            if entry.is_dir() {
+                self.insert_dir(&path);
+                continue;
+            }
+            let mut content = Vec::with_capacity(entry.size() as usize);
+            entry
+                .read_to_end(&mut content)
+                .with_context(|| format!("failed to read {path}"))?;
+            if entry.is_symlink() {
+                let target = String::from_utf8(content)
+                    .with_context(|| format!("invalid symlink target of {path}"))?;
+                self.insert(&path, ArchiveEntry::Link(target.into()));
+            } else {
+                self.insert(
+                    &path,
+                    ArchiveEntry::File(archive_file(content, entry.unix_mode())),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn read_tar(&mut self, reader: impl Read) -> Result<()> {
+        let mut archive = tar::Archive::new(reader);
+        // Hard links are resolved after all entries have been read, as their target can come
+        // later in the archive
+        let mut hard_links = Vec::new();
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let Some(path) = entry_path(&entry.path()?.to_string_lossy()) else {
+                continue;
+            };
+            let entry_type = entry.header().entry_type();
+            if entry_type.is_dir() {
+                self.insert_dir(&path);
+            } else if entry_type.is_symlink() || entry_type.is_hard_link() {
+                let target = entry
+                    .link_name()?
+                    .with_context(|| format!("missing link target of {path}"))?
+                    .to_string_lossy()
+                    .into_owned();
+                if entry_type.is_symlink() {
+                    self.insert(&path, ArchiveEntry::Link(target.into()));
+                } else if let Some(target) = entry_path(&target) {
+                    hard_links.push((path, target));
+                }
+            } else if entry_type.is_file() {
+                let mut content = Vec::with_capacity(entry.size() as usize);
+                entry
+                    .read_to_end(&mut content)
+                    .with_context(|| format!("failed to read {path}"))?;
+                let mode = entry.header().mode().ok();
+                self.insert(&path, ArchiveEntry::File(archive_file(content, mode)));
+            }
+            // Other entries, like devices and fifos, can't be read
+        }
+        for (path, target) in hard_links {
+            if let Some(ArchiveEntry::File(file)) = self.entries.get(&*target) {
+                let file = file.clone();
+                self.insert(&path, ArchiveEntry::File(file));
+            }
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, path: &str, entry: ArchiveEntry) {
+        let parent = get_parent_path(path);
+        self.insert_dir(parent);
+        if let Some(ArchiveEntry::Directory(children)) = self.entries.get_mut(parent) {
+            children.insert(file_name(path).into(), entry.kind());
+        }
+        self.entries.insert(path.into(), entry);
+    }
+
+    /// Inserts the directory at `path` and its missing parent directories. Existing directories
+    /// keep their entries.
+    fn insert_dir(&mut self, path: &str) {
+        if matches!(self.entries.get(path), Some(ArchiveEntry::Directory(_))) {
+            return;
+        }
+        self.insert(path, ArchiveEntry::Directory(BTreeMap::new()));
+    }
+}
+
+/// Normalizes the path of an archive entry. Returns `None` for the root directory and for paths
+/// that leave the archive.
+fn entry_path(name: &str) -> Option<String> {
+    normalize_path(name).filter(|path| !path.is_empty())
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+fn archive_file(content: Vec<u8>, mode: Option<u32>) -> File {
+    let permissions = match mode {
+        Some(mode) if mode & 0o111 != 0 => Permissions::Executable,
+        _ => Permissions::default(),
+    };
+    File::new(
+        FileMeta {
+            permissions,
+            content_type: None,
+        },
+        content,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{Compression, write::GzEncoder};
+    use turbo_rcstr::rcstr;
+    use turbo_tasks::ResolvedVc;
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+    use crate::{
+        MemoryFileSystem,
+        test_utils::{
+            create_turbo_tasks, entry_names, memory_fs_operation, read_dir_operation,
+            read_operation,
+        },
+    };
+
+    #[turbo_tasks::function(operation)]
+    async fn archive_fs_operation(
+        base: ResolvedVc<MemoryFileSystem>,
+        archive: RcStr,
+    ) -> Result<Vc<Box<dyn FileSystem>>> {
+        Ok(Vc::upcast(ArchiveFileSystem::new(
+            rcstr!("archive"),
+            base.root().await?.join(&archive)?,
+        )))
+    }
+
+    async fn archive_fs(
+        base: ResolvedVc<MemoryFileSystem>,
+        archive: RcStr,
+    ) -> Result<ResolvedVc<Box<dyn FileSystem>>> {
+        archive_fs_operation(base, archive)
+            .resolve_strongly_consistent()
+            .await
+    }
+
+    #[turbo_tasks::function(operation)]
+    async fn read_link_operation(
+        fs: ResolvedVc<Box<dyn FileSystem>>,
+        path: RcStr,
+    ) -> Result<Vc<LinkContent>> {
+        Ok(fs.root().await?.join(&path)?.read_link())
+    }
+
+    fn zip_archive(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (path, content) in files {
+            writer
+                .start_file(*path, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer
+            .add_symlink("node_modules/alias", "pkg", SimpleFileOptions::default())
+            .unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn tgz_archive() -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(1);
+        header.set_mode(0o755);
+        builder
+            .append_data(&mut header, "package/bin/cli.js", b"a".as_slice())
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Link);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "package/index.js", "package/bin/cli.js")
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "package/escape", "../../outside")
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_zip() {
+        let tt = create_turbo_tasks();
+        tt.run_once(async {
+            let base = memory_fs_operation().resolve_strongly_consistent().await?;
+            base.await?.write_file(
+                "cache/pkg.zip",
+                zip_archive(&[("node_modules/pkg/index.js", "a")]),
+            )?;
+            let fs = archive_fs(base, rcstr!("cache/pkg.zip")).await?;
+
+            let read = read_operation(fs, rcstr!("node_modules/alias/index.js"));
+            assert_eq!(
+                *read.read_strongly_consistent().await?,
+                FileContent::Content(File::from("a"))
+            );
+            assert_eq!(
+                entry_names(
+                    &*read_dir_operation(fs, rcstr!("node_modules"))
+                        .read_strongly_consistent()
+                        .await?
+                ),
+                ["alias", "pkg"]
+            );
+            assert_eq!(
+                *read_link_operation(fs, rcstr!("node_modules/alias"))
+                    .read_strongly_consistent()
+                    .await?,
+                LinkContent::Link {
+                    target: rcstr!("pkg"),
+                    link_type: LinkType::DIRECTORY,
+                }
+            );
+
+            // Changing the archive invalidates the reads
+            base.await?.write_file(
+                "cache/pkg.zip",
+                zip_archive(&[("node_modules/pkg/index.js", "b")]),
+            )?;
+            assert_eq!(
+                *read.read_strongly_consistent().await?,
+                FileContent::Content(File::from("b"))
+            );
+            base.await?.remove("cache/pkg.zip")?;
+            assert_eq!(
+                *read.read_strongly_consistent().await?,
+                FileContent::NotFound
+            );
+
+            anyhow::Ok(())
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_tgz() {
+        let tt = create_turbo_tasks();
+        tt.run_once(async {
+            let base = memory_fs_operation().resolve_strongly_consistent().await?;
+            base.await?.write_file("pkg.tgz", tgz_archive())?;
+            let fs = archive_fs(base, rcstr!("pkg.tgz")).await?;
+
+            // Hard links are copies of their target
+            let content = read_operation(fs, rcstr!("package/index.js"))
+                .read_strongly_consistent()
+                .await?;
+            let FileContent::Content(file) = &*content else {
+                panic!("package/index.js not found");
+            };
+            assert_eq!(file.content().to_str()?, "a");
+            assert!(matches!(file.meta.permissions, Permissions::Executable));
+
+            assert_eq!(
+                entry_names(
+                    &*read_dir_operation(fs, rcstr!("package"))
+                        .read_strongly_consistent()
+                        .await?
+                ),
+                ["bin", "escape", "index.js"]
+            );
+            // Links can't leave the archive
+            assert_eq!(
+                *read_link_operation(fs, rcstr!("package/escape"))
+                    .read_strongly_consistent()
+                    .await?,
+                LinkContent::Invalid
+            );
+
+            anyhow::Ok(())
+        })
+        .await
+        .unwrap();
+    }
+}
@@ -0,0 +1,382 @@
+//! Records the file changes observed by the watchers of [`DiskFileSystem`]s, so an editing session
+//! can be replayed later, e.g. to benchmark incremental builds with
+//! [`DiskFileSystem::apply_file_change_step`].
+//!
+//! Recording is enabled by setting `TURBO_TASKS_RECORD_FILE_CHANGES` to the path of the file the
+//! session is written to. Every batch of changes is written as one JSON line.
+
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::{LazyLock, mpsc},
+    thread,
+    time::Instant,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use turbo_rcstr::RcStr;
+use turbo_unix_path::{sys_to_unix, unix_to_sys};
+
+use crate::{DiskFileSystem, DiskFileSystemInner, watcher::invalidate_changed_paths};
+
+pub(crate) static FILE_CHANGE_RECORDER: LazyLock<Option<FileChangeRecorder>> =
+    LazyLock::new(|| {
+        let path = env::var_os("TURBO_TASKS_RECORD_FILE_CHANGES")?;
+        match FileChangeRecorder::create(Path::new(&path)) {
+            Ok(recorder) => Some(recorder),
+            Err(err) => {
+                tracing::warn!("unable to record file changes: {err:?}");
+                None
+            }
+        }
+    });
+
+/// A batch of file changes that the watcher of a filesystem has observed at once.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileChangeStep {
+    /// The time since the recording started, in milliseconds.
+    pub elapsed_ms: u64,
+    /// The name of the [`DiskFileSystem`] that observed the changes.
+    pub filesystem: RcStr,
+    pub changes: Vec<FileChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileChange {
+    /// The path relative to the root of the filesystem, or to the parent directory for the
+    /// children of a [`FileChangeContent::Directory`], using `/` as separator.
+    pub path: RcStr,
+    #[serde(flatten)]
+    pub content: FileChangeContent,
+}
+
+impl FileChange {
+    /// Reads the current state of a path relative to `root`.
+    pub fn read(root: &Path, path: RcStr) -> Self {
+        let content = FileChangeContent::read(&root.join(&*unix_to_sys(&path)));
+        Self { path, content }
+    }
+}
+
+/// The state of a changed path after the change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum FileChangeContent {
+    Text {
+        content: String,
+    },
+    /// The content of a file that is not valid UTF-8.
+    Binary {
+        content: Vec<u8>,
+    },
+    /// A directory and everything in it, so a renamed directory keeps its content. Symlinks in it
+    /// are not recorded.
+    Directory {
+        #[serde(default)]
+        children: Vec<FileChange>,
+    },
+    Removed,
+}
+
+impl FileChangeContent {
+    fn read(path: &Path) -> Self {
+        match fs::metadata(path) {
+            Ok(metadata) if metadata.is_dir() => FileChangeContent::Directory {
+                children: read_children(path).unwrap_or_default(),
+            },
+            Ok(_) => match fs::read(path) {
+                Ok(bytes) => match String::from_utf8(bytes) {
+                    Ok(content) => FileChangeContent::Text { content },
+                    Err(err) => FileChangeContent::Binary {
+                        content: err.into_bytes(),
+                    },
+                },
+                Err(_) => FileChangeContent::Removed,
+            },
+            Err(_) => FileChangeContent::Removed,
+        }
+    }
+
+    fn apply(&self, path: &Path) -> io::Result<()> {
+        match self {
+            FileChangeContent::Text { content } => write_file(path, content.as_bytes()),
+            FileChangeContent::Binary { content } => write_file(path, content),
+            FileChangeContent::Directory { children } => {
+                if fs::symlink_metadata(path).is_ok_and(|metadata| !metadata.is_dir()) {
+                    fs::remove_file(path)?;
+                }
+                fs::create_dir_all(path)?;
+                for child in children {
+                    child.content.apply(&path.join(&*child.path))?;
+                }
+                Ok(())
+            }
+            FileChangeContent::Removed => {
+                let result = match fs::symlink_metadata(path) {
+                    Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+                    Ok(_) => fs::remove_file(path),
+                    Err(err) => Err(err),
+                };
+                match result {
+                    Err(err) if err.kind() == ErrorKind::NotFound => Ok(()),
+                    result => result,
+                }
+            }
+        }
+    }
+}
+
+fn read_children(path: &Path) -> io::Result<Vec<FileChange>> {
+    let mut children = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        // Symlinks might point outside of the directory or form cycles
+        if entry.file_type()?.is_symlink() {
+            continue;
+        }
+        let Some(name) = entry.file_name().to_str().map(RcStr::from) else {
+            continue;
+        };
+        children.push(FileChange {
+            path: name,
+            content: FileChangeContent::read(&entry.path()),
+        });
+    }
+    children.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(children)
+}
+
+fn write_file(path: &Path, content: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content)
+}
+
+fn write_step(writer: &mut impl Write, step: &FileChangeStep) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, step)?;
+    writer.write_all(b"\n")?;
+    // Every step is flushed, so the recording is complete when the process is killed
+    writer.flush()
+}
+
+/// The changed paths of a batch, whose contents haven't been read yet.
+struct ObservedStep {
+    elapsed_ms: u64,
+    filesystem: RcStr,
+    root_path: PathBuf,
+    paths: Vec<RcStr>,
+}
+
+/// Records the file changes on a separate thread, since reading the contents of the changed files
+/// would delay the invalidations of the watcher.
+pub(crate) struct FileChangeRecorder {
+    start: Instant,
+    sender: mpsc::Sender<ObservedStep>,
+}
+
+impl FileChangeRecorder {
+    fn create(path: &Path) -> Result<Self> {
+        let file =
+            File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+        let (sender, receiver) = mpsc::channel::<ObservedStep>();
+        thread::Builder::new()
+            .name("file change recorder".to_string())
+            .spawn(move || {
+                let mut writer = BufWriter::new(file);
+                for observed in receiver {
+                    let step = FileChangeStep {
+                        elapsed_ms: observed.elapsed_ms,
+                        filesystem: observed.filesystem,
+                        changes: observed
+                            .paths
+                            .into_iter()
+                            .map(|path| FileChange::read(&observed.root_path, path))
+                            .collect(),
+                    };
+                    if let Err(err) = write_step(&mut writer, &step) {
+                        tracing::warn!("unable to record file changes: {err}");
+                    }
+                }
+            })
+            .context("failed to start the file change recorder")?;
+        Ok(Self {
+            start: Instant::now(),
+            sender,
+        })
+    }
+
+    /// Records the changed paths. Called by the watcher before it invalidates them. The contents
+    /// are read afterwards.
+    pub fn record<'a>(
+        &self,
+        inner: &DiskFileSystemInner,
+        paths: impl IntoIterator<Item = &'a PathBuf>,
+    ) {
+        let root_path = inner.root_path();
+        let mut paths = paths
+            .into_iter()
+            .filter_map(|path| {
+                let relative_path = sys_to_unix(path.strip_prefix(root_path).ok()?.to_str()?);
+                if relative_path.is_empty() || inner.is_unix_path_denied(&relative_path) {
+                    return None;
+                }
+                Some(RcStr::from(relative_path))
+            })
+            .collect::<Vec<_>>();
+        if paths.is_empty() {
+            return;
+        }
+        paths.sort();
+        paths.dedup();
+        // The recorder thread only stops when the sender is dropped
+        let _ = self.sender.send(ObservedStep {
+            elapsed_ms: self.start.elapsed().as_millis() as u64,
+            filesystem: inner.name.clone(),
+            root_path: root_path.to_path_buf(),
+            paths,
+        });
+    }
+}
+
+/// Reads a session that has been recorded with `TURBO_TASKS_RECORD_FILE_CHANGES`.
+pub fn read_file_change_session(path: &Path) -> Result<Vec<FileChangeStep>> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut steps = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.with_context(|| format!("failed to read {}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let step = serde_json::from_str(&line)
+            .with_context(|| format!("invalid file change in {}:{}", path.display(), index + 1))?;
+        steps.push(step);
+    }
+    Ok(steps)
+}
+
+impl DiskFileSystem {
+    /// Applies a recorded step to the files on disk and invalidates the reads of the changed
+    /// paths, like the watcher does when it observes the changes. The watcher doesn't need to be
+    /// running.
+    ///
+    /// This writes to and removes from the root of the filesystem, so it should only be used on a
+    /// copy of the project.
+    pub async fn apply_file_change_step(&self, step: &FileChangeStep) -> Result<()> {
+        let root_path = self.inner.root_path();
+        let mut paths = Vec::with_capacity(step.changes.len());
+        for change in &step.changes {
+            let path = root_path.join(&*unix_to_sys(&change.path));
+            change
+                .content
+                .apply(&path)
+                .with_context(|| format!("failed to apply change to {}", path.display()))?;
+            paths.push(path);
+        }
+        let _lock = self.inner.invalidation_lock.write().await;
+        invalidate_changed_paths(&self.inner, true, &paths);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_recorded_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let session_path = dir.path().join("session.jsonl");
+        let step = FileChangeStep {
+            elapsed_ms: 12,
+            filesystem: RcStr::from("project"),
+            changes: vec![
+                FileChange {
+                    path: RcStr::from("src/index.js"),
+                    content: FileChangeContent::Text {
+                        content: "console.log(1)".to_string(),
+                    },
+                },
+                FileChange {
+                    path: RcStr::from("src/logo.png"),
+                    content: FileChangeContent::Binary {
+                        content: vec![0x89, 0xff],
+                    },
+                },
+                FileChange {
+                    path: RcStr::from("src/old.js"),
+                    content: FileChangeContent::Removed,
+                },
+            ],
+        };
+        {
+            let mut writer = BufWriter::new(File::create(&session_path).unwrap());
+            write_step(&mut writer, &step).unwrap();
+            writer.write_all(b"\n").unwrap();
+            writer.flush().unwrap();
+        }
+        assert_eq!(read_file_change_session(&session_path).unwrap(), vec![step]);
+    }
+
+    #[test]
+    fn test_apply_file_change_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("src").join("index.js");
+        FileChangeContent::Text {
+            content: "a".to_string(),
+        }
+        .apply(&path)
+        .unwrap();
+        assert_eq!(
+            FileChangeContent::read(&path),
+            FileChangeContent::Text {
+                content: "a".to_string()
+            }
+        );
+        FileChangeContent::Removed.apply(&path).unwrap();
+        assert_eq!(FileChangeContent::read(&path), FileChangeContent::Removed);
+        // Removing a missing file is not an error
+        FileChangeContent::Removed.apply(&path).unwrap();
+        assert_eq!(
+            FileChangeContent::read(&dir.path().join("src")),
+            FileChangeContent::Directory { children: vec![] }
+        );
+    }
+
+    #[test]
+    fn test_renamed_directory_keeps_children() {
+        let dir = tempfile::tempdir().unwrap();
+        write_file(&dir.path().join("old/nested/a.js"), b"a").unwrap();
+        write_file(&dir.path().join("old/b.js"), b"b").unwrap();
+        fs::rename(dir.path().join("old"), dir.path().join("new")).unwrap();
+        let changes = [
+            FileChange::read(dir.path(), RcStr::from("new")),
+            FileChange::read(dir.path(), RcStr::from("old")),
+        ];
+
+        // Replay the rename on a copy of the state before it
+        let replay_dir = tempfile::tempdir().unwrap();
+        write_file(&replay_dir.path().join("old/nested/a.js"), b"a").unwrap();
+        write_file(&replay_dir.path().join("old/b.js"), b"b").unwrap();
+        for change in &changes {
+            change
+                .content
+                .apply(&replay_dir.path().join(&*change.path))
+                .unwrap();
+        }
+        assert!(!replay_dir.path().join("old").exists());
+        assert_eq!(
+            fs::read_to_string(replay_dir.path().join("new/nested/a.js")).unwrap(),
+            "a"
+        );
+        assert_eq!(
+            fs::read_to_string(replay_dir.path().join("new/b.js")).unwrap(),
+            "b"
+        );
+    }
+}
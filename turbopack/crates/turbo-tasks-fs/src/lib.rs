@@ -11,19 +11,26 @@
 #![allow(clippy::needless_return)] // tokio macro-generated code doesn't respect this
 #![allow(clippy::mutable_key_type)]
 
+mod archive_fs;
 pub mod attach;
 pub mod embed;
+pub mod file_change_session;
 pub mod glob;
 mod globset;
 pub mod invalidation;
 mod invalidator_map;
 pub mod json;
+mod link_resolution;
+mod memory_fs;
 mod mutex_map;
+mod overlay_fs;
 mod path_map;
 mod read_glob;
 mod retry;
 pub mod rope;
 pub mod source_context;
+#[cfg(test)]
+mod test_utils;
 pub mod util;
 pub(crate) mod virtual_fs;
 mod watcher;
@@ -65,6 +72,10 @@ use turbo_unix_path::{
     get_parent_path, get_relative_path_to, join_path, normalize_path, sys_to_unix, unix_to_sys,
 };
 
+pub use crate::{
+    archive_fs::ArchiveFileSystem, memory_fs::MemoryFileSystem, overlay_fs::OverlayFileSystem,
+    read_glob::ReadGlobResult, virtual_fs::VirtualFileSystem,
+};
 use crate::{
     attach::AttachedFileSystem,
     glob::Glob,
@@ -78,7 +89,6 @@ use crate::{
     util::extract_disk_access,
     watcher::DiskWatcher,
 };
-pub use crate::{read_glob::ReadGlobResult, virtual_fs::VirtualFileSystem};
 
 /// A (somewhat arbitrary) filename limit that we should try to keep output file names below.
 ///
@@ -284,6 +294,12 @@ impl DiskFileSystemInner {
     ///
     /// We can efficiently check using string operations
     fn is_path_denied(&self, path: &FileSystemPath) -> bool {
+        self.is_unix_path_denied(&path.path)
+    }
+
+    /// Like [`DiskFileSystemInner::is_path_denied`], for a path relative to the fs root using unix
+    /// separators.
+    fn is_unix_path_denied(&self, path: &str) -> bool {
         let Some(denied_path) = &self.denied_path else {
             return false;
         };
@@ -291,7 +307,6 @@ impl DiskFileSystemInner {
         // * they are equal => denied
         // * root relative path is a descendant which means the next character is a / => denied
         // * anything else => not denied (covers denying `.next` but allowing `.next2`)
-        let path = &path.path;
         path.starts_with(denied_path.as_str())
             && (path.len() == denied_path.len()
                 || path.as_bytes().get(denied_path.len()) == Some(&b'/'))
@@ -2499,6 +2514,11 @@ pub async fn to_sys_path(mut path: FileSystemPath) -> Result<Option<PathBuf>> {
             continue;
         }
 
+        if ResolvedVc::try_downcast_type::<OverlayFileSystem>(path.fs).is_some() {
+            path = OverlayFileSystem::strip_overlay(path).await?;
+            continue;
+        }
+
         if let Some(fs) = ResolvedVc::try_downcast_type::<DiskFileSystem>(path.fs) {
             let sys_path = fs.await?.to_sys_path(&path);
             return Ok(Some(sys_path));
@@ -0,0 +1,57 @@
+use turbo_unix_path::{join_path, normalize_path};
+
+/// The maximum number of symlinks that are followed when resolving a path, like `ELOOP`.
+const MAX_LINK_DEPTH: usize = 40;
+
+/// The target of a symlink of a filesystem that keeps its entries in memory.
+pub(crate) enum LinkTarget<'a> {
+    /// A target relative to the directory containing the link.
+    Relative(&'a str),
+    /// A target relative to the root of the filesystem.
+    Root(&'a str),
+    /// A target that can't be followed, e.g. because it points outside of the filesystem.
+    Invalid,
+}
+
+/// Resolves the symlinks in the normalized `path` of a filesystem that keeps its entries in
+/// memory. `read_link` is called with the paths that would be followed and returns the target
+/// when there is a link at that path.
+///
+/// Returns `None` when a link leaves the filesystem or there are too many links.
+pub(crate) fn resolve_links<'a>(
+    path: &str,
+    follow_last_link: bool,
+    mut read_link: impl FnMut(&str) -> Option<LinkTarget<'a>>,
+) -> Option<String> {
+    let mut resolved = String::new();
+    let mut links = 0;
+    // The segments that still need to be resolved, in reverse order
+    let mut pending = path
+        .split('/')
+        .rev()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    while let Some(segment) = pending.pop() {
+        if segment.is_empty() {
+            continue;
+        }
+        let next = join_path(&resolved, &segment)?;
+        let follow = follow_last_link || pending.iter().any(|s| !s.is_empty());
+        let Some(target) = follow.then(|| read_link(&next)).flatten() else {
+            resolved = next;
+            continue;
+        };
+        links += 1;
+        if links > MAX_LINK_DEPTH {
+            return None;
+        }
+        let target = match target {
+            LinkTarget::Relative(target) => join_path(&resolved, target)?,
+            LinkTarget::Root(target) => normalize_path(target)?,
+            LinkTarget::Invalid => return None,
+        };
+        resolved = String::new();
+        pending.extend(target.split('/').rev().map(str::to_string));
+    }
+    Some(resolved)
+}
@@ -0,0 +1,517 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Debug, Formatter},
+    ops::Bound,
+    sync::Arc,
+};
+
+use anyhow::{Context, Result, bail};
+use parking_lot::Mutex;
+use rustc_hash::{FxHashMap, FxHashSet};
+use turbo_rcstr::RcStr;
+use turbo_tasks::{
+    Invalidator, NonLocalValue, ValueToString, Vc, debug::ValueDebugFormat, effect,
+    mark_session_dependent, mark_stateful, trace::TraceRawVcs,
+};
+use turbo_unix_path::{get_parent_path, join_path, normalize_path};
+
+use crate::{
+    File, FileContent, FileMeta, FileSystem, FileSystemPath, LinkContent, LinkType,
+    RawDirectoryContent, RawDirectoryEntry,
+    invalidation::Write,
+    link_resolution::{LinkTarget, resolve_links},
+};
+
+/// A writable [`FileSystem`] that keeps all files in memory, e.g. for editor integrations, tests
+/// or in-browser playgrounds that compile projects without touching the disk.
+///
+/// Files can be changed from outside of turbo-tasks with [`MemoryFileSystem::write_file`] and
+/// friends, which invalidates the tasks that have read the changed paths. The content is lost
+/// when the process exits.
+#[turbo_tasks::value(cell = "new", eq = "manual", serialization = "none")]
+pub struct MemoryFileSystem {
+    inner: Arc<MemoryFileSystemInner>,
+}
+
+#[turbo_tasks::value_impl]
+impl MemoryFileSystem {
+    /// Creates a new, empty [`MemoryFileSystem`]. Calls with the same name in the same task
+    /// return the same filesystem.
+    #[turbo_tasks::function]
+    pub fn new(name: RcStr) -> Vc<Self> {
+        mark_stateful();
+
+        Self::cell(MemoryFileSystem {
+            inner: Arc::new(MemoryFileSystemInner {
+                name,
+                state: Default::default(),
+            }),
+        })
+    }
+}
+
+impl MemoryFileSystem {
+    pub fn name(&self) -> &RcStr {
+        &self.inner.name
+    }
+
+    /// Creates or replaces the file at `path`. Missing parent directories are created.
+    pub fn write_file(&self, path: &str, file: impl Into<File>) -> Result<()> {
+        self.inner
+            .update(path, Some(MemoryEntry::File(file.into())), false)
+    }
+
+    /// Creates or replaces the symlink at `path`. Missing parent directories are created.
+    pub fn write_link(&self, path: &str, target: RcStr, link_type: LinkType) -> Result<()> {
+        self.inner
+            .update(path, Some(MemoryEntry::Link { target, link_type }), false)
+    }
+
+    /// Creates the directory at `path` and its missing parent directories.
+    pub fn create_dir(&self, path: &str) -> Result<()> {
+        self.inner
+            .update(path, Some(MemoryEntry::Directory(BTreeMap::new())), false)
+    }
+
+    /// Removes the file, symlink or directory at `path`, including the content of directories.
+    /// Removing a missing path is not an error.
+    pub fn remove(&self, path: &str) -> Result<()> {
+        self.inner.update(path, None, true)
+    }
+}
+
+impl Debug for MemoryFileSystem {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "name: {}", self.inner.name)
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl FileSystem for MemoryFileSystem {
+    #[turbo_tasks::function]
+    fn read(&self, fs_path: FileSystemPath) -> Vc<FileContent> {
+        mark_session_dependent();
+
+        match self.inner.read_entry(&fs_path.path, true, false) {
+            Some(MemoryEntry::File(file)) => FileContent::Content(file).cell(),
+            _ => FileContent::NotFound.cell(),
+        }
+    }
+
+    #[turbo_tasks::function]
+    fn read_link(&self, fs_path: FileSystemPath) -> Vc<LinkContent> {
+        mark_session_dependent();
+
+        match self.inner.read_entry(&fs_path.path, false, false) {
+            Some(MemoryEntry::Link { target, link_type }) => {
+                LinkContent::Link { target, link_type }.cell()
+            }
+            _ => LinkContent::NotFound.cell(),
+        }
+    }
+
+    #[turbo_tasks::function]
+    fn raw_read_dir(&self, fs_path: FileSystemPath) -> Vc<RawDirectoryContent> {
+        mark_session_dependent();
+
+        match self.inner.read_entry(&fs_path.path, true, true) {
+            Some(MemoryEntry::Directory(entries)) => {
+                RawDirectoryContent::new(entries.into_iter().collect())
+            }
+            _ => RawDirectoryContent::not_found(),
+        }
+    }
+
+    #[turbo_tasks::function]
+    async fn write(&self, fs_path: FileSystemPath, content: Vc<FileContent>) -> Result<()> {
+        let content = content.await?;
+        let inner = self.inner.clone();
+        effect(async move {
+            let entry = match &*content {
+                FileContent::Content(file) => Some(MemoryEntry::File(file.clone())),
+                FileContent::NotFound => None,
+            };
+            inner.update(&fs_path.path, entry, false)
+        });
+        Ok(())
+    }
+
+    #[turbo_tasks::function]
+    async fn write_link(&self, fs_path: FileSystemPath, target: Vc<LinkContent>) -> Result<()> {
+        let target = target.await?;
+        let entry = match &*target {
+            LinkContent::Link { target, link_type } => Some(MemoryEntry::Link {
+                target: target.clone(),
+                link_type: *link_type,
+            }),
+            LinkContent::Invalid => {
+                bail!(
+                    "invalid symlink target: {}",
+                    fs_path.value_to_string().await?
+                )
+            }
+            LinkContent::NotFound => None,
+        };
+        let inner = self.inner.clone();
+        effect(async move { inner.update(&fs_path.path, entry, false) });
+        Ok(())
+    }
+
+    #[turbo_tasks::function]
+    async fn metadata(&self, fs_path: FileSystemPath) -> Result<Vc<FileMeta>> {
+        mark_session_dependent();
+
+        match self.inner.read_entry(&fs_path.path, true, false) {
+            Some(MemoryEntry::File(file)) => Ok(file.meta.clone().cell()),
+            Some(MemoryEntry::Directory(_)) => Ok(FileMeta::default().cell()),
+            _ => bail!(
+                "reading metadata for {}: not found",
+                fs_path.value_to_string().await?
+            ),
+        }
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ValueToString for MemoryFileSystem {
+    #[turbo_tasks::function]
+    fn to_string(&self) -> Vc<RcStr> {
+        Vc::cell(self.inner.name.clone())
+    }
+}
+
+#[derive(Clone, PartialEq)]
+enum MemoryEntry {
+    File(File),
+    Link {
+        target: RcStr,
+        link_type: LinkType,
+    },
+    /// The names and kinds of the entries in the directory.
+    Directory(BTreeMap<RcStr, RawDirectoryEntry>),
+}
+
+impl MemoryEntry {
+    fn kind(&self) -> RawDirectoryEntry {
+        match self {
+            MemoryEntry::File(_) => RawDirectoryEntry::File,
+            MemoryEntry::Link { .. } => RawDirectoryEntry::Symlink,
+            MemoryEntry::Directory(_) => RawDirectoryEntry::Directory,
+        }
+    }
+}
+
+#[derive(TraceRawVcs, ValueDebugFormat, NonLocalValue)]
+struct MemoryFileSystemInner {
+    name: RcStr,
+    #[turbo_tasks(debug_ignore, trace_ignore)]
+    state: Mutex<MemoryFileSystemState>,
+}
+
+impl MemoryFileSystemInner {
+    /// Reads the entry at `path` and registers the current task to be invalidated when it
+    /// changes. Directory reads are invalidated when entries are added or removed, other reads
+    /// when the entry itself changes.
+    fn read_entry(&self, path: &str, follow_last_link: bool, dir: bool) -> Option<MemoryEntry> {
+        let mut state = self.state.lock();
+        let mut links = Vec::new();
+        let resolved = state.resolve_path(path, follow_last_link, &mut links);
+        let readers = if dir {
+            &mut state.dir_readers
+        } else {
+            &mut state.readers
+        };
+        // The paths of the followed links are tracked as well, so retargeting a link invalidates
+        // the reads through it
+        for path in [RcStr::from(path)]
+            .into_iter()
+            .chain(links)
+            .chain(resolved.clone().map(RcStr::from))
+        {
+            if let Some(invalidator) = turbo_tasks::get_invalidator() {
+                readers.entry(path).or_default().insert(invalidator);
+            }
+        }
+        state.entries.get(&*resolved?).cloned()
+    }
+
+    /// Sets or removes the entry at `path` and invalidates the tasks that have read it.
+    fn update(&self, path: &str, entry: Option<MemoryEntry>, recursive: bool) -> Result<()> {
+        let normalized_path = normalize_path(path)
+            .with_context(|| format!("{path} is outside of the {} filesystem", self.name))?;
+        if normalized_path.is_empty() {
+            bail!(
+                "the root directory of the {} filesystem can't be changed",
+                self.name
+            );
+        }
+        let mut invalidators = FxHashSet::default();
+        {
+            let mut state = self.state.lock();
+            let parent = state
+                .resolve_path(get_parent_path(&normalized_path), true, &mut Vec::new())
+                .with_context(|| format!("failed to resolve the parent directory of {path}"))?;
+            let resolved_path =
+                join_path(&parent, file_name(&normalized_path)).context("invalid file name")?;
+            state.set_entry(&resolved_path, entry, recursive, &mut invalidators)?;
+        }
+        let path = format!("[{}]/{normalized_path}", self.name);
+        for invalidator in invalidators {
+            invalidator.invalidate_with_reason(Write { path: path.clone() });
+        }
+        Ok(())
+    }
+}
+
+struct MemoryFileSystemState {
+    entries: FxHashMap<RcStr, MemoryEntry>,
+    /// The tasks that have read files, links or metadata, by path.
+    readers: BTreeMap<RcStr, FxHashSet<Invalidator>>,
+    /// The tasks that have read directories, by path.
+    dir_readers: BTreeMap<RcStr, FxHashSet<Invalidator>>,
+}
+
+impl Default for MemoryFileSystemState {
+    fn default() -> Self {
+        Self {
+            entries: FxHashMap::from_iter([(
+                RcStr::default(),
+                MemoryEntry::Directory(BTreeMap::new()),
+            )]),
+            readers: BTreeMap::new(),
+            dir_readers: BTreeMap::new(),
+        }
+    }
+}
+
+impl MemoryFileSystemState {
+    /// Resolves the symlinks in `path`. The paths of the followed links are added to `links`.
+    /// Returns `None` when a link leaves the filesystem or there are too many links.
+    fn resolve_path(
+        &self,
+        path: &str,
+        follow_last_link: bool,
+        links: &mut Vec<RcStr>,
+    ) -> Option<String> {
+        resolve_links(path, follow_last_link, |path| {
+            match self.entries.get(path) {
+                Some(MemoryEntry::Link { target, link_type }) => {
+                    links.push(path.into());
+                    Some(if link_type.contains(LinkType::ABSOLUTE) {
+                        LinkTarget::Root(target)
+                    } else {
+                        LinkTarget::Relative(target)
+                    })
+                }
+                _ => None,
+            }
+        })
+    }
+
+    fn set_entry(
+        &mut self,
+        path: &str,
+        entry: Option<MemoryEntry>,
+        recursive: bool,
+        invalidators: &mut FxHashSet<Invalidator>,
+    ) -> Result<()> {
+        let parent = get_parent_path(path);
+        match self.entries.get(parent) {
+            Some(MemoryEntry::Directory(_)) => {}
+            Some(_) => bail!("{parent} is not a directory"),
+            None if entry.is_none() => return Ok(()),
+            None => self.set_entry(
+                parent,
+                Some(MemoryEntry::Directory(BTreeMap::new())),
+                false,
+                invalidators,
+            )?,
+        }
+
+        let old = self.entries.get(path);
+        match (old, &entry) {
+            (None, None) => return Ok(()),
+            (Some(old), Some(new)) if old == new => return Ok(()),
+            (Some(MemoryEntry::Directory(_)), Some(MemoryEntry::Directory(_))) => return Ok(()),
+            (Some(MemoryEntry::Directory(children)), _) => {
+                if entry.is_some() || !recursive {
+                    bail!("{path} is a directory");
+                }
+                for child in children.keys().cloned().collect::<Vec<_>>() {
+                    self.set_entry(&format!("{path}/{child}"), None, true, invalidators)?;
+                }
+            }
+            _ => {}
+        }
+
+        let old_kind = self.entries.get(path).map(MemoryEntry::kind);
+        let new_kind = entry.as_ref().map(MemoryEntry::kind);
+        match entry {
+            Some(entry) => self.entries.insert(path.into(), entry),
+            None => self.entries.remove(path),
+        };
+        if old_kind != new_kind {
+            if let Some(MemoryEntry::Directory(children)) = self.entries.get_mut(parent) {
+                let name = RcStr::from(file_name(path));
+                match new_kind {
+                    Some(kind) => children.insert(name, kind),
+                    None => children.remove(&name),
+                };
+            }
+            take_readers(&mut self.dir_readers, parent, false, invalidators);
+        }
+        take_readers(&mut self.readers, path, true, invalidators);
+        take_readers(&mut self.dir_readers, path, true, invalidators);
+        Ok(())
+    }
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Removes the readers of `path`, and of all paths inside of it when `nested` is set.
+fn take_readers(
+    readers: &mut BTreeMap<RcStr, FxHashSet<Invalidator>>,
+    path: &str,
+    nested: bool,
+    invalidators: &mut FxHashSet<Invalidator>,
+) {
+    if let Some(path_readers) = readers.remove(path) {
+        invalidators.extend(path_readers);
+    }
+    if !nested {
+        return;
+    }
+    let prefix = if path.is_empty() {
+        String::new()
+    } else {
+        format!("{path}/")
+    };
+    let nested_paths = readers
+        .range::<str, _>((Bound::Included(&*prefix), Bound::Unbounded))
+        .map(|(path, _)| path)
+        .take_while(|nested_path| nested_path.starts_with(&prefix))
+        .cloned()
+        .collect::<Vec<_>>();
+    for nested_path in nested_paths {
+        invalidators.extend(readers.remove(&nested_path).unwrap_or_default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use turbo_rcstr::rcstr;
+    use turbo_tasks::{ResolvedVc, apply_effects};
+
+    use super::*;
+    use crate::test_utils::{
+        create_turbo_tasks, entry_names, memory_fs_operation, read_dir_operation, read_operation,
+        write_operation,
+    };
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_changes_invalidate_reads() {
+        let tt = create_turbo_tasks();
+        tt.run_once(async {
+            let fs = memory_fs_operation().resolve_strongly_consistent().await?;
+            fs.await?.write_file("src/index.js", "a")?;
+
+            let read = read_operation(ResolvedVc::upcast(fs), rcstr!("src/index.js"));
+            assert_eq!(
+                *read.read_strongly_consistent().await?,
+                FileContent::Content(File::from("a"))
+            );
+            let read_dir = read_dir_operation(ResolvedVc::upcast(fs), rcstr!("src"));
+            assert_eq!(
+                entry_names(&*read_dir.read_strongly_consistent().await?),
+                ["index.js"]
+            );
+
+            fs.await?.write_file("src/index.js", "b")?;
+            fs.await?.write_file("src/lib/util.js", "c")?;
+            assert_eq!(
+                *read.read_strongly_consistent().await?,
+                FileContent::Content(File::from("b"))
+            );
+            assert_eq!(
+                entry_names(&*read_dir.read_strongly_consistent().await?),
+                ["index.js", "lib"]
+            );
+
+            fs.await?.remove("src")?;
+            assert_eq!(
+                *read.read_strongly_consistent().await?,
+                FileContent::NotFound
+            );
+            assert_eq!(
+                *read_dir.read_strongly_consistent().await?,
+                RawDirectoryContent::NotFound
+            );
+
+            // Writes from tasks are applied as effects
+            let write =
+                write_operation(ResolvedVc::upcast(fs), rcstr!("src/index.js"), rcstr!("d"));
+            write.read_strongly_consistent().await?;
+            apply_effects(write).await?;
+            assert_eq!(
+                *read.read_strongly_consistent().await?,
+                FileContent::Content(File::from("d"))
+            );
+
+            anyhow::Ok(())
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_links() {
+        let tt = create_turbo_tasks();
+        tt.run_once(async {
+            let fs = memory_fs_operation().resolve_strongly_consistent().await?;
+            let memory_fs = fs.await?;
+            memory_fs.write_file("packages/a/index.js", "a")?;
+            memory_fs.write_file("packages/b/index.js", "b")?;
+            memory_fs.write_link(
+                "node_modules/pkg",
+                rcstr!("../packages/a"),
+                LinkType::DIRECTORY,
+            )?;
+
+            let read = read_operation(ResolvedVc::upcast(fs), rcstr!("node_modules/pkg/index.js"));
+            assert_eq!(
+                *read.read_strongly_consistent().await?,
+                FileContent::Content(File::from("a"))
+            );
+
+            // Retargeting the link invalidates the reads through it
+            memory_fs.write_link(
+                "node_modules/pkg",
+                rcstr!("packages/b"),
+                LinkType::DIRECTORY | LinkType::ABSOLUTE,
+            )?;
+            assert_eq!(
+                *read.read_strongly_consistent().await?,
+                FileContent::Content(File::from("b"))
+            );
+
+            // Links that point to each other are not followed forever
+            memory_fs.write_link("loop/a", rcstr!("b"), LinkType::empty())?;
+            memory_fs.write_link("loop/b", rcstr!("a"), LinkType::empty())?;
+            assert_eq!(
+                *read_operation(ResolvedVc::upcast(fs), rcstr!("loop/a"))
+                    .read_strongly_consistent()
+                    .await?,
+                FileContent::NotFound
+            );
+
+            // Files can't be written into files
+            assert!(memory_fs.write_file("packages/a/index.js/x", "x").is_err());
+
+            anyhow::Ok(())
+        })
+        .await
+        .unwrap();
+    }
+}
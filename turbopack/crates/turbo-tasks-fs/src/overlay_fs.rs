@@ -0,0 +1,467 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Debug, Formatter},
+    ops::Bound,
+    sync::Arc,
+};
+
+use anyhow::{Context, Result, bail};
+use parking_lot::Mutex;
+use rustc_hash::{FxHashMap, FxHashSet};
+use turbo_rcstr::RcStr;
+use turbo_tasks::{
+    Invalidator, NonLocalValue, ResolvedVc, ValueToString, Vc, debug::ValueDebugFormat,
+    mark_session_dependent, mark_stateful, trace::TraceRawVcs,
+};
+use turbo_unix_path::{get_parent_path, normalize_path};
+
+use crate::{
+    FileContent, FileMeta, FileSystem, FileSystemPath, LinkContent, RawDirectoryContent,
+    RawDirectoryEntry, invalidation::Write,
+};
+
+/// A [`FileSystem`] that layers in-memory overrides of file contents over another filesystem,
+/// e.g. the unsaved buffers of an editor over the project on disk.
+///
+/// Reads check the overrides first and fall back to the base filesystem. Writes always go to the
+/// base filesystem. Overrides only apply to the exact path they are set for, they are not seen
+/// through symlinks.
+///
+/// The filesystem has the same name as the base filesystem, so paths are displayed the same way
+/// with and without the overlay.
+#[turbo_tasks::value(cell = "new", eq = "manual", serialization = "none")]
+pub struct OverlayFileSystem {
+    base: ResolvedVc<Box<dyn FileSystem>>,
+    inner: Arc<OverlayFileSystemInner>,
+}
+
+#[turbo_tasks::value_impl]
+impl OverlayFileSystem {
+    /// Creates a new [`OverlayFileSystem`] without overrides. Calls with the same base
+    /// filesystem in the same task return the same filesystem.
+    #[turbo_tasks::function]
+    pub async fn new(base: ResolvedVc<Box<dyn FileSystem>>) -> Result<Vc<Self>> {
+        mark_stateful();
+
+        let name = base.to_string().owned().await?;
+        Ok(Self::cell(OverlayFileSystem {
+            base,
+            inner: Arc::new(OverlayFileSystemInner {
+                name,
+                state: Default::default(),
+            }),
+        }))
+    }
+}
+
+impl OverlayFileSystem {
+    pub fn name(&self) -> &RcStr {
+        &self.inner.name
+    }
+
+    /// The filesystem below the overrides.
+    pub fn base(&self) -> ResolvedVc<Box<dyn FileSystem>> {
+        self.base
+    }
+
+    /// Replaces the content of the file at `path` for all reads through this filesystem.
+    /// [`FileContent::NotFound`] hides the file of the base filesystem.
+    pub fn set_override(&self, path: &str, content: FileContent) -> Result<()> {
+        self.inner.update(path, Some(content))
+    }
+
+    /// Removes the override of the file at `path`, so reads see the base filesystem again.
+    /// Clearing a missing override is not an error.
+    pub fn clear_override(&self, path: &str) -> Result<()> {
+        self.inner.update(path, None)
+    }
+
+    /// Converts a path on an [`OverlayFileSystem`] to the same path on its base filesystem, e.g.
+    /// to find the file on disk. Other paths are returned unchanged.
+    pub async fn strip_overlay(mut path: FileSystemPath) -> Result<FileSystemPath> {
+        while let Some(fs) = ResolvedVc::try_downcast_type::<OverlayFileSystem>(path.fs) {
+            path.fs = fs.await?.base();
+        }
+        Ok(path)
+    }
+
+    fn base_path(&self, fs_path: FileSystemPath) -> FileSystemPath {
+        FileSystemPath {
+            fs: self.base,
+            path: fs_path.path,
+        }
+    }
+}
+
+impl Debug for OverlayFileSystem {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "name: {}", self.inner.name)
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl FileSystem for OverlayFileSystem {
+    #[turbo_tasks::function(fs)]
+    fn read(&self, fs_path: FileSystemPath) -> Vc<FileContent> {
+        mark_session_dependent();
+
+        match self.inner.read_override(&fs_path.path) {
+            Some(content) => content.cell(),
+            None => self.base_path(fs_path).read(),
+        }
+    }
+
+    #[turbo_tasks::function(fs)]
+    fn read_link(&self, fs_path: FileSystemPath) -> Vc<LinkContent> {
+        mark_session_dependent();
+
+        match self.inner.read_override(&fs_path.path) {
+            // Overridden paths are always files
+            Some(_) => LinkContent::NotFound.cell(),
+            None => self.base_path(fs_path).read_link(),
+        }
+    }
+
+    #[turbo_tasks::function(fs)]
+    async fn raw_read_dir(&self, fs_path: FileSystemPath) -> Result<Vc<RawDirectoryContent>> {
+        mark_session_dependent();
+
+        let overrides = self.inner.read_dir_overrides(&fs_path.path);
+        let base_content = self.base_path(fs_path).raw_read_dir();
+        if overrides.is_empty() {
+            return Ok(base_content);
+        }
+        let (mut entries, mut found) = match &*base_content.await? {
+            RawDirectoryContent::Entries(entries) => (entries.clone(), true),
+            RawDirectoryContent::NotFound => (Default::default(), false),
+        };
+        for (name, entry) in overrides {
+            match entry {
+                Some(RawDirectoryEntry::Directory) => {
+                    // Files are overridden inside of this directory, which might only exist in
+                    // the overlay
+                    entries.entry(name).or_insert(RawDirectoryEntry::Directory);
+                    found = true;
+                }
+                Some(entry) => {
+                    entries.insert(name, entry);
+                    found = true;
+                }
+                None => {
+                    entries.remove(&name);
+                }
+            }
+        }
+        Ok(if found {
+            RawDirectoryContent::new(entries)
+        } else {
+            RawDirectoryContent::not_found()
+        })
+    }
+
+    #[turbo_tasks::function(fs)]
+    fn write(&self, fs_path: FileSystemPath, content: Vc<FileContent>) -> Vc<()> {
+        self.base_path(fs_path).write(content)
+    }
+
+    #[turbo_tasks::function(fs)]
+    fn write_link(&self, fs_path: FileSystemPath, target: Vc<LinkContent>) -> Vc<()> {
+        self.base_path(fs_path).write_link(target)
+    }
+
+    #[turbo_tasks::function]
+    async fn metadata(&self, fs_path: FileSystemPath) -> Result<Vc<FileMeta>> {
+        mark_session_dependent();
+
+        match self.inner.read_override(&fs_path.path) {
+            Some(FileContent::Content(file)) => Ok(file.meta.clone().cell()),
+            Some(FileContent::NotFound) => bail!(
+                "reading metadata for {}: not found",
+                fs_path.value_to_string().await?
+            ),
+            None => Ok(self.base_path(fs_path).metadata()),
+        }
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ValueToString for OverlayFileSystem {
+    #[turbo_tasks::function]
+    fn to_string(&self) -> Vc<RcStr> {
+        Vc::cell(self.inner.name.clone())
+    }
+}
+
+#[derive(TraceRawVcs, ValueDebugFormat, NonLocalValue)]
+struct OverlayFileSystemInner {
+    name: RcStr,
+    #[turbo_tasks(debug_ignore, trace_ignore)]
+    state: Mutex<OverlayFileSystemState>,
+}
+
+impl OverlayFileSystemInner {
+    /// Returns the override of `path` and registers the current task to be invalidated when it
+    /// changes.
+    fn read_override(&self, path: &str) -> Option<FileContent> {
+        let mut state = self.state.lock();
+        if let Some(invalidator) = turbo_tasks::get_invalidator() {
+            state
+                .readers
+                .entry(path.into())
+                .or_default()
+                .insert(invalidator);
+        }
+        state.overrides.get(path).cloned()
+    }
+
+    /// Returns how the overrides change the entries of the directory at `path`, and registers the
+    /// current task to be invalidated when they change. `None` hides an entry.
+    fn read_dir_overrides(&self, path: &str) -> Vec<(RcStr, Option<RawDirectoryEntry>)> {
+        let mut state = self.state.lock();
+        if let Some(invalidator) = turbo_tasks::get_invalidator() {
+            state
+                .dir_readers
+                .entry(path.into())
+                .or_default()
+                .insert(invalidator);
+        }
+        let prefix = dir_prefix(path);
+        overrides_inside(&state.overrides, &prefix)
+            .filter_map(|(override_path, content)| {
+                let rest = &override_path[prefix.len()..];
+                match (rest.split_once('/'), content) {
+                    (None, FileContent::Content(_)) => {
+                        Some((rest.into(), Some(RawDirectoryEntry::File)))
+                    }
+                    (None, FileContent::NotFound) => Some((rest.into(), None)),
+                    (Some((dir, _)), FileContent::Content(_)) => {
+                        Some((dir.into(), Some(RawDirectoryEntry::Directory)))
+                    }
+                    (Some(_), FileContent::NotFound) => None,
+                }
+            })
+            .collect()
+    }
+
+    /// Sets or removes the override of `path` and invalidates the reads and directory listings
+    /// that see a different result because of it.
+    fn update(&self, path: &str, content: Option<FileContent>) -> Result<()> {
+        let normalized_path = normalize_path(path)
+            .with_context(|| format!("{path} is outside of the {} filesystem", self.name))?;
+        if normalized_path.is_empty() {
+            bail!(
+                "the root directory of the {} filesystem can't be overridden",
+                self.name
+            );
+        }
+        let mut invalidators = FxHashSet::default();
+        {
+            let mut state = self.state.lock();
+            let ancestors = ancestors(&normalized_path).collect::<Vec<_>>();
+            let had_content = ancestors
+                .iter()
+                .map(|dir| has_content_inside(&state.overrides, dir))
+                .collect::<Vec<_>>();
+            let old = match &content {
+                Some(content) => state
+                    .overrides
+                    .insert(normalized_path.as_str().into(), content.clone()),
+                None => state.overrides.remove(normalized_path.as_str()),
+            };
+            if old == content {
+                return Ok(());
+            }
+            let state = &mut *state;
+            take_readers(&mut state.readers, &normalized_path, &mut invalidators);
+            // Changing the content of an overridden file doesn't change the directory listing,
+            // but adding, hiding or restoring the file might
+            let is_hidden = |content: &Option<FileContent>| {
+                content
+                    .as_ref()
+                    .map(|content| matches!(content, FileContent::NotFound))
+            };
+            if is_hidden(&old) != is_hidden(&content) {
+                take_readers(
+                    &mut state.dir_readers,
+                    get_parent_path(&normalized_path),
+                    &mut invalidators,
+                );
+            }
+            // Directories that only exist in the overlay appear with their first overridden file
+            // and disappear with their last one
+            for (dir, had_content) in ancestors.into_iter().zip(had_content) {
+                if has_content_inside(&state.overrides, dir) != had_content {
+                    take_readers(
+                        &mut state.dir_readers,
+                        get_parent_path(dir),
+                        &mut invalidators,
+                    );
+                }
+            }
+        }
+        let path = format!("[{}]/{normalized_path}", self.name);
+        for invalidator in invalidators {
+            invalidator.invalidate_with_reason(Write { path: path.clone() });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct OverlayFileSystemState {
+    /// The content that replaces the content of the base filesystem, by path.
+    overrides: BTreeMap<RcStr, FileContent>,
+    /// The tasks that have read files, links or metadata, by path.
+    readers: FxHashMap<RcStr, FxHashSet<Invalidator>>,
+    /// The tasks that have read directories, by path.
+    dir_readers: FxHashMap<RcStr, FxHashSet<Invalidator>>,
+}
+
+/// The parent directories of `path`, excluding the root directory.
+fn ancestors(path: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(get_parent_path(path)), |dir| {
+        Some(get_parent_path(dir))
+    })
+    .take_while(|dir| !dir.is_empty())
+}
+
+fn dir_prefix(path: &str) -> String {
+    if path.is_empty() {
+        String::new()
+    } else {
+        format!("{path}/")
+    }
+}
+
+fn overrides_inside<'a>(
+    overrides: &'a BTreeMap<RcStr, FileContent>,
+    prefix: &'a str,
+) -> impl Iterator<Item = (&'a RcStr, &'a FileContent)> {
+    overrides
+        .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+        .take_while(move |(path, _)| path.starts_with(prefix))
+}
+
+fn has_content_inside(overrides: &BTreeMap<RcStr, FileContent>, dir: &str) -> bool {
+    overrides_inside(overrides, &dir_prefix(dir))
+        .any(|(_, content)| matches!(content, FileContent::Content(_)))
+}
+
+fn take_readers(
+    readers: &mut FxHashMap<RcStr, FxHashSet<Invalidator>>,
+    path: &str,
+    invalidators: &mut FxHashSet<Invalidator>,
+) {
+    if let Some(path_readers) = readers.remove(path) {
+        invalidators.extend(path_readers);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use turbo_rcstr::rcstr;
+    use turbo_tasks::apply_effects;
+
+    use super::*;
+    use crate::{
+        File, MemoryFileSystem,
+        test_utils::{
+            create_turbo_tasks, entry_names, memory_fs_operation, read_dir_operation,
+            read_operation, write_operation,
+        },
+    };
+
+    #[turbo_tasks::function(operation)]
+    fn overlay_fs_operation(base: ResolvedVc<MemoryFileSystem>) -> Vc<OverlayFileSystem> {
+        OverlayFileSystem::new(Vc::upcast(*base))
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_overrides() {
+        let tt = create_turbo_tasks();
+        tt.run_once(async {
+            let base = memory_fs_operation().resolve_strongly_consistent().await?;
+            let fs = overlay_fs_operation(base)
+                .resolve_strongly_consistent()
+                .await?;
+            base.await?.write_file("src/index.js", "disk")?;
+            base.await?.write_file("src/util.js", "util")?;
+
+            let read = read_operation(ResolvedVc::upcast(fs), rcstr!("src/index.js"));
+            let read_dir = read_dir_operation(ResolvedVc::upcast(fs), rcstr!("src"));
+            let read_root = read_dir_operation(ResolvedVc::upcast(fs), rcstr!(""));
+            assert_eq!(
+                *read.read_strongly_consistent().await?,
+                FileContent::Content(File::from("disk"))
+            );
+
+            fs.await?
+                .set_override("src/index.js", File::from("unsaved").into())?;
+            fs.await?
+                .set_override("src/new.js", File::from("new").into())?;
+            fs.await?
+                .set_override("src/util.js", FileContent::NotFound)?;
+            fs.await?
+                .set_override("pages/index.js", File::from("page").into())?;
+            assert_eq!(
+                *read.read_strongly_consistent().await?,
+                FileContent::Content(File::from("unsaved"))
+            );
+            assert_eq!(
+                entry_names(&*read_dir.read_strongly_consistent().await?),
+                ["index.js", "new.js"]
+            );
+            assert_eq!(
+                entry_names(&*read_root.read_strongly_consistent().await?),
+                ["pages", "src"]
+            );
+
+            // Overridden files don't see changes of the base filesystem
+            base.await?.write_file("src/index.js", "disk 2")?;
+            assert_eq!(
+                *read.read_strongly_consistent().await?,
+                FileContent::Content(File::from("unsaved"))
+            );
+
+            fs.await?.clear_override("src/index.js")?;
+            fs.await?.clear_override("src/util.js")?;
+            fs.await?.clear_override("pages/index.js")?;
+            assert_eq!(
+                *read.read_strongly_consistent().await?,
+                FileContent::Content(File::from("disk 2"))
+            );
+            assert_eq!(
+                entry_names(&*read_dir.read_strongly_consistent().await?),
+                ["index.js", "new.js", "util.js"]
+            );
+            assert_eq!(
+                entry_names(&*read_root.read_strongly_consistent().await?),
+                ["src"]
+            );
+
+            // Writes go to the base filesystem, which is hidden by an override
+            fs.await?
+                .set_override("src/index.js", File::from("unsaved").into())?;
+            let write = write_operation(
+                ResolvedVc::upcast(fs),
+                rcstr!("src/index.js"),
+                rcstr!("saved"),
+            );
+            write.read_strongly_consistent().await?;
+            apply_effects(write).await?;
+            assert_eq!(
+                *read.read_strongly_consistent().await?,
+                FileContent::Content(File::from("unsaved"))
+            );
+            fs.await?.clear_override("src/index.js")?;
+            assert_eq!(
+                *read.read_strongly_consistent().await?,
+                FileContent::Content(File::from("saved"))
+            );
+
+            anyhow::Ok(())
+        })
+        .await
+        .unwrap();
+    }
+}
@@ -0,0 +1,66 @@
+//! Fixtures shared by the tests of the filesystem implementations.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use turbo_rcstr::{RcStr, rcstr};
+use turbo_tasks::{ResolvedVc, TurboTasks, Vc};
+use turbo_tasks_backend::{
+    BackendOptions, NoopBackingStorage, TurboTasksBackend, noop_backing_storage,
+};
+
+use crate::{File, FileContent, FileSystem, MemoryFileSystem, RawDirectoryContent};
+
+pub fn create_turbo_tasks() -> Arc<TurboTasks<TurboTasksBackend<NoopBackingStorage>>> {
+    TurboTasks::new(TurboTasksBackend::new(
+        BackendOptions::default(),
+        noop_backing_storage(),
+    ))
+}
+
+#[turbo_tasks::function(operation)]
+pub fn memory_fs_operation() -> Vc<MemoryFileSystem> {
+    MemoryFileSystem::new(rcstr!("memory"))
+}
+
+#[turbo_tasks::function(operation)]
+pub async fn read_operation(
+    fs: ResolvedVc<Box<dyn FileSystem>>,
+    path: RcStr,
+) -> Result<Vc<FileContent>> {
+    Ok(fs.root().await?.join(&path)?.read())
+}
+
+#[turbo_tasks::function(operation)]
+pub async fn read_dir_operation(
+    fs: ResolvedVc<Box<dyn FileSystem>>,
+    path: RcStr,
+) -> Result<Vc<RawDirectoryContent>> {
+    Ok(fs.root().await?.join(&path)?.raw_read_dir())
+}
+
+#[turbo_tasks::function(operation)]
+pub async fn write_operation(
+    fs: ResolvedVc<Box<dyn FileSystem>>,
+    path: RcStr,
+    content: RcStr,
+) -> Result<()> {
+    fs.root()
+        .await?
+        .join(&path)?
+        .write(FileContent::Content(File::from(content)).cell())
+        .await?;
+    Ok(())
+}
+
+/// The sorted names of the entries of a directory.
+pub fn entry_names(content: &RawDirectoryContent) -> Vec<&str> {
+    match content {
+        RawDirectoryContent::Entries(entries) => {
+            let mut names = entries.keys().map(|name| name.as_str()).collect::<Vec<_>>();
+            names.sort();
+            names
+        }
+        RawDirectoryContent::NotFound => panic!("directory not found"),
+    }
+}
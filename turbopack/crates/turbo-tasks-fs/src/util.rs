@@ -6,7 +6,7 @@ use std::{
 use anyhow::{Context, Result, anyhow};
 use turbo_tasks::ResolvedVc;
 
-use crate::{DiskFileSystem, FileSystemPath};
+use crate::{DiskFileSystem, FileSystemPath, OverlayFileSystem};
 
 /// Converts a disk access Result<T> into a Result<Some<T>>, where a NotFound
 /// error results in a None value. This is purely to reduce boilerplate code
@@ -23,6 +23,7 @@ pub fn extract_disk_access<T>(value: io::Result<T>, path: &Path) -> Result<Optio
 pub async fn uri_from_file(root: FileSystemPath, path: Option<&str>) -> Result<String> {
     use turbo_unix_path::sys_to_unix;
 
+    let root = OverlayFileSystem::strip_overlay(root).await?;
     let root_fs = root.fs;
     let root_fs = &*ResolvedVc::try_downcast_type::<DiskFileSystem>(root_fs)
         .context("Expected root to have a DiskFileSystem")?
@@ -48,6 +49,7 @@ pub async fn uri_from_file(root: FileSystemPath, path: Option<&str>) -> Result<S
 
 #[cfg(target_os = "windows")]
 pub async fn uri_from_file(root: FileSystemPath, path: Option<&str>) -> Result<String> {
+    let root = OverlayFileSystem::strip_overlay(root).await?;
     let root_fs = root.fs;
     let root_fs = &*ResolvedVc::try_downcast_type::<DiskFileSystem>(root_fs)
         .context("Expected root to have a DiskFileSystem")?
@@ -26,7 +26,9 @@ use turbo_tasks::{
 };
 
 use crate::{
-    DiskFileSystemInner, format_absolute_fs_path,
+    DiskFileSystemInner,
+    file_change_session::FILE_CHANGE_RECORDER,
+    format_absolute_fs_path,
     invalidation::{WatchChange, WatchStart},
     invalidator_map::LockedInvalidatorMap,
     path_map::OrderedPathMapExt,
@@ -682,6 +684,15 @@ impl DiskWatcher {
                 }
             }
 
+            if let Some(recorder) = &*FILE_CHANGE_RECORDER {
+                recorder.record(
+                    &fs_inner,
+                    batched_invalidate_path
+                        .iter()
+                        .chain(&batched_invalidate_path_and_children),
+                );
+            }
+
             let _lock = fs_inner.invalidation_lock.blocking_write();
             {
                 let mut invalidator_map = fs_inner.invalidator_map.lock().unwrap();
@@ -788,6 +799,51 @@ fn invalidate_path_and_children_execute(
     }
 }
 
+/// Invalidates the reads of the changed paths and their children, and the directory reads of their
+/// parents, like the watcher does for a change of an unknown kind. The caller must hold the
+/// invalidation lock.
+pub(crate) fn invalidate_changed_paths(
+    inner: &DiskFileSystemInner,
+    report_invalidation_reason: bool,
+    paths: &[PathBuf],
+) {
+    {
+        let mut invalidator_map = inner.invalidator_map.lock().unwrap();
+        invalidate_path(
+            inner,
+            report_invalidation_reason,
+            &mut invalidator_map,
+            paths.iter().cloned(),
+        );
+        invalidate_path_and_children_execute(
+            inner,
+            report_invalidation_reason,
+            &mut invalidator_map,
+            paths.iter().cloned(),
+        );
+    }
+    {
+        let mut dir_invalidator_map = inner.dir_invalidator_map.lock().unwrap();
+        invalidate_path(
+            inner,
+            report_invalidation_reason,
+            &mut dir_invalidator_map,
+            paths
+                .iter()
+                .filter_map(|path| path.parent())
+                .map(PathBuf::from)
+                .collect::<FxHashSet<_>>()
+                .into_iter(),
+        );
+        invalidate_path_and_children_execute(
+            inner,
+            report_invalidation_reason,
+            &mut dir_invalidator_map,
+            paths.iter().cloned(),
+        );
+    }
+}
+
 /// Invalidation was caused by a watcher rescan event. This will likely invalidate *every* watched
 /// file.
 #[derive(Clone, PartialEq, Eq, Hash)]
@@ -0,0 +1,287 @@
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::{Context, Result, bail};
+use clap::Args;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+use turbo_tasks::{
+    NonLocalValue, ResolvedVc, State, TaskInput, TurboTasks, Vc, trace::TraceRawVcs,
+};
+use turbo_tasks_backend::{
+    BackendOptions, FaultInjectingBackingStorage, FaultInjectionConfig, GitVersionInfo,
+    TurboTasksBackend, fault_injecting_backing_storage,
+};
+
+use crate::FsCleanup;
+
+#[derive(Args)]
+pub struct IncrementalModel {
+    /// The directory of the persistent cache. It's deleted before and after fuzzing.
+    #[arg(long)]
+    cache_dir: PathBuf,
+    /// The seed of the generated graph and the sequence of steps. Random by default.
+    #[arg(long)]
+    seed: Option<u64>,
+    #[arg(long, default_value_t = 1000)]
+    steps: u32,
+    /// The number of input states.
+    #[arg(long, default_value_t = 20)]
+    inputs: u32,
+    /// The number of tasks computed from the inputs.
+    #[arg(long, default_value_t = 200)]
+    nodes: u32,
+    #[arg(long, default_value_t = 4)]
+    max_dependencies: u32,
+    /// The probability that a step persists all changes to the backing storage.
+    #[arg(long, default_value_t = 0.05)]
+    snapshot_rate: f64,
+    /// The probability that a step stops the engine and starts a new one from the persistent
+    /// cache, like a process restart.
+    #[arg(long, default_value_t = 0.05)]
+    restart_rate: f64,
+    /// The probability that a commit to the backing storage fails without applying any writes.
+    #[arg(long, default_value_t = 0.0)]
+    commit_error_rate: f64,
+    /// The probability that a commit to the backing storage only applies some of its writes.
+    #[arg(long, default_value_t = 0.0)]
+    partial_commit_rate: f64,
+}
+
+#[derive(
+    Clone, Debug, PartialEq, Eq, Hash, NonLocalValue, Serialize, Deserialize, TraceRawVcs, TaskInput,
+)]
+enum Dependency {
+    Input(u32),
+    Node(u32),
+}
+
+#[derive(
+    Clone, Debug, PartialEq, Eq, Hash, NonLocalValue, Serialize, Deserialize, TraceRawVcs, TaskInput,
+)]
+struct NodeSpec {
+    dependencies: Vec<Dependency>,
+    /// Only reads the other dependencies when the first one is even, which makes the set of
+    /// dependencies change between executions.
+    conditional: bool,
+}
+
+/// A random DAG of tasks. Nodes only depend on inputs and on nodes with a higher index.
+type GraphSpec = Arc<Vec<NodeSpec>>;
+
+fn random_graph(rng: &mut StdRng, inputs: u32, nodes: u32, max_dependencies: u32) -> GraphSpec {
+    let graph = (0..nodes)
+        .map(|node| {
+            let dependencies = (0..rng.random_range(1..=max_dependencies))
+                .map(|_| {
+                    if node + 1 < nodes && rng.random_bool(0.5) {
+                        Dependency::Node(rng.random_range(node + 1..nodes))
+                    } else {
+                        Dependency::Input(rng.random_range(0..inputs))
+                    }
+                })
+                .collect();
+            NodeSpec {
+                dependencies,
+                conditional: rng.random_bool(0.25),
+            }
+        })
+        .collect();
+    Arc::new(graph)
+}
+
+fn combine(node: u32, values: impl IntoIterator<Item = u32>) -> u32 {
+    values
+        .into_iter()
+        .fold(node, |acc, value| acc.wrapping_mul(31).wrapping_add(value))
+}
+
+/// Evaluates all nodes from scratch, without any caching between calls.
+fn evaluate_model(graph: &[NodeSpec], inputs: &[u32]) -> Vec<u32> {
+    let mut values = vec![0; graph.len()];
+    for (node, spec) in graph.iter().enumerate().rev() {
+        let value_of = |dependency: &Dependency| match *dependency {
+            Dependency::Input(index) => inputs[index as usize],
+            Dependency::Node(index) => values[index as usize],
+        };
+        let first = value_of(&spec.dependencies[0]);
+        values[node] = if spec.conditional && first % 2 == 1 {
+            combine(node as u32, [first])
+        } else {
+            combine(node as u32, spec.dependencies.iter().map(value_of))
+        };
+    }
+    values
+}
+
+type Backend = TurboTasksBackend<FaultInjectingBackingStorage>;
+
+fn create_turbo_tasks(
+    args: &IncrementalModel,
+    fault_seed: u64,
+) -> Result<Arc<TurboTasks<Backend>>> {
+    let (backing_storage, _) = fault_injecting_backing_storage(
+        &args.cache_dir,
+        &GitVersionInfo {
+            describe: "fuzz-unversioned",
+            dirty: false,
+        },
+        false,
+        true,
+        FaultInjectionConfig {
+            seed: fault_seed,
+            commit_error_rate: args.commit_error_rate,
+            partial_commit_rate: args.partial_commit_rate,
+            ..Default::default()
+        },
+    )?;
+    Ok(TurboTasks::new(TurboTasksBackend::new(
+        BackendOptions::default(),
+        backing_storage,
+    )))
+}
+
+/// Applies random input changes, snapshots, restarts and reads to a random graph of tasks, and
+/// compares every read with a from-scratch evaluation of the graph.
+pub async fn fuzz_incremental_model(args: IncrementalModel) -> Result<()> {
+    let seed = args.seed.unwrap_or_else(rand::random);
+    println!("seed {seed}");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let graph = random_graph(&mut rng, args.inputs, args.nodes, args.max_dependencies);
+    let mut inputs = vec![0; args.inputs as usize];
+    let has_faults = args.commit_error_rate > 0.0 || args.partial_commit_rate > 0.0;
+
+    let _ = std::fs::remove_dir_all(&args.cache_dir);
+    std::fs::create_dir_all(&args.cache_dir)?;
+    let _guard = FsCleanup {
+        path: &args.cache_dir,
+    };
+
+    let input_count = args.inputs;
+    let mut tt = create_turbo_tasks(&args, rng.random())?;
+    for step in 0..args.steps {
+        let roll: f64 = rng.random();
+        if roll < args.snapshot_rate {
+            let backend_tt = tt.clone();
+            let persisted =
+                tokio::task::spawn_blocking(move || backend_tt.backend().snapshot_now()).await?;
+            println!("step {step}: snapshot (persisted: {persisted})");
+        } else if roll < args.snapshot_rate + args.restart_rate {
+            tt.stop_and_wait().await;
+            drop(tt);
+            tt = create_turbo_tasks(&args, rng.random())?;
+            println!("step {step}: restart");
+            let restored = tt
+                .run_once(async move {
+                    let states = inputs_operation(input_count)
+                        .resolve_strongly_consistent()
+                        .await?;
+                    let states = states.await?;
+                    Ok(states
+                        .values
+                        .iter()
+                        .map(|state| *state.get_untracked())
+                        .collect::<Vec<_>>())
+                })
+                .await?;
+            if has_faults {
+                // Failed commits lose input changes, so they are restored
+                for (index, &value) in inputs.iter().enumerate() {
+                    if restored[index] != value {
+                        set_input(&tt, input_count, index, value).await?;
+                    }
+                }
+            } else if restored != inputs {
+                bail!(
+                    "seed {seed}, step {step}: inputs after restart are {restored:?}, expected \
+                     {inputs:?}"
+                );
+            }
+        } else if rng.random_bool(0.5) {
+            let index = rng.random_range(0..inputs.len());
+            let value = rng.random_range(0..100);
+            inputs[index] = value;
+            set_input(&tt, input_count, index, value).await?;
+        } else {
+            let node = rng.random_range(0..args.nodes);
+            let expected = evaluate_model(&graph, &inputs)[node as usize];
+            let graph = graph.clone();
+            let actual = tt
+                .run_once(async move {
+                    let states = inputs_operation(input_count)
+                        .resolve_strongly_consistent()
+                        .await?;
+                    Ok(*node_operation(graph, states, node)
+                        .read_strongly_consistent()
+                        .await?)
+                })
+                .await
+                .with_context(|| format!("seed {seed}, step {step}: reading node {node} failed"))?;
+            if actual != expected {
+                bail!(
+                    "seed {seed}, step {step}: node {node} is {actual}, expected {expected} for \
+                     inputs {inputs:?}"
+                );
+            }
+        }
+    }
+    tt.stop_and_wait().await;
+    println!("{} steps passed", args.steps);
+    Ok(())
+}
+
+async fn set_input(
+    tt: &Arc<TurboTasks<Backend>>,
+    input_count: u32,
+    index: usize,
+    value: u32,
+) -> Result<()> {
+    tt.run_once(async move {
+        let states = inputs_operation(input_count)
+            .resolve_strongly_consistent()
+            .await?;
+        states.await?.values[index].set(value);
+        Ok(())
+    })
+    .await
+}
+
+#[turbo_tasks::value]
+struct Inputs {
+    values: Vec<State<u32>>,
+}
+
+#[turbo_tasks::function(operation)]
+fn inputs_operation(count: u32) -> Vc<Inputs> {
+    Inputs {
+        values: (0..count).map(|_| State::new(0)).collect(),
+    }
+    .cell()
+}
+
+#[turbo_tasks::function(operation)]
+fn node_operation(graph: GraphSpec, inputs: ResolvedVc<Inputs>, node: u32) -> Vc<u32> {
+    compute_node(graph, *inputs, node)
+}
+
+#[turbo_tasks::function]
+async fn read_input(inputs: Vc<Inputs>, index: u32) -> Result<Vc<u32>> {
+    let value = *inputs.await?.values[index as usize].get();
+    Ok(Vc::cell(value))
+}
+
+#[turbo_tasks::function]
+async fn compute_node(graph: GraphSpec, inputs: Vc<Inputs>, node: u32) -> Result<Vc<u32>> {
+    let spec = &graph[node as usize];
+    let mut values = Vec::with_capacity(spec.dependencies.len());
+    for dependency in &spec.dependencies {
+        let value = match *dependency {
+            Dependency::Input(index) => *read_input(inputs, index).await?,
+            Dependency::Node(index) => *compute_node(graph.clone(), inputs, index).await?,
+        };
+        values.push(value);
+        if spec.conditional && values[0] % 2 == 1 {
+            break;
+        }
+    }
+    Ok(Vc::cell(combine(node, values)))
+}
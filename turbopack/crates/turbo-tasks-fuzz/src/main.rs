@@ -1,3 +1,5 @@
+mod incremental_model;
+
 use std::{
     fs::OpenOptions,
     io::Write,
@@ -34,6 +36,9 @@ struct Cli {
 enum Commands {
     /// Continuously fuzzes the filesystem watcher until ctrl+c'd.
     FsWatcher(FsWatcher),
+    /// Fuzzes the incremental engine with random task graphs, input changes and restarts from the
+    /// persistent cache, and compares all reads with a non-incremental evaluation.
+    IncrementalModel(incremental_model::IncrementalModel),
 }
 
 #[derive(Args)]
@@ -63,6 +68,7 @@ async fn main() -> anyhow::Result<()> {
 
     match cli.command {
         Commands::FsWatcher(args) => fuzz_fs_watcher(args).await,
+        Commands::IncrementalModel(args) => incremental_model::fuzz_incremental_model(args).await,
     }
 }
 
@@ -45,6 +45,7 @@ pub fn primitive(input: TokenStream) -> TokenStream {
         },
         quote! {
             turbo_tasks::ValueType::new_with_any_serialization::<#ty>(#name)
+                .with_deterministic_hash(turbo_tasks::macro_helpers::deterministic_hash_fn!(#ty))
         },
     );
 
@@ -404,6 +404,11 @@ pub fn value(args: TokenStream, input: TokenStream) -> TokenStream {
             }
         }
     };
+    let new_value_type = quote! {
+        #new_value_type.with_deterministic_hash(
+            turbo_tasks::macro_helpers::deterministic_hash_fn!(#ident)
+        )
+    };
 
     let value_debug_impl = if inner_type.is_some() {
         // For transparent values, we defer directly to the inner type's `ValueDebug`
@@ -21,6 +21,7 @@ use turbo_tasks::{
     backend::{CellContent, TaskCollectiblesMap, TypedCellContent},
     event::{Event, EventListener},
     message_queue::CompilationEvent,
+    slow_tasks::SlowTask,
     test_helpers::with_turbo_tasks_for_testing,
     util::{SharedError, StaticOrArc},
 };
@@ -313,6 +314,10 @@ impl TurboTasksApi for VcStorage {
         unimplemented!()
     }
 
+    fn take_slow_tasks(&self) -> Vec<SlowTask> {
+        Vec::new()
+    }
+
     fn is_tracking_dependencies(&self) -> bool {
         false
     }
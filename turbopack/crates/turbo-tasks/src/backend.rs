@@ -17,12 +17,12 @@ use tracing::Span;
 use turbo_rcstr::RcStr;
 
 use crate::{
-    RawVc, ReadCellOptions, ReadOutputOptions, ReadRef, SharedReference, TaskId, TaskIdSet,
-    TraitRef, TraitTypeId, TurboTasksPanic, ValueTypeId, VcRead, VcValueTrait, VcValueType,
-    event::EventListener, macro_helpers::NativeFunction, magic_any::MagicAny,
+    InvalidationReason, RawVc, ReadCellOptions, ReadOutputOptions, ReadRef, SharedReference,
+    TaskId, TaskIdSet, TraitRef, TraitTypeId, TurboTasksPanic, ValueTypeId, VcRead, VcValueTrait,
+    VcValueType, event::EventListener, macro_helpers::NativeFunction, magic_any::MagicAny,
     manager::TurboTasksBackendApi, raw_vc::CellId, registry,
-    task::shared_reference::TypedSharedReference, task_statistics::TaskStatisticsApi,
-    triomphe_utils::unchecked_sidecast_triomphe_arc,
+    task::shared_reference::TypedSharedReference, task_graph::TaskGraph,
+    task_statistics::TaskStatisticsApi, triomphe_utils::unchecked_sidecast_triomphe_arc,
 };
 
 pub type TransientTaskRoot =
@@ -431,11 +431,52 @@ pub struct TurboTaskContextError {
     pub source: Option<TurboTasksExecutionError>,
 }
 
+/// Tasks that wait for each other and therefore would never finish. Every task waits for the
+/// next one and the last task waits for the first one.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TaskCycleError {
+    /// The function names and arguments of the tasks in the cycle.
+    pub cycle: Vec<RcStr>,
+}
+
+impl Display for TaskCycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Dependency cycle between tasks detected: ")?;
+        for task in &self.cycle {
+            write!(f, "{task} → ")?;
+        }
+        match self.cycle.first() {
+            Some(first) => write!(f, "{first}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl TaskCycleError {
+    /// Returns the cycle when the error has been caused by one, also across task boundaries.
+    pub fn find(err: &anyhow::Error) -> Option<&TaskCycleError> {
+        err.chain().find_map(|err| {
+            if let Some(cycle) = err.downcast_ref::<TaskCycleError>() {
+                Some(cycle)
+            } else if let Some(TurboTasksExecutionError::TaskCycle(cycle)) =
+                err.downcast_ref::<TurboTasksExecutionError>()
+            {
+                Some(&**cycle)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl Error for TaskCycleError {}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum TurboTasksExecutionError {
     Panic(Arc<TurboTasksPanic>),
     Error(Arc<TurboTasksError>),
     TaskContext(Arc<TurboTaskContextError>),
+    TaskCycle(Arc<TaskCycleError>),
 }
 
 impl TurboTasksExecutionError {
@@ -457,6 +498,7 @@ impl Error for TurboTasksExecutionError {
             TurboTasksExecutionError::TaskContext(context_error) => {
                 context_error.source.as_ref().map(|s| s as &dyn Error)
             }
+            TurboTasksExecutionError::TaskCycle(_cycle) => None,
         }
     }
 }
@@ -471,6 +513,7 @@ impl Display for TurboTasksExecutionError {
             TurboTasksExecutionError::TaskContext(context_error) => {
                 write!(f, "Execution of {} failed", context_error.task)
             }
+            TurboTasksExecutionError::TaskCycle(cycle) => write!(f, "{cycle}"),
         }
     }
 }
@@ -480,6 +523,9 @@ impl<'l> From<&'l (dyn std::error::Error + 'static)> for TurboTasksExecutionErro
         if let Some(err) = err.downcast_ref::<TurboTasksExecutionError>() {
             return err.clone();
         }
+        if let Some(err) = err.downcast_ref::<TaskCycleError>() {
+            return TurboTasksExecutionError::TaskCycle(Arc::new(err.clone()));
+        }
         let message = err.to_string();
         let source = err.source().map(|source| source.into());
 
@@ -513,6 +559,28 @@ pub trait Backend: Sync + Send {
 
     fn invalidate_task(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi<Self>);
 
+    /// Like [`Backend::invalidate_task`], but with the reason of the invalidation. Backends can
+    /// record it to explain why tasks have been re-executed.
+    fn invalidate_task_with_reason(
+        &self,
+        task: TaskId,
+        _reason: &dyn InvalidationReason,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+        self.invalidate_task(task, turbo_tasks);
+    }
+
+    /// Returns the tasks reachable from `root` via child edges, up to `max_depth` levels deep,
+    /// including their dependencies. Backends that don't keep the graph return an empty one.
+    fn task_graph(
+        &self,
+        _root: TaskId,
+        _max_depth: usize,
+        _turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) -> TaskGraph {
+        TaskGraph::default()
+    }
+
     fn invalidate_tasks(&self, tasks: &[TaskId], turbo_tasks: &dyn TurboTasksBackendApi<Self>);
     fn invalidate_tasks_set(&self, tasks: &TaskIdSet, turbo_tasks: &dyn TurboTasksBackendApi<Self>);
 
@@ -525,6 +593,12 @@ pub trait Backend: Sync + Send {
 
     fn get_task_description(&self, task: TaskId) -> String;
 
+    /// Like [`Backend::get_task_description`], but also includes the arguments of the task when
+    /// the backend knows them.
+    fn get_task_description_with_arguments(&self, task: TaskId) -> String {
+        self.get_task_description(task)
+    }
+
     fn try_start_task_execution<'a>(
         &'a self,
         task: TaskId,
@@ -69,17 +69,20 @@ pub mod panic_hooks;
 pub mod parallel;
 pub mod persisted_graph;
 pub mod primitives;
+mod priority;
 mod raw_vc;
 mod read_options;
 mod read_ref;
 pub mod registry;
 pub mod scope;
 mod serialization_invalidation;
+pub mod slow_tasks;
 pub mod small_duration;
 mod spawn;
 mod state;
 pub mod task;
 mod task_execution_reason;
+pub mod task_graph;
 pub mod task_statistics;
 pub mod trace;
 mod trait_ref;
@@ -114,6 +117,7 @@ pub use manager::{
     run_once_with_reason, trait_call, turbo_tasks, turbo_tasks_scope,
 };
 pub use output::OutputContent;
+pub use priority::TaskPriority;
 pub use raw_vc::{CellId, RawVc, ReadRawVcFuture, ResolveTypeError};
 pub use read_options::{ReadCellOptions, ReadOutputOptions};
 pub use read_ref::ReadRef;
@@ -304,5 +308,7 @@ pub use turbo_tasks_macros::value_trait;
 pub type TaskIdSet = AutoSet<TaskId, BuildHasherDefault<FxHasher>, 2>;
 
 pub mod test_helpers {
-    pub use super::manager::{current_task_for_testing, with_turbo_tasks_for_testing};
+    pub use super::manager::{
+        current_task_for_testing, current_task_priority_for_testing, with_turbo_tasks_for_testing,
+    };
 }
@@ -1,18 +1,22 @@
 //! Runtime helpers for [turbo-tasks-macro].
 
+use std::{any::Any, marker::PhantomData};
+
 pub use async_trait::async_trait;
 pub use once_cell::sync::{Lazy, OnceCell};
 use rustc_hash::FxHashMap;
 pub use serde;
 pub use shrink_to_fit;
 pub use tracing;
+use turbo_tasks_hash::{DeterministicHash, DeterministicHasher, Xxh3Hash64Hasher};
 
 use crate::{
     FxDashMap, NonLocalValue, RawVc, TaskInput, TaskPersistence, TraitTypeId, ValueType,
     ValueTypeId, Vc, debug::ValueDebugFormatString, task::TaskOutput,
+    value_type::AnyDeterministicHashFn,
 };
 pub use crate::{
-    global_name, inventory_submit,
+    deterministic_hash_fn, global_name, inventory_submit,
     magic_any::MagicAny,
     manager::{find_cell_by_type, spawn_detached_for_testing},
     native_function::{
@@ -60,6 +64,57 @@ where
 
 pub fn assert_argument_is_non_local_value<Argument: NonLocalValue>() {}
 
+/// Returns the [`AnyDeterministicHashFn`] of a value type, or `None` when the type doesn't
+/// implement [`DeterministicHash`].
+#[macro_export]
+macro_rules! deterministic_hash_fn {
+    ($ty:ty) => {{
+        use $crate::macro_helpers::{WithDeterministicHash, WithoutDeterministicHash};
+        (&&$crate::macro_helpers::DeterministicHashProbe::<$ty>::new()).deterministic_hash_fn()
+    }};
+}
+
+/// Finds the [`DeterministicHash`] implementation of a value type using autoref specialization:
+/// [`WithDeterministicHash`] is implemented for `&DeterministicHashProbe<T>` when `T` implements
+/// [`DeterministicHash`], which takes precedence over [`WithoutDeterministicHash`].
+pub struct DeterministicHashProbe<T>(PhantomData<T>);
+
+impl<T> DeterministicHashProbe<T> {
+    #[allow(clippy::new_without_default)]
+    pub const fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+pub trait WithDeterministicHash {
+    fn deterministic_hash_fn(&self) -> Option<AnyDeterministicHashFn>;
+}
+
+impl<T: DeterministicHash + Any + Send + Sync> WithDeterministicHash
+    for &DeterministicHashProbe<T>
+{
+    fn deterministic_hash_fn(&self) -> Option<AnyDeterministicHashFn> {
+        Some(|value| {
+            let value = value
+                .downcast_ref::<T>()
+                .expect("deterministic hash called with invalid type");
+            let mut hasher = Xxh3Hash64Hasher::new();
+            value.deterministic_hash(&mut hasher);
+            hasher.finish()
+        })
+    }
+}
+
+pub trait WithoutDeterministicHash {
+    fn deterministic_hash_fn(&self) -> Option<AnyDeterministicHashFn>;
+}
+
+impl<T> WithoutDeterministicHash for DeterministicHashProbe<T> {
+    fn deterministic_hash_fn(&self) -> Option<AnyDeterministicHashFn> {
+        None
+    }
+}
+
 #[macro_export]
 macro_rules! stringify_path {
     ($path:path) => {
@@ -5,7 +5,7 @@ use std::{
     mem::take,
     pin::Pin,
     sync::{
-        Arc, Mutex, RwLock, Weak,
+        Arc, Mutex, OnceLock, RwLock, Weak,
         atomic::{AtomicBool, AtomicUsize, Ordering},
     },
     time::{Duration, Instant},
@@ -20,9 +20,9 @@ use tokio_util::task::TaskTracker;
 use tracing::{Instrument, instrument};
 
 use crate::{
-    Completion, InvalidationReason, InvalidationReasonSet, OutputContent, ReadCellOptions,
-    ReadOutputOptions, ResolvedVc, SharedReference, TaskId, TraitMethod, ValueTypeId, Vc, VcRead,
-    VcValueTrait, VcValueType,
+    Completion, FxDashMap, InvalidationReason, InvalidationReasonSet, OutputContent,
+    ReadCellOptions, ReadOutputOptions, ResolvedVc, SharedReference, TaskId, TaskPriority,
+    TraitMethod, ValueTypeId, Vc, VcRead, VcValueTrait, VcValueType,
     backend::{
         Backend, CachedTaskType, CellContent, TaskCollectiblesMap, TaskExecutionSpec,
         TransientTaskType, TurboTasksExecutionError, TypedCellContent,
@@ -34,10 +34,13 @@ use crate::{
     macro_helpers::NativeFunction,
     magic_any::MagicAny,
     message_queue::{CompilationEvent, CompilationEventQueue},
+    priority::PriorityScheduler,
     raw_vc::{CellId, RawVc},
     registry,
     serialization_invalidation::SerializationInvalidator,
+    slow_tasks::{SlowTask, SlowTaskWatchdog, SlowTaskWatchdogOptions},
     task::local_task::{LocalTask, LocalTaskSpec, LocalTaskType},
+    task_graph::TaskGraph,
     task_statistics::TaskStatisticsApi,
     trace::TraceRawVcs,
     util::{IdFactory, StaticOrArc},
@@ -181,6 +184,11 @@ pub trait TurboTasksApi: TurboTasksCallApi + Sync + Send {
     ) -> Receiver<Arc<dyn CompilationEvent>>;
     fn send_compilation_event(&self, event: Arc<dyn CompilationEvent>);
 
+    /// Returns the slow task executions that the watchdog has noticed since the last call, when
+    /// it has been started with [`SlowTaskWatchdogOptions::collect_reports`]. This must not be
+    /// called from memoized functions, as their result wouldn't change when new reports arrive.
+    fn take_slow_tasks(&self) -> Vec<SlowTask>;
+
     // Returns true if TurboTasks is configured to track dependencies.
     fn is_tracking_dependencies(&self) -> bool;
 }
@@ -250,6 +258,9 @@ pub trait TurboTasksBackendApi<B: Backend + 'static>: TurboTasksCallApi + Sync +
 
     /// Returns a reference to the backend.
     fn backend(&self) -> &B;
+
+    /// Returns a snapshot of the tasks reachable from `root`, for debugging. See [`TaskGraph`].
+    fn task_graph(&self, root: TaskId, max_depth: usize) -> TaskGraph;
 }
 
 #[allow(clippy::manual_non_exhaustive)]
@@ -356,6 +367,14 @@ pub struct TurboTasks<B: Backend + 'static> {
     event_background_done: Event,
     program_start: Instant,
     compilation_events: CompilationEventQueue,
+    priority_scheduler: Arc<PriorityScheduler>,
+    /// Non-default priorities of root tasks and of tasks that can be invalidated from outside of a
+    /// task, e.g. by a file watcher. These tasks are scheduled outside of any task, so they can't
+    /// inherit a priority and use the one they have been executed with before.
+    task_priorities: FxDashMap<TaskId, TaskPriority>,
+    /// Tracks the running task executions when
+    /// [`start_slow_task_watchdog`][TurboTasks::start_slow_task_watchdog] has been called.
+    slow_task_watchdog: OnceLock<Arc<SlowTaskWatchdog>>,
 }
 
 /// Information about a non-local task. A non-local task can contain multiple "local" tasks, which
@@ -370,6 +389,9 @@ struct CurrentTaskState {
     task_id: Option<TaskId>,
     execution_id: ExecutionId,
 
+    /// The priority that tasks scheduled by this task inherit.
+    priority: TaskPriority,
+
     /// True if the current task has state in cells
     stateful: bool,
 
@@ -391,10 +413,11 @@ struct CurrentTaskState {
 }
 
 impl CurrentTaskState {
-    fn new(task_id: TaskId, execution_id: ExecutionId) -> Self {
+    fn new(task_id: TaskId, execution_id: ExecutionId, priority: TaskPriority) -> Self {
         Self {
             task_id: Some(task_id),
             execution_id,
+            priority,
             stateful: false,
             has_invalidator: false,
             cell_counters: Some(AutoMap::default()),
@@ -403,10 +426,11 @@ impl CurrentTaskState {
         }
     }
 
-    fn new_temporary(execution_id: ExecutionId) -> Self {
+    fn new_temporary(execution_id: ExecutionId, priority: TaskPriority) -> Self {
         Self {
             task_id: None,
             execution_id,
+            priority,
             stateful: false,
             has_invalidator: false,
             cell_counters: None,
@@ -489,6 +513,9 @@ impl<B: Backend + 'static> TurboTasks<B> {
             }),
             program_start: Instant::now(),
             compilation_events: CompilationEventQueue::default(),
+            priority_scheduler: Arc::new(PriorityScheduler::new()),
+            task_priorities: FxDashMap::default(),
+            slow_task_watchdog: OnceLock::new(),
         });
         this.backend.startup(&*this);
         this
@@ -500,6 +527,21 @@ impl<B: Backend + 'static> TurboTasks<B> {
 
     /// Creates a new root task
     pub fn spawn_root_task<T, F, Fut>(&self, functor: F) -> TaskId
+    where
+        T: ?Sized,
+        F: Fn() -> Fut + Send + Sync + Clone + 'static,
+        Fut: Future<Output = Result<Vc<T>>> + Send,
+    {
+        self.spawn_root_task_with_priority(TaskPriority::default(), functor)
+    }
+
+    /// Creates a new root task. The root task and all tasks scheduled by it are executed with the
+    /// given priority.
+    pub fn spawn_root_task_with_priority<T, F, Fut>(
+        &self,
+        priority: TaskPriority,
+        functor: F,
+    ) -> TaskId
     where
         T: ?Sized,
         F: Fn() -> Fut + Send + Sync + Clone + 'static,
@@ -515,11 +557,15 @@ impl<B: Backend + 'static> TurboTasks<B> {
             })),
             self,
         );
-        self.schedule(id);
+        if priority != TaskPriority::default() {
+            self.task_priorities.insert(id, priority);
+        }
+        self.schedule_with_priority(id, priority);
         id
     }
 
     pub fn dispose_root_task(&self, task_id: TaskId) {
+        self.task_priorities.remove(&task_id);
         self.backend.dispose_root_task(task_id, self);
     }
 
@@ -557,16 +603,29 @@ impl<B: Backend + 'static> TurboTasks<B> {
         rx.await?
     }
 
-    #[tracing::instrument(level = "trace", skip_all, name = "turbo_tasks::run")]
     pub async fn run<T: TraceRawVcs + Send + 'static>(
         &self,
         future: impl Future<Output = Result<T>> + Send + 'static,
+    ) -> Result<T, TurboTasksExecutionError> {
+        self.run_with_priority(TaskPriority::default(), future)
+            .await
+    }
+
+    /// Like [`TurboTasks::run`], but tasks scheduled by the future, e.g. by reading outputs of
+    /// dirty tasks, are executed with the given priority.
+    #[tracing::instrument(level = "trace", skip_all, name = "turbo_tasks::run")]
+    pub async fn run_with_priority<T: TraceRawVcs + Send + 'static>(
+        &self,
+        priority: TaskPriority,
+        future: impl Future<Output = Result<T>> + Send + 'static,
     ) -> Result<T, TurboTasksExecutionError> {
         self.begin_foreground_job();
         // it's okay for execution ids to overflow and wrap, they're just used for an assert
         let execution_id = self.execution_id_factory.wrapping_get();
-        let current_task_state =
-            Arc::new(RwLock::new(CurrentTaskState::new_temporary(execution_id)));
+        let current_task_state = Arc::new(RwLock::new(CurrentTaskState::new_temporary(
+            execution_id,
+            priority,
+        )));
 
         let result = TURBO_TASKS
             .scope(
@@ -704,19 +763,59 @@ impl<B: Backend + 'static> TurboTasks<B> {
         self.schedule_local_task(task_type, persistence)
     }
 
+    /// The priority of a task that is about to be scheduled. Tasks inherit the priority of the task
+    /// that schedules them. Tasks that are scheduled outside of any task, e.g. after a file watcher
+    /// invalidated them, use the priority they have been executed with before.
+    fn priority_for(&self, task_id: TaskId) -> TaskPriority {
+        // Root tasks always keep their own priority. They are transient, so persistent tasks don't
+        // need the lookup.
+        if task_id.is_transient()
+            && let Some(priority) = self.task_priorities.get(&task_id)
+        {
+            return *priority;
+        }
+        CURRENT_TASK_STATE
+            .try_with(|ts| ts.read().unwrap().priority)
+            .unwrap_or_else(|_| {
+                self.task_priorities
+                    .get(&task_id)
+                    .map_or_else(TaskPriority::default, |priority| *priority)
+            })
+    }
+
+    /// Remembers the priority of a task that can be invalidated from outside of a task.
+    fn remember_priority(&self, task_id: TaskId, priority: TaskPriority) {
+        if priority == TaskPriority::default() {
+            self.task_priorities.remove(&task_id);
+        } else {
+            self.task_priorities.insert(task_id, priority);
+        }
+    }
+
     #[track_caller]
     pub(crate) fn schedule(&self, task_id: TaskId) {
+        self.schedule_with_priority(task_id, self.priority_for(task_id));
+    }
+
+    #[track_caller]
+    fn schedule_with_priority(&self, task_id: TaskId, priority: TaskPriority) {
         self.begin_foreground_job();
         self.scheduled_tasks.fetch_add(1, Ordering::AcqRel);
 
+        let pending_task = self.priority_scheduler.task_scheduled(priority);
+
         let this = self.pin();
         let future = async move {
+            pending_task.wait_for_turn().await;
             let mut schedule_again = true;
             while schedule_again {
                 // it's okay for execution ids to overflow and wrap, they're just used for an assert
                 let execution_id = this.execution_id_factory.wrapping_get();
-                let current_task_state =
-                    Arc::new(RwLock::new(CurrentTaskState::new(task_id, execution_id)));
+                let current_task_state = Arc::new(RwLock::new(CurrentTaskState::new(
+                    task_id,
+                    execution_id,
+                    priority,
+                )));
                 let single_execution_future = async {
                     if this.stopped.load(Ordering::Acquire) {
                         this.backend.task_execution_canceled(task_id, &*this);
@@ -728,6 +827,10 @@ impl<B: Backend + 'static> TurboTasks<B> {
                     else {
                         return false;
                     };
+                    let _running_task = this
+                        .slow_task_watchdog
+                        .get()
+                        .map(|watchdog| watchdog.execution_started(execution_id, task_id, &span));
 
                     async {
                         let (result, duration, alloc_info) = CaptureFuture::new(future).await;
@@ -748,6 +851,9 @@ impl<B: Backend + 'static> TurboTasks<B> {
                             stateful,
                             has_invalidator,
                         } = this.finish_current_task_state();
+                        if stateful || has_invalidator {
+                            this.remember_priority(task_id, priority);
+                        }
                         let cell_counters = CURRENT_TASK_STATE
                             .with(|ts| ts.write().unwrap().cell_counters.take().unwrap());
                         this.backend.task_execution_completed(
@@ -1148,6 +1254,43 @@ impl<B: Backend + 'static> TurboTasks<B> {
     pub fn backend(&self) -> &B {
         &self.backend
     }
+
+    /// Starts a thread that periodically looks for task executions that have been running longer
+    /// than the threshold. They are logged with the function name and arguments in the span of
+    /// the execution and sent as compilation events. Calling this again has no effect.
+    pub fn start_slow_task_watchdog(&self, options: SlowTaskWatchdogOptions) {
+        let watchdog = Arc::new(SlowTaskWatchdog::new(options));
+        if self.slow_task_watchdog.set(watchdog.clone()).is_err() {
+            return;
+        }
+        let this = self.this.clone();
+        std::thread::Builder::new()
+            .name("turbo-tasks-slow-task-watchdog".to_string())
+            .spawn(move || {
+                loop {
+                    std::thread::sleep(watchdog.check_interval());
+                    let Some(this) = this.upgrade() else {
+                        return;
+                    };
+                    if this.stopped.load(Ordering::Acquire) {
+                        return;
+                    }
+                    let slow_tasks = watchdog.find_slow_tasks(|task_id| {
+                        this.backend.get_task_description_with_arguments(task_id)
+                    });
+                    for (slow_task, span) in slow_tasks {
+                        tracing::warn!(
+                            parent: &span,
+                            task = %slow_task.task,
+                            duration = ?slow_task.duration,
+                            "slow task execution"
+                        );
+                        this.send_compilation_event(Arc::new(slow_task));
+                    }
+                }
+            })
+            .expect("failed to spawn the slow task watchdog thread");
+    }
 }
 
 struct FinishedTaskState {
@@ -1235,9 +1378,10 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
     fn invalidate_with_reason(&self, task: TaskId, reason: StaticOrArc<dyn InvalidationReason>) {
         {
             let (_, reason_set) = &mut *self.aggregated_update.lock().unwrap();
-            reason_set.insert(reason);
+            reason_set.insert(reason.clone());
         }
-        self.backend.invalidate_task(task, self);
+        self.backend
+            .invalidate_task_with_reason(task, &*reason, self);
     }
 
     fn invalidate_serialization(&self, task: TaskId) {
@@ -1419,6 +1563,13 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
         }
     }
 
+    fn take_slow_tasks(&self) -> Vec<SlowTask> {
+        self.slow_task_watchdog
+            .get()
+            .map(|watchdog| watchdog.take_reports())
+            .unwrap_or_default()
+    }
+
     fn is_tracking_dependencies(&self) -> bool {
         self.backend.is_tracking_dependencies()
     }
@@ -1432,6 +1583,10 @@ impl<B: Backend + 'static> TurboTasksBackendApi<B> for TurboTasks<B> {
         &self.backend
     }
 
+    fn task_graph(&self, root: TaskId, max_depth: usize) -> TaskGraph {
+        self.backend.task_graph(root, max_depth, self)
+    }
+
     #[track_caller]
     fn schedule_backend_background_job(&self, job: B::BackendJob) {
         self.schedule_background_job(async move |this| {
@@ -1611,6 +1766,7 @@ pub fn with_turbo_tasks_for_testing<T>(
             Arc::new(RwLock::new(CurrentTaskState::new(
                 current_task,
                 execution_id,
+                TaskPriority::default(),
             ))),
             f,
         ),
@@ -1629,6 +1785,10 @@ pub fn current_task_for_testing() -> Option<TaskId> {
     CURRENT_TASK_STATE.with(|ts| ts.read().unwrap().task_id)
 }
 
+pub fn current_task_priority_for_testing() -> TaskPriority {
+    CURRENT_TASK_STATE.with(|ts| ts.read().unwrap().priority)
+}
+
 /// Marks the current task as dirty when restored from filesystem cache.
 pub fn mark_session_dependent() {
     with_turbo_tasks(|tt| {
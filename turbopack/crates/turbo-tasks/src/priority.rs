@@ -0,0 +1,173 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use crate::event::Event;
+
+/// How urgently a task should be executed. Tasks inherit the priority of the task or root that
+/// scheduled them.
+///
+/// Variants are ordered from the most to the least urgent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TaskPriority {
+    /// Work the user is actively waiting for, e.g. the HMR update of the page that is open in
+    /// the browser.
+    Interactive,
+    #[default]
+    Normal,
+    /// Work nobody is waiting for, e.g. warming up routes that haven't been requested yet.
+    Background,
+}
+
+impl TaskPriority {
+    const COUNT: usize = 3;
+
+    const ALL: [TaskPriority; Self::COUNT] = [
+        TaskPriority::Interactive,
+        TaskPriority::Normal,
+        TaskPriority::Background,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Lets scheduled tasks start in the order of their priority.
+///
+/// All tasks are spawned on the tokio runtime right away. Before a task starts executing, it waits
+/// until no task with a higher priority is waiting to start. Tasks of the highest pending priority
+/// never wait, so this can't deadlock, even when a higher priority task depends on a lower
+/// priority one.
+pub(crate) struct PriorityScheduler {
+    /// The number of scheduled tasks per priority that haven't started executing yet.
+    pending: [AtomicUsize; TaskPriority::COUNT],
+    /// Notified when there are no more pending tasks of a priority.
+    drained: [Event; TaskPriority::COUNT],
+}
+
+impl PriorityScheduler {
+    pub fn new() -> Self {
+        Self {
+            pending: Default::default(),
+            drained: std::array::from_fn(|_| {
+                Event::new(|| || "PriorityScheduler::drained".to_string())
+            }),
+        }
+    }
+
+    /// Registers a task that has been scheduled. It's pending until the returned [`PendingTask`]
+    /// is dropped, either after [`PendingTask::wait_for_turn`] or when the task is dropped before
+    /// it has been started.
+    pub fn task_scheduled(self: &Arc<Self>, priority: TaskPriority) -> PendingTask {
+        self.pending[priority.index()].fetch_add(1, Ordering::AcqRel);
+        PendingTask {
+            scheduler: self.clone(),
+            priority,
+        }
+    }
+
+    fn task_started(&self, priority: TaskPriority) {
+        if self.pending[priority.index()].fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.drained[priority.index()].notify(usize::MAX);
+        }
+    }
+}
+
+/// A scheduled task that hasn't started executing yet.
+pub(crate) struct PendingTask {
+    scheduler: Arc<PriorityScheduler>,
+    priority: TaskPriority,
+}
+
+impl PendingTask {
+    /// Waits until no task with a higher priority is waiting to start.
+    pub async fn wait_for_turn(self) {
+        for higher in &TaskPriority::ALL[..self.priority.index()] {
+            let index = higher.index();
+            while self.scheduler.pending[index].load(Ordering::Acquire) > 0 {
+                let listener = self.scheduler.drained[index].listen();
+                if self.scheduler.pending[index].load(Ordering::Acquire) == 0 {
+                    break;
+                }
+                listener.await;
+            }
+        }
+    }
+}
+
+impl Drop for PendingTask {
+    fn drop(&mut self) {
+        self.scheduler.task_started(self.priority);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_lower_priority_waits() {
+        let scheduler = Arc::new(PriorityScheduler::new());
+        let interactive = scheduler.task_scheduled(TaskPriority::Interactive);
+        let normal = tokio::spawn(
+            scheduler
+                .task_scheduled(TaskPriority::Normal)
+                .wait_for_turn(),
+        );
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!normal.is_finished());
+
+        // the highest priority never waits
+        timeout(Duration::from_secs(1), interactive.wait_for_turn())
+            .await
+            .unwrap();
+        timeout(Duration::from_secs(1), normal)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_background_waits_for_normal() {
+        let scheduler = Arc::new(PriorityScheduler::new());
+        let normal = scheduler.task_scheduled(TaskPriority::Normal);
+        let background = tokio::spawn(
+            scheduler
+                .task_scheduled(TaskPriority::Background)
+                .wait_for_turn(),
+        );
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!background.is_finished());
+
+        timeout(Duration::from_secs(1), normal.wait_for_turn())
+            .await
+            .unwrap();
+        timeout(Duration::from_secs(1), background)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dropped_task_is_no_longer_pending() {
+        let scheduler = Arc::new(PriorityScheduler::new());
+        let interactive = scheduler.task_scheduled(TaskPriority::Interactive);
+        let normal = scheduler.task_scheduled(TaskPriority::Normal);
+        drop(interactive);
+        timeout(Duration::from_secs(1), normal.wait_for_turn())
+            .await
+            .unwrap();
+        assert!(
+            scheduler
+                .pending
+                .iter()
+                .all(|pending| pending.load(Ordering::Acquire) == 0)
+        );
+    }
+}
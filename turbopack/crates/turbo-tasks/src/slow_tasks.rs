@@ -0,0 +1,205 @@
+//! A watchdog that notices task executions that take longer than a threshold, e.g. because a
+//! function is stuck in a loop or blocks on I/O.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+use tracing::Span;
+
+use crate::{
+    FxDashMap, TaskId,
+    id::ExecutionId,
+    message_queue::{CompilationEvent, Severity},
+};
+
+/// The maximum number of slow tasks that are kept until they are taken with
+/// [`TurboTasksApi::take_slow_tasks`][crate::TurboTasksApi::take_slow_tasks].
+const MAX_REPORTS: usize = 100;
+
+#[derive(Clone, Debug)]
+pub struct SlowTaskWatchdogOptions {
+    /// Task executions that run longer than this are reported. They are noticed after at most
+    /// 1.5 times the threshold.
+    pub threshold: Duration,
+    /// Keeps the reported tasks until they are taken with
+    /// [`TurboTasksApi::take_slow_tasks`][crate::TurboTasksApi::take_slow_tasks], so they can be
+    /// surfaced as issues. Otherwise they are only logged.
+    pub collect_reports: bool,
+}
+
+/// A task execution that has been running for longer than the threshold of the watchdog.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlowTask {
+    /// The function name and arguments of the task.
+    pub task: String,
+    /// How long the task had been running when it was noticed.
+    pub duration: Duration,
+}
+
+impl CompilationEvent for SlowTask {
+    fn type_name(&self) -> &'static str {
+        "SlowTaskEvent"
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    fn message(&self) -> String {
+        format!(
+            "{} has been running for {:.1}s",
+            self.task,
+            self.duration.as_secs_f64()
+        )
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+struct RunningTask {
+    task_id: TaskId,
+    start: Instant,
+    span: Span,
+    reported: bool,
+}
+
+pub(crate) struct SlowTaskWatchdog {
+    options: SlowTaskWatchdogOptions,
+    running: FxDashMap<ExecutionId, RunningTask>,
+    reports: Mutex<Vec<SlowTask>>,
+}
+
+impl SlowTaskWatchdog {
+    pub fn new(options: SlowTaskWatchdogOptions) -> Self {
+        Self {
+            options,
+            running: FxDashMap::default(),
+            reports: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn check_interval(&self) -> Duration {
+        (self.options.threshold / 2).max(Duration::from_millis(1))
+    }
+
+    /// Tracks the execution until the returned guard is dropped.
+    pub fn execution_started(
+        self: &Arc<Self>,
+        execution_id: ExecutionId,
+        task_id: TaskId,
+        span: &Span,
+    ) -> RunningTaskGuard {
+        self.running.insert(
+            execution_id,
+            RunningTask {
+                task_id,
+                start: Instant::now(),
+                span: span.clone(),
+                reported: false,
+            },
+        );
+        RunningTaskGuard {
+            watchdog: self.clone(),
+            execution_id,
+        }
+    }
+
+    /// Returns the executions that have exceeded the threshold since the last check, together
+    /// with the span of the execution. Every execution is only returned once.
+    pub fn find_slow_tasks(&self, describe: impl Fn(TaskId) -> String) -> Vec<(SlowTask, Span)> {
+        let mut new_slow_tasks = Vec::new();
+        for mut entry in self.running.iter_mut() {
+            let duration = entry.start.elapsed();
+            if entry.reported || duration < self.options.threshold {
+                continue;
+            }
+            entry.reported = true;
+            new_slow_tasks.push((entry.task_id, duration, entry.span.clone()));
+        }
+        // The tasks are described after iterating, to not block the executions while the map
+        // is locked
+        let slow_tasks = new_slow_tasks
+            .into_iter()
+            .map(|(task_id, duration, span)| {
+                let slow_task = SlowTask {
+                    task: describe(task_id),
+                    duration,
+                };
+                (slow_task, span)
+            })
+            .collect::<Vec<_>>();
+        if self.options.collect_reports && !slow_tasks.is_empty() {
+            let mut reports = self.reports.lock().unwrap();
+            let remaining = MAX_REPORTS.saturating_sub(reports.len());
+            reports.extend(
+                slow_tasks
+                    .iter()
+                    .take(remaining)
+                    .map(|(slow_task, _)| slow_task.clone()),
+            );
+        }
+        slow_tasks
+    }
+
+    pub fn take_reports(&self) -> Vec<SlowTask> {
+        std::mem::take(&mut *self.reports.lock().unwrap())
+    }
+}
+
+pub(crate) struct RunningTaskGuard {
+    watchdog: Arc<SlowTaskWatchdog>,
+    execution_id: ExecutionId,
+}
+
+impl Drop for RunningTaskGuard {
+    fn drop(&mut self) {
+        self.watchdog.running.remove(&self.execution_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_slow_task_once() {
+        let watchdog = Arc::new(SlowTaskWatchdog::new(SlowTaskWatchdogOptions {
+            threshold: Duration::from_millis(10),
+            collect_reports: true,
+        }));
+        let task_id = TaskId::try_from(1).unwrap();
+        let guard =
+            watchdog.execution_started(ExecutionId::try_from(1).unwrap(), task_id, &Span::none());
+        let fast_guard = watchdog.execution_started(
+            ExecutionId::try_from(2).unwrap(),
+            TaskId::try_from(2).unwrap(),
+            &Span::none(),
+        );
+        std::thread::sleep(Duration::from_millis(20));
+        // Finished executions and executions below the threshold are not reported
+        drop(fast_guard);
+        let _recent_guard = watchdog.execution_started(
+            ExecutionId::try_from(3).unwrap(),
+            TaskId::try_from(3).unwrap(),
+            &Span::none(),
+        );
+
+        let slow_tasks = watchdog.find_slow_tasks(|task_id| task_id.to_string());
+        assert_eq!(slow_tasks.len(), 1);
+        assert_eq!(slow_tasks[0].0.task, task_id.to_string());
+        assert!(slow_tasks[0].0.duration >= Duration::from_millis(10));
+        // Every execution is only reported once
+        assert!(watchdog.find_slow_tasks(|_| unreachable!()).is_empty());
+
+        drop(guard);
+        assert_eq!(watchdog.take_reports().len(), 1);
+        assert!(watchdog.take_reports().is_empty());
+        assert_eq!(watchdog.running.len(), 1);
+    }
+}
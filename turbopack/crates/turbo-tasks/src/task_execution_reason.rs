@@ -9,6 +9,7 @@ pub enum TaskExecutionReason {
     ActivateInitial,
     Connect,
     Stale,
+    VerifyDeterminism,
 }
 
 impl TaskExecutionReason {
@@ -23,6 +24,7 @@ impl TaskExecutionReason {
             TaskExecutionReason::ActivateInitial => "activate_initial",
             TaskExecutionReason::Connect => "connect",
             TaskExecutionReason::Stale => "stale",
+            TaskExecutionReason::VerifyDeterminism => "verify_determinism",
         }
     }
 }
@@ -0,0 +1,196 @@
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::TaskId;
+
+/// A snapshot of the part of the task graph that is reachable from a root task, returned by
+/// [`crate::TurboTasksBackendApi::task_graph`]. Meant for debugging why a task depends on
+/// something.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskGraph {
+    pub root: Option<TaskId>,
+    pub tasks: Vec<TaskGraphNode>,
+    pub edges: Vec<TaskGraphEdge>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskGraphNode {
+    pub id: TaskId,
+    /// The name of the function, or a description for root and once tasks.
+    pub function: String,
+    /// The debug representation of the `self` argument and the arguments.
+    pub arguments: Option<String>,
+    pub dirty: bool,
+    pub in_progress: bool,
+    pub aggregation_number: u32,
+    /// The number of dirty tasks in the subgraph aggregated by this task.
+    pub dirty_containers: i32,
+    pub collectibles: Vec<TaskGraphCollectible>,
+    /// False when the children of the task have been omitted, e.g. due to the depth limit.
+    pub expanded: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskGraphCollectible {
+    pub collectible_type: String,
+    pub count: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskGraphEdge {
+    pub from: TaskId,
+    pub to: TaskId,
+    pub kind: TaskGraphEdgeKind,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum TaskGraphEdgeKind {
+    /// The task called the other task.
+    Child,
+    /// The task read the output of the other task.
+    OutputDependency,
+    /// The task read a cell of the other task.
+    CellDependency { value_type: String, index: u32 },
+    /// The task read the collectibles emitted in the subgraph of the other task.
+    CollectiblesDependency { collectible_type: String },
+}
+
+impl TaskGraph {
+    /// Renders the graph in the Graphviz DOT format. Dirty tasks are filled red and tasks in
+    /// progress yellow. Children are solid edges, dependencies are dashed.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph tasks {\n  node [shape=box];\n");
+        for task in &self.tasks {
+            let mut label = escape_dot(&task.function);
+            if let Some(arguments) = &task.arguments {
+                label.push_str("\\n");
+                label.push_str(&escape_dot(arguments));
+            }
+            for collectible in &task.collectibles {
+                write!(
+                    label,
+                    "\\nemits {}x {}",
+                    collectible.count,
+                    escape_dot(&collectible.collectible_type)
+                )
+                .unwrap();
+            }
+            let mut attributes = format!("label=\"{label}\"");
+            if task.dirty {
+                attributes.push_str(", style=filled, fillcolor=\"#f4b6b6\"");
+            } else if task.in_progress {
+                attributes.push_str(", style=filled, fillcolor=\"#f4e8b6\"");
+            }
+            if !task.expanded {
+                attributes.push_str(", peripheries=2");
+            }
+            writeln!(dot, "  t{} [{attributes}];", *task.id).unwrap();
+        }
+        for edge in &self.edges {
+            let attributes = match &edge.kind {
+                TaskGraphEdgeKind::Child => String::new(),
+                TaskGraphEdgeKind::OutputDependency => " [style=dashed, label=\"output\"]".into(),
+                TaskGraphEdgeKind::CellDependency { value_type, index } => format!(
+                    " [style=dashed, label=\"{} #{index}\"]",
+                    escape_dot(value_type)
+                ),
+                TaskGraphEdgeKind::CollectiblesDependency { collectible_type } => format!(
+                    " [style=dashed, color=blue, label=\"{}\"]",
+                    escape_dot(collectible_type)
+                ),
+            };
+            writeln!(dot, "  t{} -> t{}{attributes};", *edge.from, *edge.to).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn escape_dot(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Writes the graph to a file. Uses the Graphviz DOT format when the file has a `.dot` or `.gv`
+/// extension and JSON otherwise.
+pub fn write_task_graph(graph: &TaskGraph, path: &Path) -> Result<()> {
+    let file =
+        fs::File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    let is_dot = path
+        .extension()
+        .is_some_and(|extension| extension == "dot" || extension == "gv");
+    if is_dot {
+        writer.write_all(graph.to_dot().as_bytes())?;
+    } else {
+        serde_json::to_writer_pretty(&mut writer, graph)?;
+    }
+    writer
+        .flush()
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: u32) -> TaskId {
+        TaskId::try_from(id).unwrap()
+    }
+
+    fn node(id: u32, function: &str, dirty: bool) -> TaskGraphNode {
+        TaskGraphNode {
+            id: task(id),
+            function: function.to_string(),
+            arguments: None,
+            dirty,
+            in_progress: false,
+            aggregation_number: 0,
+            dirty_containers: 0,
+            collectibles: Vec::new(),
+            expanded: true,
+        }
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let graph = TaskGraph {
+            root: Some(task(1)),
+            tasks: vec![node(1, "root", false), node(2, "read \"a\"", true)],
+            edges: vec![
+                TaskGraphEdge {
+                    from: task(1),
+                    to: task(2),
+                    kind: TaskGraphEdgeKind::Child,
+                },
+                TaskGraphEdge {
+                    from: task(1),
+                    to: task(2),
+                    kind: TaskGraphEdgeKind::CellDependency {
+                        value_type: "FileContent".to_string(),
+                        index: 0,
+                    },
+                },
+            ],
+        };
+        let dot = graph.to_dot();
+        assert!(dot.contains("t1 [label=\"root\"];"));
+        assert!(dot.contains("t2 [label=\"read \\\"a\\\"\", style=filled"));
+        assert!(dot.contains("t1 -> t2;"));
+        assert!(dot.contains("t1 -> t2 [style=dashed, label=\"FileContent #0\"];"));
+    }
+}
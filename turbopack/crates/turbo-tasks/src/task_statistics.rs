@@ -1,6 +1,15 @@
-use std::sync::{Arc, OnceLock};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    fs,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
 
-use serde::{Serialize, Serializer, ser::SerializeMap};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize, Serializer, ser::SerializeMap};
 
 use crate::{FxDashMap, macro_helpers::NativeFunction};
 
@@ -68,17 +77,25 @@ impl TaskStatistics {
     pub fn get(&self, f: &'static NativeFunction) -> TaskFunctionStatistics {
         self.inner.get(f).unwrap().value().clone()
     }
+
+    /// Returns a sorted copy of the current statistics, e.g. for writing them to a file.
+    pub fn report(&self) -> TaskStatisticsReport {
+        self.inner
+            .iter()
+            .map(|entry| (entry.key().global_name.to_string(), entry.value().clone()))
+            .collect()
+    }
 }
 
 /// Statistics for an individual function.
-#[derive(Default, Serialize, Clone)]
+#[derive(Default, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct TaskFunctionStatistics {
     pub cache_hit: u32,
     pub cache_miss: u32,
     // Generally executions == cache_miss, however they can diverge when there are invalidations.
     // The caller gets one cache miss but we might execute multiple times.
     pub executions: u32,
-    pub duration: std::time::Duration,
+    pub duration: Duration,
 }
 
 impl Serialize for TaskStatistics {
@@ -93,3 +110,280 @@ impl Serialize for TaskStatistics {
         map.end()
     }
 }
+
+/// Statistics of all functions, keyed by [`NativeFunction::global_name`]. The JSON serialization
+/// is identical to the one of [`TaskStatistics`].
+pub type TaskStatisticsReport = BTreeMap<String, TaskFunctionStatistics>;
+
+const CSV_HEADER: &str = "function,cache_hit,cache_miss,executions,duration_us";
+
+/// Writes the statistics to a file. Files with a `.csv` extension are written as CSV, all others
+/// as JSON.
+pub fn write_task_statistics(report: &TaskStatisticsReport, path: &Path) -> Result<()> {
+    let file =
+        fs::File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    if is_csv(path) {
+        write_csv(report, &mut writer)?;
+    } else {
+        serde_json::to_writer_pretty(&mut writer, report)?;
+    }
+    writer
+        .flush()
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Reads statistics written by [`write_task_statistics`].
+pub fn read_task_statistics(path: &Path) -> Result<TaskStatisticsReport> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let report = if is_csv(path) {
+        parse_csv(&content)
+    } else {
+        serde_json::from_str(&content).map_err(Into::into)
+    };
+    report.with_context(|| format!("failed to parse {}", path.display()))
+}
+
+fn is_csv(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+}
+
+fn write_csv(report: &TaskStatisticsReport, mut writer: impl Write) -> Result<()> {
+    writeln!(writer, "{CSV_HEADER}")?;
+    for (function, stats) in report {
+        writeln!(
+            writer,
+            "{},{},{},{},{}",
+            csv_escape(function),
+            stats.cache_hit,
+            stats.cache_miss,
+            stats.executions,
+            stats.duration.as_micros()
+        )?;
+    }
+    Ok(())
+}
+
+fn parse_csv(content: &str) -> Result<TaskStatisticsReport> {
+    let mut lines = content.lines();
+    if lines.next() != Some(CSV_HEADER) {
+        bail!("not a task statistics CSV file");
+    }
+    lines
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(i, line)| parse_csv_line(line).with_context(|| format!("invalid line {}", i + 2)))
+        .collect()
+}
+
+/// Function names can contain commas, e.g. in generic parameters.
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn parse_csv_line(line: &str) -> Result<(String, TaskFunctionStatistics)> {
+    let (function, rest) = if let Some(quoted) = line.strip_prefix('"') {
+        let mut function = String::new();
+        let mut chars = quoted.char_indices();
+        let rest = loop {
+            match chars.next() {
+                Some((i, '"')) => {
+                    if quoted[i + 1..].starts_with('"') {
+                        function.push('"');
+                        chars.next();
+                    } else {
+                        break &quoted[i + 1..];
+                    }
+                }
+                Some((_, c)) => function.push(c),
+                None => bail!("unterminated quote"),
+            }
+        };
+        (function, rest)
+    } else {
+        let (function, rest) = line.split_once(',').context("missing columns")?;
+        (function.to_string(), rest)
+    };
+    let columns = rest
+        .strip_prefix(',')
+        .unwrap_or(rest)
+        .split(',')
+        .map(|column| column.trim().parse::<u64>())
+        .collect::<Result<Vec<_>, _>>()?;
+    let [cache_hit, cache_miss, executions, duration_us] = columns[..] else {
+        bail!("expected 5 columns");
+    };
+    Ok((
+        function,
+        TaskFunctionStatistics {
+            cache_hit: cache_hit.try_into()?,
+            cache_miss: cache_miss.try_into()?,
+            executions: executions.try_into()?,
+            duration: Duration::from_micros(duration_us),
+        },
+    ))
+}
+
+/// Configures when [`compare_task_statistics`] reports a regression.
+#[derive(Debug, Clone)]
+pub struct RegressionThresholds {
+    /// The allowed relative increase of executions, e.g. `0.1` for 10%.
+    pub executions: f64,
+    /// The allowed relative increase of the total execution time, e.g. `0.2` for 20%.
+    pub duration: f64,
+    /// Functions with a smaller total execution time in the current run are ignored, as their
+    /// timings are too noisy.
+    pub min_duration: Duration,
+}
+
+impl Default for RegressionThresholds {
+    fn default() -> Self {
+        Self {
+            executions: 0.1,
+            duration: 0.2,
+            min_duration: Duration::from_millis(10),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegressionKind {
+    Executions {
+        baseline: u32,
+        current: u32,
+    },
+    Duration {
+        baseline: Duration,
+        current: Duration,
+    },
+}
+
+/// A function that has become slower or is executed more often than in the baseline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskStatisticsRegression {
+    pub function: String,
+    pub kind: RegressionKind,
+}
+
+impl Display for TaskStatisticsRegression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            RegressionKind::Executions { baseline, current } => write!(
+                f,
+                "{}: executions increased from {baseline} to {current}",
+                self.function
+            ),
+            RegressionKind::Duration { baseline, current } => write!(
+                f,
+                "{}: total execution time increased from {baseline:.2?} to {current:.2?}",
+                self.function
+            ),
+        }
+    }
+}
+
+/// Compares two runs and returns all functions that regressed past the thresholds. Functions that
+/// are missing in the baseline are compared against zero.
+pub fn compare_task_statistics(
+    baseline: &TaskStatisticsReport,
+    current: &TaskStatisticsReport,
+    thresholds: &RegressionThresholds,
+) -> Vec<TaskStatisticsRegression> {
+    let mut regressions = Vec::new();
+    for (function, current) in current {
+        if current.duration < thresholds.min_duration {
+            continue;
+        }
+        let baseline = baseline.get(function).cloned().unwrap_or_default();
+        if exceeds(
+            baseline.executions as f64,
+            current.executions as f64,
+            thresholds.executions,
+        ) {
+            regressions.push(TaskStatisticsRegression {
+                function: function.clone(),
+                kind: RegressionKind::Executions {
+                    baseline: baseline.executions,
+                    current: current.executions,
+                },
+            });
+        }
+        if exceeds(
+            baseline.duration.as_secs_f64(),
+            current.duration.as_secs_f64(),
+            thresholds.duration,
+        ) {
+            regressions.push(TaskStatisticsRegression {
+                function: function.clone(),
+                kind: RegressionKind::Duration {
+                    baseline: baseline.duration,
+                    current: current.duration,
+                },
+            });
+        }
+    }
+    regressions
+}
+
+fn exceeds(baseline: f64, current: f64, threshold: f64) -> bool {
+    current > baseline * (1.0 + threshold)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(executions: u32, duration_ms: u64) -> TaskFunctionStatistics {
+        TaskFunctionStatistics {
+            cache_hit: 1,
+            cache_miss: executions,
+            executions,
+            duration: Duration::from_millis(duration_ms),
+        }
+    }
+
+    #[test]
+    fn test_csv_roundtrip() -> Result<()> {
+        let report = TaskStatisticsReport::from([
+            ("a::b".to_string(), stats(3, 20)),
+            ("Foo<A, \"B\">::c".to_string(), stats(1, 5)),
+        ]);
+        let mut csv = Vec::new();
+        write_csv(&report, &mut csv)?;
+        assert_eq!(parse_csv(std::str::from_utf8(&csv)?)?, report);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compare() {
+        let baseline = TaskStatisticsReport::from([
+            ("same".to_string(), stats(10, 100)),
+            ("slower".to_string(), stats(10, 100)),
+            ("more".to_string(), stats(10, 100)),
+            ("removed".to_string(), stats(10, 100)),
+        ]);
+        let current = TaskStatisticsReport::from([
+            ("same".to_string(), stats(10, 110)),
+            ("slower".to_string(), stats(10, 200)),
+            ("more".to_string(), stats(20, 100)),
+            ("new".to_string(), stats(1, 50)),
+            ("new_but_fast".to_string(), stats(1, 1)),
+        ]);
+        let regressions = compare_task_statistics(&baseline, &current, &Default::default());
+        let functions: Vec<_> = regressions.iter().map(|r| r.function.as_str()).collect();
+        assert_eq!(functions, ["more", "new", "new", "slower"]);
+        assert_eq!(
+            regressions[0].kind,
+            RegressionKind::Executions {
+                baseline: 10,
+                current: 20
+            }
+        );
+    }
+}
@@ -8,6 +8,7 @@ use std::{
 use auto_hash_map::{AutoMap, AutoSet};
 use serde::{Deserialize, Serialize};
 use tracing::Span;
+use turbo_tasks_hash::DeterministicHash;
 
 use crate::{
     RawVc, VcValueType,
@@ -21,6 +22,7 @@ use crate::{
 
 type MagicSerializationFn = fn(&dyn MagicAny) -> &dyn erased_serde::Serialize;
 type AnySerializationFn = fn(&(dyn Any + Sync + Send)) -> &dyn erased_serde::Serialize;
+pub type AnyDeterministicHashFn = fn(&(dyn Any + Sync + Send)) -> u64;
 type RawCellFactoryFn = fn(TypedSharedReference) -> RawVc;
 
 // TODO this type need some refactoring when multiple languages are added to
@@ -46,6 +48,9 @@ pub struct ValueType {
     magic_serialization: Option<(MagicSerializationFn, MagicAnyDeserializeSeed)>,
     any_serialization: Option<(AnySerializationFn, AnyDeserializeSeed)>,
 
+    /// Computes the [`DeterministicHash`] of a value, if the type implements it.
+    deterministic_hash: Option<AnyDeterministicHashFn>,
+
     /// An implementation of
     /// [`VcCellMode::raw_cell`][crate::vc::cell_mode::VcCellMode::raw_cell].
     ///
@@ -115,6 +120,7 @@ impl ValueType {
             trait_methods: AutoMap::new(),
             magic_serialization: None,
             any_serialization: None,
+            deterministic_hash: None,
             raw_cell: <T::CellMode as VcCellMode<T>>::raw_cell,
         }
     }
@@ -132,6 +138,7 @@ impl ValueType {
             trait_methods: AutoMap::new(),
             magic_serialization: None,
             any_serialization: Some((any_as_serialize::<T>, AnyDeserializeSeed::new::<T>())),
+            deterministic_hash: None,
             raw_cell: <T::CellMode as VcCellMode<T>>::raw_cell,
         }
     }
@@ -163,6 +170,25 @@ impl ValueType {
         self.any_serialization.is_some()
     }
 
+    /// This is internally used by `#[turbo_tasks::value]`
+    pub fn with_deterministic_hash(
+        mut self,
+        deterministic_hash: Option<AnyDeterministicHashFn>,
+    ) -> Self {
+        self.deterministic_hash = deterministic_hash;
+        self
+    }
+
+    /// Computes the [`DeterministicHash`] of a value of this type. Returns `None` when the type
+    /// doesn't implement [`DeterministicHash`].
+    pub fn any_deterministic_hash(
+        &self,
+        arc: &triomphe::Arc<dyn Any + Sync + Send>,
+    ) -> Option<u64> {
+        self.deterministic_hash
+            .map(|deterministic_hash| deterministic_hash(&**arc))
+    }
+
     pub fn get_magic_deserialize_seed(&self) -> Option<MagicAnyDeserializeSeed> {
         self.magic_serialization.map(|s| s.1)
     }
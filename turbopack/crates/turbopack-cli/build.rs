@@ -0,0 +1,21 @@
+use std::env;
+
+fn main() -> anyhow::Result<()> {
+    println!("cargo:rerun-if-env-changed=CI");
+    let is_ci = env::var("CI").is_ok_and(|value| !value.is_empty());
+
+    // The persistent cache is versioned by the git commit the CLI has been built from, and is
+    // disabled for builds from a dirty repository. See `create_backend` in `src/util.rs`.
+    let git = vergen_gitcl::GitclBuilder::default()
+        .dirty(/* include_untracked */ true)
+        .describe(
+            /* tags */ true,
+            /* dirty */ !is_ci, // suppress the dirty suffix in CI
+            /* matches */ Some("v[0-9]*"), // find the last version tag
+        )
+        .build()?;
+    vergen_gitcl::Emitter::default()
+        .add_instructions(&git)?
+        .emit()?;
+    Ok(())
+}
@@ -15,6 +15,14 @@ use turbopack_core::issue::IssueSeverity;
 pub enum Arguments {
     Build(BuildArguments),
     Dev(DevArguments),
+    /// Prints why the filesystem cache has been invalidated in the past.
+    CacheLog(CacheLogArguments),
+    /// Compares two task statistics files written by `build --task-statistics` and fails if a
+    /// function regressed.
+    StatsDiff(StatsDiffArguments),
+    /// Builds the project and replays a recorded session of file changes, reporting how many
+    /// tasks have been recomputed for each step and how long it took.
+    Replay(ReplayArguments),
 }
 
 impl Arguments {
@@ -23,6 +31,8 @@ impl Arguments {
         match self {
             Arguments::Build(args) => args.common.dir.as_deref(),
             Arguments::Dev(args) => args.common.dir.as_deref(),
+            Arguments::Replay(args) => args.common.dir.as_deref(),
+            Arguments::CacheLog(_) | Arguments::StatsDiff(_) => None,
         }
     }
 
@@ -31,6 +41,8 @@ impl Arguments {
         match self {
             Arguments::Build(args) => args.common.worker_threads,
             Arguments::Dev(args) => args.common.worker_threads,
+            Arguments::Replay(args) => args.common.worker_threads,
+            Arguments::CacheLog(_) | Arguments::StatsDiff(_) => None,
         }
     }
 }
@@ -95,10 +107,16 @@ pub struct CommonArguments {
     /// Number of worker threads to use for parallel processing
     #[clap(long)]
     pub worker_threads: Option<usize>,
-    // Enable experimental garbage collection with the provided memory limit in
-    // MB.
-    // #[clap(long)]
-    // pub memory_limit: Option<usize>,
+
+    /// Enables the persistent cache and stores it in this directory.
+    #[clap(long, value_parser)]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Memory limit in MB. When exceeded, tasks that haven't been accessed recently are evicted
+    /// from memory and restored from the persistent cache when needed again. Requires
+    /// `--cache-dir`.
+    #[clap(long, requires = "cache_dir")]
+    pub memory_limit: Option<usize>,
 }
 
 #[derive(Debug, Args)]
@@ -159,6 +177,81 @@ pub struct BuildArguments {
     /// leak detectors.
     #[clap(long, hide = true)]
     pub force_memory_cleanup: bool,
+
+    /// Write per-function task statistics to this file after the build. Uses CSV when the file
+    /// has a `.csv` extension and JSON otherwise.
+    #[clap(long, value_parser)]
+    pub task_statistics: Option<PathBuf>,
+
+    /// Write the graph of the tasks of the build to this file after the build, including their
+    /// dependencies. Uses the Graphviz DOT format when the file has a `.dot` or `.gv` extension
+    /// and JSON otherwise. Enables dependency tracking, which makes the build slower.
+    #[clap(long, value_parser)]
+    pub task_graph: Option<PathBuf>,
+
+    /// How many levels of child tasks are included in the task graph.
+    #[clap(long, value_parser, default_value_t = 5)]
+    pub task_graph_depth: usize,
+}
+
+#[derive(Debug, Args)]
+#[clap(author, version, about, long_about = None)]
+pub struct CacheLogArguments {
+    /// The base directory of the filesystem cache, e.g. `.next/cache/turbopack`.
+    #[clap(value_parser)]
+    pub cache_dir: PathBuf,
+}
+
+#[derive(Debug, Args)]
+#[clap(author, version, about, long_about = None)]
+pub struct StatsDiffArguments {
+    /// The statistics of the baseline run.
+    #[clap(value_parser)]
+    pub baseline: PathBuf,
+
+    /// The statistics of the run to check.
+    #[clap(value_parser)]
+    pub current: PathBuf,
+
+    /// The allowed increase of executions of a function in percent.
+    #[clap(long, default_value_t = 10.0)]
+    pub executions_threshold: f64,
+
+    /// The allowed increase of the total execution time of a function in percent.
+    #[clap(long, default_value_t = 20.0)]
+    pub duration_threshold: f64,
+
+    /// Functions with a smaller total execution time in milliseconds are ignored.
+    #[clap(long, default_value_t = 10)]
+    pub min_duration_ms: u64,
+}
+
+#[derive(Debug, Args)]
+#[clap(author, version, about, long_about = None)]
+pub struct ReplayArguments {
+    #[clap(flatten)]
+    pub common: CommonArguments,
+
+    /// The session to replay, recorded by running with `TURBO_TASKS_RECORD_FILE_CHANGES` set to
+    /// the path of the session file.
+    #[clap(long, value_parser)]
+    pub session: PathBuf,
+
+    /// Wait between the steps as long as they were apart when they have been recorded, instead of
+    /// applying the next step as soon as the previous one has been built.
+    #[clap(long)]
+    pub keep_timing: bool,
+
+    /// Replay the session a second time after restoring the initial files, so the steps are
+    /// built by a backend that has already computed them once.
+    #[clap(long)]
+    pub warm: bool,
+
+    /// The directory the root directory is copied to before replaying, since the changes are
+    /// applied to the files. Defaults to a temporary directory. A fixed directory allows to
+    /// reuse the persistent cache of `--cache-dir` across replays.
+    #[clap(long, value_parser)]
+    pub replay_dir: Option<PathBuf>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -9,11 +9,13 @@ use anyhow::{Context, Result, bail};
 use rustc_hash::FxHashSet;
 use tracing::Instrument;
 use turbo_rcstr::RcStr;
-use turbo_tasks::{ResolvedVc, TransientInstance, TryJoinIterExt, TurboTasks, Vc, apply_effects};
-use turbo_tasks_backend::{
-    BackendOptions, NoopBackingStorage, TurboTasksBackend, noop_backing_storage,
+use turbo_tasks::{
+    OperationVc, ReadRef, ResolvedVc, TransientInstance, TryJoinIterExt, TurboTasks, TurboTasksApi,
+    TurboTasksBackendApi, Vc, apply_effects, task_graph::write_task_graph,
+    task_statistics::write_task_statistics,
 };
-use turbo_tasks_fs::FileSystem;
+use turbo_tasks_backend::BackendOptions;
+use turbo_tasks_fs::{DiskFileSystem, FileSystem};
 use turbo_unix_path::join_path;
 use turbopack::{
     css::chunk::CssChunkType, ecmascript::chunk::EcmascriptChunkType,
@@ -53,12 +55,12 @@ use crate::{
     arguments::{BuildArguments, Target},
     contexts::{NodeEnv, get_client_asset_context, get_client_compile_time_info},
     util::{
-        EntryRequest, NormalizedDirs, normalize_dirs, normalize_entries, output_fs, project_fs,
+        Backend, EntryRequest, NormalizedDirs, create_backend, normalize_dirs, normalize_entries,
+        output_fs, project_fs,
     },
 };
 
-type Backend = TurboTasksBackend<NoopBackingStorage>;
-
+#[derive(Clone)]
 pub struct TurbopackBuildBuilder {
     turbo_tasks: Arc<TurboTasks<Backend>>,
     project_dir: RcStr,
@@ -72,6 +74,7 @@ pub struct TurbopackBuildBuilder {
     minify_type: MinifyType,
     target: Target,
     scope_hoist: bool,
+    task_graph: Option<(PathBuf, usize)>,
 }
 
 impl TurbopackBuildBuilder {
@@ -93,6 +96,7 @@ impl TurbopackBuildBuilder {
             },
             target: Target::Node,
             scope_hoist: true,
+            task_graph: None,
         }
     }
 
@@ -141,8 +145,35 @@ impl TurbopackBuildBuilder {
         self
     }
 
-    pub async fn build(self) -> Result<()> {
+    /// Writes the graph of the tasks of the build to `path` after the build, following child tasks
+    /// up to `max_depth` levels deep.
+    pub fn task_graph(mut self, path: PathBuf, max_depth: usize) -> Self {
+        self.task_graph = Some((path, max_depth));
+        self
+    }
+
+    /// Returns the filesystem that the build reads the project from, e.g. to apply file changes to
+    /// it between builds.
+    pub async fn project_fs(&self) -> Result<ReadRef<DiskFileSystem>> {
+        let project_dir = self.project_dir.clone();
+        let root_dir = self.root_dir.clone();
         self.turbo_tasks
+            .run_once(async move {
+                let (_, project_fs) = build_project_fs(&project_dir, &root_dir);
+                let project_fs = ResolvedVc::try_downcast_type::<DiskFileSystem>(
+                    project_fs.to_resolved().await?,
+                )
+                .context("The project filesystem is not a DiskFileSystem")?;
+                Ok(project_fs.await?)
+            })
+            .await
+    }
+
+    pub async fn build(self) -> Result<()> {
+        let turbo_tasks = self.turbo_tasks.clone();
+        let task_graph = self.task_graph.clone();
+        let build_task_id = self
+            .turbo_tasks
             .run_once(async move {
                 let build_result_op = build_internal(
                     self.project_dir.clone(),
@@ -178,26 +209,26 @@ impl TurbopackBuildBuilder {
                 )
                 .await?;
 
-                Ok(())
+                Ok(OperationVc::into_raw(build_result_op).try_get_task_id())
             })
-            .await
+            .await?;
+
+        if let Some((path, max_depth)) = task_graph
+            && let Some(build_task_id) = build_task_id
+        {
+            write_task_graph(&turbo_tasks.task_graph(build_task_id, max_depth), &path)?;
+        }
+
+        Ok(())
     }
 }
 
-#[turbo_tasks::function(operation)]
-async fn build_internal(
-    project_dir: RcStr,
-    root_dir: RcStr,
-    entry_requests: Vec<EntryRequest>,
-    browserslist_query: RcStr,
-    source_maps_type: SourceMapsType,
-    minify_type: MinifyType,
-    target: Target,
-    scope_hoist: bool,
-) -> Result<Vc<()>> {
-    let output_fs = output_fs(project_dir.clone());
-    const OUTPUT_DIR: &str = "dist";
-    let project_relative = project_dir.strip_prefix(&*root_dir).unwrap();
+const OUTPUT_DIR: &str = "dist";
+
+/// Returns the path of the project relative to the root and the filesystem that the build reads
+/// the project from.
+fn build_project_fs(project_dir: &str, root_dir: &RcStr) -> (RcStr, Vc<Box<dyn FileSystem>>) {
+    let project_relative = project_dir.strip_prefix(&**root_dir).unwrap();
     let project_relative: RcStr = project_relative
         .strip_prefix(MAIN_SEPARATOR)
         .unwrap_or(project_relative)
@@ -210,6 +241,22 @@ async fn build_internal(
             .unwrap()
             .into(),
     );
+    (project_relative, project_fs)
+}
+
+#[turbo_tasks::function(operation)]
+async fn build_internal(
+    project_dir: RcStr,
+    root_dir: RcStr,
+    entry_requests: Vec<EntryRequest>,
+    browserslist_query: RcStr,
+    source_maps_type: SourceMapsType,
+    minify_type: MinifyType,
+    target: Target,
+    scope_hoist: bool,
+) -> Result<Vc<()>> {
+    let output_fs = output_fs(project_dir.clone());
+    let (project_relative, project_fs) = build_project_fs(&project_dir, &root_dir);
     let root_path = project_fs.root().owned().await?;
     let project_path = root_path.join(&project_relative)?;
     let build_output_root = output_fs.root().await?.join(OUTPUT_DIR)?;
@@ -310,6 +357,7 @@ async fn build_internal(
         Vc::cell(vec![ChunkGroupEntry::Entry(entries.clone())]),
         false,
     );
+    module_graph.duplicate_packages().await?;
     let module_id_strategy = ResolvedVc::upcast(
         get_global_module_id_strategy(module_graph)
             .to_resolved()
@@ -529,14 +577,18 @@ pub async fn build(args: &BuildArguments) -> Result<()> {
         root_dir,
     } = normalize_dirs(&args.common.dir, &args.common.root)?;
 
-    let tt = TurboTasks::new(TurboTasksBackend::new(
+    let tt = TurboTasks::new(create_backend(
+        &args.common,
         BackendOptions {
-            dependency_tracking: false,
-            storage_mode: None,
+            // The task graph is only useful with dependencies
+            dependency_tracking: args.task_graph.is_some(),
             ..Default::default()
         },
-        noop_backing_storage(),
-    ));
+    )?);
+    let task_statistics = args
+        .task_statistics
+        .as_ref()
+        .map(|path| (path, tt.task_statistics().enable().clone()));
 
     let mut builder = TurbopackBuildBuilder::new(tt.clone(), project_dir, root_dir)
         .log_detail(args.common.log_detail)
@@ -561,12 +613,20 @@ pub async fn build(args: &BuildArguments) -> Result<()> {
         .target(args.common.target.unwrap_or(Target::Node))
         .show_all(args.common.show_all);
 
+    if let Some(path) = &args.task_graph {
+        builder = builder.task_graph(path.clone(), args.task_graph_depth);
+    }
+
     for entry in normalize_entries(&args.common.entries) {
         builder = builder.entry_request(EntryRequest::Relative(entry));
     }
 
     builder.build().await?;
 
+    if let Some((path, task_statistics)) = task_statistics {
+        write_task_statistics(&task_statistics.report(), path)?;
+    }
+
     // Intentionally leak this `Arc`. Otherwise we'll waste time during process exit performing a
     // ton of drop calls.
     if !args.force_memory_cleanup {
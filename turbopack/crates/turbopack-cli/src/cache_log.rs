@@ -0,0 +1,19 @@
+use anyhow::Result;
+use turbo_tasks_backend::read_invalidation_log;
+
+use crate::arguments::CacheLogArguments;
+
+/// Prints the invalidation log of a filesystem cache, oldest entry first.
+pub fn print_cache_log(args: &CacheLogArguments) -> Result<()> {
+    let log = read_invalidation_log(&args.cache_dir);
+    if log.is_empty() {
+        println!(
+            "No cache invalidations have been recorded in {}",
+            args.cache_dir.display()
+        );
+    }
+    for entry in log {
+        println!("{entry}");
+    }
+    Ok(())
+}
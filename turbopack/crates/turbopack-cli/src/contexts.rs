@@ -22,7 +22,7 @@ use turbopack_core::{
     environment::{BrowserEnvironment, Environment, ExecutionEnvironment},
     free_var_references,
     ident::Layer,
-    resolve::options::{ImportMap, ImportMapping},
+    resolve::options::{ImportMap, ImportMapping, LockedVersions},
 };
 use turbopack_node::{
     execution_context::ExecutionContext, transforms::postcss::PostCssTransformOptions,
@@ -82,8 +82,16 @@ pub async fn get_client_resolve_options_context(
     let next_client_import_map = get_client_import_map(project_path.clone())
         .to_resolved()
         .await?;
+    let root = project_path.root().owned().await?;
     let module_options_context = ResolveOptionsContext {
-        enable_node_modules: Some(project_path.root().owned().await?),
+        // Only used when the manifest exists
+        enable_pnp: Some(root.join(".pnp.cjs")?),
+        enable_node_modules: Some(root),
+        locked_versions: Some(
+            LockedVersions::new(project_path.clone(), false)
+                .to_resolved()
+                .await?,
+        ),
         custom_conditions: vec![node_env.await?.to_string().into(), rcstr!("browser")],
         import_map: Some(next_client_import_map),
         browser: true,
@@ -17,9 +17,7 @@ use turbo_tasks::{
     trace::TraceRawVcs,
     util::{FormatBytes, FormatDuration},
 };
-use turbo_tasks_backend::{
-    BackendOptions, NoopBackingStorage, TurboTasksBackend, noop_backing_storage,
-};
+use turbo_tasks_backend::BackendOptions;
 use turbo_tasks_fs::FileSystem;
 use turbo_tasks_malloc::TurboMalloc;
 use turbo_unix_path::join_path;
@@ -48,14 +46,13 @@ use crate::{
     arguments::DevArguments,
     contexts::NodeEnv,
     util::{
-        EntryRequest, NormalizedDirs, normalize_dirs, normalize_entries, output_fs, project_fs,
+        Backend, EntryRequest, NormalizedDirs, create_backend, normalize_dirs, normalize_entries,
+        output_fs, project_fs,
     },
 };
 
 pub(crate) mod web_entry_source;
 
-type Backend = TurboTasksBackend<NoopBackingStorage>;
-
 pub struct TurbopackDevServerBuilder {
     turbo_tasks: Arc<TurboTasks<Backend>>,
     project_dir: RcStr,
@@ -369,13 +366,7 @@ pub async fn start_server(args: &DevArguments) -> Result<()> {
         root_dir,
     } = normalize_dirs(&args.common.dir, &args.common.root)?;
 
-    let tt = TurboTasks::new(TurboTasksBackend::new(
-        BackendOptions {
-            storage_mode: None,
-            ..Default::default()
-        },
-        noop_backing_storage(),
-    ));
+    let tt = TurboTasks::new(create_backend(&args.common, BackendOptions::default())?);
 
     let tt_clone = tt.clone();
 
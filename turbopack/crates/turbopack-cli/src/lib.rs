@@ -5,7 +5,10 @@
 
 pub mod arguments;
 pub mod build;
+pub mod cache_log;
 pub(crate) mod contexts;
 pub mod dev;
 pub(crate) mod embed_js;
+pub mod replay;
+pub mod stats_diff;
 pub(crate) mod util;
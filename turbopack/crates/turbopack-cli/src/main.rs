@@ -108,5 +108,8 @@ async fn main_inner(args: Arguments) -> Result<()> {
     match args {
         Arguments::Build(args) => turbopack_cli::build::build(&args).await,
         Arguments::Dev(args) => turbopack_cli::dev::start_server(&args).await,
+        Arguments::CacheLog(args) => turbopack_cli::cache_log::print_cache_log(&args),
+        Arguments::StatsDiff(args) => turbopack_cli::stats_diff::stats_diff(&args),
+        Arguments::Replay(args) => turbopack_cli::replay::replay(&args).await,
     }
 }
@@ -0,0 +1,243 @@
+use std::{
+    fs, io,
+    mem::forget,
+    path::{MAIN_SEPARATOR, Path},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, bail};
+use dunce::canonicalize;
+use rustc_hash::FxHashSet;
+use turbo_rcstr::RcStr;
+use turbo_tasks::{TurboTasks, task_statistics::TaskStatistics};
+use turbo_tasks_backend::BackendOptions;
+use turbo_tasks_fs::{
+    DiskFileSystem,
+    file_change_session::{FileChange, FileChangeStep, read_file_change_session},
+};
+use turbopack_core::issue::IssueSeverity;
+
+use crate::{
+    arguments::{ReplayArguments, Target},
+    build::TurbopackBuildBuilder,
+    util::{EntryRequest, NormalizedDirs, create_backend, normalize_dirs, normalize_entries},
+};
+
+/// Builds a copy of the project, then applies the recorded file changes step by step and rebuilds
+/// after every step. The project itself is never changed.
+pub async fn replay(args: &ReplayArguments) -> Result<()> {
+    let NormalizedDirs {
+        project_dir: original_project_dir,
+        root_dir: original_root_dir,
+    } = normalize_dirs(&args.common.dir, &args.common.root)?;
+    let steps = read_file_change_session(&args.session)?;
+
+    let temp_dir;
+    let replay_dir = match &args.replay_dir {
+        Some(replay_dir) => {
+            if replay_dir.exists() {
+                let replay_dir = canonicalize(replay_dir)?;
+                let original_root = Path::new(&*original_root_dir);
+                if replay_dir.starts_with(original_root) || original_root.starts_with(&replay_dir) {
+                    bail!(
+                        "the replay directory {} must be outside of the root directory",
+                        replay_dir.display()
+                    );
+                }
+                fs::remove_dir_all(&replay_dir)
+                    .with_context(|| format!("failed to clear {}", replay_dir.display()))?;
+            }
+            replay_dir.clone()
+        }
+        None => {
+            temp_dir = tempfile::tempdir()?;
+            temp_dir.path().join("root")
+        }
+    };
+    copy_dir(Path::new(&*original_root_dir), &replay_dir)
+        .with_context(|| format!("failed to copy the project to {}", replay_dir.display()))?;
+    let replay_dir = canonicalize(&replay_dir)?;
+    let root_dir: RcStr = replay_dir
+        .to_str()
+        .context("replay directory contains invalid characters")?
+        .into();
+    let project_dir: RcStr = format!(
+        "{root_dir}{}",
+        original_project_dir
+            .strip_prefix(&*original_root_dir)
+            .unwrap()
+    )
+    .trim_end_matches(MAIN_SEPARATOR)
+    .into();
+
+    let tt = TurboTasks::new(create_backend(&args.common, BackendOptions::default())?);
+    let task_statistics = tt.task_statistics().enable().clone();
+
+    let mut builder = TurbopackBuildBuilder::new(tt.clone(), project_dir, root_dir)
+        .log_detail(args.common.log_detail)
+        .log_level(
+            args.common
+                .log_level
+                .map_or_else(|| IssueSeverity::Warning, |l| l.0),
+        )
+        .target(args.common.target.unwrap_or(Target::Node))
+        .show_all(args.common.show_all);
+    for entry in normalize_entries(&args.common.entries) {
+        builder = builder.entry_request(EntryRequest::Relative(entry));
+    }
+
+    let start = Instant::now();
+    builder.clone().build().await?;
+    println!(
+        "initial build: {} tasks executed in {:?}",
+        total_executions(&task_statistics),
+        start.elapsed()
+    );
+
+    let project_fs = builder.project_fs().await?;
+    let steps = steps
+        .into_iter()
+        .filter(|step| step.filesystem == *project_fs.name())
+        .collect::<Vec<_>>();
+    if steps.is_empty() {
+        println!(
+            "the session contains no changes of the {} filesystem",
+            project_fs.name()
+        );
+    } else {
+        replay_steps(
+            args,
+            &builder,
+            &project_fs,
+            &task_statistics,
+            &steps,
+            "fresh",
+        )
+        .await?;
+        if args.warm {
+            // Restore the initial files, so the steps change the same files again
+            let restore_step = restore_step(
+                Path::new(&*original_root_dir)
+                    .join(Path::new(&**project_fs.root()).strip_prefix(&replay_dir)?)
+                    .as_path(),
+                &steps,
+            );
+            project_fs.apply_file_change_step(&restore_step).await?;
+            builder.clone().build().await?;
+            replay_steps(
+                args,
+                &builder,
+                &project_fs,
+                &task_statistics,
+                &steps,
+                "warm",
+            )
+            .await?;
+        }
+    }
+
+    // Intentionally leak this `Arc`, like the build does
+    forget(tt);
+
+    Ok(())
+}
+
+/// Applies the steps and rebuilds after each of them.
+async fn replay_steps(
+    args: &ReplayArguments,
+    builder: &TurbopackBuildBuilder,
+    project_fs: &DiskFileSystem,
+    task_statistics: &TaskStatistics,
+    steps: &[FileChangeStep],
+    name: &str,
+) -> Result<()> {
+    let replay_start = Instant::now();
+    let mut total_duration = Duration::ZERO;
+    for (index, step) in steps.iter().enumerate() {
+        if args.keep_timing {
+            let recorded = Duration::from_millis(step.elapsed_ms);
+            if let Some(delay) = recorded.checked_sub(replay_start.elapsed()) {
+                tokio::time::sleep(delay).await;
+            }
+        }
+
+        let executions_before = total_executions(task_statistics);
+        let start = Instant::now();
+        project_fs.apply_file_change_step(step).await?;
+        builder.clone().build().await?;
+        let duration = start.elapsed();
+        let executions = total_executions(task_statistics) - executions_before;
+        total_duration += duration;
+
+        let paths = step
+            .changes
+            .iter()
+            .map(|change| &*change.path)
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!(
+            "{name} step {}: {executions} tasks recomputed in {duration:?} ({paths})",
+            index + 1
+        );
+    }
+    println!(
+        "{name}: replayed {} steps in {total_duration:?}, {:?} per step on average",
+        steps.len(),
+        total_duration / steps.len() as u32
+    );
+    Ok(())
+}
+
+/// Returns a step that restores the paths changed by the steps to their state in `original_root`.
+fn restore_step(original_root: &Path, steps: &[FileChangeStep]) -> FileChangeStep {
+    let mut paths = steps
+        .iter()
+        .flat_map(|step| step.changes.iter().map(|change| change.path.clone()))
+        .collect::<FxHashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    // Parent directories are restored before their children
+    paths.sort();
+    FileChangeStep {
+        elapsed_ms: 0,
+        filesystem: steps[0].filesystem.clone(),
+        changes: paths
+            .into_iter()
+            .map(|path| FileChange::read(original_root, path))
+            .collect(),
+    }
+}
+
+/// Copies a directory recursively. Symlinks are copied as symlinks.
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let target = to.join(entry.file_name());
+        if file_type.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else if file_type.is_symlink() {
+            let link = fs::read_link(entry.path())?;
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(&link, &target)?;
+            #[cfg(windows)]
+            if fs::metadata(entry.path()).is_ok_and(|metadata| metadata.is_dir()) {
+                std::os::windows::fs::symlink_dir(&link, &target)?;
+            } else {
+                std::os::windows::fs::symlink_file(&link, &target)?;
+            }
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+fn total_executions(task_statistics: &TaskStatistics) -> u64 {
+    task_statistics
+        .report()
+        .values()
+        .map(|statistics| statistics.executions as u64)
+        .sum()
+}
@@ -0,0 +1,28 @@
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use turbo_tasks::task_statistics::{
+    RegressionThresholds, compare_task_statistics, read_task_statistics,
+};
+
+use crate::arguments::StatsDiffArguments;
+
+/// Compares two task statistics files and fails when any function regressed.
+pub fn stats_diff(args: &StatsDiffArguments) -> Result<()> {
+    let baseline = read_task_statistics(&args.baseline)?;
+    let current = read_task_statistics(&args.current)?;
+    let thresholds = RegressionThresholds {
+        executions: args.executions_threshold / 100.0,
+        duration: args.duration_threshold / 100.0,
+        min_duration: Duration::from_millis(args.min_duration_ms),
+    };
+    let regressions = compare_task_statistics(&baseline, &current, &thresholds);
+    if regressions.is_empty() {
+        println!("No regressions found in {} functions", current.len());
+        return Ok(());
+    }
+    for regression in &regressions {
+        println!("{regression}");
+    }
+    bail!("{} task statistics regressed", regressions.len())
+}
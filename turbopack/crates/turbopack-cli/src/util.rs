@@ -2,11 +2,20 @@ use std::{env::current_dir, path::PathBuf};
 
 use anyhow::{Context, Result};
 use dunce::canonicalize;
+use either::Either;
+use owo_colors::OwoColorize;
 use serde::{Deserialize, Serialize};
 use turbo_rcstr::{RcStr, rcstr};
 use turbo_tasks::{NonLocalValue, TaskInput, Vc, trace::TraceRawVcs};
+use turbo_tasks_backend::{
+    BackendOptions, DefaultBackingStorage, GitVersionInfo, InvalidationReason, NoopBackingStorage,
+    StartupCacheState, StorageMode, TurboTasksBackend, default_backing_storage,
+    noop_backing_storage,
+};
 use turbo_tasks_fs::{DiskFileSystem, FileSystem};
 
+use crate::arguments::CommonArguments;
+
 #[derive(
     Clone, Debug, TaskInput, Hash, PartialEq, Eq, NonLocalValue, Serialize, Deserialize, TraceRawVcs,
 )]
@@ -15,6 +24,8 @@ pub enum EntryRequest {
     Module(RcStr, RcStr),
 }
 
+pub type Backend = TurboTasksBackend<Either<DefaultBackingStorage, NoopBackingStorage>>;
+
 pub struct NormalizedDirs {
     /// Normalized project directory path as an absolute path
     pub project_dir: RcStr,
@@ -52,6 +63,49 @@ pub fn normalize_dirs(
     })
 }
 
+/// Creates the backend, backed by the persistent cache when `--cache-dir` is passed.
+pub fn create_backend(args: &CommonArguments, options: BackendOptions) -> Result<Backend> {
+    let Some(cache_dir) = &args.cache_dir else {
+        return Ok(TurboTasksBackend::new(
+            BackendOptions {
+                storage_mode: None,
+                ..options
+            },
+            Either::Right(noop_backing_storage()),
+        ));
+    };
+    // The cache is keyed by the git version the CLI has been built from (see `build.rs`), and
+    // disabled for builds from a dirty repository.
+    let version_info = GitVersionInfo {
+        describe: env!("VERGEN_GIT_DESCRIBE"),
+        dirty: option_env!("CI").is_none_or(|value| value.is_empty())
+            && env!("VERGEN_GIT_DIRTY") == "true",
+    };
+    let (backing_storage, cache_state) =
+        default_backing_storage(cache_dir, &version_info, false, false)
+            .context("Unable to open the persistent cache")?;
+    let invalidation_reason = match cache_state {
+        StartupCacheState::Invalidated { reason_code } => {
+            Some(InvalidationReason::Invalidated { reason_code })
+        }
+        StartupCacheState::VersionChanged { previous, current } => {
+            Some(InvalidationReason::VersionChanged { previous, current })
+        }
+        StartupCacheState::NoCache | StartupCacheState::Cached => None,
+    };
+    if let Some(reason) = invalidation_reason {
+        println!("{} - {reason}", "warn ".yellow());
+    }
+    Ok(TurboTasksBackend::new(
+        BackendOptions {
+            storage_mode: Some(StorageMode::ReadWrite),
+            memory_limit: args.memory_limit.map(|mb| mb * 1024 * 1024),
+            ..options
+        },
+        Either::Left(backing_storage),
+    ))
+}
+
 pub fn normalize_entries(entries: &Option<Vec<String>>) -> Vec<RcStr> {
     entries
         .as_ref()
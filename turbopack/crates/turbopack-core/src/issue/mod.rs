@@ -2,6 +2,8 @@ pub mod analyze;
 pub mod code_gen;
 pub mod module;
 pub mod resolve;
+pub mod slow_task;
+pub mod task_cycle;
 
 use std::{
     cmp::min,
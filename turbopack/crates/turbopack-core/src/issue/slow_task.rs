@@ -0,0 +1,62 @@
+use turbo_rcstr::{RcStr, rcstr};
+use turbo_tasks::{Vc, slow_tasks::SlowTask};
+use turbo_tasks_fs::{FileSystem, FileSystemPath, VirtualFileSystem};
+
+use super::{Issue, IssueSeverity, IssueStage, OptionStyledString, StyledString};
+
+/// A task execution that took longer than the threshold of the slow task watchdog, see
+/// [`TurboTasks::start_slow_task_watchdog`][turbo_tasks::TurboTasks::start_slow_task_watchdog].
+#[turbo_tasks::value(shared)]
+pub struct SlowTaskIssue {
+    /// The function name and arguments of the task.
+    pub task: RcStr,
+    pub duration_ms: u64,
+}
+
+impl SlowTaskIssue {
+    pub fn new(slow_task: &SlowTask) -> Self {
+        SlowTaskIssue {
+            task: slow_task.task.as_str().into(),
+            duration_ms: slow_task.duration.as_millis() as u64,
+        }
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for SlowTaskIssue {
+    fn severity(&self) -> IssueSeverity {
+        IssueSeverity::Warning
+    }
+
+    #[turbo_tasks::function]
+    fn stage(&self) -> Vc<IssueStage> {
+        IssueStage::Misc.cell()
+    }
+
+    #[turbo_tasks::function]
+    fn file_path(&self) -> Vc<FileSystemPath> {
+        VirtualFileSystem::new_with_name(rcstr!("turbo-tasks")).root()
+    }
+
+    #[turbo_tasks::function]
+    fn title(&self) -> Vc<StyledString> {
+        StyledString::Text(rcstr!("A task is taking unusually long")).cell()
+    }
+
+    #[turbo_tasks::function]
+    fn description(&self) -> Vc<OptionStyledString> {
+        Vc::cell(Some(
+            StyledString::Stack(vec![
+                StyledString::Text(
+                    format!(
+                        "This task had been running for {:.1}s when it was noticed:",
+                        self.duration_ms as f64 / 1000.0
+                    )
+                    .into(),
+                ),
+                StyledString::Code(self.task.clone()),
+            ])
+            .resolved_cell(),
+        ))
+    }
+}
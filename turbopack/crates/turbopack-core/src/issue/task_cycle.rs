@@ -0,0 +1,58 @@
+use turbo_rcstr::{RcStr, rcstr};
+use turbo_tasks::{Vc, backend::TaskCycleError};
+use turbo_tasks_fs::{FileSystem, FileSystemPath, VirtualFileSystem};
+
+use super::{Issue, IssueStage, OptionStyledString, StyledString};
+
+/// Tasks that wait for each other and would never finish. When enabled, the backend detects such
+/// cycles and fails the tasks with a [`TaskCycleError`], which is reported with this issue.
+#[turbo_tasks::value(shared)]
+pub struct TaskCycleIssue {
+    /// The function names and arguments of the tasks in the cycle.
+    pub cycle: Vec<RcStr>,
+}
+
+impl TaskCycleIssue {
+    /// Returns the issue when the error has been caused by a dependency cycle between tasks.
+    pub fn from_error(err: &anyhow::Error) -> Option<Self> {
+        TaskCycleError::find(err).map(|cycle| TaskCycleIssue {
+            cycle: cycle.cycle.clone(),
+        })
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for TaskCycleIssue {
+    #[turbo_tasks::function]
+    fn stage(&self) -> Vc<IssueStage> {
+        IssueStage::Misc.cell()
+    }
+
+    #[turbo_tasks::function]
+    fn file_path(&self) -> Vc<FileSystemPath> {
+        VirtualFileSystem::new_with_name(rcstr!("turbo-tasks")).root()
+    }
+
+    #[turbo_tasks::function]
+    fn title(&self) -> Vc<StyledString> {
+        StyledString::Text(rcstr!("Dependency cycle between tasks detected")).cell()
+    }
+
+    #[turbo_tasks::function]
+    fn description(&self) -> Vc<OptionStyledString> {
+        let mut cycle = self.cycle.join("\n→ ");
+        if let Some(first) = self.cycle.first() {
+            cycle.push_str("\n→ ");
+            cycle.push_str(first);
+        }
+        Vc::cell(Some(
+            StyledString::Stack(vec![
+                StyledString::Text(rcstr!(
+                    "These tasks are waiting for each other and would never finish:"
+                )),
+                StyledString::Code(cycle.into()),
+            ])
+            .resolved_cell(),
+        ))
+    }
+}
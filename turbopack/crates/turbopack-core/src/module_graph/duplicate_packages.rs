@@ -0,0 +1,258 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use anyhow::Result;
+use turbo_rcstr::{RcStr, rcstr};
+use turbo_tasks::{FxIndexSet, TryJoinIterExt, Vc};
+use turbo_tasks_fs::FileSystemPath;
+
+use crate::{
+    file_source::FileSource,
+    issue::{Issue, IssueExt, IssueSeverity, IssueStage, OptionStyledString, StyledString},
+    module_graph::ModuleGraph,
+    package_json::read_package_json,
+};
+
+/// The packages in node_modules that are included with more than one version, by their name and
+/// version, with the directories that contain the version.
+#[turbo_tasks::value(transparent)]
+pub struct DuplicatePackages(BTreeMap<RcStr, BTreeMap<RcStr, Vec<FileSystemPath>>>);
+
+/// Returns the directory of the package in node_modules that contains `path`.
+fn package_dir(path: &FileSystemPath) -> Option<FileSystemPath> {
+    let start = path.path.rfind("node_modules/")? + "node_modules/".len();
+    let mut segments = path.path[start..].split('/');
+    let name = segments.next().filter(|name| !name.is_empty())?;
+    let end = if name.starts_with('@') {
+        start + name.len() + 1 + segments.next()?.len()
+    } else {
+        start + name.len()
+    };
+    let mut dir = path.clone();
+    for _ in 0..path.path[end..].matches('/').count() {
+        dir = dir.parent();
+    }
+    Some(dir)
+}
+
+pub async fn compute_duplicate_packages(graph: Vc<ModuleGraph>) -> Result<Vc<DuplicatePackages>> {
+    let graph = graph.read_graphs().await?;
+    let package_dirs = graph
+        .graphs
+        .iter()
+        .flat_map(|graph| graph.iter_nodes())
+        .map(async |node| Ok(package_dir(&*node.module.ident().path().await?)))
+        .try_join()
+        .await?
+        .into_iter()
+        .flatten()
+        .collect::<FxIndexSet<_>>();
+
+    let mut packages: BTreeMap<RcStr, BTreeMap<RcStr, Vec<FileSystemPath>>> = BTreeMap::new();
+    for dir in package_dirs {
+        let package_json =
+            read_package_json(Vc::upcast(FileSource::new(dir.join("package.json")?))).await?;
+        let Some(package_json) = &*package_json else {
+            continue;
+        };
+        let (Some(name), Some(version)) = (
+            package_json["name"].as_str(),
+            package_json["version"].as_str(),
+        ) else {
+            continue;
+        };
+        packages
+            .entry(name.into())
+            .or_default()
+            .entry(version.into())
+            .or_default()
+            .push(dir);
+    }
+    packages.retain(|_, versions| versions.len() > 1);
+
+    for (name, versions) in &packages {
+        DuplicatePackageIssue {
+            name: name.clone(),
+            versions: versions.clone(),
+        }
+        .resolved_cell()
+        .emit();
+    }
+    Ok(Vc::cell(packages))
+}
+
+#[turbo_tasks::value(shared)]
+struct DuplicatePackageIssue {
+    name: RcStr,
+    versions: BTreeMap<RcStr, Vec<FileSystemPath>>,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for DuplicatePackageIssue {
+    fn severity(&self) -> IssueSeverity {
+        IssueSeverity::Warning
+    }
+
+    #[turbo_tasks::function]
+    fn title(&self) -> Vc<StyledString> {
+        StyledString::Line(vec![
+            StyledString::Text(rcstr!("Multiple versions of ")),
+            StyledString::Code(self.name.clone()),
+            StyledString::Text(rcstr!(" are included")),
+        ])
+        .cell()
+    }
+
+    #[turbo_tasks::function]
+    fn stage(&self) -> Vc<IssueStage> {
+        IssueStage::Resolve.cell()
+    }
+
+    #[turbo_tasks::function]
+    fn file_path(&self) -> Vc<FileSystemPath> {
+        // Sorted by version, so this is stable
+        self.versions.values().next().unwrap()[0].clone().cell()
+    }
+
+    #[turbo_tasks::function]
+    fn description(&self) -> Result<Vc<OptionStyledString>> {
+        let mut description = String::new();
+        for (version, dirs) in &self.versions {
+            for dir in dirs {
+                writeln!(description, "{version} in {dir}")?;
+            }
+        }
+        writeln!(
+            description,
+            "Deduplicating the dependencies reduces the size of the output and avoids bugs from \
+             several instances of the package."
+        )?;
+        Ok(Vc::cell(Some(
+            StyledString::Text(description.into()).resolved_cell(),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rustc_hash::FxHashMap;
+    use turbo_tasks::{CollectiblesSource, ResolvedVc};
+    use turbo_tasks_backend::{BackendOptions, TurboTasksBackend, noop_backing_storage};
+    use turbo_tasks_fs::{FileSystem, MemoryFileSystem};
+
+    use super::*;
+    use crate::{
+        module::Module,
+        module_graph::{
+            GraphEntries,
+            chunk_group_info::ChunkGroupEntry,
+            tests::{MockModule, TestRepo},
+        },
+    };
+
+    #[turbo_tasks::function(operation)]
+    async fn duplicate_packages_operation(
+        fs: ResolvedVc<MemoryFileSystem>,
+    ) -> Result<Vc<DuplicatePackages>> {
+        let root = fs.root().owned().await?;
+        let dependencies = [
+            (
+                "index.js",
+                vec!["node_modules/react/index.js", "node_modules/lib/index.js"],
+            ),
+            (
+                "node_modules/lib/index.js",
+                vec![
+                    "node_modules/lib/node_modules/react/cjs/react.js",
+                    "node_modules/@scope/pkg/dist/index.js",
+                ],
+            ),
+            (
+                "node_modules/react/index.js",
+                vec!["node_modules/@scope/pkg/dist/index.js"],
+            ),
+        ];
+        let repo = TestRepo {
+            repo: dependencies
+                .into_iter()
+                .map(|(path, deps)| {
+                    Ok((
+                        root.join(path)?,
+                        deps.into_iter()
+                            .map(|dep| root.join(dep))
+                            .collect::<Result<_>>()?,
+                    ))
+                })
+                .collect::<Result<FxHashMap<_, _>>>()?,
+        }
+        .resolved_cell();
+        let entry = Vc::upcast::<Box<dyn Module>>(MockModule::new(root.join("index.js")?, *repo))
+            .to_resolved()
+            .await?;
+        Ok(ModuleGraph::from_modules(
+            GraphEntries::cell(GraphEntries(vec![ChunkGroupEntry::Entry(vec![entry])])),
+            false,
+        )
+        .duplicate_packages())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_duplicate_packages() {
+        let tt = turbo_tasks::TurboTasks::new(TurboTasksBackend::new(
+            BackendOptions::default(),
+            noop_backing_storage(),
+        ));
+        tt.run_once(async move {
+            let fs = MemoryFileSystem::new(rcstr!("project"))
+                .to_resolved()
+                .await?;
+            {
+                let fs = fs.await?;
+                for (dir, name, version) in [
+                    ("node_modules/react", "react", "18.3.1"),
+                    ("node_modules/lib", "lib", "1.0.0"),
+                    ("node_modules/lib/node_modules/react", "react", "17.0.2"),
+                    ("node_modules/@scope/pkg", "@scope/pkg", "2.0.0"),
+                ] {
+                    fs.write_file(
+                        &format!("{dir}/package.json"),
+                        format!(r#"{{ "name": "{name}", "version": "{version}" }}"#),
+                    )?;
+                }
+            }
+
+            let operation = duplicate_packages_operation(fs);
+            let packages = operation.read_strongly_consistent().await?;
+            let packages = packages
+                .iter()
+                .map(|(name, versions)| {
+                    let versions = versions
+                        .iter()
+                        .map(|(version, dirs)| {
+                            let dirs = dirs.iter().map(|dir| dir.path.to_string()).collect();
+                            (version.to_string(), dirs)
+                        })
+                        .collect::<Vec<(String, Vec<String>)>>();
+                    (name.to_string(), versions)
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(
+                packages,
+                [(
+                    "react".to_string(),
+                    vec![
+                        (
+                            "17.0.2".to_string(),
+                            vec!["node_modules/lib/node_modules/react".to_string()]
+                        ),
+                        ("18.3.1".to_string(), vec!["node_modules/react".to_string()]),
+                    ]
+                )]
+            );
+            assert_eq!(operation.peek_collectibles::<Box<dyn Issue>>().len(), 1);
+
+            anyhow::Ok(())
+        })
+        .await
+        .unwrap();
+    }
+}
@@ -32,6 +32,7 @@ use crate::{
     module_graph::{
         async_module_info::{AsyncModulesInfo, compute_async_module_info},
         chunk_group_info::{ChunkGroupEntry, ChunkGroupInfo, compute_chunk_group_info},
+        duplicate_packages::{DuplicatePackages, compute_duplicate_packages},
         merged_modules::{MergedModuleInfo, compute_merged_modules},
         module_batches::{ModuleBatchesGraph, compute_module_batches},
         style_groups::{StyleGroups, StyleGroupsConfig, compute_style_groups},
@@ -43,6 +44,7 @@ use crate::{
 
 pub mod async_module_info;
 pub mod chunk_group_info;
+pub mod duplicate_packages;
 pub mod export_usage;
 pub mod merged_modules;
 pub mod module_batch;
@@ -1138,6 +1140,13 @@ impl ModuleGraph {
         compute_style_groups(self, chunking_context, &config).await
     }
 
+    /// Emits a warning for every package in node_modules that is included with more than one
+    /// version.
+    #[turbo_tasks::function]
+    pub async fn duplicate_packages(self: Vc<Self>) -> Result<Vc<DuplicatePackages>> {
+        compute_duplicate_packages(self).await
+    }
+
     #[turbo_tasks::function]
     pub async fn async_module_info(self: Vc<Self>) -> Result<Vc<AsyncModulesInfo>> {
         // `compute_async_module_info` calls `module.is_self_async()`, so we need to again ignore
@@ -2148,18 +2157,18 @@ pub mod tests {
         .await;
     }
     #[turbo_tasks::value(shared)]
-    struct TestRepo {
-        repo: FxHashMap<FileSystemPath, Vec<FileSystemPath>>,
+    pub(super) struct TestRepo {
+        pub(super) repo: FxHashMap<FileSystemPath, Vec<FileSystemPath>>,
     }
     #[turbo_tasks::value]
-    struct MockModule {
+    pub(super) struct MockModule {
         path: FileSystemPath,
         repo: ResolvedVc<TestRepo>,
     }
     #[turbo_tasks::value_impl]
     impl MockModule {
         #[turbo_tasks::function]
-        fn new(path: FileSystemPath, repo: ResolvedVc<TestRepo>) -> Vc<Self> {
+        pub(super) fn new(path: FileSystemPath, repo: ResolvedVc<TestRepo>) -> Vc<Self> {
             Self { path, repo }.cell()
         }
     }
@@ -62,9 +62,36 @@ pub enum CommonJsReferenceSubType {
 )]
 pub enum ImportWithType {
     Json,
+    /// A CSS module script, which exports a `CSSStyleSheet`.
+    Css,
+    /// The text content as string. This is a proposal.
+    Text,
+    /// The content as `Uint8Array`. This is a proposal.
     Bytes,
 }
 
+impl ImportWithType {
+    /// Parses the value of the `type` import attribute, e.g. `with { type: "json" }`.
+    pub fn from_attribute(value: &str) -> Option<Self> {
+        match value {
+            "json" => Some(ImportWithType::Json),
+            "css" => Some(ImportWithType::Css),
+            "text" => Some(ImportWithType::Text),
+            "bytes" => Some(ImportWithType::Bytes),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportWithType::Json => "json",
+            ImportWithType::Css => "css",
+            ImportWithType::Text => "text",
+            ImportWithType::Bytes => "bytes",
+        }
+    }
+}
+
 #[derive(
     PartialEq,
     Eq,
@@ -0,0 +1,610 @@
+//! Reading the versions of packages that are locked by the lockfile of the package manager, to
+//! notice packages in node_modules that are out of sync with it.
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+};
+
+use anyhow::Result;
+use serde::Deserialize;
+use turbo_rcstr::{RcStr, rcstr};
+use turbo_tasks::Vc;
+use turbo_tasks_fs::{FileContent, FileSystemPath};
+
+use super::{FindContextFileResult, find_context_file};
+use crate::{
+    file_source::FileSource,
+    issue::{Issue, IssueExt, IssueSeverity, IssueStage, OptionStyledString, StyledString},
+    package_json::read_package_json,
+};
+
+/// The lockfiles that are supported, in the order they are looked up in a directory.
+const LOCKFILES: [&str; 4] = [
+    "pnpm-lock.yaml",
+    "yarn.lock",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+];
+
+/// The versions of packages in the lockfile of a project.
+#[turbo_tasks::value(shared)]
+#[derive(Hash, Debug)]
+pub struct LockedVersions {
+    /// The lockfile that the versions have been read from. `None` if the project has no lockfile
+    /// or it can't be read.
+    pub lockfile: Option<FileSystemPath>,
+    /// The locked version of the packages by their install path relative to the directory of the
+    /// lockfile, e.g. `node_modules/@babel/core/node_modules/react` or
+    /// `node_modules/.pnpm/react@18.3.1/node_modules/react`. Only `package-lock.json` since
+    /// version 2 and `pnpm-lock.yaml` contain install paths.
+    pub paths: BTreeMap<RcStr, RcStr>,
+    /// The locked versions of every package by its name, for packages without an install path in
+    /// the lockfile.
+    pub packages: BTreeMap<RcStr, BTreeSet<RcStr>>,
+    /// Whether installed packages with a different version than the lockfile are errors instead
+    /// of warnings.
+    pub strict: bool,
+}
+
+#[turbo_tasks::value_impl]
+impl LockedVersions {
+    /// Reads the nearest `pnpm-lock.yaml`, `yarn.lock`, `package-lock.json` or
+    /// `npm-shrinkwrap.json` in `project_dir` or one of its parents.
+    #[turbo_tasks::function]
+    pub async fn new(project_dir: FileSystemPath, strict: bool) -> Result<Vc<Self>> {
+        let lockfile = find_context_file(
+            project_dir,
+            Vc::cell(LOCKFILES.into_iter().map(RcStr::from).collect()),
+            false,
+        )
+        .await?;
+        let FindContextFileResult::Found(lockfile, _) = &*lockfile else {
+            return Ok(Self::empty(strict));
+        };
+        let content = lockfile.read().await?;
+        let FileContent::Content(file) = &*content else {
+            return Ok(Self::empty(strict));
+        };
+        let content = file.content().to_str()?;
+        let versions = match lockfile.file_name() {
+            "pnpm-lock.yaml" => Ok(parse_pnpm_lock(&content)),
+            "yarn.lock" => Ok(parse_yarn_lock(&content)),
+            _ => parse_package_lock(&content),
+        };
+        match versions {
+            Ok(LockfileVersions { paths, packages }) => Ok(LockedVersions {
+                lockfile: Some(lockfile.clone()),
+                paths,
+                packages,
+                strict,
+            }
+            .cell()),
+            Err(err) => {
+                LockfileIssue {
+                    severity: IssueSeverity::Warning,
+                    file_path: lockfile.clone(),
+                    title: rcstr!("Unable to read the lockfile"),
+                    message: format!("Versions of installed packages are not checked: {err}")
+                        .into(),
+                }
+                .resolved_cell()
+                .emit();
+                Ok(Self::empty(strict))
+            }
+        }
+    }
+}
+
+impl LockedVersions {
+    fn empty(strict: bool) -> Vc<Self> {
+        LockedVersions {
+            lockfile: None,
+            paths: BTreeMap::new(),
+            packages: BTreeMap::new(),
+            strict,
+        }
+        .cell()
+    }
+}
+
+/// The versions that have been read from a lockfile, see [`LockedVersions`].
+#[derive(Default)]
+struct LockfileVersions {
+    paths: BTreeMap<RcStr, RcStr>,
+    packages: BTreeMap<RcStr, BTreeSet<RcStr>>,
+}
+
+impl LockfileVersions {
+    fn insert(&mut self, path: Option<RcStr>, name: RcStr, version: RcStr) {
+        if let Some(path) = path {
+            self.paths.insert(path, version.clone());
+        }
+        self.packages.entry(name).or_default().insert(version);
+    }
+}
+
+/// Splits a package specifier like `react@18.3.1` or `@babel/core@^7.0.0` into the name and the
+/// version.
+fn split_specifier(specifier: &str) -> Option<(&str, &str)> {
+    let index = specifier.get(1..)?.find('@')? + 1;
+    Some((&specifier[..index], &specifier[index + 1..]))
+}
+
+/// The name of the directory of a package in the virtual store of pnpm (`node_modules/.pnpm`),
+/// like `depPathToFilename` of pnpm. Returns `None` for names that pnpm shortens with a hash.
+fn pnpm_store_dir_name(key: &str) -> Option<String> {
+    let mut name = key.replace(['\\', '/', ':', '*', '?', '"', '<', '>', '|'], "+");
+    if name.contains('(') {
+        name = name
+            .strip_suffix(')')
+            .unwrap_or(&name)
+            .replace(")(", "_")
+            .replace(['(', ')'], "_");
+    }
+    (name.len() <= 120 && name == name.to_lowercase()).then_some(name)
+}
+
+/// Reads the keys of the `packages` and `snapshots` sections of `pnpm-lock.yaml`, which are
+/// `/react/18.3.1` until lockfile version 5, `/react@18.3.1` in version 6 and `react@18.3.1` since
+/// version 9. They might be quoted and followed by the resolved peer dependencies. The install
+/// paths are the directories of the packages in the virtual store.
+fn parse_pnpm_lock(content: &str) -> LockfileVersions {
+    let mut versions = LockfileVersions::default();
+    let mut slash_separated = false;
+    let mut in_packages = false;
+    for line in content.lines() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        if !line.starts_with(' ') {
+            if let Some(version) = line.strip_prefix("lockfileVersion:") {
+                let major = version.trim().trim_matches(['\'', '"']).split('.').next();
+                slash_separated = major
+                    .and_then(|major| major.parse::<u32>().ok())
+                    .is_some_and(|major| major <= 5);
+            }
+            in_packages = matches!(line.trim_end(), "packages:" | "snapshots:");
+            continue;
+        }
+        let Some(key) = line
+            .strip_prefix("  ")
+            .and_then(|line| line.trim_end().strip_suffix(':'))
+            .filter(|key| in_packages && !key.starts_with(' '))
+        else {
+            continue;
+        };
+        let key = key.trim_matches(['\'', '"']);
+        let key = key.strip_prefix('/').unwrap_or(key);
+        let (key, specifier) = if slash_separated {
+            let Some((name, version)) = key.rsplit_once('/') else {
+                continue;
+            };
+            (
+                Cow::Owned(format!("{name}@{version}")),
+                (name, version.split('_').next().unwrap_or(version)),
+            )
+        } else {
+            let Some(specifier) = split_specifier(key.split('(').next().unwrap_or(key)) else {
+                continue;
+            };
+            (Cow::Borrowed(key), specifier)
+        };
+        let (name, version) = specifier;
+        let path = pnpm_store_dir_name(&key)
+            .map(|dir| format!("node_modules/.pnpm/{dir}/node_modules/{name}").into());
+        versions.insert(path, name.into(), version.into());
+    }
+    versions
+}
+
+/// Reads the entries of `yarn.lock`, which start with a list of the descriptors that resolve to
+/// the entry, like `"react@^18.0.0", react@^18.3.0:` or `"react@npm:^18.0.0":`, followed by the
+/// indented fields of the entry. Workspaces and linked packages are skipped, as their versions are
+/// not locked.
+fn parse_yarn_lock(content: &str) -> LockfileVersions {
+    const LOCAL_PROTOCOLS: [&str; 3] = ["workspace:", "link:", "portal:"];
+
+    let mut versions = LockfileVersions::default();
+    let mut name = None;
+    for line in content.lines() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if !line.starts_with(' ') {
+            name = line.trim_end().strip_suffix(':').and_then(|descriptors| {
+                let descriptor = descriptors.split(',').next()?.trim().trim_matches('"');
+                let (name, range) = split_specifier(descriptor)?;
+                (!LOCAL_PROTOCOLS
+                    .iter()
+                    .any(|protocol| range.starts_with(protocol)))
+                .then_some(name)
+            });
+            continue;
+        }
+        // `version "18.3.1"` in yarn v1 and `version: 18.3.1` since yarn v2
+        if let Some(name) = name
+            && let Some(version) = line
+                .strip_prefix("  ")
+                .and_then(|line| line.strip_prefix("version"))
+                .filter(|version| version.starts_with([' ', ':']))
+        {
+            let version = version.trim_start_matches(':').trim().trim_matches('"');
+            versions.insert(None, name.into(), version.into());
+        }
+    }
+    versions
+}
+
+#[derive(Deserialize)]
+struct PackageLock {
+    /// The installed packages by their path since lockfile version 2.
+    #[serde(default)]
+    packages: BTreeMap<RcStr, PackageLockPackage>,
+    /// The tree of dependencies in lockfile version 1.
+    #[serde(default)]
+    dependencies: BTreeMap<RcStr, PackageLockDependency>,
+}
+
+#[derive(Deserialize)]
+struct PackageLockPackage {
+    /// Only set when the name differs from the path, e.g. for aliases.
+    name: Option<RcStr>,
+    /// Not set for links to workspaces.
+    version: Option<RcStr>,
+}
+
+#[derive(Deserialize)]
+struct PackageLockDependency {
+    version: RcStr,
+    #[serde(default)]
+    dependencies: BTreeMap<RcStr, PackageLockDependency>,
+}
+
+/// Reads `package-lock.json` or `npm-shrinkwrap.json`.
+fn parse_package_lock(content: &str) -> Result<LockfileVersions> {
+    fn add_dependencies(
+        versions: &mut LockfileVersions,
+        dependencies: BTreeMap<RcStr, PackageLockDependency>,
+    ) {
+        for (name, dependency) in dependencies {
+            add_dependencies(versions, dependency.dependencies);
+            versions.insert(None, name, dependency.version);
+        }
+    }
+
+    let lock: PackageLock = serde_json::from_str(content)?;
+    let mut versions = LockfileVersions::default();
+    if lock.packages.is_empty() {
+        add_dependencies(&mut versions, lock.dependencies);
+        return Ok(versions);
+    }
+    for (path, package) in lock.packages {
+        let Some(version) = package.version else {
+            continue;
+        };
+        let name = match package.name {
+            Some(name) => name,
+            None => match path.rsplit_once("node_modules/") {
+                Some((_, name)) => name.into(),
+                // The root package and workspaces
+                None => continue,
+            },
+        };
+        versions.insert(Some(path), name, version);
+    }
+    Ok(versions)
+}
+
+/// Emits an issue when the version of the package in `package_dir` isn't the version that the
+/// lockfile locks at that path, or, for lockfiles without install paths, one of the versions of
+/// the package. Packages that are not in the lockfile, e.g. linked packages, are ignored.
+#[turbo_tasks::function]
+pub(super) async fn check_locked_version(
+    locked_versions: Vc<LockedVersions>,
+    package_dir: FileSystemPath,
+) -> Result<()> {
+    let locked_versions = locked_versions.await?;
+    let Some(lockfile) = &locked_versions.lockfile else {
+        return Ok(());
+    };
+    let package_json_path = package_dir.join("package.json")?;
+    let package_json =
+        read_package_json(Vc::upcast(FileSource::new(package_json_path.clone()))).await?;
+    let Some(package_json) = &*package_json else {
+        return Ok(());
+    };
+    let (Some(name), Some(version)) = (
+        package_json["name"].as_str(),
+        package_json["version"].as_str(),
+    ) else {
+        return Ok(());
+    };
+    let locked = match lockfile
+        .parent()
+        .get_path_to(&package_dir)
+        .and_then(|path| locked_versions.paths.get(path))
+    {
+        Some(locked) => vec![locked],
+        None => match locked_versions.packages.get(name) {
+            Some(locked) => locked.iter().collect(),
+            None => return Ok(()),
+        },
+    };
+    if locked.iter().any(|locked| *locked == version) {
+        return Ok(());
+    }
+    LockfileIssue {
+        severity: if locked_versions.strict {
+            IssueSeverity::Error
+        } else {
+            IssueSeverity::Warning
+        },
+        file_path: package_json_path,
+        title: format!("{name}@{version} doesn't match the lockfile").into(),
+        message: format!(
+            "{} locks {name} to {}, but {version} is installed. Run the install command of your \
+             package manager to update the installed packages.",
+            lockfile.file_name(),
+            locked
+                .iter()
+                .map(|version| &***version)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+        .into(),
+    }
+    .resolved_cell()
+    .emit();
+    Ok(())
+}
+
+#[turbo_tasks::value(shared)]
+struct LockfileIssue {
+    severity: IssueSeverity,
+    file_path: FileSystemPath,
+    title: RcStr,
+    message: RcStr,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for LockfileIssue {
+    fn severity(&self) -> IssueSeverity {
+        self.severity
+    }
+
+    #[turbo_tasks::function]
+    fn title(&self) -> Vc<StyledString> {
+        StyledString::Text(self.title.clone()).cell()
+    }
+
+    #[turbo_tasks::function]
+    fn stage(&self) -> Vc<IssueStage> {
+        IssueStage::Resolve.cell()
+    }
+
+    #[turbo_tasks::function]
+    fn file_path(&self) -> Vc<FileSystemPath> {
+        self.file_path.clone().cell()
+    }
+
+    #[turbo_tasks::function]
+    fn description(&self) -> Vc<OptionStyledString> {
+        Vc::cell(Some(
+            StyledString::Text(self.message.clone()).resolved_cell(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use turbo_tasks::{CollectiblesSource, ResolvedVc};
+    use turbo_tasks_backend::{BackendOptions, TurboTasksBackend, noop_backing_storage};
+    use turbo_tasks_fs::{FileSystem, MemoryFileSystem};
+
+    use super::*;
+
+    fn versions(versions: &LockfileVersions, name: &str) -> Vec<String> {
+        versions.packages[name]
+            .iter()
+            .map(|version| version.to_string())
+            .collect()
+    }
+
+    fn path_version<'a>(versions: &'a LockfileVersions, path: &str) -> Option<&'a str> {
+        versions.paths.get(path).map(|version| version.as_str())
+    }
+
+    #[test]
+    fn test_pnpm_lock() {
+        let v9 = "lockfileVersion: '9.0'\n\nimporters:\n\n  .:\n    dependencies:\n      \
+                  react:\n        specifier: ^18.0.0\n        version: 18.3.1\n\npackages:\n\n  \
+                  '@babel/core@7.24.0':\n    resolution: {integrity: sha512-abc}\n\n  \
+                  react@18.3.1:\n    resolution: {integrity: sha512-def}\n\n  \
+                  react@17.0.2:\n    resolution: {integrity: sha512-ghi}\n\nsnapshots:\n\n  \
+                  react-dom@18.3.1(react@18.3.1):\n    dependencies:\n      react: 18.3.1\n";
+        let packages = parse_pnpm_lock(v9);
+        assert_eq!(versions(&packages, "@babel/core"), ["7.24.0"]);
+        assert_eq!(versions(&packages, "react"), ["17.0.2", "18.3.1"]);
+        assert_eq!(versions(&packages, "react-dom"), ["18.3.1"]);
+        assert_eq!(
+            path_version(
+                &packages,
+                "node_modules/.pnpm/@babel+core@7.24.0/node_modules/@babel/core"
+            ),
+            Some("7.24.0")
+        );
+        assert_eq!(
+            path_version(
+                &packages,
+                "node_modules/.pnpm/react-dom@18.3.1_react@18.3.1/node_modules/react-dom"
+            ),
+            Some("18.3.1")
+        );
+
+        let v6 = "lockfileVersion: '6.0'\n\npackages:\n\n  /react-dom@18.3.1(react@18.3.1):\n    \
+                  resolution: {integrity: sha512-abc}\n";
+        assert_eq!(versions(&parse_pnpm_lock(v6), "react-dom"), ["18.3.1"]);
+
+        let v5 = "lockfileVersion: 5.4\n\npackages:\n\n  /@babel/core/7.24.0:\n    resolution: \
+                  {integrity: sha512-abc}\n  /react-dom/18.3.1_react@18.3.1:\n    resolution: \
+                  {integrity: sha512-def}\n";
+        let packages = parse_pnpm_lock(v5);
+        assert_eq!(versions(&packages, "@babel/core"), ["7.24.0"]);
+        assert_eq!(versions(&packages, "react-dom"), ["18.3.1"]);
+        assert_eq!(
+            path_version(
+                &packages,
+                "node_modules/.pnpm/react-dom@18.3.1_react@18.3.1/node_modules/react-dom"
+            ),
+            Some("18.3.1")
+        );
+    }
+
+    #[test]
+    fn test_yarn_lock() {
+        let v1 = "# THIS IS AN AUTOGENERATED FILE.\n# yarn lockfile v1\n\n\n\"@babel/core@^7.0.0\", \
+                  \"@babel/core@^7.1.0\":\n  version \"7.24.0\"\n  resolved \
+                  \"https://registry.yarnpkg.com/@babel/core/-/core-7.24.0.tgz\"\n  \
+                  dependencies:\n    react \"^18.0.0\"\n\nreact@^18.0.0:\n  version \"18.3.1\"\n";
+        let packages = parse_yarn_lock(v1);
+        assert_eq!(versions(&packages, "@babel/core"), ["7.24.0"]);
+        assert_eq!(versions(&packages, "react"), ["18.3.1"]);
+
+        let berry = "__metadata:\n  version: 8\n  cacheKey: 10c0\n\n\"react@npm:^18.0.0, \
+                     react@npm:^18.3.0\":\n  version: 18.3.1\n  resolution: \
+                     \"react@npm:18.3.1\"\n  dependencies:\n    loose-envify: \"npm:^1.1.0\"\n";
+        let packages = parse_yarn_lock(berry);
+        assert_eq!(packages.packages.len(), 1);
+        assert_eq!(versions(&packages, "react"), ["18.3.1"]);
+        assert!(packages.paths.is_empty());
+
+        // Workspaces and linked packages are not locked
+        let local = r#"
+"app@workspace:.":
+  version: 0.0.0-use.local
+
+"lib@link:./lib::locator=app%40workspace%3A.":
+  version: 0.0.0-use.local
+
+"pkg@portal:../pkg::locator=app%40workspace%3A.":
+  version: 0.0.0-use.local
+"#;
+        assert!(parse_yarn_lock(local).packages.is_empty());
+    }
+
+    #[test]
+    fn test_package_lock() {
+        let v3 = r#"{
+            "lockfileVersion": 3,
+            "packages": {
+                "": { "name": "app", "dependencies": { "react": "^18.0.0" } },
+                "node_modules/react": { "version": "18.3.1" },
+                "node_modules/@babel/core": { "version": "7.24.0" },
+                "node_modules/@babel/core/node_modules/react": { "version": "17.0.2" },
+                "node_modules/lodash-es": { "name": "lodash", "version": "4.17.21" },
+                "node_modules/lib": { "resolved": "packages/lib", "link": true },
+                "packages/lib": { "name": "lib", "version": "1.0.0" }
+            }
+        }"#;
+        let packages = parse_package_lock(v3).unwrap();
+        assert_eq!(versions(&packages, "react"), ["17.0.2", "18.3.1"]);
+        assert_eq!(versions(&packages, "@babel/core"), ["7.24.0"]);
+        assert_eq!(versions(&packages, "lodash"), ["4.17.21"]);
+        assert_eq!(versions(&packages, "lib"), ["1.0.0"]);
+        assert!(!packages.packages.contains_key("app"));
+        assert_eq!(
+            path_version(&packages, "node_modules/@babel/core/node_modules/react"),
+            Some("17.0.2")
+        );
+        assert_eq!(path_version(&packages, "packages/lib"), Some("1.0.0"));
+        assert_eq!(path_version(&packages, "node_modules/lib"), None);
+
+        let v1 = r#"{
+            "lockfileVersion": 1,
+            "dependencies": {
+                "react": { "version": "18.3.1" },
+                "@babel/core": {
+                    "version": "7.24.0",
+                    "dependencies": { "react": { "version": "17.0.2" } }
+                }
+            }
+        }"#;
+        let packages = parse_package_lock(v1).unwrap();
+        assert_eq!(versions(&packages, "react"), ["17.0.2", "18.3.1"]);
+        assert!(packages.paths.is_empty());
+    }
+
+    #[turbo_tasks::function(operation)]
+    async fn check_locked_version_operation(
+        fs: ResolvedVc<MemoryFileSystem>,
+        package_dir: RcStr,
+    ) -> Result<()> {
+        let root = fs.root().owned().await?;
+        let locked_versions = LockedVersions::new(root.join("packages/app")?, false);
+        check_locked_version(locked_versions, root.join(&package_dir)?).await?;
+        Ok(())
+    }
+
+    /// Checks the version of the package in `package_dir` and returns the number of issues.
+    async fn check(fs: ResolvedVc<MemoryFileSystem>, package_dir: &str) -> Result<usize> {
+        let operation = check_locked_version_operation(fs, package_dir.into());
+        operation.read_strongly_consistent().await?;
+        Ok(operation.peek_collectibles::<Box<dyn Issue>>().len())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_check_locked_version() {
+        let tt = turbo_tasks::TurboTasks::new(TurboTasksBackend::new(
+            BackendOptions::default(),
+            noop_backing_storage(),
+        ));
+        tt.run_once(async move {
+            let fs = MemoryFileSystem::new(rcstr!("project"))
+                .to_resolved()
+                .await?;
+            {
+                let fs = fs.await?;
+                fs.write_file(
+                    "package-lock.json",
+                    r#"{
+                        "lockfileVersion": 3,
+                        "packages": {
+                            "": { "name": "root" },
+                            "node_modules/react": { "version": "18.3.1" },
+                            "node_modules/lib/node_modules/react": { "version": "17.0.2" },
+                            "node_modules/lib": { "version": "1.0.0" }
+                        }
+                    }"#,
+                )?;
+                fs.write_file(
+                    "node_modules/react/package.json",
+                    r#"{ "name": "react", "version": "17.0.2" }"#,
+                )?;
+                fs.write_file(
+                    "node_modules/lib/node_modules/react/package.json",
+                    r#"{ "name": "react", "version": "17.0.2" }"#,
+                )?;
+                fs.write_file(
+                    "node_modules/lib/package.json",
+                    r#"{ "name": "lib", "version": "1.0.0" }"#,
+                )?;
+                fs.write_file(
+                    "node_modules/other/package.json",
+                    r#"{ "name": "other", "version": "1.0.0" }"#,
+                )?;
+            }
+
+            // The version is locked for the path, even though the lockfile contains it elsewhere
+            assert_eq!(check(fs, "node_modules/react").await?, 1);
+            assert_eq!(check(fs, "node_modules/lib/node_modules/react").await?, 0);
+            assert_eq!(check(fs, "node_modules/lib").await?, 0);
+            // Packages that aren't in the lockfile are ignored
+            assert_eq!(check(fs, "node_modules/other").await?, 0);
+
+            anyhow::Ok(())
+        })
+        .await
+        .unwrap();
+    }
+}
@@ -43,22 +43,26 @@ use crate::{
     reference_type::ReferenceType,
     resolve::{
         alias_map::AliasKey,
+        lockfile::check_locked_version,
         node::{node_cjs_resolve_options, node_esm_resolve_options},
         parse::stringify_data_uri,
         pattern::{PatternMatch, read_matches},
         plugin::AfterResolvePlugin,
+        pnp::{PnpPackageResult, find_pnp_package},
         remap::ReplacedSubpathValueResult,
     },
     source::{OptionSource, Source, Sources},
 };
 
 mod alias_map;
+pub mod lockfile;
 pub mod node;
 pub mod options;
 pub mod origin;
 pub mod parse;
 pub mod pattern;
 pub mod plugin;
+mod pnp;
 pub(crate) mod remap;
 
 pub use alias_map::{
@@ -1270,8 +1274,11 @@ async fn find_package(
         }
     }
 
+    // Packages that are managed by Plug'n'Play must not be looked up in node_modules
+    let mut resolved_by_pnp = false;
     for resolve_modules in &options.modules {
         match resolve_modules {
+            ResolveModules::Nested(..) if resolved_by_pnp => {}
             ResolveModules::Nested(root, names) => {
                 let mut lookup_path = lookup_path.clone();
                 let mut lookup_path_value = lookup_path.clone();
@@ -1310,6 +1317,35 @@ async fn find_package(
                     lookup_path_value = new_context_value;
                 }
             }
+            ResolveModules::Pnp(manifest) => {
+                let Some(name) = package_name.as_constant_string() else {
+                    continue;
+                };
+                match find_pnp_package(
+                    manifest,
+                    &lookup_path,
+                    name,
+                    collect_affecting_sources.then_some(&mut affecting_sources),
+                )
+                .await?
+                {
+                    PnpPackageResult::NotManaged => {}
+                    PnpPackageResult::Found { name, dir } => {
+                        packages.push(FindPackageItem::PackageDirectory {
+                            name,
+                            dir: realpath(
+                                &dir,
+                                collect_affecting_sources.then_some(&mut affecting_sources),
+                            )
+                            .await?,
+                        });
+                        resolved_by_pnp = true;
+                    }
+                    PnpPackageResult::Failed => {
+                        resolved_by_pnp = true;
+                    }
+                }
+            }
             ResolveModules::Path {
                 dir,
                 excluded_extensions,
@@ -2565,6 +2601,9 @@ async fn resolve_module_request(
     for item in &result.packages {
         match item {
             FindPackageItem::PackageDirectory { name, dir } => {
+                if let Some(locked_versions) = options_value.locked_versions {
+                    check_locked_version(*locked_versions, dir.clone()).await?;
+                }
                 results.push(
                     resolve_into_package(
                         path.clone(),
@@ -9,6 +9,7 @@ use turbo_tasks::{
 };
 use turbo_tasks_fs::{FileSystemPath, glob::Glob};
 
+pub use super::lockfile::LockedVersions;
 use super::{
     AliasPattern, ExternalType, ResolveResult, ResolveResultItem,
     alias_map::{AliasMap, AliasTemplate},
@@ -17,10 +18,6 @@ use super::{
 };
 use crate::resolve::{ExternalTraced, parse::Request, plugin::AfterResolvePlugin};
 
-#[turbo_tasks::value(shared)]
-#[derive(Hash, Debug)]
-pub struct LockedVersions {}
-
 #[turbo_tasks::value(transparent)]
 #[derive(Debug)]
 pub struct ExcludedExtensions(pub FxIndexSet<RcStr>);
@@ -47,6 +44,9 @@ pub enum ResolveModules {
         dir: FileSystemPath,
         excluded_extensions: ResolvedVc<ExcludedExtensions>,
     },
+    /// resolve packages with the Yarn Plug'n'Play manifest (`.pnp.cjs` or `.pnp.data.json`) at
+    /// that path, for imports from packages that are part of it
+    Pnp(FileSystemPath),
 }
 
 #[derive(
@@ -615,6 +615,9 @@ pub struct ResolveOptions {
     /// An import map to use when a request is otherwise unresolvable.
     pub fallback_import_map: Option<ResolvedVc<ImportMap>>,
     pub resolved_map: Option<ResolvedVc<ResolvedMap>>,
+    /// The versions of the lockfile that packages resolved from node_modules are checked
+    /// against.
+    pub locked_versions: Option<ResolvedVc<LockedVersions>>,
     pub before_resolve_plugins: Vec<ResolvedVc<Box<dyn BeforeResolvePlugin>>>,
     pub after_resolve_plugins: Vec<ResolvedVc<Box<dyn AfterResolvePlugin>>>,
     /// Support resolving *.js requests to *.ts files
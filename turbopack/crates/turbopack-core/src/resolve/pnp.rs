@@ -0,0 +1,922 @@
+//! Resolving packages with the manifest of Yarn Plug'n'Play (`.pnp.cjs` or `.pnp.data.json`),
+//! which maps every package to the location of its files and the versions of its dependencies.
+//! See <https://yarnpkg.com/advanced/pnp-spec>.
+
+use std::str::Chars;
+
+use anyhow::{Context, Result, anyhow, bail};
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::Deserialize;
+use turbo_rcstr::{RcStr, rcstr};
+use turbo_tasks::{ResolvedVc, ValueToString, Vc};
+use turbo_tasks_fs::{
+    ArchiveFileSystem, FileContent, FileMeta, FileSystem, FileSystemEntryType, FileSystemPath,
+    LinkContent, RawDirectoryContent,
+};
+use turbo_unix_path::{get_parent_path, normalize_request};
+
+use crate::{
+    file_source::FileSource,
+    issue::{Issue, IssueExt, IssueStage, OptionStyledString, StyledString},
+    source::Source,
+};
+
+/// The file that Yarn writes the manifest to when it isn't inlined into `.pnp.cjs`.
+const PNP_DATA_FILE: &str = ".pnp.data.json";
+
+/// The declaration of the inlined manifest in `.pnp.cjs`, which is followed by a string literal.
+const RAW_RUNTIME_STATE: &str = "RAW_RUNTIME_STATE =";
+
+/// A package in the manifest, identified by its name and reference. Both are `None` for the
+/// top-level package.
+type Locator = (Option<RcStr>, Option<RcStr>);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PnpDependencyTarget {
+    /// The reference of the package with the name of the dependency.
+    Reference(RcStr),
+    /// The name and reference of another package, e.g. for `"alias": "npm:package@1.0.0"`.
+    Alias(RcStr, RcStr),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PnpPackageData {
+    package_location: RcStr,
+    /// A `null` target is a peer dependency that isn't provided by the ancestors.
+    #[serde(default)]
+    package_dependencies: Vec<(RcStr, Option<PnpDependencyTarget>)>,
+}
+
+/// The serialized manifest. `ignorePatternData` is not supported, since it's a JavaScript regex.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PnpData {
+    #[serde(default)]
+    enable_top_level_fallback: bool,
+    #[serde(default)]
+    fallback_pool: Vec<(RcStr, Option<PnpDependencyTarget>)>,
+    #[serde(default)]
+    fallback_exclusion_list: Vec<(RcStr, Vec<RcStr>)>,
+    package_registry_data: Vec<(Option<RcStr>, Vec<(Option<RcStr>, PnpPackageData)>)>,
+}
+
+struct PnpPackage {
+    /// The location relative to the directory of the manifest, normalized with
+    /// [`normalize_request`], e.g. `./packages/app`,
+    /// `../.yarn/berry/cache/react-npm-18.3.1-af38f3c1ae-10c0.zip/node_modules/react` or
+    /// `./.yarn/__virtual__/react-dom-virtual-abc/0/cache/react-dom-npm-18.3.1-a805663f38-10c0.
+    /// zip/node_modules/react-dom` for a virtual package.
+    location: RcStr,
+    dependencies: FxHashMap<RcStr, Option<PnpDependencyTarget>>,
+}
+
+struct PnpManifestData {
+    packages: FxHashMap<Locator, PnpPackage>,
+    /// The packages by their location, to find the package that contains an issuer.
+    locators_by_location: FxHashMap<RcStr, Locator>,
+    enable_top_level_fallback: bool,
+    fallback_pool: FxHashMap<RcStr, Option<PnpDependencyTarget>>,
+    fallback_exclusions: FxHashSet<Locator>,
+}
+
+impl PnpManifestData {
+    fn new(data: PnpData) -> Self {
+        let mut packages = FxHashMap::default();
+        // Virtual packages have locations of their own, but the top-level package shares its
+        // location with the root workspace, which wins as it's the more specific package.
+        let mut locators_by_location: FxHashMap<RcStr, Locator> = FxHashMap::default();
+        for (name, references) in data.package_registry_data {
+            for (reference, package) in references {
+                let location: RcStr =
+                    normalize_request(package.package_location.trim_end_matches('/')).into();
+                let locator = (name.clone(), reference);
+                if locator.0.is_some() || !locators_by_location.contains_key(&location) {
+                    locators_by_location.insert(location.clone(), locator.clone());
+                }
+                packages.insert(
+                    locator,
+                    PnpPackage {
+                        location,
+                        dependencies: package.package_dependencies.into_iter().collect(),
+                    },
+                );
+            }
+        }
+        let fallback_exclusions = data
+            .fallback_exclusion_list
+            .into_iter()
+            .flat_map(|(name, references)| {
+                references
+                    .into_iter()
+                    .map(move |reference| (Some(name.clone()), Some(reference)))
+            })
+            .collect();
+        PnpManifestData {
+            packages,
+            locators_by_location,
+            enable_top_level_fallback: data.enable_top_level_fallback,
+            fallback_pool: data.fallback_pool.into_iter().collect(),
+            fallback_exclusions,
+        }
+    }
+
+    fn parse(json: &str) -> Result<Self> {
+        Ok(Self::new(serde_json::from_str(json)?))
+    }
+
+    /// Finds the package that contains `location`, a path relative to the directory of the
+    /// manifest in the form of [`PnpPackage::location`].
+    fn find_locator(&self, mut location: &str) -> Option<&Locator> {
+        while !location.is_empty() {
+            if let Some(locator) = self.locators_by_location.get(location) {
+                return Some(locator);
+            }
+            location = get_parent_path(location);
+        }
+        None
+    }
+
+    /// Resolves the dependency `name` of the package `issuer`. Returns a message explaining why
+    /// the dependency can't be resolved on failure.
+    fn resolve_dependency(&self, issuer: &Locator, name: &str) -> Result<&PnpPackage, String> {
+        let access = match &issuer.0 {
+            Some(issuer_name) => format!("{issuer_name} tried to access {name}"),
+            None => format!("Your application tried to access {name}"),
+        };
+        let Some(issuer_package) = self.packages.get(issuer) else {
+            return Err(format!(
+                "{access}, but its package is missing in the manifest. The manifest might be \
+                 corrupted, try running `yarn install`."
+            ));
+        };
+        let target = match issuer_package.dependencies.get(name) {
+            Some(target) => target,
+            None => match self.fallback_pool.get(name) {
+                Some(target)
+                    if self.enable_top_level_fallback
+                        && !self.fallback_exclusions.contains(issuer) =>
+                {
+                    target
+                }
+                _ if issuer.0.is_some() => {
+                    return Err(format!(
+                        "{access}, but it isn't declared in its dependencies; this makes the \
+                         require call ambiguous and unsound."
+                    ));
+                }
+                _ => {
+                    return Err(format!(
+                        "{access}, but it isn't declared in your dependencies; this makes the \
+                         require call ambiguous and unsound."
+                    ));
+                }
+            },
+        };
+        let locator = match target {
+            Some(PnpDependencyTarget::Reference(reference)) => {
+                (Some(name.into()), Some(reference.clone()))
+            }
+            Some(PnpDependencyTarget::Alias(name, reference)) => {
+                (Some(name.clone()), Some(reference.clone()))
+            }
+            None => {
+                return Err(format!(
+                    "{access} (a peer dependency), but it isn't provided by its ancestors; this \
+                     makes the require call ambiguous and unsound."
+                ));
+            }
+        };
+        self.packages.get(&locator).ok_or_else(|| {
+            format!(
+                "{access}, but its package {}@{} is missing in the manifest. The manifest might \
+                 be corrupted, try running `yarn install`.",
+                locator.0.as_deref().unwrap_or_default(),
+                locator.1.as_deref().unwrap_or_default()
+            )
+        })
+    }
+}
+
+/// Returns the location of the files of a virtual package, like Yarn's `VirtualFS`. Virtual paths
+/// are written as `<base>/__virtual__/<hash>/<depth>/<path>`, which stands for `<path>` relative
+/// to `<depth>` levels above `<base>`. Returns `None` for locations that aren't virtual.
+fn virtual_location_target(location: &str) -> Option<String> {
+    let segments = location.split('/').collect::<Vec<_>>();
+    let index = segments
+        .iter()
+        .position(|segment| *segment == "__virtual__" || *segment == "$$virtual")?;
+    let depth = segments.get(index + 2)?.parse::<usize>().ok()?;
+    let base = segments[..index].join("/");
+    let path = segments[index + 3..].join("/");
+    Some(normalize_request(&format!(
+        "{base}/{}{path}",
+        "../".repeat(depth)
+    )))
+}
+
+/// Extracts the manifest that Yarn inlines into `.pnp.cjs` as a single-quoted string literal.
+/// Returns `None` if the manifest isn't inlined.
+fn extract_runtime_state(code: &str) -> Result<Option<String>> {
+    let Some(index) = code.find(RAW_RUNTIME_STATE) else {
+        return Ok(None);
+    };
+    let literal = code[index + RAW_RUNTIME_STATE.len()..].trim_start();
+    let mut chars = literal.chars();
+    if chars.next() != Some('\'') {
+        bail!("{RAW_RUNTIME_STATE} is not followed by a string literal");
+    }
+    let mut state = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => return Ok(Some(state)),
+            '\\' => match chars.next() {
+                // Line continuations
+                Some('\n' | '\u{2028}' | '\u{2029}') => {}
+                Some('\r') => {
+                    if chars.clone().next() == Some('\n') {
+                        chars.next();
+                    }
+                }
+                Some('n') => state.push('\n'),
+                Some('r') => state.push('\r'),
+                Some('t') => state.push('\t'),
+                Some('b') => state.push('\u{8}'),
+                Some('f') => state.push('\u{c}'),
+                Some('v') => state.push('\u{b}'),
+                Some('0') => state.push('\0'),
+                Some('x') => {
+                    let code = read_hex(&mut chars, 2)?;
+                    state.push(char::from_u32(code).context("invalid \\x escape sequence")?);
+                }
+                Some('u') => state.push(read_unicode_escape(&mut chars)?),
+                Some(c) => state.push(c),
+                None => break,
+            },
+            c => state.push(c),
+        }
+    }
+    bail!("The string literal of {RAW_RUNTIME_STATE} is not terminated")
+}
+
+/// Reads the `len` hex digits of an escape sequence.
+fn read_hex(chars: &mut Chars<'_>, len: usize) -> Result<u32> {
+    let digits = chars.take(len).collect::<String>();
+    if digits.len() != len {
+        bail!("The string literal of {RAW_RUNTIME_STATE} ends within an escape sequence");
+    }
+    u32::from_str_radix(&digits, 16)
+        .with_context(|| format!("invalid hex digits in the escape sequence {digits}"))
+}
+
+/// Reads the character of a `\uXXXX` or `\u{X}` escape sequence after the `\u`. Surrogate pairs,
+/// which are written as two escape sequences, are combined.
+fn read_unicode_escape(chars: &mut Chars<'_>) -> Result<char> {
+    let code = if chars.clone().next() == Some('{') {
+        chars.next();
+        let digits = chars.take_while(|c| *c != '}').collect::<String>();
+        u32::from_str_radix(&digits, 16)
+            .with_context(|| format!("invalid hex digits in the escape sequence {digits}"))?
+    } else {
+        read_hex(chars, 4)?
+    };
+    if (0xd800..0xdc00).contains(&code) {
+        let mut rest = chars.clone();
+        if rest.next() == Some('\\')
+            && rest.next() == Some('u')
+            && let Ok(low) = read_hex(&mut rest, 4)
+            && (0xdc00..0xe000).contains(&low)
+        {
+            *chars = rest;
+            let code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+            return char::from_u32(code).context("invalid surrogate pair");
+        }
+    }
+    char::from_u32(code).with_context(|| format!("invalid escape sequence \\u{{{code:x}}}"))
+}
+
+#[turbo_tasks::value(cell = "new", eq = "manual", serialization = "none")]
+struct PnpManifest {
+    /// The directory that the package locations are relative to.
+    dir: FileSystemPath,
+    /// The files that the manifest has been read from.
+    files: Vec<FileSystemPath>,
+    /// `None` if the manifest is missing or invalid.
+    #[turbo_tasks(debug_ignore, trace_ignore)]
+    data: Option<PnpManifestData>,
+}
+
+#[turbo_tasks::function]
+async fn pnp_manifest(manifest_path: FileSystemPath) -> Result<Vc<PnpManifest>> {
+    let dir = manifest_path.parent();
+    let mut files = vec![manifest_path.clone()];
+    let content = manifest_path.read().await?;
+    let FileContent::Content(file) = &*content else {
+        return Ok(PnpManifest {
+            dir,
+            files,
+            data: None,
+        }
+        .cell());
+    };
+    let code = file.content().to_str()?;
+    let data = if manifest_path.has_extension(".json") {
+        PnpManifestData::parse(&code)
+    } else {
+        match extract_runtime_state(&code) {
+            Ok(Some(json)) => PnpManifestData::parse(&json),
+            Ok(None) => {
+                let data_path = dir.join(PNP_DATA_FILE)?;
+                files.push(data_path.clone());
+                match &*data_path.read().await? {
+                    FileContent::Content(file) => PnpManifestData::parse(&file.content().to_str()?),
+                    FileContent::NotFound => Err(anyhow!(
+                        "The manifest is neither inlined nor written to {PNP_DATA_FILE}"
+                    )),
+                }
+            }
+            Err(err) => Err(err),
+        }
+    };
+    let data = match data {
+        Ok(data) => Some(data),
+        Err(err) => {
+            PnpIssue {
+                file_path: files.last().unwrap().clone(),
+                title: rcstr!("Invalid Yarn Plug'n'Play manifest"),
+                message: format!("{err:#}").into(),
+            }
+            .resolved_cell()
+            .emit();
+            None
+        }
+    };
+    Ok(PnpManifest { dir, files, data }.cell())
+}
+
+pub(super) enum PnpPackageResult {
+    /// The manifest is missing or the issuer isn't part of it, so the request is resolved like
+    /// without Plug'n'Play.
+    NotManaged,
+    Found {
+        name: RcStr,
+        dir: FileSystemPath,
+    },
+    /// The manifest manages the issuer, but the request can't be resolved. An issue has been
+    /// emitted.
+    Failed,
+}
+
+/// Resolves the package `package_name` for an import in `lookup_path` with the manifest at
+/// `manifest_path`. Only constant package names are resolved.
+pub(super) async fn find_pnp_package(
+    manifest_path: &FileSystemPath,
+    lookup_path: &FileSystemPath,
+    package_name: &RcStr,
+    affecting_sources: Option<&mut Vec<ResolvedVc<Box<dyn Source>>>>,
+) -> Result<PnpPackageResult> {
+    let manifest = pnp_manifest(manifest_path.clone()).await?;
+    if let Some(affecting_sources) = affecting_sources {
+        for file in &manifest.files {
+            affecting_sources.push(ResolvedVc::upcast(
+                FileSource::new(file.clone()).to_resolved().await?,
+            ));
+        }
+    }
+    let Some(data) = &manifest.data else {
+        return Ok(PnpPackageResult::NotManaged);
+    };
+    let Some(issuer_location) = pnp_location(&manifest.dir, lookup_path).await? else {
+        return Ok(PnpPackageResult::NotManaged);
+    };
+    let Some(issuer) = data.find_locator(&issuer_location) else {
+        return Ok(PnpPackageResult::NotManaged);
+    };
+    let package = match data.resolve_dependency(issuer, package_name) {
+        Ok(package) => package,
+        Err(message) => {
+            PnpIssue {
+                file_path: lookup_path.clone(),
+                title: format!("Can't resolve {package_name} with Yarn Plug'n'Play").into(),
+                message: message.into(),
+            }
+            .resolved_cell()
+            .emit();
+            return Ok(PnpPackageResult::Failed);
+        }
+    };
+    let virtual_target = virtual_location_target(&package.location);
+    let location = virtual_target.as_deref().unwrap_or(&package.location);
+    if let Some(dir) = package_dir(&manifest.dir, location).await?
+        && *dir.get_type().await? == FileSystemEntryType::Directory
+    {
+        let dir = if virtual_target.is_some() {
+            Vc::upcast::<Box<dyn FileSystem>>(PnpVirtualFileSystem::new(
+                package.location.clone(),
+                dir,
+            ))
+            .root()
+            .owned()
+            .await?
+        } else {
+            dir
+        };
+        return Ok(PnpPackageResult::Found {
+            name: package_name.clone(),
+            dir,
+        });
+    }
+    PnpIssue {
+        file_path: lookup_path.clone(),
+        title: format!("Can't resolve {package_name} with Yarn Plug'n'Play").into(),
+        message: format!(
+            "The package is expected at {location}, but it isn't installed. Run `yarn install` to \
+             install the dependencies."
+        )
+        .into(),
+    }
+    .resolved_cell()
+    .emit();
+    Ok(PnpPackageResult::Failed)
+}
+
+/// Returns the location of `path` relative to the directory of the manifest, looking through
+/// virtual packages and archives that are mounted with [`ArchiveFileSystem`].
+async fn pnp_location(
+    manifest_dir: &FileSystemPath,
+    path: &FileSystemPath,
+) -> Result<Option<RcStr>> {
+    if let Some(virtual_fs) = ResolvedVc::try_downcast_type::<PnpVirtualFileSystem>(path.fs) {
+        let virtual_fs = virtual_fs.await?;
+        return Ok(Some(if path.path.is_empty() {
+            virtual_fs.location.clone()
+        } else {
+            format!("{}/{}", virtual_fs.location, path.path).into()
+        }));
+    }
+    if let Some(archive_fs) = ResolvedVc::try_downcast_type::<ArchiveFileSystem>(path.fs) {
+        let archive_fs = archive_fs.await?;
+        let Some(location) = manifest_dir.get_relative_path_to(archive_fs.archive()) else {
+            return Ok(None);
+        };
+        return Ok(Some(if path.path.is_empty() {
+            location
+        } else {
+            format!("{location}/{}", path.path).into()
+        }));
+    }
+    Ok(manifest_dir.get_relative_path_to(path))
+}
+
+/// Returns the directory of a package location. Packages inside of a zip archive, as stored in
+/// the cache of Yarn, are read with an [`ArchiveFileSystem`].
+async fn package_dir(
+    manifest_dir: &FileSystemPath,
+    location: &str,
+) -> Result<Option<FileSystemPath>> {
+    let segments = location.split('/').collect::<Vec<_>>();
+    let Some(index) = segments
+        .iter()
+        .position(|segment| segment.ends_with(".zip"))
+    else {
+        return manifest_dir.try_join(location);
+    };
+    let Some(archive) = manifest_dir.try_join(&segments[..=index].join("/"))? else {
+        return Ok(None);
+    };
+    let archive_fs = ArchiveFileSystem::new(archive.file_name().into(), archive);
+    let root = Vc::upcast::<Box<dyn FileSystem>>(archive_fs)
+        .root()
+        .owned()
+        .await?;
+    root.try_join(&segments[index + 1..].join("/"))
+}
+
+/// The files of a virtual package, which Yarn creates for every set of peer dependencies that a
+/// package is used with. Like Yarn's `VirtualFS`, the files are read from the package that the
+/// virtual package is created from, but keep their virtual paths, so that the dependencies of the
+/// virtual package are resolved for them and every virtual package gets modules of its own.
+#[turbo_tasks::value]
+struct PnpVirtualFileSystem {
+    /// The virtual location of the package, see [`PnpPackage::location`].
+    location: RcStr,
+    /// The directory that the files are read from.
+    target: FileSystemPath,
+}
+
+#[turbo_tasks::value_impl]
+impl PnpVirtualFileSystem {
+    #[turbo_tasks::function]
+    fn new(location: RcStr, target: FileSystemPath) -> Vc<Self> {
+        PnpVirtualFileSystem { location, target }.cell()
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl FileSystem for PnpVirtualFileSystem {
+    #[turbo_tasks::function]
+    fn read(&self, fs_path: FileSystemPath) -> Result<Vc<FileContent>> {
+        Ok(self.target.join(&fs_path.path)?.read())
+    }
+
+    #[turbo_tasks::function]
+    fn read_link(&self, fs_path: FileSystemPath) -> Result<Vc<LinkContent>> {
+        Ok(self.target.join(&fs_path.path)?.read_link())
+    }
+
+    #[turbo_tasks::function]
+    fn raw_read_dir(&self, fs_path: FileSystemPath) -> Result<Vc<RawDirectoryContent>> {
+        Ok(self.target.join(&fs_path.path)?.raw_read_dir())
+    }
+
+    #[turbo_tasks::function]
+    fn write(&self, _fs_path: FileSystemPath, _content: Vc<FileContent>) -> Result<Vc<()>> {
+        bail!(
+            "Writing is not possible to the virtual package {}",
+            self.location
+        )
+    }
+
+    #[turbo_tasks::function]
+    fn write_link(&self, _fs_path: FileSystemPath, _target: Vc<LinkContent>) -> Result<Vc<()>> {
+        bail!(
+            "Writing is not possible to the virtual package {}",
+            self.location
+        )
+    }
+
+    #[turbo_tasks::function]
+    fn metadata(&self, fs_path: FileSystemPath) -> Result<Vc<FileMeta>> {
+        Ok(self.target.join(&fs_path.path)?.metadata())
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ValueToString for PnpVirtualFileSystem {
+    #[turbo_tasks::function]
+    fn to_string(&self) -> Vc<RcStr> {
+        Vc::cell(self.location.clone())
+    }
+}
+
+#[turbo_tasks::value(shared)]
+struct PnpIssue {
+    file_path: FileSystemPath,
+    title: RcStr,
+    message: RcStr,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for PnpIssue {
+    #[turbo_tasks::function]
+    fn title(&self) -> Vc<StyledString> {
+        StyledString::Text(self.title.clone()).cell()
+    }
+
+    #[turbo_tasks::function]
+    fn stage(&self) -> Vc<IssueStage> {
+        IssueStage::Resolve.cell()
+    }
+
+    #[turbo_tasks::function]
+    fn file_path(&self) -> Vc<FileSystemPath> {
+        self.file_path.clone().cell()
+    }
+
+    #[turbo_tasks::function]
+    fn description(&self) -> Vc<OptionStyledString> {
+        Vc::cell(Some(
+            StyledString::Text(self.message.clone()).resolved_cell(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use rstest::*;
+    use turbo_tasks_backend::{BackendOptions, TurboTasksBackend, noop_backing_storage};
+    use turbo_tasks_fs::MemoryFileSystem;
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+    use crate::{
+        reference_type::ReferenceType,
+        resolve::{
+            options::{ResolveIntoPackage, ResolveModules, ResolveOptions},
+            parse::Request,
+            resolve,
+        },
+    };
+
+    const MANIFEST: &str = r#"{
+        "enableTopLevelFallback": true,
+        "fallbackPool": [["lodash", "npm:4.17.21"]],
+        "fallbackExclusionList": [["app", ["workspace:packages/app"]]],
+        "packageRegistryData": [
+            [null, [[null, {
+                "packageLocation": "./",
+                "packageDependencies": [["app", "workspace:packages/app"], ["lodash", "npm:4.17.21"]]
+            }]]],
+            ["root", [["workspace:.", {
+                "packageLocation": "./",
+                "packageDependencies": [["app", "workspace:packages/app"], ["lodash", "npm:4.17.21"]]
+            }]]],
+            ["app", [["workspace:packages/app", {
+                "packageLocation": "./packages/app/",
+                "packageDependencies": [
+                    ["react", "npm:18.3.1"],
+                    ["react-dom", "virtual:abc#npm:18.3.1"],
+                    ["underscore", ["lodash", "npm:4.17.21"]]
+                ]
+            }]]],
+            ["lodash", [["npm:4.17.21", {
+                "packageLocation": "./.yarn/cache/lodash-npm-4.17.21-6382451519-10c0.zip/node_modules/lodash/",
+                "packageDependencies": []
+            }]]],
+            ["react", [["npm:18.3.1", {
+                "packageLocation": "./.yarn/cache/react-npm-18.3.1-af38f3c1ae-10c0.zip/node_modules/react/",
+                "packageDependencies": []
+            }]]],
+            ["react-dom", [
+                ["npm:18.3.1", {
+                    "packageLocation": "./.yarn/cache/react-dom-npm-18.3.1-a805663f38-10c0.zip/node_modules/react-dom/",
+                    "packageDependencies": [["react", null]]
+                }],
+                ["virtual:abc#npm:18.3.1", {
+                    "packageLocation": "./.yarn/__virtual__/react-dom-virtual-abc/0/cache/react-dom-npm-18.3.1-a805663f38-10c0.zip/node_modules/react-dom/",
+                    "packageDependencies": [["react", "npm:18.3.1"]]
+                }],
+                ["virtual:def#npm:18.3.1", {
+                    "packageLocation": "./.yarn/__virtual__/react-dom-virtual-def/0/cache/react-dom-npm-18.3.1-a805663f38-10c0.zip/node_modules/react-dom/",
+                    "packageDependencies": [["react", ["lodash", "npm:4.17.21"]]]
+                }]
+            ]]
+        ]
+    }"#;
+
+    fn resolve(data: &PnpManifestData, issuer: &str, name: &str) -> Result<String, String> {
+        let issuer = data.find_locator(issuer).unwrap();
+        data.resolve_dependency(issuer, name)
+            .map(|package| package.location.to_string())
+    }
+
+    #[test]
+    fn test_extract_runtime_state() {
+        let code = "#!/usr/bin/env node\n/* eslint-disable */\n\"use strict\";\n\nconst \
+                    RAW_RUNTIME_STATE =\n'{\\\n  \"__info\": [\"It\\'s generated\"],\\\n  \
+                    \"path\": \"a\\\\\\\\b\"\\\n}';\n\nfunction $$SETUP_STATE() {}\n";
+        assert_eq!(
+            extract_runtime_state(code).unwrap().unwrap(),
+            "{\n  \"__info\": [\"It's generated\"],\n  \"path\": \"a\\\\b\"\n}"
+        );
+        assert_eq!(
+            extract_runtime_state("function $$SETUP_STATE() {}").unwrap(),
+            None
+        );
+        assert!(extract_runtime_state("const RAW_RUNTIME_STATE = '{").is_err());
+    }
+
+    #[test]
+    fn test_extract_runtime_state_escapes() {
+        let code = r"const RAW_RUNTIME_STATE = '\x41\u00e9\u{1F600}\uD83D\uDE00\0\v';";
+        assert_eq!(
+            extract_runtime_state(code).unwrap().unwrap(),
+            "A\u{e9}\u{1F600}\u{1F600}\0\u{b}"
+        );
+        assert!(extract_runtime_state(r"const RAW_RUNTIME_STATE = '\u00';").is_err());
+        assert!(extract_runtime_state(r"const RAW_RUNTIME_STATE = '\xZZ';").is_err());
+    }
+
+    #[rstest]
+    #[case("./packages/app", None)]
+    #[case("../../.yarn/berry/cache/a.zip/node_modules/a", None)]
+    #[case(
+        "./.yarn/__virtual__/a-virtual-abc/0/cache/a.zip/node_modules/a",
+        Some("./.yarn/cache/a.zip/node_modules/a")
+    )]
+    #[case("./.yarn/__virtual__/a-virtual-abc/1/packages/a", Some("./packages/a"))]
+    #[case("./.yarn/$$virtual/a-virtual-abc/3/x", Some("../../x"))]
+    fn test_virtual_location_target(#[case] location: &str, #[case] target: Option<&str>) {
+        assert_eq!(virtual_location_target(location).as_deref(), target);
+    }
+
+    #[test]
+    fn test_resolve_dependency() {
+        let data = PnpManifestData::parse(MANIFEST).unwrap();
+        assert_eq!(
+            resolve(&data, "./packages/app/src/index.js", "react").unwrap(),
+            "./.yarn/cache/react-npm-18.3.1-af38f3c1ae-10c0.zip/node_modules/react"
+        );
+        // Aliases resolve to the package they point to
+        assert_eq!(
+            resolve(&data, "./packages/app", "underscore").unwrap(),
+            "./.yarn/cache/lodash-npm-4.17.21-6382451519-10c0.zip/node_modules/lodash"
+        );
+        // Files of a virtual package resolve the dependencies of the virtual package
+        assert_eq!(
+            resolve(
+                &data,
+                "./.yarn/__virtual__/react-dom-virtual-abc/0/cache/react-dom-npm-18.3.\
+                 1-a805663f38-10c0.zip/node_modules/react-dom/index.js",
+                "react"
+            )
+            .unwrap(),
+            "./.yarn/cache/react-npm-18.3.1-af38f3c1ae-10c0.zip/node_modules/react"
+        );
+        assert_eq!(
+            resolve(
+                &data,
+                "./.yarn/__virtual__/react-dom-virtual-def/0/cache/react-dom-npm-18.3.\
+                 1-a805663f38-10c0.zip/node_modules/react-dom/index.js",
+                "react"
+            )
+            .unwrap(),
+            "./.yarn/cache/lodash-npm-4.17.21-6382451519-10c0.zip/node_modules/lodash"
+        );
+        // Virtual packages keep their locations
+        assert_eq!(
+            resolve(&data, "./packages/app", "react-dom").unwrap(),
+            "./.yarn/__virtual__/react-dom-virtual-abc/0/cache/react-dom-npm-18.3.\
+             1-a805663f38-10c0.zip/node_modules/react-dom"
+        );
+        // Files outside of other packages belong to the root workspace
+        assert_eq!(
+            resolve(&data, "./scripts", "app").unwrap(),
+            "./packages/app"
+        );
+        assert!(data.find_locator("../other").is_none());
+    }
+
+    #[test]
+    fn test_resolve_dependency_errors() {
+        let mut data = PnpManifestData::parse(MANIFEST).unwrap();
+        // The app is excluded from the fallback
+        assert!(
+            resolve(&data, "./packages/app", "lodash")
+                .unwrap_err()
+                .contains("isn't declared in its dependencies")
+        );
+        assert_eq!(
+            resolve(
+                &data,
+                "./.yarn/cache/react-npm-18.3.1-af38f3c1ae-10c0.zip/node_modules/react",
+                "lodash"
+            )
+            .unwrap(),
+            "./.yarn/cache/lodash-npm-4.17.21-6382451519-10c0.zip/node_modules/lodash"
+        );
+        data.enable_top_level_fallback = false;
+        assert!(
+            resolve(
+                &data,
+                "./.yarn/cache/react-npm-18.3.1-af38f3c1ae-10c0.zip/node_modules/react",
+                "lodash"
+            )
+            .is_err()
+        );
+        let peer = (Some(rcstr!("react-dom")), Some(rcstr!("npm:18.3.1")));
+        assert!(
+            data.resolve_dependency(&peer, "react")
+                .err()
+                .unwrap()
+                .contains("(a peer dependency)")
+        );
+    }
+
+    const RESOLVE_MANIFEST: &str = r#"{
+        "packageRegistryData": [
+            [null, [[null, {
+                "packageLocation": "./",
+                "packageDependencies": [["react", "npm:1.0.0"], ["plugin", "virtual:abc#npm:1.0.0"]]
+            }]]],
+            ["app", [["workspace:.", {
+                "packageLocation": "./",
+                "packageDependencies": [["react", "npm:1.0.0"], ["plugin", "virtual:abc#npm:1.0.0"]]
+            }]]],
+            ["react", [["npm:1.0.0", {
+                "packageLocation": "./.yarn/cache/react-npm-1.0.0.zip/node_modules/react/",
+                "packageDependencies": []
+            }]]],
+            ["plugin", [
+                ["npm:1.0.0", {
+                    "packageLocation": "./.yarn/cache/plugin-npm-1.0.0.zip/node_modules/plugin/",
+                    "packageDependencies": [["react", null]]
+                }],
+                ["virtual:abc#npm:1.0.0", {
+                    "packageLocation": "./.yarn/__virtual__/plugin-virtual-abc/0/cache/plugin-npm-1.0.0.zip/node_modules/plugin/",
+                    "packageDependencies": [["react", "npm:1.0.0"]]
+                }]
+            ]]
+        ]
+    }"#;
+
+    fn zip_archive(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (path, content) in files {
+            writer
+                .start_file(*path, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    /// Resolves `request` and returns the path of the first source.
+    async fn resolve_path(
+        lookup_path: &FileSystemPath,
+        request: &str,
+        options: Vc<ResolveOptions>,
+    ) -> Result<Option<FileSystemPath>> {
+        let result = resolve(
+            lookup_path.clone(),
+            ReferenceType::Undefined,
+            Request::parse_string(request.into()),
+            options,
+        )
+        .await?;
+        let Some(source) = *result.first_source().await? else {
+            return Ok(None);
+        };
+        Ok(Some(source.ident().path().owned().await?))
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_resolve_packages() {
+        let tt = turbo_tasks::TurboTasks::new(TurboTasksBackend::new(
+            BackendOptions::default(),
+            noop_backing_storage(),
+        ));
+        tt.run_once(async move {
+            let fs = MemoryFileSystem::new(rcstr!("project"))
+                .to_resolved()
+                .await?;
+            {
+                let fs = fs.await?;
+                fs.write_file(PNP_DATA_FILE, RESOLVE_MANIFEST)?;
+                fs.write_file("src/index.js", "")?;
+                fs.write_file(
+                    ".yarn/cache/react-npm-1.0.0.zip",
+                    zip_archive(&[
+                        ("node_modules/react/package.json", r#"{"main": "main.js"}"#),
+                        ("node_modules/react/main.js", ""),
+                    ]),
+                )?;
+                fs.write_file(
+                    ".yarn/cache/plugin-npm-1.0.0.zip",
+                    zip_archive(&[("node_modules/plugin/index.js", "")]),
+                )?;
+                fs.write_file("node_modules/other/index.js", "")?;
+            }
+            let root = fs.root().owned().await?;
+            let options = ResolveOptions {
+                extensions: vec![rcstr!(".js")],
+                modules: vec![
+                    ResolveModules::Pnp(root.join(PNP_DATA_FILE)?),
+                    ResolveModules::Nested(root.clone(), vec![rcstr!("node_modules")]),
+                ],
+                into_package: vec![ResolveIntoPackage::MainField {
+                    field: rcstr!("main"),
+                }],
+                default_files: vec![rcstr!("index")],
+                ..Default::default()
+            }
+            .cell();
+            let src = root.join("src")?;
+
+            // Packages are read from the zip archives in the cache
+            let react = resolve_path(&src, "react", options).await?.unwrap();
+            assert_eq!(react.path, "node_modules/react/main.js");
+            let archive_fs = ResolvedVc::try_downcast_type::<ArchiveFileSystem>(react.fs)
+                .unwrap()
+                .await?;
+            assert_eq!(archive_fs.archive().path, ".yarn/cache/react-npm-1.0.0.zip");
+
+            // Virtual packages keep their paths, so they resolve the dependencies of the virtual
+            // package instead of the peer dependencies of the package they are created from
+            let plugin = resolve_path(&src, "plugin", options).await?.unwrap();
+            assert_eq!(plugin.path, "index.js");
+            let virtual_fs = ResolvedVc::try_downcast_type::<PnpVirtualFileSystem>(plugin.fs)
+                .unwrap()
+                .await?;
+            assert_eq!(
+                virtual_fs.location,
+                "./.yarn/__virtual__/plugin-virtual-abc/0/cache/plugin-npm-1.0.0.zip/node_modules/\
+                 plugin"
+            );
+            assert_eq!(
+                resolve_path(&plugin.parent(), "react", options).await?,
+                Some(react)
+            );
+
+            // Packages that aren't declared as dependencies are not looked up in node_modules
+            assert_eq!(resolve_path(&src, "other", options).await?, None);
+
+            anyhow::Ok(())
+        })
+        .await
+        .unwrap();
+    }
+}
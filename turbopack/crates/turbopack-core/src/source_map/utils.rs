@@ -8,7 +8,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::value::RawValue;
 use turbo_tasks::{ResolvedVc, ValueToString};
 use turbo_tasks_fs::{
-    DiskFileSystem, FileContent, FileSystemPath, rope::Rope, util::uri_from_file,
+    DiskFileSystem, FileContent, FileSystemPath, OverlayFileSystem, rope::Rope, util::uri_from_file,
 };
 use url::Url;
 
@@ -86,11 +86,15 @@ pub async fn resolve_source_map_sources(
     let fs_vc = origin.fs().to_resolved().await?;
     let fs_str = &*format!("[{}]", fs_vc.to_string().await?);
 
-    let disk_fs = if let Some(fs_vc) = ResolvedVc::try_downcast_type::<DiskFileSystem>(fs_vc) {
-        Some((fs_vc, fs_vc.await?))
-    } else {
-        None
-    };
+    // Unsaved changes in an overlay are read through `origin`, but paths on disk are resolved
+    // with the filesystem below it
+    let disk_origin = OverlayFileSystem::strip_overlay(origin.clone()).await?;
+    let disk_fs =
+        if let Some(fs_vc) = ResolvedVc::try_downcast_type::<DiskFileSystem>(disk_origin.fs) {
+            Some((fs_vc, fs_vc.await?))
+        } else {
+            None
+        };
     let disk_fs = &disk_fs;
 
     let resolve_source =
@@ -114,7 +118,12 @@ pub async fn resolve_source_map_sources(
                 // We have an absolute URL, try to parse it as a `file://` URL
                 if let Ok(sys_path) = original_source_url_obj.to_file_path() {
                     if let Some((disk_fs_vc, disk_fs)) = disk_fs {
-                        disk_fs.try_from_sys_path(*disk_fs_vc, &sys_path, Some(origin))
+                        disk_fs
+                            .try_from_sys_path(*disk_fs_vc, &sys_path, Some(&disk_origin))
+                            .map(|path| FileSystemPath {
+                                fs: origin.fs,
+                                path: path.path,
+                            })
                     } else {
                         None
                     }
@@ -239,7 +248,9 @@ pub async fn fileify_source_map(
         return Ok(None);
     };
 
-    let context_fs = context_path.fs;
+    let context_fs = OverlayFileSystem::strip_overlay(context_path.clone())
+        .await?
+        .fs;
     let context_fs = &*ResolvedVc::try_downcast_type::<DiskFileSystem>(context_fs)
         .context("Expected the chunking context to have a DiskFileSystem")?
         .await?;
@@ -13,14 +13,14 @@ use swc_core::{
 };
 use turbo_rcstr::{RcStr, rcstr};
 use turbo_tasks::{FxIndexMap, FxIndexSet, ResolvedVc};
-use turbopack_core::{issue::IssueSource, source::Source};
+use turbopack_core::{issue::IssueSource, reference_type::ImportWithType, source::Source};
 
 use super::{JsValue, ModuleValue, top_level_await::has_top_level_await};
 use crate::{
     SpecifiedModuleType,
     analyzer::{ConstantValue, ObjectPart},
     magic_identifier,
-    tree_shake::{PartId, find_turbopack_part_id_in_asserts},
+    tree_shake::{ASSERT_CHUNK_KEY, PartId, find_turbopack_part_id_in_asserts},
 };
 
 #[turbo_tasks::value]
@@ -39,7 +39,10 @@ static ANNOTATION_TRANSITION: Lazy<Atom> =
 static ANNOTATION_CHUNKING_TYPE: Lazy<Atom> =
     Lazy::new(|| crate::annotations::ANNOTATION_CHUNKING_TYPE.into());
 
-/// Changes the type of the resolved module (only "json" is supported currently)
+/// Selects the part of the module that is imported after tree shaking
+static ANNOTATION_PART: Lazy<Atom> = Lazy::new(|| ASSERT_CHUNK_KEY.into());
+
+/// Changes the type of the resolved module ("json", "css", "text" or "bytes")
 static ATTRIBUTE_MODULE_TYPE: Lazy<Atom> = Lazy::new(|| atom!("type"));
 
 impl ImportAnnotations {
@@ -115,6 +118,27 @@ impl ImportAnnotations {
         self.get(&ATTRIBUTE_MODULE_TYPE)
     }
 
+    /// Returns the parsed type attribute, or the raw value when the type is not supported
+    pub fn import_with_type(&self) -> Result<Option<ImportWithType>, &str> {
+        self.module_type()
+            .map(|ty| ImportWithType::from_attribute(ty).ok_or(ty))
+            .transpose()
+    }
+
+    /// Returns the keys of import attributes that are neither the type attribute nor one of the
+    /// internal turbopack annotations
+    pub fn unsupported_attributes(&self) -> impl Iterator<Item = &Atom> {
+        self.map.keys().filter(|key| {
+            ![
+                &*ATTRIBUTE_MODULE_TYPE,
+                &*ANNOTATION_TRANSITION,
+                &*ANNOTATION_CHUNKING_TYPE,
+                &*ANNOTATION_PART,
+            ]
+            .contains(key)
+        })
+    }
+
     pub fn get(&self, key: &Atom) -> Option<&str> {
         self.map.get(key).map(|w| w.as_str())
     }
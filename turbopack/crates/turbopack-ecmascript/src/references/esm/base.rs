@@ -391,10 +391,8 @@ impl EsmAssetReference {
 impl ModuleReference for EsmAssetReference {
     #[turbo_tasks::function]
     async fn resolve_reference(&self) -> Result<Vc<ModuleResolveResult>> {
-        let ty = if matches!(self.annotations.module_type(), Some("json")) {
-            EcmaScriptModulesReferenceSubType::ImportWithType(ImportWithType::Json)
-        } else if matches!(self.annotations.module_type(), Some("bytes")) {
-            EcmaScriptModulesReferenceSubType::ImportWithType(ImportWithType::Bytes)
+        let ty = if let Some(ty) = import_with_type(&self.annotations, self.issue_source) {
+            EcmaScriptModulesReferenceSubType::ImportWithType(ty)
         } else if let Some(part) = &self.export_name {
             EcmaScriptModulesReferenceSubType::ImportPart(part.clone())
         } else {
@@ -756,6 +754,50 @@ fn var_decl_with_span(mut decl: Stmt, span: Span) -> Stmt {
     decl
 }
 
+/// Returns the module type requested by the `type` import attribute. Unsupported attributes and
+/// types are reported as issues.
+pub(crate) fn import_with_type(
+    annotations: &ImportAnnotations,
+    issue_source: IssueSource,
+) -> Option<ImportWithType> {
+    for key in annotations.unsupported_attributes() {
+        ImportAttributeIssue {
+            severity: IssueSeverity::Warning,
+            source: issue_source,
+            title: StyledString::Line(vec![
+                StyledString::Text(rcstr!("Unsupported import attribute ")),
+                StyledString::Code(key.as_str().into()),
+            ])
+            .resolved_cell(),
+            description: rcstr!(
+                "Only the `type` import attribute is supported, other attributes are ignored."
+            ),
+        }
+        .resolved_cell()
+        .emit();
+    }
+    match annotations.import_with_type() {
+        Ok(ty) => ty,
+        Err(ty) => {
+            ImportAttributeIssue {
+                severity: IssueSeverity::Error,
+                source: issue_source,
+                title: StyledString::Line(vec![
+                    StyledString::Text(rcstr!("Unsupported import type ")),
+                    StyledString::Code(ty.into()),
+                ])
+                .resolved_cell(),
+                description: rcstr!(
+                    "The `type` import attribute must be one of `json`, `css`, `text` or `bytes`."
+                ),
+            }
+            .resolved_cell()
+            .emit();
+            None
+        }
+    }
+}
+
 #[turbo_tasks::value(shared)]
 pub struct InvalidExport {
     export: RcStr,
@@ -915,3 +957,45 @@ impl Issue for CircularReExport {
         Vc::cell(None)
     }
 }
+
+#[turbo_tasks::value(shared)]
+struct ImportAttributeIssue {
+    severity: IssueSeverity,
+    source: IssueSource,
+    title: ResolvedVc<StyledString>,
+    description: RcStr,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for ImportAttributeIssue {
+    fn severity(&self) -> IssueSeverity {
+        self.severity
+    }
+
+    #[turbo_tasks::function]
+    fn title(&self) -> Vc<StyledString> {
+        *self.title
+    }
+
+    #[turbo_tasks::function]
+    fn stage(&self) -> Vc<IssueStage> {
+        IssueStage::Analysis.into()
+    }
+
+    #[turbo_tasks::function]
+    fn file_path(&self) -> Vc<FileSystemPath> {
+        self.source.file_path()
+    }
+
+    #[turbo_tasks::function]
+    fn description(&self) -> Vc<OptionStyledString> {
+        Vc::cell(Some(
+            StyledString::Text(self.description.clone()).resolved_cell(),
+        ))
+    }
+
+    #[turbo_tasks::function]
+    fn source(&self) -> Vc<OptionIssueSource> {
+        Vc::cell(Some(self.source))
+    }
+}
@@ -23,7 +23,10 @@ use turbopack_core::{
 };
 use turbopack_resolve::ecmascript::esm_resolve;
 
-use super::super::pattern_mapping::{PatternMapping, ResolveType};
+use super::{
+    super::pattern_mapping::{PatternMapping, ResolveType},
+    base::import_with_type,
+};
 use crate::{
     analyzer::imports::ImportAnnotations,
     code_gen::{CodeGen, CodeGeneration, IntoCodeGenReference},
@@ -79,7 +82,10 @@ impl ModuleReference for EsmAsyncAssetReference {
         esm_resolve(
             self.get_origin().resolve().await?,
             *self.request,
-            EcmaScriptModulesReferenceSubType::DynamicImport,
+            import_with_type(&self.annotations, self.issue_source).map_or(
+                EcmaScriptModulesReferenceSubType::DynamicImport,
+                EcmaScriptModulesReferenceSubType::ImportWithType,
+            ),
             self.in_try,
             Some(self.issue_source),
         )
@@ -1,4 +1,7 @@
+use std::borrow::Cow;
+
 use anyhow::Result;
+use indoc::formatdoc;
 use turbo_rcstr::{RcStr, rcstr};
 use turbo_tasks::{ResolvedVc, Vc};
 use turbo_tasks_fs::FileContent;
@@ -50,3 +53,161 @@ impl Asset for TextContentFileSource {
         Ok(AssetContent::file(content))
     }
 }
+
+/// A source asset that exports the content of a CSS file as a constructed
+/// `CSSStyleSheet` as the default export of a JS module, like a CSS module script.
+///
+/// Relative `url()` references are resolved with `new URL(..., import.meta.url)`, so the
+/// referenced assets are emitted like for the CSS pipeline. Outside of browsers, e.g. during
+/// server rendering, there is no `CSSStyleSheet` and `null` is exported instead.
+#[turbo_tasks::value]
+pub struct CssStyleSheetFileSource {
+    pub source: ResolvedVc<Box<dyn Source>>,
+}
+
+#[turbo_tasks::value_impl]
+impl CssStyleSheetFileSource {
+    #[turbo_tasks::function]
+    pub fn new(source: ResolvedVc<Box<dyn Source>>) -> Vc<Self> {
+        CssStyleSheetFileSource { source }.cell()
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl Source for CssStyleSheetFileSource {
+    #[turbo_tasks::function]
+    fn ident(&self) -> Vc<AssetIdent> {
+        self.source
+            .ident()
+            .with_modifier(rcstr!("css style sheet"))
+            .rename_as(rcstr!("*.mjs"))
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl Asset for CssStyleSheetFileSource {
+    #[turbo_tasks::function]
+    async fn content(&self) -> Result<Vc<AssetContent>> {
+        let source = self.source.content().file_content();
+        let FileContent::Content(content) = &*source.await? else {
+            return Ok(AssetContent::file(FileContent::NotFound.cell()));
+        };
+        let text = content.content().to_str()?;
+        let code: RcStr = formatdoc! {
+            r#"
+                const css = {css};
+                let sheet = null;
+                if (typeof CSSStyleSheet === "function") {{
+                    sheet = new CSSStyleSheet();
+                    sheet.replaceSync(css);
+                }}
+                export default sheet;
+            "#,
+            css = css_text_expression(&text),
+        }
+        .into();
+        let content = FileContent::Content(code.into()).cell();
+        Ok(AssetContent::file(content))
+    }
+}
+
+/// Returns a JS expression that evaluates to the CSS text, with the relative `url()` references
+/// replaced by the URLs of the referenced assets.
+fn css_text_expression(css: &str) -> String {
+    let mut parts = Vec::new();
+    let mut literal_start = 0;
+    let mut search_start = 0;
+    while let Some(index) = css[search_start..].find("url(") {
+        let start = search_start + index;
+        search_start = start + "url(".len();
+        // e.g. `my-url(`
+        if css[..start].ends_with(|c: char| c.is_alphanumeric() || c == '-' || c == '_') {
+            continue;
+        }
+        let Some((url, end)) = parse_url_token(&css[search_start..]) else {
+            continue;
+        };
+        if !is_relative_url(url) {
+            continue;
+        }
+        // `url(img.png)` is relative to the CSS file, but `img.png` would be a module request
+        let request = if url.starts_with('.') {
+            Cow::Borrowed(url)
+        } else {
+            Cow::Owned(format!("./{url}"))
+        };
+        parts.push(StringifyJs(&css[literal_start..search_start]).to_string());
+        parts.push(format!(
+            "JSON.stringify(new URL({}, import.meta.url).href)",
+            StringifyJs(&request)
+        ));
+        literal_start = search_start + end;
+        search_start = literal_start;
+    }
+    parts.push(StringifyJs(&css[literal_start..]).to_string());
+    parts.join(" + ")
+}
+
+/// Parses the URL after `url(` and returns it with the index of the closing parenthesis.
+fn parse_url_token(token: &str) -> Option<(&str, usize)> {
+    let trimmed = token.trim_start();
+    let offset = token.len() - trimmed.len();
+    let (url, url_end) = match trimmed.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let len = trimmed[1..].find(quote)?;
+            (&trimmed[1..1 + len], len + 2)
+        }
+        _ => {
+            let len = trimmed.find(')')?;
+            (trimmed[..len].trim_end(), len)
+        }
+    };
+    let close = trimmed[url_end..].find(')')?;
+    if !trimmed[url_end..url_end + close].trim().is_empty() {
+        return None;
+    }
+    Some((url, offset + url_end + close))
+}
+
+/// Whether `url` is relative to the CSS file, i.e. it has neither a scheme like `data:` nor an
+/// absolute path and isn't a fragment.
+fn is_relative_url(url: &str) -> bool {
+    let has_scheme = url.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    });
+    !url.is_empty() && !url.starts_with(['/', '#']) && !has_scheme
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_css_text_expression() {
+        assert_eq!(
+            css_text_expression("a { color: red }"),
+            r#""a { color: red }""#
+        );
+        assert_eq!(
+            css_text_expression("a { background: url(img.png) }"),
+            r#""a { background: url(" + JSON.stringify(new URL("./img.png", import.meta.url).href) + ") }""#
+        );
+        assert_eq!(
+            css_text_expression(r#"a { background: url( "../img.png" ) }"#),
+            r#""a { background: url(" + JSON.stringify(new URL("../img.png", import.meta.url).href) + ") }""#
+        );
+        // Absolute URLs, fragments and data URLs are kept
+        for css in [
+            "a { background: url(/img.png) }",
+            "a { background: url('https://example.com/img.png') }",
+            "a { background: url(data:image/png;base64,AAAA) }",
+            "a { filter: url(#filter) }",
+            "a { background: my-url(img.png) }",
+        ] {
+            assert_eq!(css_text_expression(css), StringifyJs(css).to_string());
+        }
+    }
+}
@@ -1644,7 +1644,7 @@ impl DepGraph {
     }
 }
 
-const ASSERT_CHUNK_KEY: &str = "__turbopack_part__";
+pub(crate) const ASSERT_CHUNK_KEY: &str = "__turbopack_part__";
 
 #[derive(Debug, Clone)]
 pub(crate) enum PartId {
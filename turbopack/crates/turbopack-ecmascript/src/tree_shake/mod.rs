@@ -16,10 +16,10 @@ use turbo_rcstr::RcStr;
 use turbo_tasks::{FxIndexSet, ResolvedVc, ValueToString, Vc};
 use turbopack_core::{ident::AssetIdent, resolve::ModulePart, source::Source};
 
-use self::graph::{DepGraph, ItemData, ItemId, ItemIdGroupKind, Mode, SplitModuleResult};
 pub(crate) use self::graph::{
-    PartId, create_turbopack_part_id_assert, find_turbopack_part_id_in_asserts,
+    ASSERT_CHUNK_KEY, PartId, create_turbopack_part_id_assert, find_turbopack_part_id_in_asserts,
 };
+use self::graph::{DepGraph, ItemData, ItemId, ItemIdGroupKind, Mode, SplitModuleResult};
 use crate::{
     EcmascriptModuleAsset, EcmascriptParsable, analyzer::graph::EvalContext, parse::ParseResult,
 };
@@ -149,22 +149,24 @@ async fn base_resolve_options(
     };
     Ok(ResolveOptions {
         extensions,
-        modules: if let Some(environment) = emulating {
-            if *environment.resolve_node_modules().await? {
-                vec![ResolveModules::Nested(
-                    root.clone(),
-                    vec![rcstr!("node_modules")],
-                )]
+        modules: {
+            let node_modules = if let Some(environment) = emulating {
+                if *environment.resolve_node_modules().await? {
+                    Some(root.clone())
+                } else {
+                    None
+                }
             } else {
-                Vec::new()
-            }
-        } else {
+                opt.enable_node_modules.clone()
+            };
             let mut mods = Vec::new();
-            if let Some(dir) = &opt.enable_node_modules {
-                mods.push(ResolveModules::Nested(
-                    dir.clone(),
-                    vec![rcstr!("node_modules")],
-                ));
+            if let Some(dir) = node_modules {
+                // Packages managed by Plug'n'Play are resolved with the manifest, other
+                // packages fall back to node_modules
+                if let Some(manifest) = &opt.enable_pnp {
+                    mods.push(ResolveModules::Pnp(manifest.clone()));
+                }
+                mods.push(ResolveModules::Nested(dir, vec![rcstr!("node_modules")]));
             }
             mods
         },
@@ -201,6 +203,7 @@ async fn base_resolve_options(
         default_files: vec![rcstr!("index")],
         import_map: Some(import_map),
         resolved_map: opt.resolved_map,
+        locked_versions: opt.locked_versions,
         after_resolve_plugins: opt.after_resolve_plugins.clone(),
         before_resolve_plugins: opt.before_resolve_plugins.clone(),
         loose_errors: opt.loose_errors,
@@ -6,7 +6,7 @@ use turbopack_core::{
     condition::ContextCondition,
     environment::Environment,
     resolve::{
-        options::{ImportMap, ResolvedMap},
+        options::{ImportMap, LockedVersions, ResolvedMap},
         plugin::{AfterResolvePlugin, BeforeResolvePlugin},
     },
 };
@@ -32,6 +32,10 @@ pub struct ResolveOptionsContext {
     /// directory
     pub enable_node_modules: Option<FileSystemPath>,
     #[serde(default)]
+    /// Enable resolving packages with the Yarn Plug'n'Play manifest (`.pnp.cjs` or
+    /// `.pnp.data.json`) at the provided path. Requires `enable_node_modules`.
+    pub enable_pnp: Option<FileSystemPath>,
+    #[serde(default)]
     /// A specific path to a tsconfig.json file to use for resolving modules. If `None`, one will
     /// be looked up through the filesystem
     pub tsconfig_path: Option<FileSystemPath>,
@@ -70,6 +74,10 @@ pub struct ResolveOptionsContext {
     /// An additional resolved map to use after modules have been resolved.
     pub resolved_map: Option<ResolvedVc<ResolvedMap>>,
     #[serde(default)]
+    /// The versions of the lockfile that packages resolved from node_modules are checked
+    /// against.
+    pub locked_versions: Option<ResolvedVc<LockedVersions>>,
+    #[serde(default)]
     /// A list of rules to use a different resolve option context for certain
     /// context paths. The first matching is used.
     pub rules: Vec<(ContextCondition, ResolvedVc<ResolveOptionsContext>)>,
@@ -0,0 +1,28 @@
+import data from './data.json' with { type: 'json' }
+import sheet from './style.css' with { type: 'css' }
+import text from './hello.txt' with { type: 'text' }
+
+it('imports json', async () => {
+  expect(data).toEqual({ name: 'data' })
+  const { default: dynamicData } = await import('./data.json', {
+    with: { type: 'json' },
+  })
+  expect(dynamicData).toBe(data)
+})
+
+it('imports text', async () => {
+  expect(text).toBe('Hello, world!\n')
+  const { default: dynamicText } = await import('./hello.txt', {
+    with: { type: 'text' },
+  })
+  expect(dynamicText).toBe(text)
+})
+
+it('exports null for css outside of browsers', async () => {
+  expect(typeof CSSStyleSheet).toBe('undefined')
+  expect(sheet).toBe(null)
+  const { default: dynamicSheet } = await import('./style.css', {
+    with: { type: 'css' },
+  })
+  expect(dynamicSheet).toBe(null)
+})
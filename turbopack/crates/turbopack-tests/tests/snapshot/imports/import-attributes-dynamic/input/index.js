@@ -0,0 +1,16 @@
+import('./data.json', { with: { type: 'json' } }).then(console.log)
+import('./style.css', { with: { type: 'css' } }).then(console.log)
+import('./hello.txt', { with: { type: 'text' } }).then(console.log)
+import('./hello.bin', { with: { type: 'bytes' } }).then(console.log)
+
+// Unknown type
+import('./data.json', { with: { type: 'yaml' } }).then(console.log)
+
+// Unsupported key
+import('./data.json', { with: { type: 'json', integrity: 'sha384-abc' } }).then(
+  console.log
+)
+
+// Extension mismatches
+import('./data.json', { with: { type: 'css' } }).then(console.log)
+import('./module.js', { with: { type: 'json' } }).then(console.log)
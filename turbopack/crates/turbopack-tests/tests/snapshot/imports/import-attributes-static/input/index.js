@@ -0,0 +1,18 @@
+import data from './data.json' with { type: 'json' }
+import sheet from './style.css' with { type: 'css' }
+import text from './hello.txt' with { type: 'text' }
+import bytes from './hello.bin' with { type: 'bytes' }
+console.log(data, sheet, text, bytes)
+
+// Unknown type
+import unknown from './data.json' with { type: 'yaml' }
+console.log(unknown)
+
+// Unsupported key
+import integrity from './data.json' with { type: 'json', integrity: 'sha384-abc' }
+console.log(integrity)
+
+// Extension mismatches
+import dataAsCss from './data.json' with { type: 'css' }
+import moduleAsJson from './module.js' with { type: 'json' }
+console.log(dataAsCss, moduleAsJson)
@@ -62,6 +62,7 @@ use turbopack_ecmascript::{
         CachedExternalModule, CachedExternalTracingMode, CachedExternalType,
     },
     side_effect_optimization::locals::module::EcmascriptModuleLocalsModule,
+    text::{CssStyleSheetFileSource, TextContentFileSource},
     tree_shake::asset::EcmascriptModulePartAsset,
 };
 use turbopack_json::JsonModuleAsset;
@@ -536,8 +537,60 @@ async fn process_default_internal(
         ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::ImportWithType(ty)) => {
             has_type_attribute = true;
 
+            let expected_extension = match ty {
+                ImportWithType::Json => Some("json"),
+                ImportWithType::Css => Some("css"),
+                ImportWithType::Text | ImportWithType::Bytes => None,
+            };
+            if let Some(extension) = path_ref.extension_ref()
+                && matches!(
+                    extension,
+                    "js" | "mjs" | "cjs" | "jsx" | "ts" | "mts" | "cts" | "tsx" | "json" | "css"
+                )
+                && expected_extension.is_some_and(|expected| expected != extension)
+            {
+                ModuleIssue::new(
+                    *ident,
+                    rcstr!("Mismatched import type"),
+                    format!(
+                        "A `.{extension}` file is imported with `type: \"{ty}\"`, so it's \
+                         processed as {ty} instead of its usual module type.",
+                        ty = ty.as_str()
+                    )
+                    .into(),
+                    Some(IssueSource::from_source_only(source)),
+                )
+                .to_resolved()
+                .await?
+                .emit();
+            }
+
             match ty {
                 ImportWithType::Json => Some(ModuleType::Json),
+                // The content is wrapped into an ecmascript module, which is processed with the
+                // usual rules
+                ImportWithType::Css => {
+                    return Box::pin(process_default(
+                        module_asset_context,
+                        ResolvedVc::upcast(
+                            CssStyleSheetFileSource::new(*source).to_resolved().await?,
+                        ),
+                        ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::Import),
+                        processed_rules,
+                    ))
+                    .await;
+                }
+                ImportWithType::Text => {
+                    return Box::pin(process_default(
+                        module_asset_context,
+                        ResolvedVc::upcast(
+                            TextContentFileSource::new(*source).to_resolved().await?,
+                        ),
+                        ReferenceType::EcmaScriptModules(EcmaScriptModulesReferenceSubType::Import),
+                        processed_rules,
+                    ))
+                    .await;
+                }
                 // Reenable this once `import {type: "bytes"}` is stabilized
                 ImportWithType::Bytes => None,
             }